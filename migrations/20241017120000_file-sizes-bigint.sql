@@ -0,0 +1 @@
+ALTER TABLE files ALTER COLUMN size TYPE bigint;
@@ -0,0 +1,21 @@
+CREATE TABLE upload_sessions (
+    id bigint PRIMARY KEY,
+    user_id bigint REFERENCES users NOT NULL,
+    file_name varchar(1024) NOT NULL,
+    file_size bigint NOT NULL,
+    chunk_size integer NOT NULL,
+    created timestamptz DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    expires timestamptz NOT NULL
+);
+
+CREATE INDEX upload_sessions_user_id ON upload_sessions (user_id);
+CREATE INDEX upload_sessions_expires ON upload_sessions (expires);
+
+CREATE TABLE upload_session_chunks (
+    session_id bigint REFERENCES upload_sessions ON DELETE CASCADE NOT NULL,
+    chunk_index integer NOT NULL,
+    file_id varchar(1024) NOT NULL,
+    file_name varchar(1024) NOT NULL,
+    size integer NOT NULL,
+    PRIMARY KEY (session_id, chunk_index)
+);
@@ -0,0 +1,11 @@
+CREATE TABLE file_replicas (
+    file_name varchar(1024) NOT NULL,
+    backend varchar(64) NOT NULL,
+    file_id varchar(1024) NULL,
+    content_type varchar(255) NOT NULL,
+    status varchar(64) NOT NULL,
+    updated timestamptz DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    PRIMARY KEY (file_name, backend)
+);
+
+CREATE INDEX file_replicas_pending ON file_replicas (updated) WHERE status <> 'stored';
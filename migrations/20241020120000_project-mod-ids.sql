@@ -0,0 +1,8 @@
+CREATE TABLE project_mod_ids (
+    mod_id varchar(255) NOT NULL,
+    project_id bigint REFERENCES mods NOT NULL,
+    created timestamptz DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    PRIMARY KEY (mod_id, project_id)
+);
+
+CREATE INDEX project_mod_ids_project_id ON project_mod_ids (project_id);
@@ -0,0 +1,9 @@
+CREATE TABLE file_scan_findings (
+    id bigserial PRIMARY KEY,
+    file_id bigint REFERENCES files NOT NULL,
+    kind varchar(64) NOT NULL,
+    path varchar(2048) NOT NULL,
+    detail varchar(255) NULL
+);
+
+CREATE INDEX file_scan_findings_file_id ON file_scan_findings (file_id);
@@ -0,0 +1 @@
+ALTER TABLE files ADD COLUMN validation_report jsonb NULL;
@@ -0,0 +1,5 @@
+-- The set of validators run on the files uploaded for a game, or 'none' to store files as uploaded.
+-- There is no default, so every game added has to choose its policy explicitly.
+ALTER TABLE games ADD COLUMN validation_policy varchar(64) NOT NULL DEFAULT 'none';
+UPDATE games SET validation_policy = 'minecraft-java' WHERE slug = 'minecraft-java';
+ALTER TABLE games ALTER COLUMN validation_policy DROP DEFAULT;
@@ -0,0 +1,20 @@
+CREATE TABLE file_revalidation_jobs (
+    id bigserial PRIMARY KEY,
+    status varchar(64) NOT NULL,
+    update_primary boolean NOT NULL,
+    open_threads boolean NOT NULL,
+    files_per_minute integer NOT NULL,
+    -- Files are revalidated in order of their ids, so the job resumes after the last one checked
+    last_file_id bigint NULL,
+    total_files bigint NOT NULL,
+    checked_files bigint NOT NULL DEFAULT 0,
+    newly_failing_files bigint NOT NULL DEFAULT 0,
+    errored_files bigint NOT NULL DEFAULT 0,
+    -- Set while a batch of the job is being processed, so only one worker processes it
+    locked_until timestamptz NULL,
+    created timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    finished timestamptz NULL
+);
+
+CREATE INDEX file_revalidation_jobs_status ON file_revalidation_jobs (status);
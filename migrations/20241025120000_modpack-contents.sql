@@ -0,0 +1,15 @@
+CREATE TABLE modpack_contents (
+    id bigserial PRIMARY KEY,
+    file_id bigint REFERENCES files NOT NULL,
+    path varchar(2048) NOT NULL,
+    status varchar(64) NOT NULL,
+    -- The version whose file the entry resolved to. Not a foreign key, as the version may
+    -- be deleted after the modpack was uploaded.
+    project_id bigint NULL,
+    version_id bigint NULL,
+    file_size bigint NOT NULL,
+    size_mismatch boolean NOT NULL
+);
+
+CREATE INDEX modpack_contents_file_id ON modpack_contents (file_id);
+CREATE INDEX modpack_contents_project_id ON modpack_contents (project_id);
@@ -0,0 +1,84 @@
+-- The pack formats of resource packs and data packs made for each release of Minecraft Java,
+-- which uploaded packs are checked against
+UPDATE loader_field_enum_values lfev
+SET metadata = COALESCE(lfev.metadata, '{}') || jsonb_strip_nulls(jsonb_build_object(
+    'resource_pack_format', f.resource_pack_format,
+    'data_pack_format', f.data_pack_format
+))
+FROM (VALUES
+    ('1.6.1', 1, NULL),
+    ('1.6.2', 1, NULL),
+    ('1.6.4', 1, NULL),
+    ('1.7.2', 1, NULL),
+    ('1.7.4', 1, NULL),
+    ('1.7.5', 1, NULL),
+    ('1.7.6', 1, NULL),
+    ('1.7.7', 1, NULL),
+    ('1.7.8', 1, NULL),
+    ('1.7.9', 1, NULL),
+    ('1.7.10', 1, NULL),
+    ('1.8', 1, NULL),
+    ('1.8.1', 1, NULL),
+    ('1.8.2', 1, NULL),
+    ('1.8.3', 1, NULL),
+    ('1.8.4', 1, NULL),
+    ('1.8.5', 1, NULL),
+    ('1.8.6', 1, NULL),
+    ('1.8.7', 1, NULL),
+    ('1.8.8', 1, NULL),
+    ('1.8.9', 1, NULL),
+    ('1.9', 2, NULL),
+    ('1.9.1', 2, NULL),
+    ('1.9.2', 2, NULL),
+    ('1.9.3', 2, NULL),
+    ('1.9.4', 2, NULL),
+    ('1.10', 2, NULL),
+    ('1.10.1', 2, NULL),
+    ('1.10.2', 2, NULL),
+    ('1.11', 3, NULL),
+    ('1.11.1', 3, NULL),
+    ('1.11.2', 3, NULL),
+    ('1.12', 3, NULL),
+    ('1.12.1', 3, NULL),
+    ('1.12.2', 3, NULL),
+    ('1.13', 4, 4),
+    ('1.13.1', 4, 4),
+    ('1.13.2', 4, 4),
+    ('1.14', 4, 4),
+    ('1.14.1', 4, 4),
+    ('1.14.2', 4, 4),
+    ('1.14.3', 4, 4),
+    ('1.14.4', 4, 4),
+    ('1.15', 5, 5),
+    ('1.15.1', 5, 5),
+    ('1.15.2', 5, 5),
+    ('1.16', 5, 5),
+    ('1.16.1', 5, 5),
+    ('1.16.2', 6, 6),
+    ('1.16.3', 6, 6),
+    ('1.16.4', 6, 6),
+    ('1.16.5', 6, 6),
+    ('1.17', 7, 7),
+    ('1.17.1', 7, 7),
+    ('1.18', 8, 8),
+    ('1.18.1', 8, 8),
+    ('1.18.2', 8, 9),
+    ('1.19', 9, 10),
+    ('1.19.1', 9, 10),
+    ('1.19.2', 9, 10),
+    ('1.19.3', 12, 10),
+    ('1.19.4', 13, 12),
+    ('1.20', 15, 15),
+    ('1.20.1', 15, 15),
+    ('1.20.2', 18, 18),
+    ('1.20.3', 22, 26),
+    ('1.20.4', 22, 26),
+    ('1.20.5', 32, 41),
+    ('1.20.6', 32, 41),
+    ('1.21', 34, 48),
+    ('1.21.1', 34, 48),
+    ('1.21.2', 42, 57),
+    ('1.21.3', 42, 57)
+) AS f(version, resource_pack_format, data_pack_format)
+WHERE lfev.value = f.version
+    AND lfev.enum_id = (SELECT id FROM loader_field_enums WHERE enum_name = 'game_versions');
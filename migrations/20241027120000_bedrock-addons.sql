@@ -0,0 +1,35 @@
+-- Bedrock add-ons (.mcpack, .mcaddon and .mcworld files) are validated by reading the
+-- manifest.json of each of their packs
+UPDATE games SET validation_policy = 'minecraft-bedrock' WHERE slug = 'minecraft-bedrock';
+
+INSERT INTO loaders (loader) VALUES ('bedrock');
+
+INSERT INTO loaders_project_types (joining_loader_id, joining_project_type_id)
+SELECT l.id, pt.id FROM loaders l CROSS JOIN project_types pt
+WHERE l.loader = 'bedrock' AND pt.name IN ('mod', 'resourcepack');
+
+INSERT INTO loaders_project_types_games (loader_id, project_type_id, game_id)
+SELECT lpt.joining_loader_id, lpt.joining_project_type_id, g.id
+FROM loaders_project_types lpt
+INNER JOIN loaders l ON l.id = lpt.joining_loader_id
+CROSS JOIN games g
+WHERE l.loader = 'bedrock' AND g.slug = 'minecraft-bedrock';
+
+-- The module types declared by the packs of an add-on
+INSERT INTO loader_field_enums (id, enum_name, hidable) VALUES (4, 'bedrock_module_types', true);
+INSERT INTO loader_field_enum_values (enum_id, value)
+SELECT lfe.id, t.value
+FROM loader_field_enums lfe
+CROSS JOIN unnest(ARRAY['resources', 'data', 'client_data', 'interface', 'world_template', 'skin_pack', 'script', 'javascript']) t(value)
+WHERE lfe.enum_name = 'bedrock_module_types';
+
+-- The lowest engine version an add-on requires, both as declared and as
+-- major * 1000000 + minor * 1000 + patch, which can be compared when filtering
+INSERT INTO loader_fields (field, field_type, optional)
+VALUES ('min_engine_version', 'text', true), ('min_engine_version_number', 'integer', true);
+INSERT INTO loader_fields (field, field_type, enum_type, optional, min_val)
+SELECT 'bedrock_modules', 'array_enum', id, true, 0 FROM loader_field_enums WHERE enum_name = 'bedrock_module_types';
+
+INSERT INTO loader_fields_loaders (loader_id, loader_field_id)
+SELECT l.id, lf.id FROM loaders l CROSS JOIN loader_fields lf
+WHERE l.loader = 'bedrock' AND lf.field IN ('min_engine_version', 'min_engine_version_number', 'bedrock_modules');
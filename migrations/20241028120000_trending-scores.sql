@@ -0,0 +1,3 @@
+-- How much a project is trending, from its recent downloads and follows. Kept up to date by a
+-- scheduled job and copied into search documents to sort by.
+ALTER TABLE mods ADD COLUMN trending double precision NOT NULL DEFAULT 0;
@@ -0,0 +1,12 @@
+CREATE TABLE saved_searches (
+    id bigint PRIMARY KEY,
+    user_id bigint REFERENCES users ON DELETE CASCADE NOT NULL,
+    name varchar(64) NOT NULL,
+    -- The search parameters, as accepted by the project search route
+    request jsonb NOT NULL,
+    created timestamptz DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    -- Projects approved up to this time have already been matched against the search
+    last_checked timestamptz DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX saved_searches_user_id ON saved_searches (user_id);
@@ -0,0 +1,6 @@
+-- Whether the projects of the entry were notified of their inclusion. This only happens once
+-- the modpack version and its project are public.
+ALTER TABLE modpack_contents ADD COLUMN notified boolean NOT NULL DEFAULT FALSE;
+
+-- Existing entries were notified when their modpack was uploaded
+UPDATE modpack_contents SET notified = TRUE;
@@ -0,0 +1,2 @@
+ALTER TABLE upload_sessions ALTER COLUMN chunk_size TYPE bigint;
+ALTER TABLE upload_session_chunks ALTER COLUMN size TYPE bigint;
@@ -0,0 +1,34 @@
+-- The validators each validation policy is composed of, so that games can get their own
+-- validation rules from the validators labrinth provides. Policies without validators, such as
+-- 'none', store files as uploaded.
+CREATE TABLE validation_policies (
+    name varchar(64) PRIMARY KEY,
+    -- The names of the validators run, which are further selected by the loaders of a version
+    validators varchar(64)[] NOT NULL,
+    -- Whether the validators are restricted to some game versions, which requires the
+    -- game_versions field of a version to be resolved
+    uses_game_versions boolean NOT NULL,
+    -- The extensions which are accepted, but can't make a file primary, when no validator of a
+    -- version's loaders supports them
+    always_allowed_extensions varchar(32)[] NOT NULL
+);
+
+INSERT INTO validation_policies (name, validators, uses_game_versions, always_allowed_extensions)
+VALUES
+    ('none', '{}', FALSE, '{}'),
+    (
+        'minecraft-java',
+        ARRAY[
+            'modpack', 'fabric', 'forge', 'legacy-forge', 'quilt', 'liteloader', 'pack',
+            'texture-pack', 'plugin-yml', 'bungeecord', 'velocity', 'sponge', 'canvas-shader',
+            'shader', 'core-shader', 'datapack', 'rift', 'neoforge'
+        ],
+        TRUE,
+        ARRAY['zip', 'txt']
+    ),
+    -- Add-ons declare the engine version they require instead of game versions
+    ('minecraft-bedrock', ARRAY['mcpack', 'mcaddon', 'mcworld'], FALSE, ARRAY['zip', 'txt']);
+
+ALTER TABLE games
+    ADD CONSTRAINT games_validation_policy_fkey
+    FOREIGN KEY (validation_policy) REFERENCES validation_policies (name);
@@ -0,0 +1,3 @@
+ALTER TABLE upload_sessions DROP CONSTRAINT upload_sessions_user_id_fkey;
+ALTER TABLE upload_sessions
+    ADD CONSTRAINT upload_sessions_user_id_fkey FOREIGN KEY (user_id) REFERENCES users ON DELETE CASCADE;
@@ -0,0 +1,10 @@
+CREATE TABLE storage_reconciliation_runs (
+    id bigserial PRIMARY KEY,
+    status varchar(64) NOT NULL,
+    purge_grace_period_hours integer NULL,
+    -- The missing, orphaned and purged files found by the run, once it finished
+    report jsonb NULL,
+    error text NULL,
+    started timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    finished timestamptz NULL
+);
@@ -109,6 +109,32 @@ pub async fn fetch_downloads(
     Ok(query.fetch_all().await?)
 }
 
+// Fetches the downloads of every project which had any as a Vec of ReturnIntervals
+pub async fn fetch_all_downloads(
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    resolution_minutes: u32,
+    client: &clickhouse::Client,
+) -> Result<Vec<ReturnIntervals>, ApiError> {
+    let query = client
+        .query(
+            "
+            SELECT
+                toUnixTimestamp(toStartOfInterval(recorded, toIntervalMinute(?))) AS time,
+                project_id as id,
+                count(1) AS total
+            FROM downloads
+            WHERE recorded BETWEEN ? AND ?
+            GROUP BY time, project_id
+            ",
+        )
+        .bind(resolution_minutes)
+        .bind(start_date.timestamp())
+        .bind(end_date.timestamp());
+
+    Ok(query.fetch_all().await?)
+}
+
 pub async fn fetch_countries_downloads(
     projects: Vec<ProjectId>,
     start_date: DateTime<Utc>,
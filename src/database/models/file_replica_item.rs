@@ -0,0 +1,181 @@
+use crate::database::models::DatabaseError;
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+
+/// The copy of a stored file on one of the backends of a replicated file host
+pub struct FileReplica {
+    pub file_name: String,
+    pub backend: String,
+    pub file_id: Option<String>,
+    pub content_type: String,
+    pub status: ReplicaStatus,
+    pub updated: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ReplicaStatus {
+    /// The backend holds the current copy of the file
+    Stored,
+    /// The file could not be written to the backend, and should be copied from another one
+    Missing,
+    /// The file could not be deleted from the backend, and the deletion should be retried
+    PendingDelete,
+}
+
+impl ReplicaStatus {
+    pub fn from_string(string: &str) -> ReplicaStatus {
+        match string {
+            "stored" => ReplicaStatus::Stored,
+            "missing" => ReplicaStatus::Missing,
+            "pending_delete" => ReplicaStatus::PendingDelete,
+            _ => ReplicaStatus::Missing,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReplicaStatus::Stored => "stored",
+            ReplicaStatus::Missing => "missing",
+            ReplicaStatus::PendingDelete => "pending_delete",
+        }
+    }
+}
+
+impl FileReplica {
+    pub async fn upsert(
+        &self,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            INSERT INTO file_replicas (file_name, backend, file_id, content_type, status, updated)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (file_name, backend)
+            DO UPDATE SET file_id = EXCLUDED.file_id, content_type = EXCLUDED.content_type,
+                status = EXCLUDED.status, updated = EXCLUDED.updated
+            ",
+            self.file_name,
+            self.backend,
+            self.file_id,
+            self.content_type,
+            self.status.as_str(),
+            self.updated,
+        )
+        .execute(exec)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_file(
+        file_name: &str,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Vec<FileReplica>, DatabaseError> {
+        let replicas = sqlx::query!(
+            "
+            SELECT file_name, backend, file_id, content_type, status, updated
+            FROM file_replicas
+            WHERE file_name = $1
+            ",
+            file_name,
+        )
+        .fetch(exec)
+        .map_ok(|x| FileReplica {
+            file_name: x.file_name,
+            backend: x.backend,
+            file_id: x.file_id,
+            content_type: x.content_type,
+            status: ReplicaStatus::from_string(&x.status),
+            updated: x.updated,
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        Ok(replicas)
+    }
+
+    /// Gets the replicas which are missing or still need to be deleted, least recently
+    /// attempted first
+    pub async fn get_pending(
+        limit: i64,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Vec<FileReplica>, DatabaseError> {
+        let replicas = sqlx::query!(
+            "
+            SELECT file_name, backend, file_id, content_type, status, updated
+            FROM file_replicas
+            WHERE status <> $1
+            ORDER BY updated ASC
+            LIMIT $2
+            ",
+            ReplicaStatus::Stored.as_str(),
+            limit,
+        )
+        .fetch(exec)
+        .map_ok(|x| FileReplica {
+            file_name: x.file_name,
+            backend: x.backend,
+            file_id: x.file_id,
+            content_type: x.content_type,
+            status: ReplicaStatus::from_string(&x.status),
+            updated: x.updated,
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        Ok(replicas)
+    }
+
+    /// Starts tracking files which were stored on `stored_backend` before replication was
+    /// enabled, marking them as missing from every other backend so that they are repaired.
+    /// Files which already have replicas are left alone. `files` holds the name, id and content
+    /// type of each file. Returns the number of replicas which were added.
+    pub async fn backfill(
+        files: &[(String, String, String)],
+        stored_backend: &str,
+        backends: &[String],
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<u64, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            INSERT INTO file_replicas (file_name, backend, file_id, content_type, status)
+            SELECT f.file_name, b.backend, CASE WHEN b.backend = $4 THEN f.file_id END,
+                f.content_type, CASE WHEN b.backend = $4 THEN $6 ELSE $7 END
+            FROM UNNEST ($1::varchar[], $2::varchar[], $3::varchar[]) f (file_name, file_id, content_type)
+            CROSS JOIN UNNEST ($5::varchar[]) b (backend)
+            WHERE NOT EXISTS (SELECT 1 FROM file_replicas r WHERE r.file_name = f.file_name)
+            ON CONFLICT DO NOTHING
+            ",
+            &files.iter().map(|x| x.0.clone()).collect::<Vec<_>>(),
+            &files.iter().map(|x| x.1.clone()).collect::<Vec<_>>(),
+            &files.iter().map(|x| x.2.clone()).collect::<Vec<_>>(),
+            stored_backend,
+            backends,
+            ReplicaStatus::Stored.as_str(),
+            ReplicaStatus::Missing.as_str(),
+        )
+        .execute(exec)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn remove(
+        file_name: &str,
+        backend: &str,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            DELETE FROM file_replicas
+            WHERE file_name = $1 AND backend = $2
+            ",
+            file_name,
+            backend,
+        )
+        .execute(exec)
+        .await?;
+
+        Ok(())
+    }
+}
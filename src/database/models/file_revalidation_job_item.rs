@@ -0,0 +1,233 @@
+use super::ids::FileId;
+use crate::database::models::DatabaseError;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// A run of the current validators over all files which were already uploaded
+#[derive(Serialize, Clone, Debug)]
+pub struct FileRevalidationJob {
+    pub id: i64,
+    pub status: RevalidationJobStatus,
+    /// Whether newly failing primary files are replaced by another passing file of their version
+    pub update_primary: bool,
+    /// Whether moderation messages are posted on the threads of projects with newly failing files
+    pub open_threads: bool,
+    pub files_per_minute: u32,
+    /// The last file which was checked, after which the job resumes
+    pub last_file_id: Option<FileId>,
+    /// The number of files which existed when the job was started
+    pub total_files: i64,
+    pub checked_files: i64,
+    /// Files which passed validation before, but don't anymore
+    pub newly_failing_files: i64,
+    /// Files which couldn't be checked, for example because they couldn't be downloaded
+    pub errored_files: i64,
+    pub created: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+    pub finished: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RevalidationJobStatus {
+    Running,
+    Cancelled,
+    Finished,
+}
+
+impl RevalidationJobStatus {
+    pub fn from_string(string: &str) -> RevalidationJobStatus {
+        match string {
+            "running" => RevalidationJobStatus::Running,
+            "cancelled" => RevalidationJobStatus::Cancelled,
+            "finished" => RevalidationJobStatus::Finished,
+            _ => RevalidationJobStatus::Cancelled,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RevalidationJobStatus::Running => "running",
+            RevalidationJobStatus::Cancelled => "cancelled",
+            RevalidationJobStatus::Finished => "finished",
+        }
+    }
+}
+
+/// The progress made by processing a batch of a job's files
+#[derive(Default)]
+pub struct RevalidationBatchProgress {
+    pub last_file_id: Option<FileId>,
+    pub checked_files: i64,
+    pub newly_failing_files: i64,
+    pub errored_files: i64,
+    /// Whether there are no files left after the batch
+    pub finished: bool,
+}
+
+impl FileRevalidationJob {
+    pub async fn insert(
+        update_primary: bool,
+        open_threads: bool,
+        files_per_minute: u32,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres> + Copy,
+    ) -> Result<FileRevalidationJob, DatabaseError> {
+        let id = sqlx::query!(
+            "
+            INSERT INTO file_revalidation_jobs (status, update_primary, open_threads, files_per_minute, total_files)
+            SELECT $1, $2, $3, $4, COUNT(*) FROM files
+            RETURNING id
+            ",
+            RevalidationJobStatus::Running.as_str(),
+            update_primary,
+            open_threads,
+            files_per_minute as i32,
+        )
+        .fetch_one(exec)
+        .await?
+        .id;
+
+        Self::get(id, exec)
+            .await?
+            .ok_or_else(|| DatabaseError::SchemaError("Inserted job does not exist".to_string()))
+    }
+
+    pub async fn get(
+        id: i64,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Option<FileRevalidationJob>, DatabaseError> {
+        let job = sqlx::query!(
+            "
+            SELECT id, status, update_primary, open_threads, files_per_minute, last_file_id,
+                total_files, checked_files, newly_failing_files, errored_files, created, updated,
+                finished
+            FROM file_revalidation_jobs
+            WHERE id = $1
+            ",
+            id,
+        )
+        .fetch_optional(exec)
+        .await?
+        .map(|x| FileRevalidationJob {
+            id: x.id,
+            status: RevalidationJobStatus::from_string(&x.status),
+            update_primary: x.update_primary,
+            open_threads: x.open_threads,
+            files_per_minute: x.files_per_minute as u32,
+            last_file_id: x.last_file_id.map(FileId),
+            total_files: x.total_files,
+            checked_files: x.checked_files,
+            newly_failing_files: x.newly_failing_files,
+            errored_files: x.errored_files,
+            created: x.created,
+            updated: x.updated,
+            finished: x.finished,
+        });
+
+        Ok(job)
+    }
+
+    /// Gets the id of the job which is running, if there is one
+    pub async fn get_running(
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Option<i64>, DatabaseError> {
+        let id = sqlx::query!(
+            "
+            SELECT id FROM file_revalidation_jobs
+            WHERE status = $1
+            ORDER BY id
+            LIMIT 1
+            ",
+            RevalidationJobStatus::Running.as_str(),
+        )
+        .fetch_optional(exec)
+        .await?
+        .map(|x| x.id);
+
+        Ok(id)
+    }
+
+    /// Locks the running job for `lease`, unless another worker is already processing it
+    pub async fn claim(
+        lease: chrono::Duration,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres> + Copy,
+    ) -> Result<Option<FileRevalidationJob>, DatabaseError> {
+        let id = sqlx::query!(
+            "
+            UPDATE file_revalidation_jobs
+            SET locked_until = NOW() + $2 * INTERVAL '1 second'
+            WHERE id = (
+                SELECT id FROM file_revalidation_jobs
+                WHERE status = $1 AND (locked_until IS NULL OR locked_until < NOW())
+                ORDER BY id
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id
+            ",
+            RevalidationJobStatus::Running.as_str(),
+            lease.num_seconds() as f64,
+        )
+        .fetch_optional(exec)
+        .await?;
+
+        match id {
+            Some(x) => Self::get(x.id, exec).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Records the progress of a processed batch and releases the job's lock
+    pub async fn record_batch(
+        id: i64,
+        progress: &RevalidationBatchProgress,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            UPDATE file_revalidation_jobs
+            SET last_file_id = COALESCE($2, last_file_id),
+                checked_files = checked_files + $3,
+                newly_failing_files = newly_failing_files + $4,
+                errored_files = errored_files + $5,
+                status = CASE WHEN $6 AND status = $7 THEN $8 ELSE status END,
+                finished = CASE WHEN $6 AND status = $7 THEN NOW() ELSE finished END,
+                locked_until = NULL,
+                updated = NOW()
+            WHERE id = $1
+            ",
+            id,
+            progress.last_file_id.map(|x| x.0),
+            progress.checked_files,
+            progress.newly_failing_files,
+            progress.errored_files,
+            progress.finished,
+            RevalidationJobStatus::Running.as_str(),
+            RevalidationJobStatus::Finished.as_str(),
+        )
+        .execute(exec)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_status(
+        id: i64,
+        status: RevalidationJobStatus,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            UPDATE file_revalidation_jobs
+            SET status = $2, updated = NOW()
+            WHERE id = $1
+            ",
+            id,
+            status.as_str(),
+        )
+        .execute(exec)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -49,7 +49,7 @@ macro_rules! generate_bulk_ids {
             count: usize,
             con: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         ) -> Result<Vec<$return_type>, DatabaseError> {
-            let mut rng = rand::thread_rng();
+            let mut rng = ChaCha20Rng::from_entropy();
             let mut retry_count = 0;
 
             // Check if ID is unique
@@ -264,6 +264,22 @@ generate_ids!(
     ChargeId
 );
 
+generate_ids!(
+    pub generate_upload_session_id,
+    UploadSessionId,
+    8,
+    "SELECT EXISTS(SELECT 1 FROM upload_sessions WHERE id=$1)",
+    UploadSessionId
+);
+
+generate_ids!(
+    pub generate_saved_search_id,
+    SavedSearchId,
+    8,
+    "SELECT EXISTS(SELECT 1 FROM saved_searches WHERE id=$1)",
+    SavedSearchId
+);
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Type, Hash, Serialize, Deserialize)]
 #[sqlx(transparent)]
 pub struct UserId(pub i64);
@@ -398,6 +414,14 @@ pub struct UserSubscriptionId(pub i64);
 #[sqlx(transparent)]
 pub struct ChargeId(pub i64);
 
+#[derive(Copy, Clone, Debug, Type, Serialize, Deserialize, Eq, PartialEq, Hash)]
+#[sqlx(transparent)]
+pub struct UploadSessionId(pub i64);
+
+#[derive(Copy, Clone, Debug, Type, Serialize, Deserialize, Eq, PartialEq, Hash)]
+#[sqlx(transparent)]
+pub struct SavedSearchId(pub i64);
+
 use crate::models::ids;
 
 impl From<ids::ProjectId> for ProjectId {
@@ -594,3 +618,25 @@ impl From<ChargeId> for ids::ChargeId {
         ids::ChargeId(id.0 as u64)
     }
 }
+
+impl From<ids::UploadSessionId> for UploadSessionId {
+    fn from(id: ids::UploadSessionId) -> Self {
+        UploadSessionId(id.0 as i64)
+    }
+}
+impl From<UploadSessionId> for ids::UploadSessionId {
+    fn from(id: UploadSessionId) -> Self {
+        ids::UploadSessionId(id.0 as u64)
+    }
+}
+
+impl From<ids::SavedSearchId> for SavedSearchId {
+    fn from(id: ids::SavedSearchId) -> Self {
+        SavedSearchId(id.0 as i64)
+    }
+}
+impl From<SavedSearchId> for ids::SavedSearchId {
+    fn from(id: SavedSearchId) -> Self {
+        ids::SavedSearchId(id.0 as u64)
+    }
+}
@@ -24,6 +24,10 @@ pub struct MinecraftGameVersion {
     pub type_: String,
     pub created: DateTime<Utc>,
     pub major: bool,
+    /// The `pack_format` of resource packs made for this version, if it's known
+    pub resource_pack_format: Option<i32>,
+    /// The `pack_format` of data packs made for this version, if it's known
+    pub data_pack_format: Option<i32>,
 }
 
 impl MinecraftGameVersion {
@@ -121,6 +125,16 @@ impl MinecraftGameVersion {
                 .get("major")
                 .and_then(|x| x.as_bool())
                 .unwrap_or_default(),
+            resource_pack_format: loader_field_enum_value
+                .metadata
+                .get("resource_pack_format")
+                .and_then(|x| x.as_i64())
+                .map(|x| x as i32),
+            data_pack_format: loader_field_enum_value
+                .metadata
+                .get("data_pack_format")
+                .and_then(|x| x.as_i64())
+                .map(|x| x as i32),
         }
     }
 }
@@ -130,6 +144,8 @@ pub struct MinecraftGameVersionBuilder<'a> {
     pub version: Option<&'a str>,
     pub version_type: Option<&'a str>,
     pub date: Option<&'a DateTime<Utc>>,
+    pub resource_pack_format: Option<i32>,
+    pub data_pack_format: Option<i32>,
 }
 
 impl<'a> MinecraftGameVersionBuilder<'a> {
@@ -164,6 +180,22 @@ impl<'a> MinecraftGameVersionBuilder<'a> {
         }
     }
 
+    /// The `pack_format` of resource packs made for this version
+    pub fn resource_pack_format(self, format: i32) -> MinecraftGameVersionBuilder<'a> {
+        Self {
+            resource_pack_format: Some(format),
+            ..self
+        }
+    }
+
+    /// The `pack_format` of data packs made for this version
+    pub fn data_pack_format(self, format: i32) -> MinecraftGameVersionBuilder<'a> {
+        Self {
+            data_pack_format: Some(format),
+            ..self
+        }
+    }
+
     pub async fn insert<'b, E>(
         self,
         exec: E,
@@ -178,25 +210,30 @@ impl<'a> MinecraftGameVersionBuilder<'a> {
                 "Missing loaders field: 'game_versions'".to_string(),
             ))?;
 
-        // Get enum id for game versions
-        let metadata = json!({
-            "type": self.version_type,
+        // Get enum id for game versions. Only the fields which were set are included, so that
+        // they don't replace the existing ones with nulls.
+        let mut metadata = json!({
             "major": false
         });
+        if let Some(version_type) = self.version_type {
+            metadata["type"] = json!(version_type);
+        }
+        if let Some(format) = self.resource_pack_format {
+            metadata["resource_pack_format"] = json!(format);
+        }
+        if let Some(format) = self.data_pack_format {
+            metadata["data_pack_format"] = json!(format);
+        }
 
         // This looks like a mess, but it *should* work
         // This allows game versions to be partially updated without
-        // replacing the unspecified fields with defaults.
+        // replacing the unspecified fields with defaults. `major` is only set for new versions.
         let result = sqlx::query!(
             "
                 INSERT INTO loader_field_enum_values (enum_id, value, created, metadata)
                 VALUES ($1, $2, COALESCE($3, timezone('utc', now())), $4)
                 ON CONFLICT (enum_id, value) DO UPDATE
-                    SET metadata = jsonb_set(
-                        COALESCE(loader_field_enum_values.metadata, $4),
-                        '{type}', 
-                        COALESCE($4->'type', loader_field_enum_values.metadata->'type')
-                    ),
+                    SET metadata = COALESCE(loader_field_enum_values.metadata, $4) || ($4 - 'major'),
                     created = COALESCE($3, loader_field_enum_values.created)
                 RETURNING id
                 ",
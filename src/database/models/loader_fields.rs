@@ -12,6 +12,10 @@ use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
 const GAMES_LIST_NAMESPACE: &str = "games";
+const VALIDATION_POLICIES_NAMESPACE: &str = "validation_policies";
+// Validation policies are edited directly in the database, so they are only cached briefly for
+// edits to apply without the cache being cleared
+const VALIDATION_POLICIES_EXPIRY: i64 = 5 * 60;
 const LOADER_ID: &str = "loader_id";
 const LOADERS_LIST_NAMESPACE: &str = "loaders";
 const LOADER_FIELDS_NAMESPACE: &str = "loader_fields";
@@ -26,6 +30,8 @@ pub struct Game {
     pub name: String,
     pub icon_url: Option<String>,
     pub banner_url: Option<String>,
+    /// The set of validators run on files uploaded for this game, or `none`
+    pub validation_policy: String,
 }
 
 impl Game {
@@ -57,7 +63,7 @@ impl Game {
 
         let result = sqlx::query!(
             "
-            SELECT id, slug, name, icon_url, banner_url FROM games
+            SELECT id, slug, name, icon_url, banner_url, validation_policy FROM games
             ",
         )
         .fetch(exec)
@@ -67,6 +73,7 @@ impl Game {
             name: x.name,
             icon_url: x.icon_url,
             banner_url: x.banner_url,
+            validation_policy: x.validation_policy,
         })
         .try_collect::<Vec<Game>>()
         .await?;
@@ -79,6 +86,66 @@ impl Game {
     }
 }
 
+/// The validators run on the files uploaded for the games which select the policy. Policies
+/// without validators store files as uploaded.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ValidationPolicy {
+    pub name: String,
+    /// The names of the validators, which are further selected by the loaders of the version
+    pub validators: Vec<String>,
+    /// Whether the validators are restricted to some game versions, which requires the
+    /// version's `game_versions` to be resolved
+    pub uses_game_versions: bool,
+    /// The extensions which are accepted, but can't make a file primary, when no validator of
+    /// the version's loaders supports them
+    pub always_allowed_extensions: Vec<String>,
+}
+
+impl ValidationPolicy {
+    pub async fn list<'a, E>(
+        exec: E,
+        redis: &RedisPool,
+    ) -> Result<Vec<ValidationPolicy>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let mut redis = redis.connect().await?;
+        let cached_policies: Option<Vec<ValidationPolicy>> = redis
+            .get_deserialized_from_json(VALIDATION_POLICIES_NAMESPACE, "all")
+            .await?;
+        if let Some(cached_policies) = cached_policies {
+            return Ok(cached_policies);
+        }
+
+        let result = sqlx::query!(
+            "
+            SELECT name, validators, uses_game_versions, always_allowed_extensions
+            FROM validation_policies
+            ",
+        )
+        .fetch(exec)
+        .map_ok(|x| ValidationPolicy {
+            name: x.name,
+            validators: x.validators,
+            uses_game_versions: x.uses_game_versions,
+            always_allowed_extensions: x.always_allowed_extensions,
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        redis
+            .set_serialized_to_json(
+                VALIDATION_POLICIES_NAMESPACE,
+                "all",
+                &result,
+                Some(VALIDATION_POLICIES_EXPIRY),
+            )
+            .await?;
+
+        Ok(result)
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Loader {
     pub id: LoaderId,
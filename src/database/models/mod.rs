@@ -3,11 +3,14 @@ use thiserror::Error;
 pub mod categories;
 pub mod charge_item;
 pub mod collection_item;
+pub mod file_replica_item;
+pub mod file_revalidation_job_item;
 pub mod flow_item;
 pub mod ids;
 pub mod image_item;
 pub mod legacy_loader_fields;
 pub mod loader_fields;
+pub mod modpack_content_item;
 pub mod notification_item;
 pub mod oauth_client_authorization_item;
 pub mod oauth_client_item;
@@ -17,10 +20,15 @@ pub mod pat_item;
 pub mod payout_item;
 pub mod product_item;
 pub mod project_item;
+pub mod project_mod_id_item;
 pub mod report_item;
+pub mod saved_search_item;
+pub mod scan_finding_item;
 pub mod session_item;
+pub mod storage_reconciliation_run_item;
 pub mod team_item;
 pub mod thread_item;
+pub mod upload_session_item;
 pub mod user_item;
 pub mod user_subscription_item;
 pub mod version_item;
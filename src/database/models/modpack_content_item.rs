@@ -0,0 +1,230 @@
+use super::ids::{FileId, ProjectId, UserId, VersionId};
+use super::notification_item::NotificationBuilder;
+use crate::database::models::DatabaseError;
+use crate::database::redis::RedisPool;
+use crate::models::notifications::NotificationBody;
+use crate::models::pack::PackFileStatus;
+use crate::models::projects::{ProjectStatus, VersionStatus};
+use futures::TryStreamExt;
+
+/// A file of an uploaded modpack, resolved against the files hosted on Modrinth
+#[derive(Clone, Debug)]
+pub struct ModpackContent {
+    pub path: String,
+    pub status: PackFileStatus,
+    pub project_id: Option<ProjectId>,
+    pub version_id: Option<VersionId>,
+    pub file_size: u64,
+    pub size_mismatch: bool,
+}
+
+impl ModpackContent {
+    pub async fn insert_many(
+        file_id: FileId,
+        contents: &[ModpackContent],
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        let paths = contents.iter().map(|x| x.path.clone()).collect::<Vec<_>>();
+        let statuses = contents
+            .iter()
+            .map(|x| x.status.as_str().to_string())
+            .collect::<Vec<_>>();
+        let project_ids = contents
+            .iter()
+            .map(|x| x.project_id.map(|x| x.0))
+            .collect::<Vec<_>>();
+        let version_ids = contents
+            .iter()
+            .map(|x| x.version_id.map(|x| x.0))
+            .collect::<Vec<_>>();
+        let file_sizes = contents
+            .iter()
+            .map(|x| x.file_size as i64)
+            .collect::<Vec<_>>();
+        let size_mismatches = contents.iter().map(|x| x.size_mismatch).collect::<Vec<_>>();
+
+        sqlx::query!(
+            "
+            INSERT INTO modpack_contents (file_id, path, status, project_id, version_id, file_size, size_mismatch)
+            SELECT $1, * FROM UNNEST ($2::varchar[], $3::varchar[], $4::bigint[], $5::bigint[], $6::bigint[], $7::boolean[])
+            ",
+            file_id as FileId,
+            &paths[..],
+            &statuses[..],
+            &project_ids[..] as &[Option<i64>],
+            &version_ids[..] as &[Option<i64>],
+            &file_sizes[..],
+            &size_mismatches[..],
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Stores the contents of a modpack file uploaded before the contents of modpacks were
+    /// stored, unless it already has them. Their projects aren't notified, as the modpack may
+    /// have been public for a long time.
+    pub async fn backfill(
+        file_id: FileId,
+        contents: &[ModpackContent],
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        let stored = sqlx::query!(
+            "
+            SELECT EXISTS(SELECT 1 FROM modpack_contents WHERE file_id = $1)
+            ",
+            file_id as FileId
+        )
+        .fetch_one(&mut **transaction)
+        .await?
+        .exists
+        .unwrap_or(false);
+
+        if stored || contents.is_empty() {
+            return Ok(());
+        }
+
+        Self::insert_many(file_id, contents, transaction).await?;
+        sqlx::query!(
+            "
+            UPDATE modpack_contents
+            SET notified = TRUE
+            WHERE file_id = $1
+            ",
+            file_id as FileId
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Gets the contents of the modpack files of a version
+    pub async fn get_version(
+        version_id: VersionId,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Vec<ModpackContent>, DatabaseError> {
+        let contents = sqlx::query!(
+            "
+            SELECT mc.path, mc.status, mc.project_id, mc.version_id, mc.file_size, mc.size_mismatch
+            FROM modpack_contents mc
+            INNER JOIN files f ON f.id = mc.file_id
+            WHERE f.version_id = $1
+            ORDER BY mc.id
+            ",
+            version_id as VersionId,
+        )
+        .fetch(exec)
+        .map_ok(|x| ModpackContent {
+            path: x.path,
+            status: PackFileStatus::from_string(&x.status),
+            project_id: x.project_id.map(ProjectId),
+            version_id: x.version_id.map(VersionId),
+            file_size: x.file_size as u64,
+            size_mismatch: x.size_mismatch,
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        Ok(contents)
+    }
+
+    /// Notifies the teams of projects included in a modpack for the first time. Only public
+    /// versions of a public modpack are considered, so that hidden modpacks aren't revealed, and
+    /// their contents are then marked as notified. This is called whenever a version or the
+    /// modpack may have become public.
+    pub async fn notify_newly_included(
+        modpack_id: ProjectId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        redis: &RedisPool,
+    ) -> Result<(), DatabaseError> {
+        let version_statuses = VersionStatus::iterator()
+            .filter(|x| !x.is_hidden())
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        let project_statuses = ProjectStatus::iterator()
+            .filter(|x| !x.is_hidden())
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+
+        // Each project is attributed to the first public version which included it
+        let included = sqlx::query!(
+            "
+            SELECT DISTINCT ON (mc.project_id) mc.project_id \"project_id!\", v.id version_id
+            FROM modpack_contents mc
+            INNER JOIN files f ON f.id = mc.file_id
+            INNER JOIN versions v ON v.id = f.version_id
+            INNER JOIN mods m ON m.id = v.mod_id
+            WHERE v.mod_id = $1 AND v.status = ANY($2) AND m.status = ANY($3)
+                AND mc.status = $4 AND NOT mc.notified
+                AND mc.project_id IS NOT NULL AND mc.project_id != v.mod_id
+                AND NOT EXISTS(
+                    SELECT 1 FROM modpack_contents pmc
+                    INNER JOIN files pf ON pf.id = pmc.file_id
+                    INNER JOIN versions pv ON pv.id = pf.version_id
+                    WHERE pv.mod_id = v.mod_id AND pmc.notified AND pmc.project_id = mc.project_id
+                )
+            ORDER BY mc.project_id, v.date_published
+            ",
+            modpack_id as ProjectId,
+            &version_statuses,
+            &project_statuses,
+            PackFileStatus::Known.as_str(),
+        )
+        .fetch_all(&mut **transaction)
+        .await?;
+
+        if !included.is_empty() {
+            let members = sqlx::query!(
+                "
+                SELECT DISTINCT m.id project_id, tm.user_id
+                FROM mods m
+                LEFT JOIN organizations o ON o.id = m.organization_id
+                INNER JOIN team_members tm ON tm.team_id = m.team_id OR tm.team_id = o.team_id
+                WHERE m.id = ANY($1) AND tm.accepted = TRUE
+                ",
+                &included.iter().map(|x| x.project_id).collect::<Vec<_>>(),
+            )
+            .fetch_all(&mut **transaction)
+            .await?;
+
+            for included in included {
+                let users = members
+                    .iter()
+                    .filter(|x| x.project_id == included.project_id)
+                    .map(|x| UserId(x.user_id))
+                    .collect::<Vec<_>>();
+
+                NotificationBuilder {
+                    body: NotificationBody::IncludedInModpack {
+                        project_id: ProjectId(included.project_id).into(),
+                        modpack_project_id: modpack_id.into(),
+                        modpack_version_id: VersionId(included.version_id).into(),
+                    },
+                }
+                .insert_many(users, &mut *transaction, redis)
+                .await?;
+            }
+        }
+
+        sqlx::query!(
+            "
+            UPDATE modpack_contents mc
+            SET notified = TRUE
+            FROM files f
+            INNER JOIN versions v ON v.id = f.version_id
+            INNER JOIN mods m ON m.id = v.mod_id
+            WHERE f.id = mc.file_id AND v.mod_id = $1 AND v.status = ANY($2) AND m.status = ANY($3)
+                AND NOT mc.notified
+            ",
+            modpack_id as ProjectId,
+            &version_statuses,
+            &project_statuses,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(())
+    }
+}
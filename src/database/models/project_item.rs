@@ -355,6 +355,16 @@ impl Project {
             .execute(&mut **transaction)
             .await?;
 
+            sqlx::query!(
+                "
+                DELETE FROM project_mod_ids
+                WHERE project_id = $1
+                ",
+                id as ProjectId,
+            )
+            .execute(&mut **transaction)
+            .await?;
+
             models::Thread::remove_full(project.thread_id, transaction).await?;
 
             sqlx::query!(
@@ -0,0 +1,58 @@
+use super::ids::ProjectId;
+use crate::database::models::DatabaseError;
+use futures::TryStreamExt;
+use std::collections::HashMap;
+
+/// The mod ids declared in the manifests of a project's files, used to resolve the
+/// dependencies declared by other mods to projects
+pub struct ProjectModId;
+
+impl ProjectModId {
+    pub async fn insert_many(
+        project_id: ProjectId,
+        mod_ids: &[String],
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            INSERT INTO project_mod_ids (mod_id, project_id)
+            SELECT * FROM UNNEST ($1::varchar[], $2::bigint[])
+            ON CONFLICT DO NOTHING
+            ",
+            mod_ids,
+            &vec![project_id.0; mod_ids.len()],
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Resolves mod ids to the searchable project declaring them. If several projects declare
+    /// the same mod id, the most downloaded one is used.
+    pub async fn get_projects(
+        mod_ids: &[String],
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<HashMap<String, ProjectId>, DatabaseError> {
+        let projects = sqlx::query!(
+            "
+            SELECT DISTINCT ON (pmi.mod_id) pmi.mod_id, pmi.project_id
+            FROM project_mod_ids pmi
+            INNER JOIN mods m ON m.id = pmi.project_id
+            WHERE pmi.mod_id = ANY($1) AND m.status = ANY($2)
+            ORDER BY pmi.mod_id, m.downloads DESC
+            ",
+            mod_ids,
+            &*crate::models::projects::ProjectStatus::iterator()
+                .filter(|x| x.is_searchable())
+                .map(|x| x.to_string())
+                .collect::<Vec<String>>(),
+        )
+        .fetch(exec)
+        .map_ok(|x| (x.mod_id, ProjectId(x.project_id)))
+        .try_collect::<HashMap<_, _>>()
+        .await?;
+
+        Ok(projects)
+    }
+}
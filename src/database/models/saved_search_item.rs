@@ -0,0 +1,207 @@
+use super::ids::*;
+use crate::database::models::DatabaseError;
+use crate::models::projects::SearchRequest;
+use chrono::{DateTime, Utc};
+
+/// A project search which a user saved, to be notified of newly approved projects matching it
+pub struct SavedSearch {
+    pub id: SavedSearchId,
+    pub user_id: UserId,
+    pub name: String,
+    pub request: SearchRequest,
+    pub created: DateTime<Utc>,
+    /// Projects approved up to this time have already been matched against the search
+    pub last_checked: DateTime<Utc>,
+}
+
+impl SavedSearch {
+    pub async fn insert(
+        &self,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            INSERT INTO saved_searches (id, user_id, name, request, created, last_checked)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ",
+            self.id as SavedSearchId,
+            self.user_id as UserId,
+            self.name,
+            serde_json::to_value(&self.request)?,
+            self.created,
+            self.last_checked,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get(
+        id: SavedSearchId,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Option<SavedSearch>, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            SELECT id, user_id, name, request, created, last_checked
+            FROM saved_searches
+            WHERE id = $1
+            ",
+            id as SavedSearchId,
+        )
+        .fetch_optional(exec)
+        .await?;
+
+        result
+            .map(|x| {
+                Ok(SavedSearch {
+                    id: SavedSearchId(x.id),
+                    user_id: UserId(x.user_id),
+                    name: x.name,
+                    request: serde_json::from_value(x.request)?,
+                    created: x.created,
+                    last_checked: x.last_checked,
+                })
+            })
+            .transpose()
+    }
+
+    pub async fn get_many_user(
+        user_id: UserId,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Vec<SavedSearch>, DatabaseError> {
+        let results = sqlx::query!(
+            "
+            SELECT id, user_id, name, request, created, last_checked
+            FROM saved_searches
+            WHERE user_id = $1
+            ORDER BY created
+            ",
+            user_id as UserId,
+        )
+        .fetch_all(exec)
+        .await?;
+
+        results
+            .into_iter()
+            .map(|x| {
+                Ok(SavedSearch {
+                    id: SavedSearchId(x.id),
+                    user_id: UserId(x.user_id),
+                    name: x.name,
+                    request: serde_json::from_value(x.request)?,
+                    created: x.created,
+                    last_checked: x.last_checked,
+                })
+            })
+            .collect()
+    }
+
+    /// The saved searches which haven't been matched against projects approved after `before`
+    pub async fn get_unchecked(
+        before: DateTime<Utc>,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Vec<SavedSearch>, DatabaseError> {
+        let results = sqlx::query!(
+            "
+            SELECT id, user_id, name, request, created, last_checked
+            FROM saved_searches
+            WHERE last_checked < $1
+            ",
+            before,
+        )
+        .fetch_all(exec)
+        .await?;
+
+        results
+            .into_iter()
+            .map(|x| {
+                Ok(SavedSearch {
+                    id: SavedSearchId(x.id),
+                    user_id: UserId(x.user_id),
+                    name: x.name,
+                    request: serde_json::from_value(x.request)?,
+                    created: x.created,
+                    last_checked: x.last_checked,
+                })
+            })
+            .collect()
+    }
+
+    pub async fn count_user(
+        user_id: UserId,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<i64, DatabaseError> {
+        let count = sqlx::query!(
+            "
+            SELECT COUNT(*) FROM saved_searches
+            WHERE user_id = $1
+            ",
+            user_id as UserId,
+        )
+        .fetch_one(exec)
+        .await?
+        .count
+        .unwrap_or(0);
+
+        Ok(count)
+    }
+
+    pub async fn update(
+        &self,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            UPDATE saved_searches
+            SET name = $2, request = $3
+            WHERE id = $1
+            ",
+            self.id as SavedSearchId,
+            self.name,
+            serde_json::to_value(&self.request)?,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Marks saved searches as matched against all projects approved up to `last_checked`
+    pub async fn update_last_checked(
+        ids: &[SavedSearchId],
+        last_checked: DateTime<Utc>,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            UPDATE saved_searches
+            SET last_checked = $2
+            WHERE id = ANY($1)
+            ",
+            &ids.iter().map(|x| x.0).collect::<Vec<_>>(),
+            last_checked,
+        )
+        .execute(exec)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn remove(
+        id: SavedSearchId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Option<()>, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            DELETE FROM saved_searches
+            WHERE id = $1
+            ",
+            id as SavedSearchId,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok((result.rows_affected() > 0).then_some(()))
+    }
+}
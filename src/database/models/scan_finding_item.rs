@@ -0,0 +1,74 @@
+use super::ids::{FileId, VersionId};
+use crate::database::models::DatabaseError;
+use crate::validate::scanner::{ScanFinding, ScanFindingKind};
+use futures::TryStreamExt;
+
+/// Suspicious code found by the scanner in one of a version's files
+pub struct FileScanFinding {
+    pub version_id: VersionId,
+    pub file_name: String,
+    pub finding: ScanFinding,
+}
+
+impl FileScanFinding {
+    pub async fn insert_many(
+        file_id: FileId,
+        findings: &[ScanFinding],
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        let kinds = findings
+            .iter()
+            .map(|x| x.kind.as_str().to_string())
+            .collect::<Vec<_>>();
+        let paths = findings.iter().map(|x| x.path.clone()).collect::<Vec<_>>();
+        let details = findings
+            .iter()
+            .map(|x| x.detail.clone())
+            .collect::<Vec<_>>();
+
+        sqlx::query!(
+            "
+            INSERT INTO file_scan_findings (file_id, kind, path, detail)
+            SELECT $1, * FROM UNNEST ($2::varchar[], $3::varchar[], $4::varchar[])
+            ",
+            file_id as FileId,
+            &kinds[..],
+            &paths[..],
+            &details[..] as &[Option<String>],
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_many_versions(
+        version_ids: &[VersionId],
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Vec<FileScanFinding>, DatabaseError> {
+        let findings = sqlx::query!(
+            "
+            SELECT f.version_id, f.filename, fsf.kind, fsf.path, fsf.detail
+            FROM file_scan_findings fsf
+            INNER JOIN files f ON f.id = fsf.file_id
+            WHERE f.version_id = ANY($1)
+            ORDER BY fsf.id
+            ",
+            &version_ids.iter().map(|x| x.0).collect::<Vec<_>>(),
+        )
+        .fetch(exec)
+        .map_ok(|x| FileScanFinding {
+            version_id: VersionId(x.version_id),
+            file_name: x.filename,
+            finding: ScanFinding {
+                kind: ScanFindingKind::from_string(&x.kind),
+                path: x.path,
+                detail: x.detail,
+            },
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        Ok(findings)
+    }
+}
@@ -0,0 +1,143 @@
+use crate::database::models::DatabaseError;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// A run of the reconciliation of storage with the database, and the files it found
+#[derive(Serialize, Clone, Debug)]
+pub struct StorageReconciliationRun {
+    pub id: i64,
+    pub status: ReconciliationRunStatus,
+    /// If set, orphaned files older than this many hours were deleted
+    pub purge_grace_period_hours: Option<u32>,
+    /// The report of the run, once it finished
+    pub report: Option<serde_json::Value>,
+    /// Why the run failed, if it did
+    pub error: Option<String>,
+    pub started: DateTime<Utc>,
+    pub finished: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReconciliationRunStatus {
+    Running,
+    Finished,
+    Failed,
+}
+
+impl ReconciliationRunStatus {
+    pub fn from_string(string: &str) -> ReconciliationRunStatus {
+        match string {
+            "running" => ReconciliationRunStatus::Running,
+            "finished" => ReconciliationRunStatus::Finished,
+            _ => ReconciliationRunStatus::Failed,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReconciliationRunStatus::Running => "running",
+            ReconciliationRunStatus::Finished => "finished",
+            ReconciliationRunStatus::Failed => "failed",
+        }
+    }
+}
+
+impl StorageReconciliationRun {
+    pub async fn insert(
+        purge_grace_period_hours: Option<u32>,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<i64, DatabaseError> {
+        let id = sqlx::query!(
+            "
+            INSERT INTO storage_reconciliation_runs (status, purge_grace_period_hours)
+            VALUES ($1, $2)
+            RETURNING id
+            ",
+            ReconciliationRunStatus::Running.as_str(),
+            purge_grace_period_hours.map(|x| x as i32),
+        )
+        .fetch_one(exec)
+        .await?
+        .id;
+
+        Ok(id)
+    }
+
+    pub async fn get(
+        id: i64,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Option<StorageReconciliationRun>, DatabaseError> {
+        let run = sqlx::query!(
+            "
+            SELECT id, status, purge_grace_period_hours, report, error, started, finished
+            FROM storage_reconciliation_runs
+            WHERE id = $1
+            ",
+            id,
+        )
+        .fetch_optional(exec)
+        .await?
+        .map(|x| StorageReconciliationRun {
+            id: x.id,
+            status: ReconciliationRunStatus::from_string(&x.status),
+            purge_grace_period_hours: x.purge_grace_period_hours.map(|x| x as u32),
+            report: x.report,
+            error: x.error,
+            started: x.started,
+            finished: x.finished,
+        });
+
+        Ok(run)
+    }
+
+    /// Gets the ids of the most recent runs, newest first
+    pub async fn get_latest_ids(
+        limit: i64,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Vec<i64>, DatabaseError> {
+        let ids = sqlx::query!(
+            "
+            SELECT id FROM storage_reconciliation_runs
+            ORDER BY id DESC
+            LIMIT $1
+            ",
+            limit,
+        )
+        .fetch_all(exec)
+        .await?
+        .into_iter()
+        .map(|x| x.id)
+        .collect();
+
+        Ok(ids)
+    }
+
+    /// Records the outcome of a run, either its report or why it failed
+    pub async fn finish(
+        id: i64,
+        outcome: Result<serde_json::Value, String>,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        let (status, report, error) = match outcome {
+            Ok(report) => (ReconciliationRunStatus::Finished, Some(report), None),
+            Err(error) => (ReconciliationRunStatus::Failed, None, Some(error)),
+        };
+
+        sqlx::query!(
+            "
+            UPDATE storage_reconciliation_runs
+            SET status = $2, report = $3, error = $4, finished = NOW()
+            WHERE id = $1
+            ",
+            id,
+            status.as_str(),
+            report,
+            error,
+        )
+        .execute(exec)
+        .await?;
+
+        Ok(())
+    }
+}
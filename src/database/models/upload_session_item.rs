@@ -0,0 +1,251 @@
+use super::ids::*;
+use crate::database::models::DatabaseError;
+use chrono::{DateTime, Utc};
+
+pub struct UploadSession {
+    pub id: UploadSessionId,
+    pub user_id: UserId,
+    pub file_name: String,
+    pub file_size: i64,
+    pub chunk_size: i64,
+    pub created: DateTime<Utc>,
+    pub expires: DateTime<Utc>,
+}
+
+pub struct UploadSessionChunk {
+    pub session_id: UploadSessionId,
+    pub chunk_index: i32,
+    pub file_id: String,
+    pub file_name: String,
+    pub size: i64,
+}
+
+impl UploadSession {
+    pub fn chunk_count(&self) -> u32 {
+        ((self.file_size + self.chunk_size - 1) / self.chunk_size) as u32
+    }
+
+    /// The expected size of the chunk at `index`, or `None` if it is out of range
+    pub fn expected_chunk_size(&self, index: u32) -> Option<i64> {
+        if index >= self.chunk_count() {
+            return None;
+        }
+
+        let offset = index as i64 * self.chunk_size;
+        Some((self.file_size - offset).min(self.chunk_size))
+    }
+
+    /// A new storage path for a temporary object holding the chunk at `index`. Every upload of
+    /// a chunk gets its own path, so that deleting the object of an earlier upload of the chunk
+    /// never deletes the one which replaced it.
+    pub fn chunk_path(&self, index: u32) -> String {
+        format!(
+            "uploads/{}/{}-{}",
+            crate::models::ids::UploadSessionId::from(self.id),
+            index,
+            uuid::Uuid::new_v4()
+        )
+    }
+
+    pub async fn insert(
+        &self,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            INSERT INTO upload_sessions (id, user_id, file_name, file_size, chunk_size, created, expires)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ",
+            self.id as UploadSessionId,
+            self.user_id as UserId,
+            self.file_name,
+            self.file_size,
+            self.chunk_size,
+            self.created,
+            self.expires,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get(
+        id: UploadSessionId,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Option<UploadSession>, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            SELECT id, user_id, file_name, file_size, chunk_size, created, expires
+            FROM upload_sessions
+            WHERE id = $1
+            ",
+            id as UploadSessionId,
+        )
+        .fetch_optional(exec)
+        .await?;
+
+        Ok(result.map(|x| UploadSession {
+            id: UploadSessionId(x.id),
+            user_id: UserId(x.user_id),
+            file_name: x.file_name,
+            file_size: x.file_size,
+            chunk_size: x.chunk_size,
+            created: x.created,
+            expires: x.expires,
+        }))
+    }
+
+    /// Gets a session and locks it until the transaction ends, so that concurrent requests
+    /// wait for each other instead of both finalizing the session
+    pub async fn get_for_update(
+        id: UploadSessionId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Option<UploadSession>, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            SELECT id, user_id, file_name, file_size, chunk_size, created, expires
+            FROM upload_sessions
+            WHERE id = $1
+            FOR UPDATE
+            ",
+            id as UploadSessionId,
+        )
+        .fetch_optional(&mut **transaction)
+        .await?;
+
+        Ok(result.map(|x| UploadSession {
+            id: UploadSessionId(x.id),
+            user_id: UserId(x.user_id),
+            file_name: x.file_name,
+            file_size: x.file_size,
+            chunk_size: x.chunk_size,
+            created: x.created,
+            expires: x.expires,
+        }))
+    }
+
+    pub async fn get_chunks(
+        id: UploadSessionId,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Vec<UploadSessionChunk>, DatabaseError> {
+        let chunks = sqlx::query!(
+            "
+            SELECT session_id, chunk_index, file_id, file_name, size
+            FROM upload_session_chunks
+            WHERE session_id = $1
+            ORDER BY chunk_index
+            ",
+            id as UploadSessionId,
+        )
+        .fetch_all(exec)
+        .await?
+        .into_iter()
+        .map(|x| UploadSessionChunk {
+            session_id: UploadSessionId(x.session_id),
+            chunk_index: x.chunk_index,
+            file_id: x.file_id,
+            file_name: x.file_name,
+            size: x.size,
+        })
+        .collect();
+
+        Ok(chunks)
+    }
+
+    /// Records a received chunk, extending the session's expiry. Returns the chunk it
+    /// replaced, if the same chunk had already been uploaded.
+    pub async fn upsert_chunk(
+        &self,
+        chunk: &UploadSessionChunk,
+        expires: DateTime<Utc>,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Option<UploadSessionChunk>, DatabaseError> {
+        let previous = sqlx::query!(
+            "
+            DELETE FROM upload_session_chunks
+            WHERE session_id = $1 AND chunk_index = $2
+            RETURNING file_id, file_name, size
+            ",
+            self.id as UploadSessionId,
+            chunk.chunk_index,
+        )
+        .fetch_optional(&mut **transaction)
+        .await?;
+
+        sqlx::query!(
+            "
+            INSERT INTO upload_session_chunks (session_id, chunk_index, file_id, file_name, size)
+            VALUES ($1, $2, $3, $4, $5)
+            ",
+            self.id as UploadSessionId,
+            chunk.chunk_index,
+            chunk.file_id,
+            chunk.file_name,
+            chunk.size,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        sqlx::query!(
+            "
+            UPDATE upload_sessions
+            SET expires = $1
+            WHERE id = $2
+            ",
+            expires,
+            self.id as UploadSessionId,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(previous.map(|x| UploadSessionChunk {
+            session_id: self.id,
+            chunk_index: chunk.chunk_index,
+            file_id: x.file_id,
+            file_name: x.file_name,
+            size: x.size,
+        }))
+    }
+
+    /// Removes the session and its chunk records. The chunk objects themselves must be
+    /// deleted from the file host by the caller.
+    pub async fn remove(
+        id: UploadSessionId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Option<()>, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            DELETE FROM upload_sessions
+            WHERE id = $1
+            ",
+            id as UploadSessionId,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(if result.rows_affected() > 0 {
+            Some(())
+        } else {
+            None
+        })
+    }
+
+    pub async fn get_expired(
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Vec<UploadSessionId>, DatabaseError> {
+        let ids = sqlx::query!(
+            "
+            SELECT id FROM upload_sessions
+            WHERE expires < CURRENT_TIMESTAMP
+            "
+        )
+        .fetch_all(exec)
+        .await?
+        .into_iter()
+        .map(|x| UploadSessionId(x.id))
+        .collect();
+
+        Ok(ids)
+    }
+}
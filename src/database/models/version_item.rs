@@ -4,8 +4,11 @@ use super::DatabaseError;
 use crate::database::models::loader_fields::{
     QueryLoaderField, QueryLoaderFieldEnumValue, QueryVersionField,
 };
+use crate::database::models::modpack_content_item::ModpackContent;
+use crate::database::models::scan_finding_item::FileScanFinding;
 use crate::database::redis::RedisPool;
-use crate::models::projects::{FileType, VersionStatus};
+use crate::models::projects::{FileType, ValidationReport, VersionStatus};
+use crate::validate::scanner::ScanFinding;
 use chrono::{DateTime, Utc};
 use dashmap::{DashMap, DashSet};
 use futures::TryStreamExt;
@@ -122,8 +125,12 @@ pub struct VersionFileBuilder {
     pub filename: String,
     pub hashes: Vec<HashBuilder>,
     pub primary: bool,
-    pub size: u32,
+    pub size: u64,
     pub file_type: Option<FileType>,
+    pub validation: ValidationReport,
+    pub scan_findings: Vec<ScanFinding>,
+    /// The files of a modpack, resolved against the files hosted on Modrinth
+    pub pack_contents: Vec<ModpackContent>,
 }
 
 impl VersionFileBuilder {
@@ -136,16 +143,17 @@ impl VersionFileBuilder {
 
         sqlx::query!(
             "
-            INSERT INTO files (id, version_id, url, filename, is_primary, size, file_type)
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            INSERT INTO files (id, version_id, url, filename, is_primary, size, file_type, validation_report)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
             ",
             file_id as FileId,
             version_id as VersionId,
             self.url,
             self.filename,
             self.primary,
-            self.size as i32,
+            self.size as i64,
             self.file_type.map(|x| x.as_str()),
+            serde_json::to_value(&self.validation).ok(),
         )
         .execute(&mut **transaction)
         .await?;
@@ -164,6 +172,14 @@ impl VersionFileBuilder {
             .await?;
         }
 
+        if !self.scan_findings.is_empty() {
+            FileScanFinding::insert_many(file_id, &self.scan_findings, transaction).await?;
+        }
+
+        if !self.pack_contents.is_empty() {
+            ModpackContent::insert_many(file_id, &self.pack_contents, transaction).await?;
+        }
+
         Ok(file_id)
     }
 }
@@ -364,6 +380,34 @@ impl Version {
         .execute(&mut **transaction)
         .await?;
 
+        sqlx::query!(
+            "
+            DELETE FROM file_scan_findings
+            WHERE EXISTS(
+                SELECT 1 FROM files WHERE
+                    (files.version_id = $1) AND
+                    (file_scan_findings.file_id = files.id)
+            )
+            ",
+            id as VersionId
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        sqlx::query!(
+            "
+            DELETE FROM modpack_contents
+            WHERE EXISTS(
+                SELECT 1 FROM files WHERE
+                    (files.version_id = $1) AND
+                    (modpack_contents.file_id = files.id)
+            )
+            ",
+            id as VersionId
+        )
+        .execute(&mut **transaction)
+        .await?;
+
         sqlx::query!(
             "
             DELETE FROM hashes
@@ -617,15 +661,16 @@ impl Version {
                     pub url: String,
                     pub filename: String,
                     pub primary: bool,
-                    pub size: u32,
+                    pub size: u64,
                     pub file_type: Option<FileType>,
+                    pub validation: ValidationReport,
                 }
 
                 let file_ids = DashSet::new();
                 let reverse_file_map = DashMap::new();
                 let files : DashMap<VersionId, Vec<File>> = sqlx::query!(
                     "
-                    SELECT DISTINCT version_id, f.id, f.url, f.filename, f.is_primary, f.size, f.file_type
+                    SELECT DISTINCT version_id, f.id, f.url, f.filename, f.is_primary, f.size, f.file_type, f.validation_report
                     FROM files f
                     WHERE f.version_id = ANY($1)
                     ",
@@ -637,8 +682,11 @@ impl Version {
                             url: m.url,
                             filename: m.filename,
                             primary: m.is_primary,
-                            size: m.size as u32,
+                            size: m.size as u64,
                             file_type: m.file_type.map(|x| FileType::from_string(&x)),
+                            validation: m.validation_report
+                                .and_then(|x| serde_json::from_value(x).ok())
+                                .unwrap_or_default(),
                         };
 
                         file_ids.insert(FileId(m.id));
@@ -765,6 +813,7 @@ impl Version {
                                         primary: x.primary,
                                         size: x.size,
                                         file_type: x.file_type,
+                                        validation: x.validation.clone(),
                                     }
                                 }).collect::<Vec<_>>();
 
@@ -872,7 +921,7 @@ impl Version {
                                 filename: f.filename,
                                 hashes,
                                 primary: f.is_primary,
-                                size: f.size as u32,
+                                size: f.size as u64,
                                 file_type: f.file_type.map(|x| FileType::from_string(&x)),
                             };
 
@@ -909,6 +958,42 @@ impl Version {
             .await?;
         Ok(())
     }
+
+    /// Clears the cache of several versions by their ids, for when they aren't loaded
+    pub async fn clear_cache_many(
+        version_ids: &[VersionId],
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+        redis: &RedisPool,
+    ) -> Result<(), DatabaseError> {
+        let hashes = sqlx::query!(
+            "
+            SELECT h.algorithm, encode(h.hash, 'escape') hash
+            FROM hashes h
+            INNER JOIN files f ON f.id = h.file_id
+            WHERE f.version_id = ANY($1)
+            ",
+            &version_ids.iter().map(|x| x.0).collect::<Vec<_>>(),
+        )
+        .fetch_all(exec)
+        .await?;
+
+        let mut redis = redis.connect().await?;
+
+        redis
+            .delete_many(
+                version_ids
+                    .iter()
+                    .map(|id| (VERSIONS_NAMESPACE, Some(id.0.to_string())))
+                    .chain(hashes.into_iter().filter_map(|x| {
+                        Some((
+                            VERSION_FILES_NAMESPACE,
+                            Some(format!("{}_{}", x.algorithm, x.hash?)),
+                        ))
+                    })),
+            )
+            .await?;
+        Ok(())
+    }
 }
 
 #[derive(Clone, Deserialize, Serialize, PartialEq, Eq)]
@@ -938,8 +1023,10 @@ pub struct QueryFile {
     pub filename: String,
     pub hashes: HashMap<String, String>,
     pub primary: bool,
-    pub size: u32,
+    pub size: u64,
     pub file_type: Option<FileType>,
+    #[serde(default)]
+    pub validation: ValidationReport,
 }
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -951,7 +1038,7 @@ pub struct SingleFile {
     pub filename: String,
     pub hashes: HashMap<String, String>,
     pub primary: bool,
-    pub size: u32,
+    pub size: u64,
     pub file_type: Option<FileType>,
 }
 
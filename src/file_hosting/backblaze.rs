@@ -1,17 +1,27 @@
-use super::{DeleteFileData, FileHost, FileHostingError, UploadFileData};
+use super::{
+    DeleteFileData, FileHasher, FileHost, FileHostingError, FileListPage, FileMetadata, FileStream,
+    UploadFileData,
+};
 use async_trait::async_trait;
 use bytes::Bytes;
+use chrono::{TimeZone, Utc};
+use futures::StreamExt;
 use reqwest::Response;
 use serde::Deserialize;
 use sha2::Digest;
+use std::collections::HashMap;
+use tokio_stream::wrappers::ReceiverStream;
 
 mod authorization;
 mod delete;
+mod download;
+mod list;
 mod upload;
 
 pub struct BackblazeHost {
     upload_url_data: authorization::UploadUrlData,
     authorization_data: authorization::AuthorizationData,
+    bucket_name: String,
 }
 
 impl BackblazeHost {
@@ -20,10 +30,14 @@ impl BackblazeHost {
         let upload_url_data = authorization::get_upload_url(&authorization_data, bucket_id)
             .await
             .unwrap();
+        let bucket = authorization::get_bucket(&authorization_data, bucket_id)
+            .await
+            .unwrap();
 
         BackblazeHost {
             upload_url_data,
             authorization_data,
+            bucket_name: bucket.bucket_name,
         }
     }
 }
@@ -52,22 +66,67 @@ impl FileHost for BackblazeHost {
         })
     }
 
-    /*
-        async fn upload_file_streaming(
-            &self,
-            content_type: &str,
-            file_name: &str,
-            stream: reqwest::Body
-        ) -> Result<UploadFileData, FileHostingError> {
-            use futures::stream::StreamExt;
+    async fn upload_file_streaming(
+        &self,
+        content_type: &str,
+        file_name: &str,
+        content_length: u64,
+        mut stream: FileStream,
+    ) -> Result<UploadFileData, FileHostingError> {
+        let (sender, receiver) = tokio::sync::mpsc::channel::<Result<Bytes, FileHostingError>>(4);
 
-            let mut data = Vec::new();
+        // Chunks are hashed as they are forwarded to Backblaze, and the SHA1 is sent last
+        let forward = async move {
+            let mut hasher = FileHasher::new();
             while let Some(chunk) = stream.next().await {
-                data.extend_from_slice(&chunk.map_err(|e| FileHostingError::Other(e))?);
+                let chunk = chunk?;
+                hasher.update(&chunk);
+                if sender.send(Ok(chunk)).await.is_err() {
+                    break;
+                }
             }
-            self.upload_file(content_type, file_name, data).await
-        }
-    */
+
+            let hashes = hasher.finish();
+            let _ = sender
+                .send(Ok(Bytes::from(hashes.sha1.clone().into_bytes())))
+                .await;
+            Ok::<_, FileHostingError>(hashes)
+        };
+        let upload = upload::upload_file_streaming(
+            &self.upload_url_data,
+            content_type,
+            file_name,
+            content_length,
+            reqwest::Body::wrap_stream(ReceiverStream::new(receiver)),
+        );
+
+        let (hashes, upload_data) = futures::join!(forward, upload);
+        let (hashes, upload_data) = (hashes?, upload_data?);
+
+        Ok(UploadFileData {
+            file_id: upload_data.file_id,
+            file_name: upload_data.file_name,
+            content_length: hashes.length,
+            content_sha512: hashes.sha512,
+            content_sha1: hashes.sha1,
+            content_md5: upload_data.content_md5,
+            content_type: upload_data.content_type,
+            upload_timestamp: upload_data.upload_timestamp,
+        })
+    }
+
+    async fn download_file(
+        &self,
+        file_id: &str,
+        _file_name: &str,
+    ) -> Result<FileStream, FileHostingError> {
+        let response = download::download_file_by_id(&self.authorization_data, file_id).await?;
+
+        Ok(response
+            .bytes_stream()
+            .map(|chunk| chunk.map_err(FileHostingError::from))
+            .boxed())
+    }
 
     async fn delete_file_version(
         &self,
@@ -81,6 +140,123 @@ impl FileHost for BackblazeHost {
             file_name: delete_data.file_name,
         })
     }
+
+    async fn list_files(
+        &self,
+        prefix: &str,
+        cursor: Option<String>,
+    ) -> Result<FileListPage, FileHostingError> {
+        let list_data = list::list_file_names(
+            &self.authorization_data,
+            &self.upload_url_data.bucket_id,
+            prefix,
+            cursor.as_deref(),
+            1000,
+        )
+        .await?;
+
+        Ok(FileListPage {
+            files: list_data
+                .files
+                .into_iter()
+                .filter_map(file_metadata)
+                .collect(),
+            next_cursor: list_data.next_file_name,
+        })
+    }
+
+    async fn stat_file(&self, file_name: &str) -> Result<Option<FileMetadata>, FileHostingError> {
+        let list_data = list::list_file_names(
+            &self.authorization_data,
+            &self.upload_url_data.bucket_id,
+            file_name,
+            Some(file_name),
+            1,
+        )
+        .await?;
+
+        Ok(list_data
+            .files
+            .into_iter()
+            .filter(|x| x.file_name == file_name)
+            .find_map(file_metadata))
+    }
+
+    async fn get_signed_url(
+        &self,
+        file_name: &str,
+        expires_in: std::time::Duration,
+    ) -> Result<String, FileHostingError> {
+        Ok(self
+            .get_signed_urls(&[file_name.to_string()], expires_in)
+            .await?
+            .remove(0))
+    }
+
+    // Downloads are authorized by file name prefix, so files in the same directory, such as
+    // those of a version, share a single authorization
+    async fn get_signed_urls(
+        &self,
+        file_names: &[String],
+        expires_in: std::time::Duration,
+    ) -> Result<Vec<String>, FileHostingError> {
+        let mut authorizations: HashMap<&str, String> = HashMap::new();
+        let mut urls = Vec::with_capacity(file_names.len());
+
+        for file_name in file_names {
+            let prefix = match file_name.rfind('/') {
+                Some(i) => &file_name[..=i],
+                None => file_name.as_str(),
+            };
+
+            let authorization = match authorizations.get(prefix) {
+                Some(authorization) => authorization.clone(),
+                None => {
+                    let authorization = download::get_download_authorization(
+                        &self.authorization_data,
+                        &self.upload_url_data.bucket_id,
+                        prefix,
+                        expires_in.as_secs(),
+                    )
+                    .await?
+                    .authorization_token;
+                    authorizations.insert(prefix, authorization.clone());
+                    authorization
+                }
+            };
+
+            urls.push(format!(
+                "{}/file/{}/{}?Authorization={}",
+                self.authorization_data.download_url,
+                self.bucket_name,
+                file_name
+                    .split('/')
+                    .map(|x| urlencoding::encode(x).into_owned())
+                    .collect::<Vec<_>>()
+                    .join("/"),
+                urlencoding::encode(&authorization)
+            ));
+        }
+
+        Ok(urls)
+    }
+}
+
+// Only uploaded files are returned, not hidden files or unfinished large files
+fn file_metadata(file: list::FileNameData) -> Option<FileMetadata> {
+    if file.action != "upload" {
+        return None;
+    }
+
+    Some(FileMetadata {
+        file_id: file.file_id?,
+        file_name: file.file_name,
+        content_length: file.content_length,
+        last_modified: Utc
+            .timestamp_millis_opt(file.upload_timestamp)
+            .single()
+            .unwrap_or_else(Utc::now),
+    })
 }
 
 pub async fn process_response<T>(response: Response) -> Result<T, FileHostingError>
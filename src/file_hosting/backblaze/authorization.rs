@@ -73,3 +73,49 @@ pub async fn get_upload_url(
 
     super::process_response(response).await
 }
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BucketData {
+    pub bucket_id: String,
+    pub bucket_name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ListBucketsData {
+    pub buckets: Vec<BucketData>,
+}
+
+pub async fn get_bucket(
+    authorization_data: &AuthorizationData,
+    bucket_id: &str,
+) -> Result<BucketData, FileHostingError> {
+    let response = reqwest::Client::new()
+        .post(format!("{}/b2api/v2/b2_list_buckets", authorization_data.api_url).to_string())
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(
+            reqwest::header::AUTHORIZATION,
+            &authorization_data.authorization_token,
+        )
+        .body(
+            serde_json::json!({
+                "accountId": authorization_data.account_id,
+                "bucketId": bucket_id,
+            })
+            .to_string(),
+        )
+        .send()
+        .await?;
+
+    let list_data: ListBucketsData = super::process_response(response).await?;
+    list_data
+        .buckets
+        .into_iter()
+        .find(|x| x.bucket_id == bucket_id)
+        .ok_or_else(|| {
+            FileHostingError::BackblazeError(serde_json::json!({
+                "message": format!("Bucket {bucket_id} does not exist"),
+            }))
+        })
+}
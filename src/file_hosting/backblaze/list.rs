@@ -0,0 +1,52 @@
+use super::authorization::AuthorizationData;
+use crate::file_hosting::FileHostingError;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ListFileNamesData {
+    pub files: Vec<FileNameData>,
+    pub next_file_name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FileNameData {
+    pub file_id: Option<String>,
+    pub file_name: String,
+    pub content_length: u64,
+    pub upload_timestamp: i64,
+    pub action: String,
+}
+
+pub async fn list_file_names(
+    authorization_data: &AuthorizationData,
+    bucket_id: &str,
+    prefix: &str,
+    start_file_name: Option<&str>,
+    max_file_count: u32,
+) -> Result<ListFileNamesData, FileHostingError> {
+    let response = reqwest::Client::new()
+        .post(format!(
+            "{}/b2api/v2/b2_list_file_names",
+            authorization_data.api_url
+        ))
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(
+            reqwest::header::AUTHORIZATION,
+            &authorization_data.authorization_token,
+        )
+        .body(
+            serde_json::json!({
+                "bucketId": bucket_id,
+                "prefix": prefix,
+                "startFileName": start_file_name,
+                "maxFileCount": max_file_count,
+            })
+            .to_string(),
+        )
+        .send()
+        .await?;
+
+    super::process_response(response).await
+}
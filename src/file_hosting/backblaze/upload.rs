@@ -10,7 +10,7 @@ pub struct UploadFileData {
     pub file_name: String,
     pub account_id: String,
     pub bucket_id: String,
-    pub content_length: u32,
+    pub content_length: u64,
     pub content_sha1: String,
     pub content_md5: Option<String>,
     pub content_type: String,
@@ -43,3 +43,29 @@ pub async fn upload_file(
 
     super::process_response(response).await
 }
+
+/// Uploads a file without knowing its SHA1 ahead of time. The hex digest of the file must be
+/// appended to the end of `body`, and `content_length` excludes those trailing 40 bytes.
+pub async fn upload_file_streaming(
+    url_data: &UploadUrlData,
+    content_type: &str,
+    file_name: &str,
+    content_length: u64,
+    body: reqwest::Body,
+) -> Result<UploadFileData, FileHostingError> {
+    let response = reqwest::Client::new()
+        .post(&url_data.upload_url)
+        .header(
+            reqwest::header::AUTHORIZATION,
+            &url_data.authorization_token,
+        )
+        .header("X-Bz-File-Name", file_name)
+        .header(reqwest::header::CONTENT_TYPE, content_type)
+        .header(reqwest::header::CONTENT_LENGTH, content_length + 40)
+        .header("X-Bz-Content-Sha1", "hex_digits_at_end")
+        .body(body)
+        .send()
+        .await?;
+
+    super::process_response(response).await
+}
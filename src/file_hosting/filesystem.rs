@@ -0,0 +1,483 @@
+use super::{
+    DeleteFileData, FileHasher, FileHost, FileHostingError, FileListPage, FileMetadata, FileStream,
+    UploadFileData,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::Utc;
+use futures::StreamExt;
+use std::io::ErrorKind;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Component, Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use tokio_util::io::ReaderStream;
+
+/// A content-addressed file host which stores files on the local filesystem.
+///
+/// The contents of each file are stored once in `blobs`, named by their SHA-512 hash. Every
+/// file name under `files` is a hard link to its blob, so identical files uploaded under
+/// several names only take up disk space once, and the link count of a blob is its reference
+/// count. A blob is removed when the last file name linking to it is deleted.
+///
+/// The CDN should serve the `files` directory. Only one instance may use a storage directory
+/// at a time.
+pub struct FilesystemHost {
+    root: PathBuf,
+    // Serializes changes to the links of blobs, so that a blob is never removed while a new
+    // file name is being linked to it
+    links: Mutex<()>,
+}
+
+impl FilesystemHost {
+    pub fn new(root: &str) -> Result<Self, FileHostingError> {
+        let root = PathBuf::from(root);
+        for dir in ["files", "blobs", "tmp"] {
+            std::fs::create_dir_all(root.join(dir))?;
+        }
+
+        // Remove temporary files left behind by interrupted uploads
+        for entry in std::fs::read_dir(root.join("tmp"))? {
+            std::fs::remove_file(entry?.path())?;
+        }
+
+        Ok(FilesystemHost {
+            root,
+            links: Mutex::new(()),
+        })
+    }
+
+    fn file_path(&self, file_name: &str) -> Result<PathBuf, FileHostingError> {
+        let path = Path::new(file_name);
+        if file_name.is_empty()
+            || path
+                .components()
+                .any(|x| !matches!(x, Component::Normal(_)))
+        {
+            return Err(FileHostingError::InvalidFilename);
+        }
+
+        Ok(self.root.join("files").join(path))
+    }
+
+    fn blob_path(&self, sha512: &str) -> PathBuf {
+        self.root
+            .join("blobs")
+            .join(&sha512[..2])
+            .join(&sha512[2..4])
+            .join(sha512)
+    }
+
+    fn temp_path(&self) -> PathBuf {
+        self.root.join("tmp").join(uuid::Uuid::new_v4().to_string())
+    }
+
+    /// Finds the blob which a file links to by hashing the file
+    async fn find_blob(&self, path: &Path) -> Result<Option<PathBuf>, FileHostingError> {
+        let metadata = tokio::fs::metadata(path).await?;
+
+        let mut stream = ReaderStream::new(tokio::fs::File::open(path).await?);
+        let mut hasher = FileHasher::new();
+        while let Some(chunk) = stream.next().await {
+            hasher.update(&chunk?);
+        }
+
+        let blob = self.blob_path(&hasher.finish().sha512);
+        Ok(if is_same_file(&metadata, &blob).await? {
+            Some(blob)
+        } else {
+            None
+        })
+    }
+
+    /// Moves a newly written file into its blob, unless the blob already exists, and links
+    /// the file name to it, replacing any previous file with that name
+    async fn link_blob(
+        &self,
+        temp_path: &Path,
+        blob: &Path,
+        path: &Path,
+    ) -> Result<(), FileHostingError> {
+        if !tokio::fs::try_exists(blob).await? {
+            if let Some(parent) = blob.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::rename(temp_path, blob).await?;
+        }
+
+        let previous = match tokio::fs::metadata(path).await {
+            Ok(metadata) => {
+                if is_same_file(&metadata, blob).await? {
+                    return Ok(());
+                }
+
+                self.find_blob(path).await?
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        // Link through a temporary name, so an existing file is replaced atomically
+        let link_path = self.temp_path();
+        tokio::fs::hard_link(blob, &link_path).await?;
+        if let Err(err) = tokio::fs::rename(&link_path, path).await {
+            let _ = tokio::fs::remove_file(&link_path).await;
+            return Err(err.into());
+        }
+
+        if let Some(previous) = previous {
+            self.release_blob(&previous).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes a blob if no file names link to it anymore
+    async fn release_blob(&self, blob: &Path) -> Result<(), FileHostingError> {
+        if tokio::fs::metadata(blob).await?.nlink() <= 1 {
+            tokio::fs::remove_file(blob).await?;
+        }
+
+        Ok(())
+    }
+}
+
+fn is_sha512(hash: &str) -> bool {
+    hash.len() == 128 && hash.bytes().all(|x| x.is_ascii_hexdigit())
+}
+
+async fn is_same_file(
+    metadata: &std::fs::Metadata,
+    other: &Path,
+) -> Result<bool, FileHostingError> {
+    match tokio::fs::metadata(other).await {
+        Ok(other) => Ok(metadata.dev() == other.dev() && metadata.ino() == other.ino()),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[async_trait]
+impl FileHost for FilesystemHost {
+    async fn upload_file(
+        &self,
+        content_type: &str,
+        file_name: &str,
+        file_bytes: Bytes,
+    ) -> Result<UploadFileData, FileHostingError> {
+        let content_length = file_bytes.len() as u64;
+
+        self.upload_file_streaming(
+            content_type,
+            file_name,
+            content_length,
+            futures::stream::once(async { Ok(file_bytes) }).boxed(),
+        )
+        .await
+    }
+
+    async fn upload_file_streaming(
+        &self,
+        content_type: &str,
+        file_name: &str,
+        _content_length: u64,
+        mut stream: FileStream,
+    ) -> Result<UploadFileData, FileHostingError> {
+        let path = self.file_path(file_name)?;
+
+        // Write to a temporary file first, so a failed upload never leaves a partial file behind
+        let temp_path = self.temp_path();
+        let mut hasher = FileHasher::new();
+
+        let result = async {
+            let mut file = tokio::fs::File::create(&temp_path).await?;
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk?;
+                hasher.update(&chunk);
+                file.write_all(&chunk).await?;
+            }
+            file.sync_all().await?;
+
+            Ok::<_, FileHostingError>(())
+        }
+        .await;
+
+        if let Err(err) = result {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return Err(err);
+        }
+
+        let hashes = hasher.finish();
+        let blob = self.blob_path(&hashes.sha512);
+
+        {
+            let _links = self.links.lock().await;
+            let result = self.link_blob(&temp_path, &blob, &path).await;
+
+            // The temporary file is left over if the blob was already stored
+            if tokio::fs::try_exists(&temp_path).await.unwrap_or(false) {
+                let _ = tokio::fs::remove_file(&temp_path).await;
+            }
+            result?;
+        }
+
+        Ok(UploadFileData {
+            file_id: hashes.sha512.clone(),
+            file_name: file_name.to_string(),
+            content_length: hashes.length,
+            content_sha512: hashes.sha512,
+            content_sha1: hashes.sha1,
+            content_md5: None,
+            content_type: content_type.to_string(),
+            upload_timestamp: Utc::now().timestamp() as u64,
+        })
+    }
+
+    async fn download_file(
+        &self,
+        _file_id: &str,
+        file_name: &str,
+    ) -> Result<FileStream, FileHostingError> {
+        let file = tokio::fs::File::open(self.file_path(file_name)?).await?;
+
+        Ok(ReaderStream::new(file)
+            .map(|chunk| chunk.map_err(FileHostingError::from))
+            .boxed())
+    }
+
+    async fn delete_file_version(
+        &self,
+        file_id: &str,
+        file_name: &str,
+    ) -> Result<DeleteFileData, FileHostingError> {
+        let path = self.file_path(file_name)?;
+
+        let _links = self.links.lock().await;
+        let metadata = match tokio::fs::metadata(&path).await {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                return Ok(DeleteFileData {
+                    file_id: file_id.to_string(),
+                    file_name: file_name.to_string(),
+                })
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        // The file id is the hash of the uploaded version, so a file which was replaced since
+        // is left alone. Files listed without an id are deleted whatever their contents.
+        let blob = if is_sha512(file_id) {
+            let blob = self.blob_path(file_id);
+            if !is_same_file(&metadata, &blob).await? {
+                return Ok(DeleteFileData {
+                    file_id: file_id.to_string(),
+                    file_name: file_name.to_string(),
+                });
+            }
+
+            Some(blob)
+        } else {
+            self.find_blob(&path).await?
+        };
+
+        tokio::fs::remove_file(&path).await?;
+        if let Some(blob) = blob {
+            self.release_blob(&blob).await?;
+        }
+
+        Ok(DeleteFileData {
+            file_id: file_id.to_string(),
+            file_name: file_name.to_string(),
+        })
+    }
+
+    // All files are listed in a single page, as the directory is walked in one go
+    async fn list_files(
+        &self,
+        prefix: &str,
+        _cursor: Option<String>,
+    ) -> Result<FileListPage, FileHostingError> {
+        let root = self.root.join("files");
+        let prefix = prefix.to_string();
+        let files = actix_rt::task::spawn_blocking(move || super::list_local_files(&root, &prefix))
+            .await
+            .map_err(std::io::Error::other)??;
+
+        Ok(FileListPage {
+            files,
+            next_cursor: None,
+        })
+    }
+
+    async fn stat_file(&self, file_name: &str) -> Result<Option<FileMetadata>, FileHostingError> {
+        match tokio::fs::metadata(self.file_path(file_name)?).await {
+            Ok(metadata) if metadata.is_file() => Ok(Some(super::local_file_metadata(
+                file_name.to_string(),
+                file_name.to_string(),
+                &metadata,
+            ))),
+            Ok(_) => Ok(None),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn get_signed_url(
+        &self,
+        file_name: &str,
+        expires_in: std::time::Duration,
+    ) -> Result<String, FileHostingError> {
+        super::get_local_signed_url(file_name, expires_in)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestHost {
+        root: PathBuf,
+        host: FilesystemHost,
+    }
+
+    impl TestHost {
+        fn new() -> Self {
+            let root =
+                std::env::temp_dir().join(format!("labrinth-filesystem-{}", uuid::Uuid::new_v4()));
+            let host = FilesystemHost::new(root.to_str().unwrap()).unwrap();
+
+            TestHost { root, host }
+        }
+
+        fn blob_count(&self) -> usize {
+            fn count(path: &Path) -> usize {
+                std::fs::read_dir(path)
+                    .unwrap()
+                    .map(|x| {
+                        let path = x.unwrap().path();
+                        if path.is_dir() {
+                            count(&path)
+                        } else {
+                            1
+                        }
+                    })
+                    .sum()
+            }
+
+            count(&self.root.join("blobs"))
+        }
+
+        fn read(&self, file_name: &str) -> Vec<u8> {
+            std::fs::read(self.root.join("files").join(file_name)).unwrap()
+        }
+    }
+
+    impl Drop for TestHost {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.root);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn identical_files_share_a_blob() {
+        let test = TestHost::new();
+        let contents = Bytes::from_static(b"identical contents");
+
+        let first = test
+            .host
+            .upload_file(
+                "application/java-archive",
+                "data/a/mod.jar",
+                contents.clone(),
+            )
+            .await
+            .unwrap();
+        let second = test
+            .host
+            .upload_file(
+                "application/java-archive",
+                "data/b/mod.jar",
+                contents.clone(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(first.file_id, second.file_id);
+        assert_eq!(test.blob_count(), 1);
+
+        test.host
+            .delete_file_version(&first.file_id, &first.file_name)
+            .await
+            .unwrap();
+        assert_eq!(test.blob_count(), 1);
+        assert_eq!(test.read("data/b/mod.jar"), contents);
+
+        // Deleting without a file id finds the blob by hashing the file
+        test.host
+            .delete_file_version("", &second.file_name)
+            .await
+            .unwrap();
+        assert_eq!(test.blob_count(), 0);
+    }
+
+    #[actix_rt::test]
+    async fn overwriting_a_file_releases_its_blob() {
+        let test = TestHost::new();
+
+        test.host
+            .upload_file("text/plain", "file.txt", Bytes::from_static(b"first"))
+            .await
+            .unwrap();
+        let second = test
+            .host
+            .upload_file("text/plain", "file.txt", Bytes::from_static(b"second"))
+            .await
+            .unwrap();
+        assert_eq!(test.blob_count(), 1);
+        assert_eq!(test.read("file.txt"), b"second");
+
+        test.host
+            .delete_file_version(&second.file_id, &second.file_name)
+            .await
+            .unwrap();
+        assert_eq!(test.blob_count(), 0);
+    }
+
+    #[actix_rt::test]
+    async fn deleting_a_replaced_version_keeps_the_file() {
+        let test = TestHost::new();
+
+        let first = test
+            .host
+            .upload_file("text/plain", "file.txt", Bytes::from_static(b"first"))
+            .await
+            .unwrap();
+        test.host
+            .upload_file("text/plain", "file.txt", Bytes::from_static(b"second"))
+            .await
+            .unwrap();
+
+        test.host
+            .delete_file_version(&first.file_id, &first.file_name)
+            .await
+            .unwrap();
+        assert_eq!(test.blob_count(), 1);
+        assert_eq!(test.read("file.txt"), b"second");
+    }
+
+    #[actix_rt::test]
+    async fn rejects_file_names_outside_the_storage_directory() {
+        let test = TestHost::new();
+
+        for file_name in ["../escape.txt", "/etc/passwd", "data/../../escape.txt", ""] {
+            let result = test
+                .host
+                .upload_file("text/plain", file_name, Bytes::from_static(b"contents"))
+                .await;
+            assert!(matches!(result, Err(FileHostingError::InvalidFilename)));
+        }
+    }
+}
@@ -1,8 +1,14 @@
-use super::{DeleteFileData, FileHost, FileHostingError, UploadFileData};
+use super::{
+    DeleteFileData, FileHasher, FileHost, FileHostingError, FileListPage, FileMetadata, FileStream,
+    UploadFileData,
+};
 use async_trait::async_trait;
 use bytes::Bytes;
 use chrono::Utc;
+use futures::StreamExt;
 use sha2::Digest;
+use tokio::io::AsyncWriteExt;
+use tokio_util::io::ReaderStream;
 
 #[derive(Default)]
 pub struct MockHost(());
@@ -13,6 +19,11 @@ impl MockHost {
     }
 }
 
+fn get_file_path(file_name: &str) -> std::path::PathBuf {
+    std::path::Path::new(&dotenvy::var("MOCK_FILE_PATH").unwrap())
+        .join(file_name.replace("../", ""))
+}
+
 #[async_trait]
 impl FileHost for MockHost {
     async fn upload_file(
@@ -21,17 +32,16 @@ impl FileHost for MockHost {
         file_name: &str,
         file_bytes: Bytes,
     ) -> Result<UploadFileData, FileHostingError> {
-        let path = std::path::Path::new(&dotenvy::var("MOCK_FILE_PATH").unwrap())
-            .join(file_name.replace("../", ""));
-        std::fs::create_dir_all(path.parent().ok_or(FileHostingError::InvalidFilename)?)?;
+        let path = get_file_path(file_name);
+        tokio::fs::create_dir_all(path.parent().ok_or(FileHostingError::InvalidFilename)?).await?;
         let content_sha1 = sha1::Sha1::from(&file_bytes).hexdigest();
         let content_sha512 = format!("{:x}", sha2::Sha512::digest(&file_bytes));
 
-        std::fs::write(path, &*file_bytes)?;
+        tokio::fs::write(path, &file_bytes).await?;
         Ok(UploadFileData {
             file_id: String::from("MOCK_FILE_ID"),
             file_name: file_name.to_string(),
-            content_length: file_bytes.len() as u32,
+            content_length: file_bytes.len() as u64,
             content_sha512,
             content_sha1,
             content_md5: None,
@@ -40,19 +50,115 @@ impl FileHost for MockHost {
         })
     }
 
+    async fn upload_file_streaming(
+        &self,
+        content_type: &str,
+        file_name: &str,
+        _content_length: u64,
+        mut stream: FileStream,
+    ) -> Result<UploadFileData, FileHostingError> {
+        let path = get_file_path(file_name);
+        let parent = path.parent().ok_or(FileHostingError::InvalidFilename)?;
+        tokio::fs::create_dir_all(parent).await?;
+
+        // Write to a temporary file first, so a failed upload never leaves a partial file behind
+        let temp_path = parent.join(format!(".{}.part", uuid::Uuid::new_v4()));
+        let mut hasher = FileHasher::new();
+
+        let result = async {
+            let mut file = tokio::fs::File::create(&temp_path).await?;
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk?;
+                hasher.update(&chunk);
+                file.write_all(&chunk).await?;
+            }
+            file.flush().await?;
+
+            Ok::<_, FileHostingError>(())
+        }
+        .await;
+
+        if let Err(err) = result {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return Err(err);
+        }
+        tokio::fs::rename(&temp_path, &path).await?;
+
+        let hashes = hasher.finish();
+        Ok(UploadFileData {
+            file_id: String::from("MOCK_FILE_ID"),
+            file_name: file_name.to_string(),
+            content_length: hashes.length,
+            content_sha512: hashes.sha512,
+            content_sha1: hashes.sha1,
+            content_md5: None,
+            content_type: content_type.to_string(),
+            upload_timestamp: Utc::now().timestamp() as u64,
+        })
+    }
+
+    async fn download_file(
+        &self,
+        _file_id: &str,
+        file_name: &str,
+    ) -> Result<FileStream, FileHostingError> {
+        let file = tokio::fs::File::open(get_file_path(file_name)).await?;
+
+        Ok(ReaderStream::new(file)
+            .map(|chunk| chunk.map_err(FileHostingError::from))
+            .boxed())
+    }
+
     async fn delete_file_version(
         &self,
         file_id: &str,
         file_name: &str,
     ) -> Result<DeleteFileData, FileHostingError> {
-        let path = std::path::Path::new(&dotenvy::var("MOCK_FILE_PATH").unwrap())
-            .join(file_name.replace("../", ""));
-        if path.exists() {
-            std::fs::remove_file(path)?;
+        let path = get_file_path(file_name);
+        if tokio::fs::try_exists(&path).await? {
+            tokio::fs::remove_file(path).await?;
         }
         Ok(DeleteFileData {
             file_id: file_id.to_string(),
             file_name: file_name.to_string(),
         })
     }
+
+    async fn list_files(
+        &self,
+        prefix: &str,
+        _cursor: Option<String>,
+    ) -> Result<FileListPage, FileHostingError> {
+        let root = std::path::PathBuf::from(dotenvy::var("MOCK_FILE_PATH").unwrap());
+        let prefix = prefix.to_string();
+        let files = actix_rt::task::spawn_blocking(move || super::list_local_files(&root, &prefix))
+            .await
+            .map_err(std::io::Error::other)??;
+
+        Ok(FileListPage {
+            files,
+            next_cursor: None,
+        })
+    }
+
+    async fn stat_file(&self, file_name: &str) -> Result<Option<FileMetadata>, FileHostingError> {
+        match tokio::fs::metadata(get_file_path(file_name)).await {
+            Ok(metadata) if metadata.is_file() => Ok(Some(super::local_file_metadata(
+                String::from("MOCK_FILE_ID"),
+                file_name.to_string(),
+                &metadata,
+            ))),
+            Ok(_) => Ok(None),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn get_signed_url(
+        &self,
+        file_name: &str,
+        expires_in: std::time::Duration,
+    ) -> Result<String, FileHostingError> {
+        super::get_local_signed_url(file_name, expires_in)
+    }
 }
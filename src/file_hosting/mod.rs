@@ -1,13 +1,24 @@
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::stream::BoxStream;
+use hmac::{Hmac, Mac, NewMac};
+use sha2::{Digest, Sha256};
 use thiserror::Error;
 
 mod backblaze;
+#[cfg(unix)]
+mod filesystem;
 mod mock;
+mod replicated;
 mod s3_host;
+pub mod visibility;
 
 pub use backblaze::BackblazeHost;
 use bytes::Bytes;
+#[cfg(unix)]
+pub use filesystem::FilesystemHost;
 pub use mock::MockHost;
+pub use replicated::{ReplicaBackend, ReplicatedHost};
 pub use s3_host::S3Host;
 
 #[derive(Error, Debug)]
@@ -22,13 +33,24 @@ pub enum FileHostingError {
     FileSystemError(#[from] std::io::Error),
     #[error("Invalid Filename")]
     InvalidFilename,
+    #[error("Error while signing file URL")]
+    SigningError,
+    #[error("Environment error in file hosting: {0}")]
+    EnvError(#[from] dotenvy::Error),
+    #[error("Database error in file hosting: {0}")]
+    DatabaseError(#[from] crate::database::models::DatabaseError),
+    #[error("File is not available from any storage backend")]
+    Unavailable,
 }
 
+/// A stream of file contents, used for uploads which should not be buffered in memory
+pub type FileStream = BoxStream<'static, Result<Bytes, FileHostingError>>;
+
 #[derive(Debug, Clone)]
 pub struct UploadFileData {
     pub file_id: String,
     pub file_name: String,
-    pub content_length: u32,
+    pub content_length: u64,
     pub content_sha512: String,
     pub content_sha1: String,
     pub content_md5: Option<String>,
@@ -36,14 +58,90 @@ pub struct UploadFileData {
     pub upload_timestamp: u64,
 }
 
+/// The metadata of a file in storage
+#[derive(Debug, Clone)]
+pub struct FileMetadata {
+    pub file_id: String,
+    pub file_name: String,
+    pub content_length: u64,
+    pub last_modified: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileListPage {
+    pub files: Vec<FileMetadata>,
+    /// The cursor to list the next page with, or `None` if this is the last page
+    pub next_cursor: Option<String>,
+}
+
+/// The outcome of a run of [`FileHost::repair_replicas`]
+#[derive(Debug, Clone, Default)]
+pub struct ReplicationReport {
+    /// Files which were copied to a replica missing them
+    pub repaired: u64,
+    /// Files which were deleted from a replica after an earlier deletion failed
+    pub deleted: u64,
+    /// Files which could not be repaired, and will be retried
+    pub failed: u64,
+}
+
 #[derive(Debug, Clone)]
 pub struct DeleteFileData {
     pub file_id: String,
     pub file_name: String,
 }
 
+/// Incrementally computes the hashes and length of a file as its chunks are received
+pub struct FileHasher {
+    sha1: sha1::Sha1,
+    sha512: sha2::Sha512,
+    length: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileHashes {
+    pub sha1: String,
+    pub sha512: String,
+    pub length: u64,
+}
+
+impl Default for FileHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileHasher {
+    pub fn new() -> Self {
+        FileHasher {
+            sha1: sha1::Sha1::new(),
+            sha512: sha2::Sha512::new(),
+            length: 0,
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.sha1.update(data);
+        self.sha512.update(data);
+        self.length += data.len() as u64;
+    }
+
+    /// The number of bytes hashed so far
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    pub fn finish(self) -> FileHashes {
+        FileHashes {
+            sha1: self.sha1.hexdigest(),
+            sha512: format!("{:x}", self.sha512.finalize()),
+            length: self.length,
+        }
+    }
+}
+
 #[async_trait]
-pub trait FileHost {
+pub trait FileHost: Sync {
     async fn upload_file(
         &self,
         content_type: &str,
@@ -51,9 +149,256 @@ pub trait FileHost {
         file_bytes: Bytes,
     ) -> Result<UploadFileData, FileHostingError>;
 
+    /// Uploads a file from a stream of chunks without buffering it in memory.
+    /// `content_length` must be the exact length of the streamed file.
+    async fn upload_file_streaming(
+        &self,
+        content_type: &str,
+        file_name: &str,
+        content_length: u64,
+        stream: FileStream,
+    ) -> Result<UploadFileData, FileHostingError>;
+
+    /// Streams back the contents of a previously uploaded file
+    async fn download_file(
+        &self,
+        file_id: &str,
+        file_name: &str,
+    ) -> Result<FileStream, FileHostingError>;
+
     async fn delete_file_version(
         &self,
         file_id: &str,
         file_name: &str,
     ) -> Result<DeleteFileData, FileHostingError>;
+
+    /// Lists a page of the stored files whose names start with `prefix`. `cursor` is the
+    /// `next_cursor` of the previous page, or `None` to start from the first page.
+    async fn list_files(
+        &self,
+        prefix: &str,
+        cursor: Option<String>,
+    ) -> Result<FileListPage, FileHostingError>;
+
+    /// Gets the metadata of a stored file, or `None` if it does not exist
+    async fn stat_file(&self, file_name: &str) -> Result<Option<FileMetadata>, FileHostingError>;
+
+    /// Gets a URL through which a file can be downloaded until `expires_in` has passed. This
+    /// is used instead of the permanent CDN URL for files which are not public.
+    async fn get_signed_url(
+        &self,
+        file_name: &str,
+        expires_in: std::time::Duration,
+    ) -> Result<String, FileHostingError>;
+
+    /// Gets signed URLs for several files at once, in the same order. Hosts which have to
+    /// authorize each download with their backend can batch the requests.
+    async fn get_signed_urls(
+        &self,
+        file_names: &[String],
+        expires_in: std::time::Duration,
+    ) -> Result<Vec<String>, FileHostingError> {
+        let mut urls = Vec::with_capacity(file_names.len());
+        for file_name in file_names {
+            urls.push(self.get_signed_url(file_name, expires_in).await?);
+        }
+
+        Ok(urls)
+    }
+
+    /// Copies files to the replicas which are missing them, and retries deletions which
+    /// failed on a replica. Hosts without replicas have nothing to repair.
+    async fn repair_replicas(&self) -> Result<ReplicationReport, FileHostingError> {
+        Ok(ReplicationReport::default())
+    }
+
+    /// Starts replicating listed files which were stored before replication was enabled, so
+    /// that [`FileHost::repair_replicas`] copies them to every backend. Returns the number of
+    /// files which weren't replicated yet. Hosts without replicas have nothing to backfill.
+    async fn backfill_replicas(&self, _files: &[FileMetadata]) -> Result<u64, FileHostingError> {
+        Ok(0)
+    }
+}
+
+/// Gets the type of a backend of a replicated file host from its id, along with the prefix of
+/// the variables it is configured with. The type of a backend is set by `STORAGE_{ID}_TYPE`
+/// and its variables are prefixed with `STORAGE_{ID}_`, so that several backends of the same
+/// type can be configured. If the type isn't set, the id is the type, and the backend is
+/// configured with the same variables as when it's the only backend.
+pub fn get_replica_backend_config(id: &str) -> (String, String) {
+    let prefix = format!("STORAGE_{}_", id.to_uppercase());
+
+    match dotenvy::var(format!("{prefix}TYPE")) {
+        Ok(backend_type) => (backend_type, prefix),
+        Err(_) => (id.to_string(), String::new()),
+    }
+}
+
+/// Gets the name of a stored file from its CDN URL, or `None` if the URL is not on the CDN
+pub fn get_file_name_from_url(cdn_url: &str, url: &str) -> Option<String> {
+    let path = url.strip_prefix(&format!("{}/", cdn_url.trim_end_matches('/')))?;
+
+    Some(
+        urlencoding::decode(path)
+            .map(|x| x.into_owned())
+            .unwrap_or_else(|_| path.to_string()),
+    )
+}
+
+// How long signed URLs to files which are not public can be used for
+pub const SIGNED_URL_EXPIRY: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Replaces the CDN URL of a stored file with an expiring signed URL. URLs which are not on
+/// the CDN are returned as they are.
+///
+/// Files of hidden versions are stored under [`visibility::PRIVATE_PREFIX`], which the CDN
+/// doesn't serve, so the path of a signed URL can't be used to download the file from the CDN.
+pub async fn sign_cdn_url(
+    file_host: &dyn FileHost,
+    cdn_url: &str,
+    url: &str,
+) -> Result<String, FileHostingError> {
+    Ok(sign_cdn_urls(file_host, cdn_url, &[url.to_string()])
+        .await?
+        .remove(0))
+}
+
+/// Replaces the CDN URLs of several stored files with expiring signed URLs, as
+/// [`sign_cdn_url`] does, signing them in a single batch
+pub async fn sign_cdn_urls(
+    file_host: &dyn FileHost,
+    cdn_url: &str,
+    urls: &[String],
+) -> Result<Vec<String>, FileHostingError> {
+    let file_names = urls
+        .iter()
+        .filter_map(|x| get_file_name_from_url(cdn_url, x))
+        .collect::<Vec<_>>();
+    let mut signed = file_host
+        .get_signed_urls(&file_names, SIGNED_URL_EXPIRY)
+        .await?
+        .into_iter();
+
+    Ok(urls
+        .iter()
+        .map(|url| match get_file_name_from_url(cdn_url, url) {
+            Some(_) => signed.next().unwrap_or_default(),
+            None => url.clone(),
+        })
+        .collect())
+}
+
+fn local_url_signature(file_name: &str, expires: i64) -> Result<Hmac<Sha256>, FileHostingError> {
+    let mut mac: Hmac<Sha256> =
+        Hmac::new_from_slice(dotenvy::var("FILE_SIGNING_SECRET")?.as_bytes())
+            .map_err(|_| FileHostingError::SigningError)?;
+    mac.update(format!("{expires}:{file_name}").as_bytes());
+
+    Ok(mac)
+}
+
+/// Signs a URL through which a file on a local host can be downloaded from labrinth. Local
+/// hosts can't sign URLs themselves, so the file is streamed back by `/v3/file/{file_name}`.
+fn get_local_signed_url(
+    file_name: &str,
+    expires_in: std::time::Duration,
+) -> Result<String, FileHostingError> {
+    let expires = Utc::now().timestamp() + expires_in.as_secs() as i64;
+    let signature = local_url_signature(file_name, expires)?
+        .finalize()
+        .into_bytes();
+
+    Ok(format!(
+        "{}/v3/file/{}?expires={}&signature={}",
+        dotenvy::var("SELF_ADDR")?,
+        file_name
+            .split('/')
+            .map(|x| urlencoding::encode(x).into_owned())
+            .collect::<Vec<_>>()
+            .join("/"),
+        expires,
+        hex::encode(signature)
+    ))
+}
+
+/// Checks the signature of a URL created by a local host, and that it has not expired
+pub fn verify_local_signed_url(file_name: &str, expires: i64, signature: &str) -> bool {
+    if expires < Utc::now().timestamp() {
+        return false;
+    }
+
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+
+    local_url_signature(file_name, expires)
+        .map(|mac| mac.verify(&signature).is_ok())
+        .unwrap_or(false)
+}
+
+/// Lists the files under a local directory whose names, relative to it, start with `prefix`
+fn list_local_files(
+    root: &std::path::Path,
+    prefix: &str,
+) -> Result<Vec<FileMetadata>, FileHostingError> {
+    let mut files = Vec::new();
+    let mut directories = vec![root.to_path_buf()];
+
+    while let Some(directory) = directories.pop() {
+        let entries = match std::fs::read_dir(&directory) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err.into()),
+        };
+
+        for entry in entries {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                directories.push(path);
+                continue;
+            }
+
+            // Skip the temporary files of uploads in progress
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+
+            let Ok(relative) = path.strip_prefix(root) else {
+                continue;
+            };
+            let file_name = relative
+                .components()
+                .map(|x| x.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            if file_name.starts_with(prefix) {
+                files.push(local_file_metadata(
+                    file_name.clone(),
+                    file_name,
+                    &entry.metadata()?,
+                ));
+            }
+        }
+    }
+
+    files.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    Ok(files)
+}
+
+fn local_file_metadata(
+    file_id: String,
+    file_name: String,
+    metadata: &std::fs::Metadata,
+) -> FileMetadata {
+    FileMetadata {
+        file_id,
+        file_name,
+        content_length: metadata.len(),
+        last_modified: metadata
+            .modified()
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_else(|_| Utc::now()),
+    }
 }
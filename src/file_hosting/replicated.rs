@@ -0,0 +1,499 @@
+use super::{
+    DeleteFileData, FileHost, FileHostingError, FileListPage, FileMetadata, FileStream,
+    ReplicationReport, UploadFileData,
+};
+use crate::database::models::file_replica_item::{FileReplica, ReplicaStatus};
+use crate::util::ext::{get_image_content_type, project_file_type};
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::Utc;
+use futures::StreamExt;
+use log::warn;
+use sqlx::PgPool;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio_util::io::ReaderStream;
+
+// The number of pending replicas handled by each run of the repair task
+const REPAIR_BATCH_SIZE: i64 = 1000;
+
+/// A backend of a replicated file host
+pub struct ReplicaBackend {
+    /// The configured id of the backend, which its replicas are recorded under. Backends of
+    /// the same type have distinct ids.
+    pub id: String,
+    pub host: Arc<dyn FileHost + Send + Sync>,
+}
+
+/// A file host which writes every file to a primary backend and one or more secondary
+/// backends, so that an outage of one backend does not take down uploads or downloads.
+///
+/// The status of every file on every backend is recorded in the `file_replicas` table. Writes
+/// succeed as long as one backend accepts them; backends which failed are marked as missing
+/// the file, and [`FileHost::repair_replicas`] later copies it to them from a backend which
+/// has it. Reads try the primary first and fail over to the secondaries.
+///
+/// Listing and stat-ing files only use the primary. Files uploaded before replication was
+/// enabled have no recorded replicas, and are only read from and deleted on the primary until
+/// [`FileHost::backfill_replicas`] starts replicating them.
+pub struct ReplicatedHost {
+    pool: PgPool,
+    backends: Vec<ReplicaBackend>,
+}
+
+impl ReplicatedHost {
+    pub fn new(pool: PgPool, primary: ReplicaBackend, secondaries: Vec<ReplicaBackend>) -> Self {
+        let mut backends = vec![primary];
+        backends.extend(secondaries);
+
+        ReplicatedHost { pool, backends }
+    }
+
+    fn primary(&self) -> &ReplicaBackend {
+        &self.backends[0]
+    }
+
+    fn backend(&self, id: &str) -> Option<&ReplicaBackend> {
+        self.backends.iter().find(|x| x.id == id)
+    }
+
+    /// The backends which hold a file, primary first, along with the file's id on each
+    async fn stored_backends(
+        &self,
+        file_id: &str,
+        file_name: &str,
+    ) -> Result<Vec<(&ReplicaBackend, String)>, FileHostingError> {
+        let replicas = FileReplica::get_file(file_name, &self.pool).await?;
+        if replicas.is_empty() {
+            return Ok(vec![(self.primary(), file_id.to_string())]);
+        }
+
+        Ok(self
+            .backends
+            .iter()
+            .filter_map(|backend| {
+                let replica = replicas
+                    .iter()
+                    .find(|x| x.backend == backend.id && x.status == ReplicaStatus::Stored)?;
+                Some((backend, replica.file_id.clone().unwrap_or_default()))
+            })
+            .collect())
+    }
+
+    /// Records the outcome of writing a file to each backend, and returns the upload data of
+    /// the first backend which succeeded
+    async fn record_upload(
+        &self,
+        content_type: &str,
+        file_name: &str,
+        results: Vec<Result<UploadFileData, FileHostingError>>,
+    ) -> Result<UploadFileData, FileHostingError> {
+        let mut upload_data = None;
+        let mut first_error = None;
+
+        for (backend, result) in self.backends.iter().zip(results) {
+            let (file_id, status) = match result {
+                Ok(data) => {
+                    let file_id = data.file_id.clone();
+                    upload_data.get_or_insert(data);
+                    (Some(file_id), ReplicaStatus::Stored)
+                }
+                Err(err) => {
+                    warn!(
+                        "Uploading file {} to storage backend {} failed: {:?}",
+                        file_name, backend.id, err
+                    );
+                    first_error.get_or_insert(err);
+                    (None, ReplicaStatus::Missing)
+                }
+            };
+
+            FileReplica {
+                file_name: file_name.to_string(),
+                backend: backend.id.clone(),
+                file_id,
+                content_type: content_type.to_string(),
+                status,
+                updated: Utc::now(),
+            }
+            .upsert(&self.pool)
+            .await?;
+        }
+
+        match (upload_data, first_error) {
+            (Some(data), _) => Ok(data),
+            (None, Some(err)) => Err(err),
+            (None, None) => Err(FileHostingError::Unavailable),
+        }
+    }
+
+    /// Copies a file which is missing from a backend from one which has it
+    async fn repair_missing(
+        &self,
+        replica: &FileReplica,
+        backend: &ReplicaBackend,
+    ) -> Result<(), FileHostingError> {
+        let sources = self
+            .stored_backends("", &replica.file_name)
+            .await?
+            .into_iter()
+            .filter(|(source, _)| source.id != backend.id);
+
+        let mut last_error = None;
+        for (source, file_id) in sources {
+            let result = async {
+                let metadata = source
+                    .host
+                    .stat_file(&replica.file_name)
+                    .await?
+                    .ok_or(FileHostingError::Unavailable)?;
+                let stream = source
+                    .host
+                    .download_file(&file_id, &replica.file_name)
+                    .await?;
+
+                backend
+                    .host
+                    .upload_file_streaming(
+                        &replica.content_type,
+                        &replica.file_name,
+                        metadata.content_length,
+                        stream,
+                    )
+                    .await
+            }
+            .await;
+
+            match result {
+                Ok(data) => {
+                    FileReplica {
+                        file_name: replica.file_name.clone(),
+                        backend: backend.id.clone(),
+                        file_id: Some(data.file_id),
+                        content_type: replica.content_type.clone(),
+                        status: ReplicaStatus::Stored,
+                        updated: Utc::now(),
+                    }
+                    .upsert(&self.pool)
+                    .await?;
+
+                    return Ok(());
+                }
+                Err(err) => last_error = Some(err),
+            }
+        }
+
+        Err(last_error.unwrap_or(FileHostingError::Unavailable))
+    }
+
+    /// Writes a stream to a temporary file, so it can be uploaded to each backend in turn
+    async fn spool(mut stream: FileStream) -> Result<PathBuf, FileHostingError> {
+        let path = std::env::temp_dir().join(format!("labrinth-replica-{}", uuid::Uuid::new_v4()));
+
+        let result = async {
+            let mut file = tokio::fs::File::create(&path).await?;
+            while let Some(chunk) = stream.next().await {
+                file.write_all(&chunk?).await?;
+            }
+            file.flush().await?;
+
+            Ok::<_, FileHostingError>(())
+        }
+        .await;
+
+        match result {
+            Ok(()) => Ok(path),
+            Err(err) => {
+                let _ = tokio::fs::remove_file(&path).await;
+                Err(err)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl FileHost for ReplicatedHost {
+    async fn upload_file(
+        &self,
+        content_type: &str,
+        file_name: &str,
+        file_bytes: Bytes,
+    ) -> Result<UploadFileData, FileHostingError> {
+        let mut results = Vec::new();
+        for backend in &self.backends {
+            results.push(
+                backend
+                    .host
+                    .upload_file(content_type, file_name, file_bytes.clone())
+                    .await,
+            );
+        }
+
+        self.record_upload(content_type, file_name, results).await
+    }
+
+    async fn upload_file_streaming(
+        &self,
+        content_type: &str,
+        file_name: &str,
+        content_length: u64,
+        stream: FileStream,
+    ) -> Result<UploadFileData, FileHostingError> {
+        let path = Self::spool(stream).await?;
+
+        let mut results = Vec::new();
+        for backend in &self.backends {
+            let result = match tokio::fs::File::open(&path).await {
+                Ok(file) => {
+                    let stream = ReaderStream::new(file)
+                        .map(|chunk| chunk.map_err(FileHostingError::from))
+                        .boxed();
+                    backend
+                        .host
+                        .upload_file_streaming(content_type, file_name, content_length, stream)
+                        .await
+                }
+                Err(err) => Err(err.into()),
+            };
+            results.push(result);
+        }
+        let _ = tokio::fs::remove_file(&path).await;
+
+        self.record_upload(content_type, file_name, results).await
+    }
+
+    async fn download_file(
+        &self,
+        file_id: &str,
+        file_name: &str,
+    ) -> Result<FileStream, FileHostingError> {
+        let mut last_error = None;
+        for (backend, file_id) in self.stored_backends(file_id, file_name).await? {
+            match backend.host.download_file(&file_id, file_name).await {
+                Ok(stream) => return Ok(stream),
+                Err(err) => {
+                    warn!(
+                        "Downloading file {} from storage backend {} failed: {:?}",
+                        file_name, backend.id, err
+                    );
+                    last_error = Some(err);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or(FileHostingError::Unavailable))
+    }
+
+    async fn delete_file_version(
+        &self,
+        file_id: &str,
+        file_name: &str,
+    ) -> Result<DeleteFileData, FileHostingError> {
+        let replicas = FileReplica::get_file(file_name, &self.pool).await?;
+        if replicas.is_empty() {
+            return self
+                .primary()
+                .host
+                .delete_file_version(file_id, file_name)
+                .await;
+        }
+
+        // The file id is a handle to the version returned by an upload. Each backend stores
+        // that version under its own id, so matching any of them means the stored version is
+        // the one being deleted. An id which is empty or the file name itself doesn't name a
+        // version, and deletes whichever one is stored.
+        let current = file_id.is_empty()
+            || file_id == file_name
+            || replicas
+                .iter()
+                .any(|x| x.file_id.as_deref() == Some(file_id));
+        if !current {
+            return Ok(DeleteFileData {
+                file_id: file_id.to_string(),
+                file_name: file_name.to_string(),
+            });
+        }
+
+        for replica in replicas {
+            let Some(backend) = self.backend(&replica.backend) else {
+                continue;
+            };
+
+            if replica.status != ReplicaStatus::Missing {
+                let result = backend
+                    .host
+                    .delete_file_version(&replica.file_id.clone().unwrap_or_default(), file_name)
+                    .await;
+
+                // The deletion is retried by the repair task
+                if let Err(err) = result {
+                    warn!(
+                        "Deleting file {} from storage backend {} failed: {:?}",
+                        file_name, backend.id, err
+                    );
+                    FileReplica {
+                        status: ReplicaStatus::PendingDelete,
+                        updated: Utc::now(),
+                        ..replica
+                    }
+                    .upsert(&self.pool)
+                    .await?;
+                    continue;
+                }
+            }
+
+            FileReplica::remove(file_name, &backend.id, &self.pool).await?;
+        }
+
+        Ok(DeleteFileData {
+            file_id: file_id.to_string(),
+            file_name: file_name.to_string(),
+        })
+    }
+
+    async fn list_files(
+        &self,
+        prefix: &str,
+        cursor: Option<String>,
+    ) -> Result<FileListPage, FileHostingError> {
+        self.primary().host.list_files(prefix, cursor).await
+    }
+
+    async fn stat_file(&self, file_name: &str) -> Result<Option<FileMetadata>, FileHostingError> {
+        self.primary().host.stat_file(file_name).await
+    }
+
+    async fn get_signed_url(
+        &self,
+        file_name: &str,
+        expires_in: std::time::Duration,
+    ) -> Result<String, FileHostingError> {
+        let backend = self
+            .stored_backends("", file_name)
+            .await?
+            .into_iter()
+            .next()
+            .map_or(self.primary(), |(backend, _)| backend);
+
+        backend.host.get_signed_url(file_name, expires_in).await
+    }
+
+    async fn get_signed_urls(
+        &self,
+        file_names: &[String],
+        expires_in: std::time::Duration,
+    ) -> Result<Vec<String>, FileHostingError> {
+        // Files are signed in a batch by each backend which holds some of them
+        let mut batches: Vec<(&ReplicaBackend, Vec<usize>)> = Vec::new();
+        for (i, file_name) in file_names.iter().enumerate() {
+            let backend = self
+                .stored_backends("", file_name)
+                .await?
+                .into_iter()
+                .next()
+                .map_or(self.primary(), |(backend, _)| backend);
+
+            match batches.iter_mut().find(|(x, _)| x.id == backend.id) {
+                Some((_, indices)) => indices.push(i),
+                None => batches.push((backend, vec![i])),
+            }
+        }
+
+        let mut urls = vec![String::new(); file_names.len()];
+        for (backend, indices) in batches {
+            let names = indices
+                .iter()
+                .map(|i| file_names[*i].clone())
+                .collect::<Vec<_>>();
+            let signed = backend.host.get_signed_urls(&names, expires_in).await?;
+
+            for (i, url) in indices.into_iter().zip(signed) {
+                urls[i] = url;
+            }
+        }
+
+        Ok(urls)
+    }
+
+    async fn backfill_replicas(&self, files: &[FileMetadata]) -> Result<u64, FileHostingError> {
+        let files = files
+            .iter()
+            .map(|x| {
+                let extension = x.file_name.rsplit_once('.').map_or("", |(_, ext)| ext);
+                let content_type = get_image_content_type(extension)
+                    .or_else(|| project_file_type(extension))
+                    .unwrap_or("application/octet-stream");
+
+                (
+                    x.file_name.clone(),
+                    x.file_id.clone(),
+                    content_type.to_string(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let backends = self
+            .backends
+            .iter()
+            .map(|x| x.id.clone())
+            .collect::<Vec<_>>();
+
+        let added =
+            FileReplica::backfill(&files, &self.primary().id, &backends, &self.pool).await?;
+
+        Ok(added / backends.len() as u64)
+    }
+
+    async fn repair_replicas(&self) -> Result<ReplicationReport, FileHostingError> {
+        let mut report = ReplicationReport::default();
+
+        for replica in FileReplica::get_pending(REPAIR_BATCH_SIZE, &self.pool).await? {
+            let Some(backend) = self.backend(&replica.backend) else {
+                continue;
+            };
+
+            let result = match replica.status {
+                ReplicaStatus::Missing => self.repair_missing(&replica, backend).await,
+                ReplicaStatus::PendingDelete => {
+                    async {
+                        backend
+                            .host
+                            .delete_file_version(
+                                &replica.file_id.clone().unwrap_or_default(),
+                                &replica.file_name,
+                            )
+                            .await?;
+                        FileReplica::remove(&replica.file_name, &backend.id, &self.pool).await?;
+
+                        Ok(())
+                    }
+                    .await
+                }
+                ReplicaStatus::Stored => continue,
+            };
+
+            match result {
+                Ok(()) if replica.status == ReplicaStatus::Missing => report.repaired += 1,
+                Ok(()) => report.deleted += 1,
+                Err(err) => {
+                    warn!(
+                        "Repairing file {} on storage backend {} failed: {:?}",
+                        replica.file_name, backend.id, err
+                    );
+                    report.failed += 1;
+
+                    // Move the replica to the back of the queue, so one file which can't be
+                    // repaired doesn't hold up the others
+                    FileReplica {
+                        updated: Utc::now(),
+                        ..replica
+                    }
+                    .upsert(&self.pool)
+                    .await?;
+                }
+            }
+        }
+
+        Ok(report)
+    }
+}
@@ -1,11 +1,17 @@
-use crate::file_hosting::{DeleteFileData, FileHost, FileHostingError, UploadFileData};
+use crate::file_hosting::{
+    DeleteFileData, FileHasher, FileHost, FileHostingError, FileListPage, FileMetadata, FileStream,
+    UploadFileData,
+};
 use async_trait::async_trait;
 use bytes::Bytes;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
 use s3::bucket::Bucket;
 use s3::creds::Credentials;
+use s3::error::S3Error;
 use s3::region::Region;
 use sha2::Digest;
+use tokio_util::io::StreamReader;
 
 pub struct S3Host {
     bucket: Bucket,
@@ -64,7 +70,7 @@ impl FileHost for S3Host {
         Ok(UploadFileData {
             file_id: file_name.to_string(),
             file_name: file_name.to_string(),
-            content_length: file_bytes.len() as u32,
+            content_length: file_bytes.len() as u64,
             content_sha512,
             content_sha1,
             content_md5: None,
@@ -73,6 +79,68 @@ impl FileHost for S3Host {
         })
     }
 
+    async fn upload_file_streaming(
+        &self,
+        content_type: &str,
+        file_name: &str,
+        _content_length: u64,
+        stream: FileStream,
+    ) -> Result<UploadFileData, FileHostingError> {
+        let mut hasher = FileHasher::new();
+
+        {
+            // Large files are sent as a multipart upload, so only one part is buffered at a time
+            let mut reader = StreamReader::new(stream.map(|chunk| {
+                let chunk = chunk.map_err(std::io::Error::other)?;
+                hasher.update(&chunk);
+                Ok::<_, std::io::Error>(chunk)
+            }));
+
+            self.bucket
+                .put_object_stream_with_content_type(
+                    &mut reader,
+                    format!("/{file_name}"),
+                    content_type,
+                )
+                .await
+                .map_err(|_| {
+                    FileHostingError::S3Error("Error while uploading file to S3".to_string())
+                })?;
+        }
+
+        let hashes = hasher.finish();
+        Ok(UploadFileData {
+            file_id: file_name.to_string(),
+            file_name: file_name.to_string(),
+            content_length: hashes.length,
+            content_sha512: hashes.sha512,
+            content_sha1: hashes.sha1,
+            content_md5: None,
+            content_type: content_type.to_string(),
+            upload_timestamp: Utc::now().timestamp() as u64,
+        })
+    }
+
+    async fn download_file(
+        &self,
+        _file_id: &str,
+        file_name: &str,
+    ) -> Result<FileStream, FileHostingError> {
+        let url = self
+            .bucket
+            .presign_get(format!("/{file_name}"), 60 * 5, None)
+            .map_err(|_| {
+                FileHostingError::S3Error("Error while signing file download URL".to_string())
+            })?;
+
+        let response = reqwest::get(url).await?.error_for_status()?;
+
+        Ok(response
+            .bytes_stream()
+            .map(|chunk| chunk.map_err(FileHostingError::from))
+            .boxed())
+    }
+
     async fn delete_file_version(
         &self,
         file_id: &str,
@@ -90,4 +158,74 @@ impl FileHost for S3Host {
             file_name: file_name.to_string(),
         })
     }
+
+    async fn list_files(
+        &self,
+        prefix: &str,
+        cursor: Option<String>,
+    ) -> Result<FileListPage, FileHostingError> {
+        let (list_data, _) = self
+            .bucket
+            .list_page(prefix.to_string(), None, cursor, None, Some(1000))
+            .await
+            .map_err(|_| {
+                FileHostingError::S3Error("Error while listing files in S3".to_string())
+            })?;
+
+        Ok(FileListPage {
+            files: list_data
+                .contents
+                .into_iter()
+                .map(|x| FileMetadata {
+                    file_id: x.key.clone(),
+                    file_name: x.key,
+                    content_length: x.size,
+                    // Files with an unknown age are treated as new
+                    last_modified: DateTime::parse_from_rfc3339(&x.last_modified)
+                        .map(|x| x.with_timezone(&Utc))
+                        .unwrap_or_else(|_| Utc::now()),
+                })
+                .collect(),
+            next_cursor: if list_data.is_truncated {
+                list_data.next_continuation_token
+            } else {
+                None
+            },
+        })
+    }
+
+    async fn stat_file(&self, file_name: &str) -> Result<Option<FileMetadata>, FileHostingError> {
+        let head_data = match self.bucket.head_object(format!("/{file_name}")).await {
+            Ok((_, 404)) | Err(S3Error::Http(404, _)) => return Ok(None),
+            Ok((head_data, _)) => head_data,
+            Err(_) => {
+                return Err(FileHostingError::S3Error(
+                    "Error while getting file metadata from S3".to_string(),
+                ))
+            }
+        };
+
+        Ok(Some(FileMetadata {
+            file_id: file_name.to_string(),
+            file_name: file_name.to_string(),
+            content_length: head_data.content_length.unwrap_or(0) as u64,
+            last_modified: head_data
+                .last_modified
+                .and_then(|x| DateTime::parse_from_rfc2822(&x).ok())
+                .map(|x| x.with_timezone(&Utc))
+                .unwrap_or_else(Utc::now),
+        }))
+    }
+
+    async fn get_signed_url(
+        &self,
+        file_name: &str,
+        expires_in: std::time::Duration,
+    ) -> Result<String, FileHostingError> {
+        self.bucket
+            .presign_get(format!("/{file_name}"), expires_in.as_secs() as u32, None)
+            .map_err(|_| {
+                FileHostingError::S3Error("Error while signing file download URL".to_string())
+            })
+    }
 }
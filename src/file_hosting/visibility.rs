@@ -0,0 +1,151 @@
+use super::{get_file_name_from_url, FileHost, FileHostingError};
+use crate::database::models::ids::{ProjectId, VersionId};
+use crate::database::models::{DatabaseError, Version};
+use crate::database::redis::RedisPool;
+use crate::models::projects::{ProjectStatus, VersionStatus};
+use crate::util::ext::project_file_type;
+use futures::TryStreamExt;
+use log::warn;
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+/// The prefix files of hidden versions, or versions of hidden projects, are stored under. The
+/// CDN must not serve it, so that those files can only be downloaded through signed URLs.
+pub const PRIVATE_PREFIX: &str = "private/";
+
+/// Files which were moved by [`sync_file_visibility`]. Their old copies are deleted by
+/// [`MovedFiles::finish`] once the transaction which updated their URLs is committed.
+#[derive(Default)]
+pub struct MovedFiles {
+    versions: Vec<VersionId>,
+    /// The stored names of the old copies
+    old_files: Vec<String>,
+    /// The new URLs of the moved files, by their old URLs
+    urls: HashMap<String, String>,
+}
+
+impl MovedFiles {
+    /// The new URL of a file which was moved, or the URL as it is
+    pub fn url(&self, url: &str) -> String {
+        self.urls
+            .get(url)
+            .cloned()
+            .unwrap_or_else(|| url.to_string())
+    }
+
+    /// Deletes the old copies of the moved files and clears the cache of their versions
+    pub async fn finish(
+        self,
+        file_host: &dyn FileHost,
+        pool: &PgPool,
+        redis: &RedisPool,
+    ) -> Result<(), DatabaseError> {
+        if self.old_files.is_empty() {
+            return Ok(());
+        }
+
+        Version::clear_cache_many(&self.versions, pool, redis).await?;
+
+        for file_name in &self.old_files {
+            if let Err(err) = file_host.delete_file_version("", file_name).await {
+                warn!("Deleting moved file {} failed: {:?}", file_name, err);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Moves the files of a project's versions between the public and private paths, so that only
+/// files of public versions of a public project can be downloaded from the CDN. This is called
+/// whenever the project or one of its versions may have been hidden or made public.
+pub async fn sync_file_visibility(
+    project_id: ProjectId,
+    file_host: &dyn FileHost,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<MovedFiles, FileHostingError> {
+    let cdn_url = dotenvy::var("CDN_URL")?;
+    let cdn_prefix = format!("{}/", cdn_url.trim_end_matches('/'));
+
+    let files = sqlx::query!(
+        "
+        SELECT f.id, f.version_id, f.url, v.status version_status, m.status project_status
+        FROM files f
+        INNER JOIN versions v ON v.id = f.version_id
+        INNER JOIN mods m ON m.id = v.mod_id
+        WHERE v.mod_id = $1
+        ",
+        project_id as ProjectId,
+    )
+    .fetch(&mut **transaction)
+    .try_collect::<Vec<_>>()
+    .await
+    .map_err(DatabaseError::from)?;
+
+    let mut moved = MovedFiles::default();
+    for file in files {
+        let (Some(file_name), Some(path)) = (
+            get_file_name_from_url(&cdn_url, &file.url),
+            file.url.strip_prefix(&cdn_prefix),
+        ) else {
+            continue;
+        };
+
+        let hidden = VersionStatus::from_string(&file.version_status).is_hidden()
+            || ProjectStatus::from_string(&file.project_status).is_hidden();
+
+        let (new_file_name, new_path) = match file_name.strip_prefix(PRIVATE_PREFIX) {
+            Some(public_name) if !hidden => (
+                public_name.to_string(),
+                path.strip_prefix(PRIVATE_PREFIX)
+                    .unwrap_or(path)
+                    .to_string(),
+            ),
+            None if hidden => (
+                format!("{PRIVATE_PREFIX}{file_name}"),
+                format!("{PRIVATE_PREFIX}{path}"),
+            ),
+            _ => continue,
+        };
+
+        let metadata = file_host
+            .stat_file(&file_name)
+            .await?
+            .ok_or(FileHostingError::Unavailable)?;
+        let content_type = file_name
+            .rsplit_once('.')
+            .and_then(|(_, ext)| project_file_type(ext))
+            .unwrap_or("application/octet-stream");
+        let stream = file_host.download_file("", &file_name).await?;
+        file_host
+            .upload_file_streaming(
+                content_type,
+                &new_file_name,
+                metadata.content_length,
+                stream,
+            )
+            .await?;
+
+        let new_url = format!("{cdn_prefix}{new_path}");
+        sqlx::query!(
+            "
+            UPDATE files
+            SET url = $1
+            WHERE id = $2
+            ",
+            new_url,
+            file.id,
+        )
+        .execute(&mut **transaction)
+        .await
+        .map_err(DatabaseError::from)?;
+
+        if !moved.versions.contains(&VersionId(file.version_id)) {
+            moved.versions.push(VersionId(file.version_id));
+        }
+        moved.old_files.push(file_name);
+        moved.urls.insert(file.url, new_url);
+    }
+
+    Ok(moved)
+}
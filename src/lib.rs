@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::num::NonZeroU32;
 use std::sync::Arc;
 use std::time::Duration;
@@ -6,7 +7,8 @@ use actix_web::web;
 use database::redis::RedisPool;
 use log::{info, warn};
 use queue::{
-    analytics::AnalyticsQueue, payouts::PayoutsQueue, session::AuthQueue, socket::ActiveSockets,
+    analytics::AnalyticsQueue, payouts::PayoutsQueue, search_index::SearchIndexQueue,
+    session::AuthQueue, socket::ActiveSockets,
 };
 use sqlx::Postgres;
 use tokio::sync::RwLock;
@@ -17,11 +19,13 @@ use governor::middleware::StateInformationMiddleware;
 use governor::{Quota, RateLimiter};
 use util::cors::default_cors;
 
+use crate::database::models::modpack_content_item::ModpackContent;
+use crate::database::models::storage_reconciliation_run_item::StorageReconciliationRun;
+use crate::database::models::{DatabaseError, ProjectId};
 use crate::queue::moderation::AutomatedModerationQueue;
 use crate::util::ratelimit::KeyedRateLimiter;
 use crate::{
     queue::payouts::process_payout,
-    search::indexing::index_projects,
     util::env::{parse_strings_from_var, parse_var},
 };
 
@@ -55,6 +59,7 @@ pub struct LabrinthConfig {
     pub session_queue: web::Data<AuthQueue>,
     pub payouts_queue: web::Data<PayoutsQueue>,
     pub analytics_queue: Arc<AnalyticsQueue>,
+    pub search_index_queue: web::Data<SearchIndexQueue>,
     pub active_sockets: web::Data<RwLock<ActiveSockets>>,
     pub automated_moderation_queue: web::Data<AutomatedModerationQueue>,
     pub rate_limiter: KeyedRateLimiter,
@@ -108,49 +113,92 @@ pub fn app_setup(
         async move {}
     });
 
-    // The interval in seconds at which the local database is indexed
-    // for searching.  Defaults to 1 hour if unset.
+    // The interval in seconds at which the whole local database is reindexed for searching, as a
+    // consistency pass over the incremental updates. Defaults to 1 day if unset.
     let local_index_interval =
-        std::time::Duration::from_secs(parse_var("LOCAL_INDEX_INTERVAL").unwrap_or(3600));
+        std::time::Duration::from_secs(parse_var("LOCAL_INDEX_INTERVAL").unwrap_or(86400));
+
+    // Changed projects are reindexed once they have not changed for this many seconds.
+    // Defaults to 5 seconds if unset.
+    let search_index_debounce =
+        std::time::Duration::from_secs(parse_var("SEARCH_INDEX_DEBOUNCE").unwrap_or(5));
+    let search_index_queue = web::Data::new(SearchIndexQueue::new(search_index_debounce));
 
     let pool_ref = pool.clone();
-    let search_config_ref = search_config.clone();
     let redis_pool_ref = redis_pool.clone();
+    let search_config_ref = search_config.clone();
+    let search_index_queue_ref = search_index_queue.clone();
     scheduler.run(local_index_interval, move || {
         let pool_ref = pool_ref.clone();
         let redis_pool_ref = redis_pool_ref.clone();
         let search_config_ref = search_config_ref.clone();
+        let search_index_queue_ref = search_index_queue_ref.clone();
         async move {
             info!("Indexing local database");
-            let result = index_projects(pool_ref, redis_pool_ref.clone(), &search_config_ref).await;
+            let result = search_index_queue_ref
+                .rebuild(pool_ref, &redis_pool_ref, &search_config_ref)
+                .await;
             if let Err(e) = result {
                 warn!("Local project indexing failed: {:?}", e);
             }
             info!("Done indexing local database");
         }
     });
+    {
+        let pool_ref = pool.clone();
+        let search_config_ref = search_config.clone();
+        let search_index_queue_ref = search_index_queue.clone();
+        scheduler.run(search_index_debounce, move || {
+            let pool_ref = pool_ref.clone();
+            let search_config_ref = search_config_ref.clone();
+            let search_index_queue_ref = search_index_queue_ref.clone();
+            async move {
+                let result = search_index_queue_ref
+                    .index(&pool_ref, &search_config_ref)
+                    .await;
+                if let Err(e) = result {
+                    warn!("Updating changed projects in search failed: {:?}", e);
+                }
+            }
+        });
+    }
 
     // Changes statuses of scheduled projects/versions
     let pool_ref = pool.clone();
+    let redis_pool_ref = redis_pool.clone();
+    let search_index_queue_ref = search_index_queue.clone();
+    let file_host_ref = file_host.clone();
     // TODO: Clear cache when these are run
     scheduler.run(std::time::Duration::from_secs(60 * 5), move || {
         let pool_ref = pool_ref.clone();
+        let redis_pool_ref = redis_pool_ref.clone();
+        let search_index_queue_ref = search_index_queue_ref.clone();
+        let file_host_ref = file_host_ref.clone();
         info!("Releasing scheduled versions/projects!");
 
         async move {
+            let mut released = HashSet::new();
+
             let projects_results = sqlx::query!(
                 "
                 UPDATE mods
                 SET status = requested_status
                 WHERE status = $1 AND approved < CURRENT_DATE AND requested_status IS NOT NULL
+                RETURNING id
                 ",
                 crate::models::projects::ProjectStatus::Scheduled.as_str(),
             )
-            .execute(&pool_ref)
+            .fetch_all(&pool_ref)
             .await;
 
-            if let Err(e) = projects_results {
-                warn!("Syncing scheduled releases for projects failed: {:?}", e);
+            match projects_results {
+                Ok(projects) => {
+                    released.extend(projects.iter().map(|x| ProjectId(x.id)));
+                    search_index_queue_ref
+                        .add_many(projects.into_iter().map(|x| ProjectId(x.id)))
+                        .await
+                }
+                Err(e) => warn!("Syncing scheduled releases for projects failed: {:?}", e),
             }
 
             let versions_results = sqlx::query!(
@@ -158,14 +206,52 @@ pub fn app_setup(
                 UPDATE versions
                 SET status = requested_status
                 WHERE status = $1 AND date_published < CURRENT_DATE AND requested_status IS NOT NULL
+                RETURNING mod_id
                 ",
                 crate::models::projects::VersionStatus::Scheduled.as_str(),
             )
-            .execute(&pool_ref)
+            .fetch_all(&pool_ref)
             .await;
 
-            if let Err(e) = versions_results {
-                warn!("Syncing scheduled releases for versions failed: {:?}", e);
+            match versions_results {
+                Ok(versions) => {
+                    released.extend(versions.iter().map(|x| ProjectId(x.mod_id)));
+                    search_index_queue_ref
+                        .add_many(versions.into_iter().map(|x| ProjectId(x.mod_id)))
+                        .await
+                }
+                Err(e) => warn!("Syncing scheduled releases for versions failed: {:?}", e),
+            }
+
+            // Released modpacks may now notify the projects they include, and the files of
+            // released versions are moved back to their public path
+            for project_id in released {
+                let result = async {
+                    let mut transaction = pool_ref.begin().await.map_err(DatabaseError::from)?;
+                    ModpackContent::notify_newly_included(
+                        project_id,
+                        &mut transaction,
+                        &redis_pool_ref,
+                    )
+                    .await?;
+                    let moved = file_hosting::visibility::sync_file_visibility(
+                        project_id,
+                        &*file_host_ref,
+                        &mut transaction,
+                    )
+                    .await?;
+                    transaction.commit().await.map_err(DatabaseError::from)?;
+                    moved
+                        .finish(&*file_host_ref, &pool_ref, &redis_pool_ref)
+                        .await?;
+
+                    Ok::<(), file_hosting::FileHostingError>(())
+                }
+                .await;
+
+                if let Err(e) = result {
+                    warn!("Releasing files of scheduled versions failed: {:?}", e);
+                }
             }
 
             info!("Finished releasing scheduled versions/projects");
@@ -194,6 +280,81 @@ pub fn app_setup(
         }
     });
 
+    let pool_ref = pool.clone();
+    let file_host_ref = file_host.clone();
+    scheduler.run(std::time::Duration::from_secs(60 * 30), move || {
+        let pool_ref = pool_ref.clone();
+        let file_host_ref = file_host_ref.clone();
+
+        async move {
+            info!("Removing expired upload sessions");
+            let result =
+                routes::v3::uploads::remove_expired_sessions(&pool_ref, &*file_host_ref).await;
+            if let Err(e) = result {
+                warn!("Removing expired upload sessions failed: {:?}", e);
+            }
+            info!("Done removing expired upload sessions");
+        }
+    });
+
+    // The interval in seconds at which storage is reconciled with the database. Orphaned
+    // files are only purged if a grace period is set. Defaults to 1 day if unset.
+    let storage_reconcile_interval =
+        std::time::Duration::from_secs(parse_var("STORAGE_RECONCILE_INTERVAL").unwrap_or(86400));
+    let storage_purge_grace_period =
+        parse_var::<i64>("STORAGE_ORPHAN_GRACE_HOURS").map(chrono::Duration::hours);
+
+    let pool_ref = pool.clone();
+    let file_host_ref = file_host.clone();
+    scheduler.run(storage_reconcile_interval, move || {
+        let pool_ref = pool_ref.clone();
+        let file_host_ref = file_host_ref.clone();
+
+        async move {
+            let run_id = match StorageReconciliationRun::insert(
+                storage_purge_grace_period.map(|x| x.num_hours() as u32),
+                &pool_ref,
+            )
+            .await
+            {
+                Ok(run_id) => run_id,
+                Err(e) => {
+                    warn!("Starting storage reconciliation run failed: {:?}", e);
+                    return;
+                }
+            };
+
+            queue::storage::reconcile_storage_logged(
+                &pool_ref,
+                &*file_host_ref,
+                storage_purge_grace_period,
+                run_id,
+            )
+            .await;
+        }
+    });
+
+    // The interval in seconds at which files missing from storage replicas are repaired.
+    // Defaults to 1 hour if unset. Only replicated file hosts have anything to repair.
+    let replica_repair_interval =
+        std::time::Duration::from_secs(parse_var("STORAGE_REPAIR_INTERVAL").unwrap_or(3600));
+
+    let file_host_ref = file_host.clone();
+    scheduler.run(replica_repair_interval, move || {
+        let file_host_ref = file_host_ref.clone();
+
+        async move {
+            info!("Repairing storage replicas");
+            match file_host_ref.repair_replicas().await {
+                Ok(report) => info!(
+                    "Done repairing storage replicas: {} repaired, {} deleted and {} failed files",
+                    report.repaired, report.deleted, report.failed
+                ),
+                Err(e) => warn!("Repairing storage replicas failed: {:?}", e),
+            }
+        }
+    });
+
     let reader = maxmind.clone();
     {
         let reader_ref = reader;
@@ -221,16 +382,18 @@ pub fn app_setup(
         let analytics_queue_ref = analytics_queue.clone();
         let pool_ref = pool.clone();
         let redis_ref = redis_pool.clone();
+        let search_index_queue_ref = search_index_queue.clone();
         scheduler.run(std::time::Duration::from_secs(15), move || {
             let client_ref = client_ref.clone();
             let analytics_queue_ref = analytics_queue_ref.clone();
             let pool_ref = pool_ref.clone();
             let redis_ref = redis_ref.clone();
+            let search_index_queue_ref = search_index_queue_ref.clone();
 
             async move {
                 info!("Indexing analytics queue");
                 let result = analytics_queue_ref
-                    .index(client_ref, &redis_ref, &pool_ref)
+                    .index(client_ref, &redis_ref, &pool_ref, &search_index_queue_ref)
                     .await;
                 if let Err(e) = result {
                     warn!("Indexing analytics queue failed: {:?}", e);
@@ -240,6 +403,76 @@ pub fn app_setup(
         });
     }
 
+    // The interval in seconds at which trending scores are computed from recent downloads and
+    // follows. Defaults to 1 hour if unset.
+    let trending_interval =
+        std::time::Duration::from_secs(parse_var("TRENDING_INTERVAL").unwrap_or(3600));
+    {
+        let pool_ref = pool.clone();
+        let client_ref = clickhouse.clone();
+        let search_index_queue_ref = search_index_queue.clone();
+        scheduler.run(trending_interval, move || {
+            let pool_ref = pool_ref.clone();
+            let client_ref = client_ref.clone();
+            let search_index_queue_ref = search_index_queue_ref.clone();
+
+            async move {
+                info!("Updating trending scores");
+                let result = queue::trending::update_trending_scores(
+                    &pool_ref,
+                    &client_ref,
+                    &search_index_queue_ref,
+                )
+                .await;
+                match result {
+                    Ok(changed) => info!("Done updating trending scores of {} projects", changed),
+                    Err(e) => warn!("Updating trending scores failed: {:?}", e),
+                }
+            }
+        });
+    }
+
+    // The interval in seconds at which the similar projects of every project are ranked. Rankings
+    // are cached for twice as long, so they don't expire before the next run. Defaults to 1 day.
+    let similar_projects_interval = parse_var("SIMILAR_PROJECTS_INTERVAL").unwrap_or(86400);
+    {
+        let pool_ref = pool.clone();
+        let redis_pool_ref = redis_pool.clone();
+        scheduler.run(
+            std::time::Duration::from_secs(similar_projects_interval),
+            move || {
+                let pool_ref = pool_ref.clone();
+                let redis_pool_ref = redis_pool_ref.clone();
+
+                async move {
+                    info!("Ranking similar projects");
+                    let result = queue::similar_projects::update_similar_projects(
+                        &pool_ref,
+                        &redis_pool_ref,
+                        similar_projects_interval as i64 * 2,
+                    )
+                    .await;
+                    match result {
+                        Ok(ranked) => {
+                            info!("Done ranking similar projects of {} projects", ranked)
+                        }
+                        Err(e) => warn!("Ranking similar projects failed: {:?}", e),
+                    }
+                }
+            },
+        );
+    }
+
+    {
+        let pool_ref = pool.clone();
+        let redis_pool_ref = redis_pool.clone();
+        let search_config_ref = search_config.clone();
+
+        actix_rt::spawn(async move {
+            queue::saved_searches::task(pool_ref, redis_pool_ref, search_config_ref).await;
+        });
+    }
+
     {
         let pool_ref = pool.clone();
         let client_ref = clickhouse.clone();
@@ -258,6 +491,16 @@ pub fn app_setup(
         });
     }
 
+    {
+        let pool_ref = pool.clone();
+        let redis_ref = redis_pool.clone();
+        let file_host_ref = file_host.clone();
+
+        actix_rt::spawn(async move {
+            queue::revalidation::task(pool_ref, redis_ref, file_host_ref).await;
+        });
+    }
+
     let stripe_client = stripe::Client::new(dotenvy::var("STRIPE_API_KEY").unwrap());
     {
         let pool_ref = pool.clone();
@@ -297,6 +540,7 @@ pub fn app_setup(
         session_queue,
         payouts_queue,
         analytics_queue,
+        search_index_queue,
         active_sockets,
         automated_moderation_queue,
         rate_limiter: limiter,
@@ -326,6 +570,7 @@ pub fn app_config(cfg: &mut web::ServiceConfig, labrinth_config: LabrinthConfig)
     .app_data(web::Data::new(labrinth_config.file_host.clone()))
     .app_data(web::Data::new(labrinth_config.search_config.clone()))
     .app_data(labrinth_config.session_queue.clone())
+    .app_data(labrinth_config.search_index_queue.clone())
     .app_data(labrinth_config.payouts_queue.clone())
     .app_data(web::Data::new(labrinth_config.ip_salt.clone()))
     .app_data(web::Data::new(labrinth_config.analytics_queue.clone()))
@@ -345,7 +590,7 @@ pub fn app_config(cfg: &mut web::ServiceConfig, labrinth_config: LabrinthConfig)
 pub fn check_env_vars() -> bool {
     let mut failed = false;
 
-    fn check_var<T: std::str::FromStr>(var: &'static str) -> bool {
+    fn check_var<T: std::str::FromStr>(var: &str) -> bool {
         let check = parse_var::<T>(var).is_none();
         if check {
             warn!(
@@ -362,43 +607,102 @@ pub fn check_env_vars() -> bool {
     failed |= check_var::<String>("LABRINTH_ADMIN_KEY");
     failed |= check_var::<String>("RATE_LIMIT_IGNORE_KEY");
     failed |= check_var::<String>("DATABASE_URL");
-    failed |= check_var::<String>("MEILISEARCH_ADDR");
-    failed |= check_var::<String>("MEILISEARCH_KEY");
+
+    match dotenvy::var("SEARCH_BACKEND").as_deref() {
+        Ok("meilisearch") | Err(_) => {
+            failed |= check_var::<String>("MEILISEARCH_ADDR");
+            failed |= check_var::<String>("MEILISEARCH_KEY");
+        }
+        Ok("embedded") => {}
+        Ok(backend) => {
+            warn!("Variable `SEARCH_BACKEND` contains an invalid value: {}. Expected \"meilisearch\" or \"embedded\".", backend);
+            failed |= true;
+        }
+    }
+
     failed |= check_var::<String>("REDIS_URL");
     failed |= check_var::<String>("BIND_ADDR");
     failed |= check_var::<String>("SELF_ADDR");
 
+    fn check_storage_backend(var: &'static str, backend: Option<&str>, prefix: &str) -> bool {
+        let check_backend_var = |name: &str| check_var::<String>(&format!("{prefix}{name}"));
+
+        let mut failed = false;
+        match backend {
+            Some("backblaze") => {
+                failed |= check_backend_var("BACKBLAZE_KEY_ID");
+                failed |= check_backend_var("BACKBLAZE_KEY");
+                failed |= check_backend_var("BACKBLAZE_BUCKET_ID");
+            }
+            Some("s3") => {
+                failed |= check_backend_var("S3_ACCESS_TOKEN");
+                failed |= check_backend_var("S3_SECRET");
+                failed |= check_backend_var("S3_URL");
+                failed |= check_backend_var("S3_REGION");
+                failed |= check_backend_var("S3_BUCKET_NAME");
+            }
+            Some("filesystem") => {
+                failed |= check_backend_var("FILESYSTEM_STORAGE_PATH");
+                failed |= check_var::<String>("FILE_SIGNING_SECRET");
+            }
+            Some("local") => {
+                failed |= check_var::<String>("MOCK_FILE_PATH");
+                failed |= check_var::<String>("FILE_SIGNING_SECRET");
+            }
+            Some(backend) => {
+                warn!("Variable `{}` contains an invalid value: {}. Expected \"backblaze\", \"s3\", \"filesystem\", or \"local\".", var, backend);
+                failed |= true;
+            }
+            _ => {
+                warn!("Variable `{}` is not set!", var);
+                failed |= true;
+            }
+        }
+        failed
+    }
+
     failed |= check_var::<String>("STORAGE_BACKEND");
 
     let storage_backend = dotenvy::var("STORAGE_BACKEND").ok();
-    match storage_backend.as_deref() {
-        Some("backblaze") => {
-            failed |= check_var::<String>("BACKBLAZE_KEY_ID");
-            failed |= check_var::<String>("BACKBLAZE_KEY");
-            failed |= check_var::<String>("BACKBLAZE_BUCKET_ID");
-        }
-        Some("s3") => {
-            failed |= check_var::<String>("S3_ACCESS_TOKEN");
-            failed |= check_var::<String>("S3_SECRET");
-            failed |= check_var::<String>("S3_URL");
-            failed |= check_var::<String>("S3_REGION");
-            failed |= check_var::<String>("S3_BUCKET_NAME");
-        }
-        Some("local") => {
-            failed |= check_var::<String>("MOCK_FILE_PATH");
-        }
-        Some(backend) => {
-            warn!("Variable `STORAGE_BACKEND` contains an invalid value: {}. Expected \"backblaze\", \"s3\", or \"local\".", backend);
-            failed |= true;
+    if storage_backend.as_deref() == Some("replicated") {
+        let check_replica_backend = |var: &'static str, id: &str| {
+            let (backend_type, prefix) = file_hosting::get_replica_backend_config(id);
+            check_storage_backend(var, Some(&backend_type), &prefix)
+        };
+
+        let primary = dotenvy::var("STORAGE_PRIMARY").ok();
+        match &primary {
+            Some(id) => failed |= check_replica_backend("STORAGE_PRIMARY", id),
+            None => failed |= check_storage_backend("STORAGE_PRIMARY", None, ""),
         }
-        _ => {
-            warn!("Variable `STORAGE_BACKEND` is not set!");
-            failed |= true;
+
+        match parse_strings_from_var("STORAGE_SECONDARIES") {
+            Some(secondaries) if !secondaries.is_empty() => {
+                for id in &secondaries {
+                    failed |= check_replica_backend("STORAGE_SECONDARIES", id);
+                }
+
+                let ids = secondaries.iter().chain(&primary).collect::<HashSet<_>>();
+                if ids.len() != secondaries.len() + usize::from(primary.is_some()) {
+                    warn!("Variables `STORAGE_PRIMARY` and `STORAGE_SECONDARIES` must not contain the same backend id twice");
+                    failed |= true;
+                }
+            }
+            _ => {
+                warn!("Variable `STORAGE_SECONDARIES` missing in dotenv or not a non-empty json array of strings");
+                failed |= true;
+            }
         }
+    } else {
+        failed |= check_storage_backend("STORAGE_BACKEND", storage_backend.as_deref(), "");
     }
 
     failed |= check_var::<usize>("LOCAL_INDEX_INTERVAL");
+    failed |= check_var::<u64>("SEARCH_INDEX_DEBOUNCE");
     failed |= check_var::<usize>("VERSION_INDEX_INTERVAL");
+    failed |= check_var::<u64>("TRENDING_INTERVAL");
+    failed |= check_var::<u64>("SIMILAR_PROJECTS_INTERVAL");
+    failed |= check_var::<u64>("SAVED_SEARCHES_INTERVAL");
 
     if parse_strings_from_var("WHITELISTED_MODPACK_DOMAINS").is_none() {
         warn!("Variable `WHITELISTED_MODPACK_DOMAINS` missing in dotenv or not a json array of strings");
@@ -4,6 +4,7 @@ use env_logger::Env;
 use labrinth::database::redis::RedisPool;
 use labrinth::file_hosting::S3Host;
 use labrinth::search;
+use labrinth::util::env::parse_strings_from_var;
 use labrinth::util::ratelimit::RateLimit;
 use labrinth::{check_env_vars, clickhouse, database, file_hosting, queue};
 use log::{error, info};
@@ -59,26 +60,22 @@ async fn main() -> std::io::Result<()> {
     let storage_backend = dotenvy::var("STORAGE_BACKEND").unwrap_or_else(|_| "local".to_string());
 
     let file_host: Arc<dyn file_hosting::FileHost + Send + Sync> = match storage_backend.as_str() {
-        "backblaze" => Arc::new(
-            file_hosting::BackblazeHost::new(
-                &dotenvy::var("BACKBLAZE_KEY_ID").unwrap(),
-                &dotenvy::var("BACKBLAZE_KEY").unwrap(),
-                &dotenvy::var("BACKBLAZE_BUCKET_ID").unwrap(),
-            )
-            .await,
-        ),
-        "s3" => Arc::new(
-            S3Host::new(
-                &dotenvy::var("S3_BUCKET_NAME").unwrap(),
-                &dotenvy::var("S3_REGION").unwrap(),
-                &dotenvy::var("S3_URL").unwrap(),
-                &dotenvy::var("S3_ACCESS_TOKEN").unwrap(),
-                &dotenvy::var("S3_SECRET").unwrap(),
-            )
-            .unwrap(),
-        ),
-        "local" => Arc::new(file_hosting::MockHost::new()),
-        _ => panic!("Invalid storage backend specified. Aborting startup!"),
+        "replicated" => {
+            let primary = dotenvy::var("STORAGE_PRIMARY").unwrap();
+            let secondaries = parse_strings_from_var("STORAGE_SECONDARIES").unwrap();
+
+            let mut secondary_backends = Vec::new();
+            for id in secondaries {
+                secondary_backends.push(get_replica_backend(id).await);
+            }
+
+            Arc::new(file_hosting::ReplicatedHost::new(
+                pool.clone(),
+                get_replica_backend(primary).await,
+                secondary_backends,
+            ))
+        }
+        backend => get_file_host(backend, "").await,
     };
 
     info!("Initializing clickhouse connection");
@@ -117,3 +114,47 @@ async fn main() -> std::io::Result<()> {
     .run()
     .await
 }
+
+async fn get_replica_backend(id: String) -> file_hosting::ReplicaBackend {
+    let (backend_type, prefix) = file_hosting::get_replica_backend_config(&id);
+
+    file_hosting::ReplicaBackend {
+        host: get_file_host(&backend_type, &prefix).await,
+        id,
+    }
+}
+
+/// Creates a file host of the given type, configured with the variables with the given prefix
+async fn get_file_host(
+    storage_backend: &str,
+    prefix: &str,
+) -> Arc<dyn file_hosting::FileHost + Send + Sync> {
+    let var = |name: &str| dotenvy::var(format!("{prefix}{name}")).unwrap();
+
+    match storage_backend {
+        "backblaze" => Arc::new(
+            file_hosting::BackblazeHost::new(
+                &var("BACKBLAZE_KEY_ID"),
+                &var("BACKBLAZE_KEY"),
+                &var("BACKBLAZE_BUCKET_ID"),
+            )
+            .await,
+        ),
+        "s3" => Arc::new(
+            S3Host::new(
+                &var("S3_BUCKET_NAME"),
+                &var("S3_REGION"),
+                &var("S3_URL"),
+                &var("S3_ACCESS_TOKEN"),
+                &var("S3_SECRET"),
+            )
+            .unwrap(),
+        ),
+        #[cfg(unix)]
+        "filesystem" => {
+            Arc::new(file_hosting::FilesystemHost::new(&var("FILESYSTEM_STORAGE_PATH")).unwrap())
+        }
+        "local" => Arc::new(file_hosting::MockHost::new()),
+        _ => panic!("Invalid storage backend specified. Aborting startup!"),
+    }
+}
@@ -15,7 +15,9 @@ pub use v3::pats;
 pub use v3::payouts;
 pub use v3::projects;
 pub use v3::reports;
+pub use v3::saved_searches;
 pub use v3::sessions;
 pub use v3::teams;
 pub use v3::threads;
+pub use v3::uploads;
 pub use v3::users;
@@ -3,8 +3,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::models::{
     ids::{
-        NotificationId, OrganizationId, ProjectId, ReportId, TeamId, ThreadId, ThreadMessageId,
-        UserId, VersionId,
+        NotificationId, OrganizationId, ProjectId, ReportId, SavedSearchId, TeamId, ThreadId,
+        ThreadMessageId, UserId, VersionId,
     },
     notifications::{Notification, NotificationAction, NotificationBody},
     projects::ProjectStatus,
@@ -65,6 +65,16 @@ pub enum LegacyNotificationBody {
         project_id: Option<ProjectId>,
         report_id: Option<ReportId>,
     },
+    IncludedInModpack {
+        project_id: ProjectId,
+        modpack_project_id: ProjectId,
+        modpack_version_id: VersionId,
+    },
+    SavedSearchMatches {
+        saved_search_id: SavedSearchId,
+        name: String,
+        project_ids: Vec<ProjectId>,
+    },
     LegacyMarkdown {
         notification_type: Option<String>,
         title: String,
@@ -83,6 +93,8 @@ impl LegacyNotification {
             NotificationBody::OrganizationInvite { .. } => Some("organization_invite".to_string()),
             NotificationBody::StatusChange { .. } => Some("status_change".to_string()),
             NotificationBody::ModeratorMessage { .. } => Some("moderator_message".to_string()),
+            NotificationBody::IncludedInModpack { .. } => Some("included_in_modpack".to_string()),
+            NotificationBody::SavedSearchMatches { .. } => Some("saved_search_matches".to_string()),
             NotificationBody::LegacyMarkdown {
                 notification_type, ..
             } => notification_type.clone(),
@@ -139,6 +151,24 @@ impl LegacyNotification {
                 project_id,
                 report_id,
             },
+            NotificationBody::IncludedInModpack {
+                project_id,
+                modpack_project_id,
+                modpack_version_id,
+            } => LegacyNotificationBody::IncludedInModpack {
+                project_id,
+                modpack_project_id,
+                modpack_version_id,
+            },
+            NotificationBody::SavedSearchMatches {
+                saved_search_id,
+                name,
+                project_ids,
+            } => LegacyNotificationBody::SavedSearchMatches {
+                saved_search_id,
+                name,
+                project_ids,
+            },
             NotificationBody::LegacyMarkdown {
                 notification_type,
                 name,
@@ -1,7 +1,10 @@
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
-use crate::{routes::v2_reroute, search::ResultSearchProject};
+use crate::{
+    routes::v2_reroute,
+    search::{FacetDistribution, ResultSearchProject},
+};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct LegacySearchResults {
@@ -9,6 +12,8 @@ pub struct LegacySearchResults {
     pub offset: usize,
     pub limit: usize,
     pub total_hits: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub facet_distribution: Option<FacetDistribution>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -173,6 +178,21 @@ impl LegacySearchResults {
             offset,
             limit,
             total_hits: search_results.total_hits,
+            // Facets are named as they were requested in v2
+            facet_distribution: search_results.facet_distribution.map(|facets| {
+                facets
+                    .into_iter()
+                    .map(|(facet, counts)| {
+                        let facet = match facet.as_str() {
+                            "game_versions" => "versions".to_string(),
+                            "project_types" => "project_type".to_string(),
+                            "name" => "title".to_string(),
+                            _ => facet,
+                        };
+                        (facet, counts)
+                    })
+                    .collect()
+            }),
         }
     }
 }
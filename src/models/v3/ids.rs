@@ -8,10 +8,12 @@ pub use super::pats::PatId;
 pub use super::payouts::PayoutId;
 pub use super::projects::{ProjectId, VersionId};
 pub use super::reports::ReportId;
+pub use super::saved_searches::SavedSearchId;
 pub use super::sessions::SessionId;
 pub use super::teams::TeamId;
 pub use super::threads::ThreadId;
 pub use super::threads::ThreadMessageId;
+pub use super::uploads::UploadSessionId;
 pub use super::users::UserId;
 pub use crate::models::billing::{ChargeId, ProductId, ProductPriceId, UserSubscriptionId};
 use thiserror::Error;
@@ -137,6 +139,8 @@ base62_id_impl!(ProductId, ProductId);
 base62_id_impl!(ProductPriceId, ProductPriceId);
 base62_id_impl!(UserSubscriptionId, UserSubscriptionId);
 base62_id_impl!(ChargeId, ChargeId);
+base62_id_impl!(UploadSessionId, UploadSessionId);
+base62_id_impl!(SavedSearchId, SavedSearchId);
 
 pub mod base62_impl {
     use serde::de::{self, Deserializer, Visitor};
@@ -11,7 +11,9 @@ pub mod pats;
 pub mod payouts;
 pub mod projects;
 pub mod reports;
+pub mod saved_searches;
 pub mod sessions;
 pub mod teams;
 pub mod threads;
+pub mod uploads;
 pub mod users;
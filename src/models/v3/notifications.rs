@@ -3,7 +3,9 @@ use super::ids::OrganizationId;
 use super::users::UserId;
 use crate::database::models::notification_item::Notification as DBNotification;
 use crate::database::models::notification_item::NotificationAction as DBNotificationAction;
-use crate::models::ids::{ProjectId, ReportId, TeamId, ThreadId, ThreadMessageId, VersionId};
+use crate::models::ids::{
+    ProjectId, ReportId, SavedSearchId, TeamId, ThreadId, ThreadMessageId, VersionId,
+};
 use crate::models::projects::ProjectStatus;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -58,6 +60,17 @@ pub enum NotificationBody {
         project_id: Option<ProjectId>,
         report_id: Option<ReportId>,
     },
+    IncludedInModpack {
+        project_id: ProjectId,
+        modpack_project_id: ProjectId,
+        modpack_version_id: VersionId,
+    },
+    SavedSearchMatches {
+        saved_search_id: SavedSearchId,
+        name: String,
+        /// The newly approved projects which match the saved search
+        project_ids: Vec<ProjectId>,
+    },
     LegacyMarkdown {
         notification_type: Option<String>,
         name: String,
@@ -166,6 +179,42 @@ impl From<DBNotification> for Notification {
                     },
                     vec![],
                 ),
+                NotificationBody::IncludedInModpack {
+                    project_id,
+                    modpack_project_id,
+                    modpack_version_id,
+                } => (
+                    "Your project has been included in a modpack!".to_string(),
+                    format!(
+                        "The project {} is included in version {} of the modpack {}",
+                        project_id, modpack_version_id, modpack_project_id
+                    ),
+                    format!(
+                        "/project/{}/version/{}",
+                        modpack_project_id, modpack_version_id
+                    ),
+                    vec![],
+                ),
+                NotificationBody::SavedSearchMatches {
+                    name, project_ids, ..
+                } => (
+                    "New projects match your saved search!".to_string(),
+                    if project_ids.len() == 1 {
+                        format!("A new project matches your saved search \"{}\"", name)
+                    } else {
+                        format!(
+                            "{} new projects match your saved search \"{}\"",
+                            project_ids.len(),
+                            name
+                        )
+                    },
+                    if let [project_id] = project_ids.as_slice() {
+                        format!("/project/{}", project_id)
+                    } else {
+                        "/dashboard/notifications".to_string()
+                    },
+                    vec![],
+                ),
                 NotificationBody::LegacyMarkdown {
                     name,
                     text,
@@ -1,3 +1,5 @@
+use crate::database::models::modpack_content_item::ModpackContent as DBModpackContent;
+use crate::models::ids::{ProjectId, VersionId};
 use crate::{models::v2::projects::LegacySideType, util::env::parse_strings_from_var};
 use serde::{Deserialize, Serialize};
 use validator::Validate;
@@ -107,3 +109,66 @@ impl PackDependency {
         }
     }
 }
+
+/// A file of an uploaded modpack, resolved against the files hosted on Modrinth
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModpackContent {
+    pub path: String,
+    pub status: PackFileStatus,
+    /// The project and version the file belongs to. These are omitted for hidden versions.
+    pub project_id: Option<ProjectId>,
+    pub version_id: Option<VersionId>,
+    /// The size declared by the modpack
+    pub file_size: u64,
+    /// Whether the declared size differs from the size of the hosted file
+    pub size_mismatch: bool,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PackFileStatus {
+    /// The file belongs to a visible version
+    Known,
+    /// The file isn't hosted on Modrinth
+    Unknown,
+    /// The file was downloaded from a version which has since been deleted
+    Deleted,
+    /// The file belongs to a version which isn't publicly visible
+    Hidden,
+}
+
+impl PackFileStatus {
+    pub fn from_string(string: &str) -> PackFileStatus {
+        match string {
+            "known" => PackFileStatus::Known,
+            "deleted" => PackFileStatus::Deleted,
+            "hidden" => PackFileStatus::Hidden,
+            _ => PackFileStatus::Unknown,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PackFileStatus::Known => "known",
+            PackFileStatus::Unknown => "unknown",
+            PackFileStatus::Deleted => "deleted",
+            PackFileStatus::Hidden => "hidden",
+        }
+    }
+}
+
+impl From<DBModpackContent> for ModpackContent {
+    fn from(data: DBModpackContent) -> Self {
+        // Hidden versions are not revealed to whoever views the modpack
+        let visible = data.status != PackFileStatus::Hidden;
+
+        Self {
+            path: data.path,
+            status: data.status,
+            project_id: data.project_id.filter(|_| visible).map(Into::into),
+            version_id: data.version_id.filter(|_| visible).map(Into::into),
+            file_size: data.file_size,
+            size_mismatch: data.size_mismatch,
+        }
+    }
+}
@@ -699,6 +699,7 @@ impl From<QueryVersion> for Version {
                     primary: f.primary,
                     size: f.size,
                     file_type: f.file_type,
+                    validation: f.validation,
                 })
                 .collect(),
             dependencies: data
@@ -729,6 +730,7 @@ impl From<QueryVersion> for Version {
 /// Draft - Version is not displayed on project, and not accessible by URL
 /// Unlisted - Version is not displayed on project, and accessible by URL
 /// Scheduled - Version is scheduled to be released in the future
+/// Held - Version is hidden until a moderator reviews suspicious code found in its files
 #[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Debug)]
 #[serde(rename_all = "lowercase")]
 pub enum VersionStatus {
@@ -737,6 +739,7 @@ pub enum VersionStatus {
     Draft,
     Unlisted,
     Scheduled,
+    Held,
     Unknown,
 }
 
@@ -753,6 +756,7 @@ impl VersionStatus {
             "draft" => VersionStatus::Draft,
             "unlisted" => VersionStatus::Unlisted,
             "scheduled" => VersionStatus::Scheduled,
+            "held" => VersionStatus::Held,
             _ => VersionStatus::Unknown,
         }
     }
@@ -764,6 +768,7 @@ impl VersionStatus {
             VersionStatus::Unlisted => "unlisted",
             VersionStatus::Unknown => "unknown",
             VersionStatus::Scheduled => "scheduled",
+            VersionStatus::Held => "held",
         }
     }
 
@@ -774,6 +779,7 @@ impl VersionStatus {
             VersionStatus::Draft,
             VersionStatus::Unlisted,
             VersionStatus::Scheduled,
+            VersionStatus::Held,
             VersionStatus::Unknown,
         ]
         .iter()
@@ -789,6 +795,7 @@ impl VersionStatus {
 
             VersionStatus::Draft => true,
             VersionStatus::Scheduled => true,
+            VersionStatus::Held => true,
             VersionStatus::Unknown => true,
         }
     }
@@ -806,6 +813,7 @@ impl VersionStatus {
             VersionStatus::Draft => true,
             VersionStatus::Unlisted => true,
             VersionStatus::Scheduled => false,
+            VersionStatus::Held => false,
 
             VersionStatus::Unknown => false,
         }
@@ -825,9 +833,87 @@ pub struct VersionFile {
     /// Whether the file is the primary file of a version
     pub primary: bool,
     /// The size in bytes of the file
-    pub size: u32,
+    pub size: u64,
     /// The type of the file
     pub file_type: Option<FileType>,
+    /// The problems found while validating the file when it was uploaded
+    #[serde(default, skip_serializing_if = "ValidationReport::is_empty")]
+    pub validation: ValidationReport,
+}
+
+/// The findings of validating an uploaded file
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub findings: Vec<ValidationFinding>,
+}
+
+impl ValidationReport {
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// Whether the file may be marked as primary, which is the case if no findings are errors
+    pub fn is_passed(&self) -> bool {
+        !self
+            .findings
+            .iter()
+            .any(|x| x.severity == ValidationSeverity::Error)
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ValidationFinding> {
+        self.findings
+            .iter()
+            .filter(|x| x.severity == ValidationSeverity::Error)
+    }
+
+    pub fn push(
+        &mut self,
+        severity: ValidationSeverity,
+        code: &str,
+        message: impl Into<String>,
+        path: Option<String>,
+    ) {
+        self.findings.push(ValidationFinding {
+            severity,
+            code: code.to_string(),
+            message: message.into(),
+            path,
+        });
+    }
+
+    pub fn error(&mut self, code: &str, message: impl Into<String>) {
+        self.push(ValidationSeverity::Error, code, message, None);
+    }
+
+    pub fn warning(&mut self, code: &str, message: impl Into<String>) {
+        self.push(ValidationSeverity::Warning, code, message, None);
+    }
+
+    pub fn extend(&mut self, other: ValidationReport) {
+        self.findings.extend(other.findings);
+    }
+}
+
+/// A single problem found while validating a file
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ValidationFinding {
+    pub severity: ValidationSeverity,
+    /// A stable identifier for the kind of problem, e.g. `missing_pack_mcmeta`
+    pub code: String,
+    /// A human readable description of the problem
+    pub message: String,
+    /// The path of the offending entry inside the archive, if the problem is tied to one
+    pub path: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidationSeverity {
+    /// The file is not valid for its loaders and cannot be the primary file
+    Error,
+    /// The file is usable, but something about it is likely a mistake
+    Warning,
+    Info,
 }
 
 /// A dendency which describes what versions are required, break support, or are optional to the
@@ -947,7 +1033,7 @@ pub struct Loader(pub String);
 
 // These fields must always succeed parsing; deserialize errors aren't
 // processed correctly (don't return JSON errors)
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct SearchRequest {
     pub query: Option<String>,
     pub offset: Option<String>,
@@ -955,6 +1041,12 @@ pub struct SearchRequest {
     pub limit: Option<String>,
 
     pub new_filters: Option<String>,
+    /// A JSON array of facetable attributes, such as `["categories","game_versions"]`, to return
+    /// the number of matching projects for each value of
+    pub facet_distribution: Option<String>,
+    /// Either `projects`, the default, or `versions` to filter each version on its own, such as
+    /// for a Fabric version for 1.20.4, and return the newest matching version of each project
+    pub mode: Option<String>,
 
     // TODO: Deprecated values below. WILL BE REMOVED V3!
     pub facets: Option<String>,
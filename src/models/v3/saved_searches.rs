@@ -0,0 +1,33 @@
+use super::ids::Base62Id;
+use crate::models::projects::SearchRequest;
+use crate::models::users::UserId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Hash, Debug)]
+#[serde(from = "Base62Id")]
+#[serde(into = "Base62Id")]
+pub struct SavedSearchId(pub u64);
+
+/// A project search which a user saved, to be notified when newly approved projects match it
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SavedSearch {
+    pub id: SavedSearchId,
+    pub user_id: UserId,
+    pub name: String,
+    /// The search parameters, as accepted by the project search route
+    pub request: SearchRequest,
+    pub created: DateTime<Utc>,
+}
+
+impl From<crate::database::models::saved_search_item::SavedSearch> for SavedSearch {
+    fn from(data: crate::database::models::saved_search_item::SavedSearch) -> Self {
+        SavedSearch {
+            id: data.id.into(),
+            user_id: data.user_id.into(),
+            name: data.name,
+            request: data.request,
+            created: data.created,
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use super::ids::Base62Id;
+use crate::models::users::UserId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Hash, Debug)]
+#[serde(from = "Base62Id")]
+#[serde(into = "Base62Id")]
+pub struct UploadSessionId(pub u64);
+
+/// A resumable upload of a single file, sent in numbered chunks
+#[derive(Serialize, Deserialize, Clone)]
+pub struct UploadSession {
+    pub id: UploadSessionId,
+    pub user_id: UserId,
+    pub file_name: String,
+    /// The total size in bytes of the file being uploaded
+    pub file_size: u64,
+    /// The size in bytes of every chunk except the last one
+    pub chunk_size: u32,
+    pub chunk_count: u32,
+
+    /// The byte ranges which have been received so far
+    pub received: Vec<UploadedChunk>,
+    /// The indices of the chunks which still need to be uploaded
+    pub missing_chunks: Vec<u32>,
+    pub complete: bool,
+
+    pub created: DateTime<Utc>,
+    pub expires: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct UploadedChunk {
+    pub index: u32,
+    pub offset: u64,
+    pub size: u32,
+}
+
+impl UploadSession {
+    pub fn from(
+        data: crate::database::models::upload_session_item::UploadSession,
+        chunks: &[crate::database::models::upload_session_item::UploadSessionChunk],
+    ) -> Self {
+        let chunk_count = data.chunk_count();
+        let received = chunks
+            .iter()
+            .map(|x| UploadedChunk {
+                index: x.chunk_index as u32,
+                offset: x.chunk_index as u64 * data.chunk_size as u64,
+                size: x.size as u32,
+            })
+            .collect::<Vec<_>>();
+        let missing_chunks = (0..chunk_count)
+            .filter(|i| !received.iter().any(|x| x.index == *i))
+            .collect::<Vec<_>>();
+
+        UploadSession {
+            id: data.id.into(),
+            user_id: data.user_id.into(),
+            file_name: data.file_name,
+            file_size: data.file_size as u64,
+            chunk_size: data.chunk_size as u32,
+            chunk_count,
+            received,
+            complete: missing_chunks.is_empty(),
+            missing_chunks,
+            created: data.created,
+            expires: data.expires,
+        }
+    }
+}
@@ -1,6 +1,7 @@
-use crate::database::models::DatabaseError;
+use crate::database::models::{DatabaseError, ProjectId};
 use crate::database::redis::RedisPool;
 use crate::models::analytics::{Download, PageView, Playtime};
+use crate::queue::search_index::SearchIndexQueue;
 use crate::routes::ApiError;
 use dashmap::{DashMap, DashSet};
 use redis::cmd;
@@ -69,6 +70,7 @@ impl AnalyticsQueue {
         client: clickhouse::Client,
         redis: &RedisPool,
         pool: &PgPool,
+        search_index_queue: &SearchIndexQueue,
     ) -> Result<(), ApiError> {
         let views_queue = self.views_queue.clone();
         self.views_queue.clear();
@@ -251,6 +253,10 @@ impl AnalyticsQueue {
 
             transaction.commit().await?;
             downloads.end().await?;
+
+            search_index_queue
+                .add_many(project_downloads.keys().map(|x| ProjectId(*x)))
+                .await;
         }
 
         Ok(())
@@ -2,5 +2,11 @@ pub mod analytics;
 pub mod maxmind;
 pub mod moderation;
 pub mod payouts;
+pub mod revalidation;
+pub mod saved_searches;
+pub mod search_index;
 pub mod session;
+pub mod similar_projects;
 pub mod socket;
+pub mod storage;
+pub mod trending;
@@ -1,16 +1,19 @@
 use crate::auth::checks::filter_visible_versions;
 use crate::database;
 use crate::database::models::notification_item::NotificationBuilder;
+use crate::database::models::scan_finding_item::FileScanFinding;
 use crate::database::models::thread_item::ThreadMessageBuilder;
+use crate::database::models::version_item::QueryVersion;
 use crate::database::redis::RedisPool;
-use crate::models::ids::ProjectId;
+use crate::models::ids::{ProjectId, VersionId};
 use crate::models::notifications::NotificationBody;
 use crate::models::pack::{PackFile, PackFileHash, PackFormat};
-use crate::models::projects::ProjectStatus;
+use crate::models::projects::{ProjectStatus, ValidationReport, VersionStatus};
 use crate::models::threads::MessageBody;
 use crate::routes::ApiError;
 use dashmap::DashSet;
 use itertools::Itertools;
+use log::warn;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use std::collections::HashMap;
@@ -88,6 +91,13 @@ pub enum ModerationMessage {
     MissingCustomLicenseUrl {
         license: String,
     },
+    SuspiciousCode {
+        findings: Vec<FileScanFinding>,
+    },
+    /// Files which passed validation when they were uploaded, but not after it was tightened
+    InvalidFiles {
+        files: Vec<(String, ValidationReport)>,
+    },
 }
 
 impl ModerationMessage {
@@ -109,6 +119,9 @@ impl ModerationMessage {
             ModerationMessage::MissingLicense => true,
             ModerationMessage::MissingCustomLicenseUrl { .. } => true,
             ModerationMessage::NoSideTypes => true,
+            // Findings can be false positives, so they're left for a moderator to decide
+            ModerationMessage::SuspiciousCode { .. } => false,
+            ModerationMessage::InvalidFiles { .. } => false,
         }
     }
 
@@ -122,6 +135,8 @@ impl ModerationMessage {
             ModerationMessage::MissingLicense => false,
             ModerationMessage::MissingCustomLicenseUrl { .. } => false,
             ModerationMessage::NoSideTypes => false,
+            ModerationMessage::SuspiciousCode { .. } => false,
+            ModerationMessage::InvalidFiles { .. } => false,
         }
     }
 
@@ -133,6 +148,8 @@ impl ModerationMessage {
             ModerationMessage::MissingLicense => "Missing License",
             ModerationMessage::MissingCustomLicenseUrl { .. } => "Missing License URL",
             ModerationMessage::NoSideTypes => "Missing Environment Information",
+            ModerationMessage::SuspiciousCode { .. } => "Suspicious Code",
+            ModerationMessage::InvalidFiles { .. } => "Invalid Files",
         }
     }
 
@@ -202,18 +219,54 @@ Keep in mind that you should:\n
             ModerationMessage::MissingLicense => "You must select a License before your project can be published publicly, having a License associated with your project is important to protecting your rights and allowing others to use your content as you intend. For more information, you can see our [Guide to Licensing Mods](<https://blog.modrinth.com/licensing-guide/>).".to_string(),
             ModerationMessage::MissingCustomLicenseUrl { license } => format!("It looks like you've selected the License \"{license}\" without providing a valid License link. When using a custom License you must provide a link directly to the License in the License Link field."),
             ModerationMessage::NoSideTypes => "Your project's side types are currently set to Unknown on both sides. Please set accurate side types!".to_string(),
+            ModerationMessage::SuspiciousCode { findings } => {
+                let mut str = "Our automated scan found code in this version which is commonly used by malware. The version is hidden until a moderator has reviewed it, and no action is needed from you unless we reach out.\n\n".to_string();
+
+                for finding in findings {
+                    str.push_str(&format!(
+                        "- `{}` (`{}`): {}",
+                        finding.file_name,
+                        finding.finding.path,
+                        finding.finding.kind.description(),
+                    ));
+                    if let Some(detail) = &finding.finding.detail {
+                        str.push_str(&format!(" ({detail})"));
+                    }
+                    str.push('\n');
+                }
+
+                str
+            }
+            ModerationMessage::InvalidFiles { files } => {
+                let mut str = "Our file validation has changed since this version was uploaded, and some of its files no longer pass it. Please upload fixed files to this version.\n\n".to_string();
+
+                for (file_name, report) in files {
+                    for finding in report.errors() {
+                        str.push_str(&format!("- `{}`: {}", file_name, finding.message));
+                        if let Some(path) = &finding.path {
+                            str.push_str(&format!(" (`{path}`)"));
+                        }
+                        str.push('\n');
+                    }
+                }
+
+                str
+            }
         }
     }
 }
 
 pub struct AutomatedModerationQueue {
     pub projects: DashSet<ProjectId>,
+    /// Versions of projects which aren't under review which were held for moderation
+    pub versions: DashSet<VersionId>,
 }
 
 impl Default for AutomatedModerationQueue {
     fn default() -> Self {
         Self {
             projects: DashSet::new(),
+            versions: DashSet::new(),
         }
     }
 }
@@ -256,7 +309,13 @@ impl AutomatedModerationQueue {
 
                             let versions =
                                 database::Version::get_many(&project.versions, &pool, &redis)
-                                    .await?
+                                    .await?;
+
+                            for (version_number, messages) in held_version_messages(&versions, &pool).await? {
+                                mod_messages.version_specific.entry(version_number).or_default().extend(messages);
+                            }
+
+                            let versions = versions
                                     .into_iter()
                                     // we only support modpacks at this time
                                     .filter(|x| x.project_types.contains(&"modpack".to_string()))
@@ -754,11 +813,181 @@ impl AutomatedModerationQueue {
                 }.await.ok();
             }
 
+            let versions = self.versions.clone();
+            self.versions.clear();
+
+            for version in versions {
+                if let Err(err) = moderate_held_version(version, &pool, &redis).await {
+                    warn!("Moderating held version {} failed: {:?}", version, err);
+                }
+            }
+
             tokio::time::sleep(Duration::from_secs(5)).await
         }
     }
 }
 
+/// The scan findings of the versions which are held for moderation, by version number
+async fn held_version_messages(
+    versions: &[QueryVersion],
+    pool: &PgPool,
+) -> Result<HashMap<String, Vec<ModerationMessage>>, ApiError> {
+    let held = versions
+        .iter()
+        .filter(|x| x.inner.status == VersionStatus::Held)
+        .collect::<Vec<_>>();
+    if held.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let mut findings = FileScanFinding::get_many_versions(
+        &held.iter().map(|x| x.inner.id).collect::<Vec<_>>(),
+        pool,
+    )
+    .await?
+    .into_iter()
+    .into_group_map_by(|x| x.version_id);
+
+    Ok(held
+        .into_iter()
+        .filter_map(|version| {
+            let findings = findings.remove(&version.inner.id)?;
+            Some((
+                version.inner.version_number.clone(),
+                vec![ModerationMessage::SuspiciousCode { findings }],
+            ))
+        })
+        .collect())
+}
+
+/// Posts the scan findings of a held version on its project's thread, for projects which
+/// aren't already under review
+async fn moderate_held_version(
+    version_id: VersionId,
+    pool: &PgPool,
+    redis: &RedisPool,
+) -> Result<(), ApiError> {
+    let Some(version) = database::Version::get(version_id.into(), pool, redis).await? else {
+        return Ok(());
+    };
+    let Some(project) = database::Project::get_id(version.inner.project_id, pool, redis).await?
+    else {
+        return Ok(());
+    };
+
+    let mod_messages = ModerationMessages {
+        messages: vec![],
+        version_specific: held_version_messages(&[version], pool).await?,
+    };
+
+    post_version_messages(
+        &project,
+        mod_messages,
+        "held a version for review after finding suspicious code",
+        pool,
+        redis,
+    )
+    .await
+}
+
+/// Posts the files of a version which failed a revalidation on its project's thread
+pub async fn moderate_invalid_files(
+    version_id: crate::database::models::VersionId,
+    files: Vec<(String, ValidationReport)>,
+    pool: &PgPool,
+    redis: &RedisPool,
+) -> Result<(), ApiError> {
+    let Some(version) = database::Version::get(version_id, pool, redis).await? else {
+        return Ok(());
+    };
+    let Some(project) = database::Project::get_id(version.inner.project_id, pool, redis).await?
+    else {
+        return Ok(());
+    };
+
+    let mod_messages = ModerationMessages {
+        messages: vec![],
+        version_specific: HashMap::from([(
+            version.inner.version_number,
+            vec![ModerationMessage::InvalidFiles { files }],
+        )]),
+    };
+
+    post_version_messages(
+        &project,
+        mod_messages,
+        "found files which no longer pass validation",
+        pool,
+        redis,
+    )
+    .await
+}
+
+/// Posts AutoMod messages about a project's versions on its thread and notifies its members
+async fn post_version_messages(
+    project: &database::models::project_item::QueryProject,
+    mod_messages: ModerationMessages,
+    webhook_action: &str,
+    pool: &PgPool,
+    redis: &RedisPool,
+) -> Result<(), ApiError> {
+    if mod_messages.is_empty() {
+        return Ok(());
+    }
+
+    let mut transaction = pool.begin().await?;
+    let id = ThreadMessageBuilder {
+        author_id: Some(database::models::UserId(AUTOMOD_ID)),
+        body: MessageBody::Text {
+            body: mod_messages.markdown(true),
+            private: false,
+            replying_to: None,
+            associated_images: vec![],
+        },
+        thread_id: project.thread_id,
+        hide_identity: false,
+    }
+    .insert(&mut transaction)
+    .await?;
+
+    let members =
+        database::models::TeamMember::get_from_team_full(project.inner.team_id, pool, redis)
+            .await?;
+    NotificationBuilder {
+        body: NotificationBody::ModeratorMessage {
+            thread_id: project.thread_id.into(),
+            message_id: id.into(),
+            project_id: Some(project.inner.id.into()),
+            report_id: None,
+        },
+    }
+    .insert_many(
+        members.into_iter().map(|x| x.user_id).collect(),
+        &mut transaction,
+        redis,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    if let Ok(webhook_url) = dotenvy::var("MODERATION_SLACK_WEBHOOK") {
+        crate::util::webhook::send_slack_webhook(
+            project.inner.id.into(),
+            pool,
+            redis,
+            webhook_url,
+            Some(format!(
+                "*<{}/user/AutoMod|AutoMod>* {}",
+                dotenvy::var("SITE_URL")?,
+                webhook_action,
+            )),
+        )
+        .await
+        .ok();
+    }
+
+    Ok(())
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct MissingMetadata {
     pub identified: HashMap<String, IdentifiedFile>,
@@ -0,0 +1,274 @@
+use crate::database;
+use crate::database::models::file_revalidation_job_item::{
+    FileRevalidationJob, RevalidationBatchProgress,
+};
+use crate::database::models::ids::{FileId, VersionId};
+use crate::database::models::modpack_content_item::ModpackContent;
+use crate::database::models::version_item::{QueryFile, QueryVersion};
+use crate::database::redis::RedisPool;
+use crate::file_hosting::{get_file_name_from_url, FileHost};
+use crate::models::projects::{Loader, ValidationReport};
+use crate::queue::moderation::moderate_invalid_files;
+use crate::routes::v3::version_creation::get_file_extension;
+use crate::routes::ApiError;
+use crate::util::env::parse_var;
+use crate::util::routes::spool_from_stream;
+use crate::validate::pack_contents::resolve_pack_files;
+use crate::validate::{validate_file, ValidationError, ValidationResult};
+use log::{info, warn};
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// The most files checked in a single batch. A batch holds at most one minute's worth of
+/// files at the job's rate, so that it finishes well within its lease.
+const MAX_BATCH_SIZE: u32 = 500;
+
+/// How often batches are taken while a job is running, as each holds one minute's worth of files
+const BATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How long a batch may run before another worker can take over the job
+const BATCH_LEASE_MINUTES: i64 = 10;
+
+pub const DEFAULT_FILES_PER_MINUTE: u32 = 60;
+
+/// Processes the running file revalidation job, if there is one. While a job runs, a batch is
+/// started every minute, and otherwise jobs are looked for at the interval in seconds set by
+/// `REVALIDATION_INTERVAL`, which defaults to 1 minute.
+pub async fn task(pool: PgPool, redis: RedisPool, file_host: Arc<dyn FileHost + Send + Sync>) {
+    let interval = std::time::Duration::from_secs(parse_var("REVALIDATION_INTERVAL").unwrap_or(60));
+
+    loop {
+        let start = std::time::Instant::now();
+        let delay = match revalidate_files_batch(&pool, &redis, &*file_host).await {
+            Ok(Some(job)) => {
+                info!(
+                    "Revalidated a batch of files for job {}: {} of {} files checked",
+                    job.id, job.checked_files, job.total_files
+                );
+                BATCH_INTERVAL.saturating_sub(start.elapsed())
+            }
+            Ok(None) => interval,
+            Err(e) => {
+                warn!("Revalidating files failed: {:?}", e);
+                interval
+            }
+        };
+
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Revalidates the next batch of files of the running job, if there is one which no other
+/// worker is processing. Returns the job with the progress of the batch recorded.
+pub async fn revalidate_files_batch(
+    pool: &PgPool,
+    redis: &RedisPool,
+    file_host: &dyn FileHost,
+) -> Result<Option<FileRevalidationJob>, ApiError> {
+    let Some(job) =
+        FileRevalidationJob::claim(chrono::Duration::minutes(BATCH_LEASE_MINUTES), pool).await?
+    else {
+        return Ok(None);
+    };
+
+    let batch_size = job.files_per_minute.clamp(1, MAX_BATCH_SIZE);
+    let files = sqlx::query!(
+        "
+        SELECT id, version_id, validation_report IS NOT NULL \"has_report!\" FROM files
+        WHERE id > $1
+        ORDER BY id
+        LIMIT $2
+        ",
+        job.last_file_id.map(|x| x.0).unwrap_or(i64::MIN),
+        batch_size as i64,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let version_ids = files
+        .iter()
+        .map(|x| VersionId(x.version_id))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    let versions = database::Version::get_many(&version_ids, pool, redis)
+        .await?
+        .into_iter()
+        .map(|x| (x.inner.id, x))
+        .collect::<HashMap<_, _>>();
+
+    let cdn_url = dotenvy::var("CDN_URL")?;
+
+    let mut progress = RevalidationBatchProgress {
+        last_file_id: files.last().map(|x| FileId(x.id)),
+        finished: files.len() < batch_size as usize,
+        ..Default::default()
+    };
+    // The new reports of files which no longer pass validation, by their version
+    let mut newly_failing: HashMap<VersionId, Vec<(FileId, ValidationReport)>> = HashMap::new();
+
+    for row in &files {
+        let Some(version) = versions.get(&VersionId(row.version_id)) else {
+            continue;
+        };
+        let Some(file) = version.files.iter().find(|x| x.id.0 == row.id) else {
+            continue;
+        };
+
+        match revalidate_file(version, file, &cdn_url, pool, redis, file_host).await {
+            Ok(report) => {
+                progress.checked_files += 1;
+
+                // Files uploaded before validation reports were stored have no baseline to
+                // compare against, so their new report is only recorded
+                if row.has_report && file.validation.is_passed() && !report.is_passed() {
+                    progress.newly_failing_files += 1;
+                    newly_failing
+                        .entry(version.inner.id)
+                        .or_default()
+                        .push((file.id, report));
+                }
+            }
+            Err(err) => {
+                warn!("Revalidating file {} failed: {:?}", row.id, err);
+                progress.errored_files += 1;
+            }
+        }
+    }
+
+    for version in versions.values() {
+        if let Some(failing) = newly_failing.get(&version.inner.id) {
+            if job.update_primary {
+                replace_failing_primary(version, failing, pool).await?;
+            }
+
+            if job.open_threads {
+                let files = failing
+                    .iter()
+                    .filter_map(|(id, report)| {
+                        let file = version.files.iter().find(|x| x.id == *id)?;
+                        Some((file.filename.clone(), report.clone()))
+                    })
+                    .collect();
+                moderate_invalid_files(version.inner.id, files, pool, redis).await?;
+            }
+        }
+
+        database::Version::clear_cache(version, redis).await?;
+    }
+
+    FileRevalidationJob::record_batch(job.id, &progress, pool).await?;
+
+    Ok(FileRevalidationJob::get(job.id, pool).await?)
+}
+
+/// Downloads a stored file, runs the current validators on it and stores the new report
+async fn revalidate_file(
+    version: &QueryVersion,
+    file: &QueryFile,
+    cdn_url: &str,
+    pool: &PgPool,
+    redis: &RedisPool,
+    file_host: &dyn FileHost,
+) -> Result<ValidationReport, ApiError> {
+    let file_name = get_file_name_from_url(cdn_url, &file.url)
+        .ok_or_else(|| ApiError::InvalidInput(format!("{} is not on the CDN", file.url)))?;
+    let file_extension =
+        get_file_extension(&file.filename).map_err(|e| ApiError::InvalidInput(e.to_string()))?;
+
+    let stream = file_host.download_file("", &file_name).await?;
+    let spooled = spool_from_stream(stream, u64::MAX, "Stored file could not be read")
+        .await
+        .map_err(|e| ApiError::InvalidInput(e.to_string()))?;
+
+    let mut transaction = pool.begin().await?;
+    let report = match validate_file(
+        spooled
+            .open()
+            .map_err(crate::file_hosting::FileHostingError::from)?,
+        file_extension.to_string(),
+        version.loaders.iter().cloned().map(Loader).collect(),
+        file.file_type,
+        version.version_fields.clone(),
+        &mut transaction,
+        redis,
+    )
+    .await
+    {
+        Ok(validation) => {
+            let mut report = validation.report;
+
+            // Modpacks are resolved as when they are uploaded, which fills in the contents of
+            // those uploaded before contents were stored
+            if let ValidationResult::PassWithPackDataAndFiles { format, .. } = &validation.result {
+                let contents =
+                    resolve_pack_files(&format.files, cdn_url, &mut report, &mut transaction)
+                        .await?;
+                ModpackContent::backfill(file.id, &contents, &mut transaction).await?;
+            }
+
+            report
+        }
+        Err(
+            err @ (ValidationError::Database(_)
+            | ValidationError::Blocking(_)
+            | ValidationError::UnknownValidator(..)),
+        ) => return Err(ApiError::Validation(err.to_string())),
+        // Files which would be rejected if they were uploaded now
+        Err(err) => {
+            let mut report = ValidationReport::default();
+            report.error("invalid_file", err.to_string());
+            report
+        }
+    };
+
+    sqlx::query!(
+        "
+        UPDATE files
+        SET validation_report = $1
+        WHERE id = $2
+        ",
+        serde_json::to_value(&report)?,
+        file.id.0,
+    )
+    .execute(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+
+    Ok(report)
+}
+
+/// Makes another passing file of the version primary if its primary file no longer passes
+async fn replace_failing_primary(
+    version: &QueryVersion,
+    failing: &[(FileId, ValidationReport)],
+    pool: &PgPool,
+) -> Result<(), ApiError> {
+    let Some(primary) = version.files.iter().find(|x| x.primary) else {
+        return Ok(());
+    };
+    if !failing.iter().any(|(id, _)| *id == primary.id) {
+        return Ok(());
+    }
+
+    let replacement = version.files.iter().find(|x| {
+        x.id != primary.id && x.validation.is_passed() && !failing.iter().any(|(id, _)| *id == x.id)
+    });
+
+    if let Some(replacement) = replacement {
+        sqlx::query!(
+            "
+            UPDATE files
+            SET is_primary = (id = $2)
+            WHERE version_id = $1
+            ",
+            version.inner.id.0,
+            replacement.id.0,
+        )
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,151 @@
+use crate::database::models::notification_item::NotificationBuilder;
+use crate::database::models::saved_search_item::SavedSearch;
+use crate::database::models::ProjectId;
+use crate::database::redis::RedisPool;
+use crate::models::ids;
+use crate::models::ids::base62_impl::parse_base62;
+use crate::models::notifications::NotificationBody;
+use crate::models::projects::{ProjectStatus, SearchRequest};
+use crate::routes::ApiError;
+use crate::search::filter::Filter;
+use crate::search::{search_for_project_filtered, SearchConfig, SearchError};
+use crate::util::env::parse_var;
+use chrono::{DateTime, Duration, Utc};
+use log::{info, warn};
+use sqlx::PgPool;
+
+/// How long after their approval projects are matched, so that they have been indexed
+const INDEXING_DELAY_MINUTES: i64 = 5;
+/// How many newly approved projects are matched against a saved search at once, which is the
+/// most results a search returns, so that no matches are left out
+const MATCH_CHUNK_SIZE: usize = 100;
+
+/// Matches saved searches against newly approved projects at the interval in seconds set by
+/// `SAVED_SEARCHES_INTERVAL`, which defaults to 1 hour.
+pub async fn task(pool: PgPool, redis: RedisPool, config: SearchConfig) {
+    let interval =
+        std::time::Duration::from_secs(parse_var("SAVED_SEARCHES_INTERVAL").unwrap_or(3600));
+
+    loop {
+        match notify_saved_search_matches(&pool, &redis, &config).await {
+            Ok(notified) => info!(
+                "Done matching saved searches, sent {} notifications",
+                notified
+            ),
+            Err(e) => warn!("Matching saved searches failed: {:?}", e),
+        }
+
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Matches the projects approved since each saved search was last checked against it, and
+/// notifies the owners of searches with fresh matches. Returns the number of notifications sent.
+pub async fn notify_saved_search_matches(
+    pool: &PgPool,
+    redis: &RedisPool,
+    config: &SearchConfig,
+) -> Result<usize, ApiError> {
+    let checked_until = Utc::now() - Duration::minutes(INDEXING_DELAY_MINUTES);
+
+    let searches = SavedSearch::get_unchecked(checked_until, pool).await?;
+    let Some(since) = searches.iter().map(|x| x.last_checked).min() else {
+        return Ok(0);
+    };
+
+    let statuses = ProjectStatus::iterator()
+        .filter(|x| x.is_searchable())
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>();
+    let approved: Vec<(ProjectId, DateTime<Utc>)> = sqlx::query!(
+        "
+        SELECT m.id, m.approved
+        FROM mods m
+        WHERE m.approved > $1 AND m.approved <= $2 AND m.status = ANY($3)
+        ",
+        since,
+        checked_until,
+        &statuses,
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .filter_map(|m| Some((ProjectId(m.id), m.approved?)))
+    .collect();
+
+    let mut notified = 0;
+    'searches: for search in searches {
+        let new_projects = approved
+            .iter()
+            .filter(|(_, approved)| *approved > search.last_checked)
+            .map(|(id, _)| ids::ProjectId::from(*id).to_string())
+            .collect::<Vec<_>>();
+
+        let mut matches = Vec::new();
+        for chunk in new_projects.chunks(MATCH_CHUNK_SIZE) {
+            let request = SearchRequest {
+                offset: None,
+                limit: Some(MATCH_CHUNK_SIZE.to_string()),
+                ..search.request.clone()
+            };
+            let restrict = Filter::In {
+                field: "project_id".to_string(),
+                values: chunk.to_vec(),
+            };
+
+            match search_for_project_filtered(&request, Some(restrict), config, pool, redis).await {
+                Ok(results) => matches.extend(
+                    results
+                        .hits
+                        .into_iter()
+                        .filter_map(|x| parse_base62(&x.project_id).ok())
+                        .map(ids::ProjectId),
+                ),
+                // Searches may stop working, such as when a loader field they filter on is
+                // removed, and are skipped rather than retried every time
+                Err(
+                    e @ (SearchError::InvalidFilter(..)
+                    | SearchError::InvalidFacet(..)
+                    | SearchError::InvalidIndex(..)
+                    | SearchError::InvalidMode(..)),
+                ) => {
+                    warn!(
+                        "Saved search {} is invalid, skipping it: {:?}",
+                        ids::SavedSearchId::from(search.id),
+                        e
+                    );
+                    matches.clear();
+                    break;
+                }
+                // Other errors, such as the search backend being unavailable, leave the search
+                // unchecked so that it is retried on the next run
+                Err(e) => {
+                    warn!(
+                        "Matching saved search {} failed: {:?}",
+                        ids::SavedSearchId::from(search.id),
+                        e
+                    );
+                    continue 'searches;
+                }
+            }
+        }
+
+        let mut transaction = pool.begin().await?;
+        if !matches.is_empty() {
+            NotificationBuilder {
+                body: NotificationBody::SavedSearchMatches {
+                    saved_search_id: search.id.into(),
+                    name: search.name.clone(),
+                    project_ids: matches,
+                },
+            }
+            .insert(search.user_id, &mut transaction, redis)
+            .await?;
+            notified += 1;
+        }
+        SavedSearch::update_last_checked(&[search.id], checked_until, &mut *transaction).await?;
+        transaction.commit().await?;
+    }
+
+    Ok(notified)
+}
@@ -0,0 +1,134 @@
+use crate::database::models::ProjectId;
+use crate::database::redis::RedisPool;
+use crate::search::indexing::{index_projects, update_projects, IndexingError};
+use crate::search::SearchConfig;
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// The longest a project waits to be reindexed while it keeps changing
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Changed projects waiting to be reindexed in search. A project is reindexed once it has not
+/// changed for the debounce period, so that a burst of edits results in a single update.
+pub struct SearchIndexQueue {
+    debounce: Duration,
+    /// When each project was first and last queued since it was reindexed
+    projects: Mutex<HashMap<ProjectId, (Instant, Instant)>>,
+    /// The number of full rebuilds running, and the projects reindexed while they run
+    rebuilds: Mutex<(u32, HashSet<ProjectId>)>,
+}
+
+impl SearchIndexQueue {
+    pub fn new(debounce: Duration) -> Self {
+        SearchIndexQueue {
+            debounce,
+            projects: Mutex::new(HashMap::new()),
+            rebuilds: Mutex::new((0, HashSet::new())),
+        }
+    }
+
+    pub async fn add(&self, id: ProjectId) {
+        self.add_many([id]).await;
+    }
+
+    pub async fn add_many(&self, ids: impl IntoIterator<Item = ProjectId>) {
+        let now = Instant::now();
+        let mut projects = self.projects.lock().await;
+
+        for id in ids {
+            projects
+                .entry(id)
+                .and_modify(|(_, last)| *last = now)
+                .or_insert((now, now));
+        }
+    }
+
+    /// Removes and returns the projects which are due to be reindexed
+    async fn take_ready(&self) -> Vec<ProjectId> {
+        let now = Instant::now();
+        let mut projects = self.projects.lock().await;
+
+        let ready = projects
+            .iter()
+            .filter(|(_, (first, last))| {
+                now.duration_since(*last) >= self.debounce
+                    || now.duration_since(*first) >= MAX_DELAY
+            })
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+
+        for id in &ready {
+            projects.remove(id);
+        }
+
+        ready
+    }
+
+    pub async fn index(&self, pool: &PgPool, config: &SearchConfig) -> Result<(), IndexingError> {
+        let projects = self.take_ready().await;
+
+        if projects.is_empty() {
+            return Ok(());
+        }
+
+        if let Err(err) = update_projects(&projects, pool, config).await {
+            // Retry on the next run
+            self.add_many(projects).await;
+            return Err(err);
+        }
+
+        let mut rebuilds = self.rebuilds.lock().await;
+        if rebuilds.0 > 0 {
+            rebuilds.1.extend(projects);
+        }
+
+        Ok(())
+    }
+
+    /// Rebuilds every search index from the database. Rebuilt indexes replace the current ones
+    /// once filled, so projects reindexed while it runs, which are only updated in the current
+    /// indexes, are queued to be reindexed again afterwards.
+    pub async fn rebuild(
+        &self,
+        pool: PgPool,
+        redis: &RedisPool,
+        config: &SearchConfig,
+    ) -> Result<(), IndexingError> {
+        self.rebuilds.lock().await.0 += 1;
+
+        let result = index_projects(pool, redis, config).await;
+
+        let reindexed = {
+            let mut rebuilds = self.rebuilds.lock().await;
+            rebuilds.0 -= 1;
+            std::mem::take(&mut rebuilds.1)
+        };
+        self.add_many(reindexed).await;
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_rt::test]
+    async fn debounces_changes() {
+        let queue = SearchIndexQueue::new(Duration::from_millis(50));
+        queue.add(ProjectId(1)).await;
+        queue.add(ProjectId(2)).await;
+        assert!(queue.take_ready().await.is_empty());
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        queue.add(ProjectId(2)).await;
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(queue.take_ready().await, vec![ProjectId(1)]);
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(queue.take_ready().await, vec![ProjectId(2)]);
+        assert!(queue.take_ready().await.is_empty());
+    }
+}
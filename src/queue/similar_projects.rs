@@ -0,0 +1,379 @@
+use crate::database::models::ProjectId;
+use crate::database::redis::RedisPool;
+use crate::models::collections::CollectionStatus;
+use crate::models::projects::ProjectStatus;
+use crate::routes::ApiError;
+use futures::TryStreamExt;
+use itertools::Itertools;
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+
+pub const SIMILAR_PROJECTS_NAMESPACE: &str = "similar_projects";
+
+/// How many similar projects are kept for each project
+const SIMILAR_COUNT: usize = 20;
+/// How many of the most downloaded projects of each category are considered similar candidates
+/// to the projects sharing it, on top of those found in the same modpacks or collections
+const CATEGORY_CANDIDATES: usize = 200;
+/// Modpacks and collections with more projects than this say little about how the projects
+/// relate, and are too costly to pair up, so they are skipped
+const MAX_GROUP_SIZE: usize = 500;
+
+const CATEGORY_WEIGHT: f64 = 1.0;
+const LOADER_WEIGHT: f64 = 0.5;
+const GAME_VERSION_WEIGHT: f64 = 0.5;
+const MODPACK_WEIGHT: f64 = 2.0;
+const COLLECTION_WEIGHT: f64 = 1.0;
+
+/// What projects are compared by. Every list is sorted.
+#[derive(Default, Debug, Clone)]
+struct Features {
+    downloads: i32,
+    categories: Vec<i32>,
+    loaders: Vec<i32>,
+    project_types: Vec<i32>,
+    game_versions: Vec<i32>,
+    /// The modpacks containing the project
+    modpacks: Vec<i64>,
+    /// The listed collections containing the project
+    collections: Vec<i64>,
+}
+
+/// The size of the intersection of two sorted lists relative to the size of their union
+fn jaccard(a: &[i32], b: &[i32]) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 0.0;
+    }
+
+    let (mut i, mut j, mut shared) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                shared += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+
+    shared as f64 / (a.len() + b.len() - shared) as f64
+}
+
+/// How often two projects appear together in groups such as modpacks, relative to how often
+/// they each appear, so that projects which are in every modpack don't rank first everywhere
+fn co_occurrence(shared: u32, a: usize, b: usize) -> f64 {
+    if shared == 0 {
+        return 0.0;
+    }
+
+    shared as f64 / ((a * b) as f64).sqrt()
+}
+
+/// How similar two projects are, from their shared attributes and how often they appear in the
+/// same modpacks and collections. Projects of different types are never similar.
+fn similarity(a: &Features, b: &Features, shared_modpacks: u32, shared_collections: u32) -> f64 {
+    if jaccard(&a.project_types, &b.project_types) == 0.0 {
+        return 0.0;
+    }
+
+    CATEGORY_WEIGHT * jaccard(&a.categories, &b.categories)
+        + LOADER_WEIGHT * jaccard(&a.loaders, &b.loaders)
+        + GAME_VERSION_WEIGHT * jaccard(&a.game_versions, &b.game_versions)
+        + MODPACK_WEIGHT * co_occurrence(shared_modpacks, a.modpacks.len(), b.modpacks.len())
+        + COLLECTION_WEIGHT
+            * co_occurrence(shared_collections, a.collections.len(), b.collections.len())
+}
+
+/// Counts how many groups each other project shares with a project
+fn count_shared(id: i64, groups: &[i64], members: &HashMap<i64, Vec<i64>>) -> HashMap<i64, u32> {
+    let mut shared = HashMap::new();
+    for member in groups.iter().filter_map(|x| members.get(x)).flatten() {
+        if *member != id {
+            *shared.entry(*member).or_default() += 1;
+        }
+    }
+    shared
+}
+
+/// Ranks the most similar projects of every project, most similar first
+fn rank_similar(projects: &HashMap<i64, Features>) -> HashMap<i64, Vec<i64>> {
+    let group_members = |groups: fn(&Features) -> &Vec<i64>| {
+        let mut members: HashMap<i64, Vec<i64>> = HashMap::new();
+        for (id, features) in projects {
+            for group in groups(features) {
+                members.entry(*group).or_default().push(*id);
+            }
+        }
+        members.retain(|_, x| x.len() <= MAX_GROUP_SIZE);
+        members
+    };
+    let modpack_members = group_members(|x| &x.modpacks);
+    let collection_members = group_members(|x| &x.collections);
+
+    let mut category_top: HashMap<i32, Vec<i64>> = HashMap::new();
+    for (id, features) in projects
+        .iter()
+        .sorted_by_key(|(_, x)| std::cmp::Reverse(x.downloads))
+    {
+        for category in &features.categories {
+            let top = category_top.entry(*category).or_default();
+            if top.len() < CATEGORY_CANDIDATES {
+                top.push(*id);
+            }
+        }
+    }
+
+    projects
+        .iter()
+        .map(|(id, features)| {
+            let shared_modpacks = count_shared(*id, &features.modpacks, &modpack_members);
+            let shared_collections = count_shared(*id, &features.collections, &collection_members);
+
+            let candidates = shared_modpacks
+                .keys()
+                .chain(shared_collections.keys())
+                .chain(
+                    features
+                        .categories
+                        .iter()
+                        .filter_map(|x| category_top.get(x))
+                        .flatten(),
+                )
+                .filter(|x| *x != id)
+                .collect::<HashSet<_>>();
+
+            let similar = candidates
+                .into_iter()
+                .filter_map(|candidate| {
+                    let score = similarity(
+                        features,
+                        projects.get(candidate)?,
+                        shared_modpacks.get(candidate).copied().unwrap_or_default(),
+                        shared_collections
+                            .get(candidate)
+                            .copied()
+                            .unwrap_or_default(),
+                    );
+                    (score > 0.0).then_some((*candidate, score))
+                })
+                // Ties are broken by id, so that rankings are stable between runs
+                .sorted_by(|(a_id, a), (b_id, b)| b.total_cmp(a).then(a_id.cmp(b_id)))
+                .take(SIMILAR_COUNT)
+                .map(|(candidate, _)| candidate)
+                .collect();
+
+            (*id, similar)
+        })
+        .collect()
+}
+
+/// Ranks the projects most similar to every searchable project by their shared categories,
+/// loaders and game versions, and by how often they appear in the same modpacks and listed
+/// collections. The rankings are cached in Redis for the given number of seconds, to be
+/// returned by the similar projects route. Returns the number of projects ranked.
+pub async fn update_similar_projects(
+    pool: &PgPool,
+    redis: &RedisPool,
+    expiry: i64,
+) -> Result<usize, ApiError> {
+    let statuses = ProjectStatus::iterator()
+        .filter(|x| x.is_searchable())
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>();
+
+    let mut projects: HashMap<i64, Features> = sqlx::query!(
+        "
+        SELECT m.id, m.downloads
+        FROM mods m
+        WHERE m.status = ANY($1)
+        ",
+        &statuses,
+    )
+    .fetch(pool)
+    .map_ok(|m| {
+        (
+            m.id,
+            Features {
+                downloads: m.downloads,
+                ..Default::default()
+            },
+        )
+    })
+    .try_collect()
+    .await?;
+    let project_ids = projects.keys().copied().collect::<Vec<_>>();
+
+    let categories = sqlx::query!(
+        "
+        SELECT mc.joining_mod_id mod_id, ARRAY_AGG(DISTINCT mc.joining_category_id) categories
+        FROM mods_categories mc
+        WHERE mc.joining_mod_id = ANY($1)
+        GROUP BY mc.joining_mod_id
+        ",
+        &project_ids,
+    )
+    .fetch_all(pool)
+    .await?;
+    for m in categories {
+        if let Some(features) = projects.get_mut(&m.mod_id) {
+            features.categories = m
+                .categories
+                .unwrap_or_default()
+                .into_iter()
+                .sorted()
+                .collect();
+        }
+    }
+
+    let loaders = sqlx::query!(
+        "
+        SELECT v.mod_id, ARRAY_AGG(DISTINCT lv.loader_id) loaders,
+        ARRAY_AGG(DISTINCT lpt.joining_project_type_id) project_types
+        FROM versions v
+        INNER JOIN loaders_versions lv ON lv.version_id = v.id
+        INNER JOIN loaders_project_types lpt ON lpt.joining_loader_id = lv.loader_id
+        WHERE v.mod_id = ANY($1)
+        GROUP BY v.mod_id
+        ",
+        &project_ids,
+    )
+    .fetch_all(pool)
+    .await?;
+    for m in loaders {
+        if let Some(features) = projects.get_mut(&m.mod_id) {
+            features.loaders = m.loaders.unwrap_or_default().into_iter().sorted().collect();
+            features.project_types = m
+                .project_types
+                .unwrap_or_default()
+                .into_iter()
+                .sorted()
+                .collect();
+        }
+    }
+
+    let game_versions = sqlx::query!(
+        "
+        SELECT v.mod_id, ARRAY_AGG(DISTINCT vf.enum_value) game_versions
+        FROM versions v
+        INNER JOIN version_fields vf ON vf.version_id = v.id
+        INNER JOIN loader_fields lf ON lf.id = vf.field_id
+        WHERE v.mod_id = ANY($1) AND lf.field = 'game_versions' AND vf.enum_value IS NOT NULL
+        GROUP BY v.mod_id
+        ",
+        &project_ids,
+    )
+    .fetch_all(pool)
+    .await?;
+    for m in game_versions {
+        if let Some(features) = projects.get_mut(&m.mod_id) {
+            features.game_versions = m
+                .game_versions
+                .unwrap_or_default()
+                .into_iter()
+                .sorted()
+                .collect();
+        }
+    }
+
+    // The projects which the files of each modpack's versions resolved to. Modpacks uploaded
+    // before their contents were stored only count once a revalidation job has filled them in.
+    let modpack_contents = sqlx::query!(
+        "
+        SELECT DISTINCT v.mod_id pack_id, mc.project_id
+        FROM modpack_contents mc
+        INNER JOIN files f ON f.id = mc.file_id
+        INNER JOIN versions v ON v.id = f.version_id
+        WHERE v.mod_id = ANY($1) AND mc.project_id = ANY($1)
+        ",
+        &project_ids,
+    )
+    .fetch_all(pool)
+    .await?;
+    for m in modpack_contents {
+        if let Some(features) = m.project_id.and_then(|x| projects.get_mut(&x)) {
+            features.modpacks.push(m.pack_id);
+        }
+    }
+
+    let collections = sqlx::query!(
+        "
+        SELECT cm.collection_id, cm.mod_id
+        FROM collections_mods cm
+        INNER JOIN collections c ON c.id = cm.collection_id
+        WHERE c.status = $1 AND cm.mod_id = ANY($2)
+        ",
+        CollectionStatus::Listed.as_str(),
+        &project_ids,
+    )
+    .fetch_all(pool)
+    .await?;
+    for m in collections {
+        if let Some(features) = projects.get_mut(&m.mod_id) {
+            features.collections.push(m.collection_id);
+        }
+    }
+
+    // Ranking compares every project with hundreds of others, so it is kept off the async runtime
+    let similar = actix_rt::task::spawn_blocking(move || rank_similar(&projects))
+        .await
+        .map_err(std::io::Error::other)?;
+
+    let mut redis = redis.connect().await?;
+    for (id, similar) in &similar {
+        let similar = similar.iter().map(|x| ProjectId(*x)).collect::<Vec<_>>();
+        redis
+            .set_serialized_to_json(SIMILAR_PROJECTS_NAMESPACE, id, &similar, Some(expiry))
+            .await?;
+    }
+
+    Ok(similar.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rank_by_shared_attributes_and_groups() {
+        let features = |categories: &[i32], modpacks: &[i64], downloads| Features {
+            downloads,
+            categories: categories.to_vec(),
+            loaders: vec![1],
+            project_types: vec![1],
+            game_versions: vec![1, 2],
+            modpacks: modpacks.to_vec(),
+            collections: Vec::new(),
+        };
+
+        let projects = vec![
+            (1, features(&[1, 2], &[100], 10)),
+            // Shares a category and a modpack
+            (2, features(&[2], &[100], 10)),
+            // Shares both categories but no modpack
+            (3, features(&[1, 2], &[], 10)),
+            // Shares nothing but its loader and game versions
+            (4, features(&[3], &[], 1000)),
+            // Shares a category, but is of another type
+            (
+                5,
+                Features {
+                    project_types: vec![2],
+                    ..features(&[1, 2], &[100], 10)
+                },
+            ),
+        ]
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+        let similar = rank_similar(&projects);
+        assert_eq!(similar[&1], vec![2, 3]);
+        // Project 4 is only a candidate through its own category, which no other project has
+        assert!(similar[&4].is_empty());
+
+        assert_eq!(jaccard(&[1, 2, 3], &[2, 3, 4]), 0.5);
+        assert_eq!(jaccard(&[], &[]), 0.0);
+        assert_eq!(co_occurrence(2, 4, 4), 0.5);
+    }
+}
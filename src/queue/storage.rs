@@ -0,0 +1,344 @@
+use crate::database::models::storage_reconciliation_run_item::StorageReconciliationRun;
+use crate::file_hosting::visibility::PRIVATE_PREFIX;
+use crate::file_hosting::{get_file_name_from_url, FileHost};
+use crate::routes::ApiError;
+use chrono::{DateTime, Duration, Utc};
+use futures::TryStreamExt;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashSet;
+
+// Only files under these prefixes are reconciled, so other files sharing the same bucket are
+// never reported or purged
+const RECONCILED_PREFIXES: &[&str] = &["data/", "user/", "uploads/", PRIVATE_PREFIX];
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct StorageReport {
+    /// The number of stored files which were checked
+    pub stored_files: u64,
+    /// The number of stored files which are referenced by the database
+    pub referenced_files: u64,
+    /// Files referenced by the database which do not exist in storage
+    pub missing: Vec<MissingFile>,
+    /// Stored files which are not referenced by the database
+    pub orphaned: Vec<OrphanedFile>,
+    /// Orphaned files which were deleted from storage
+    pub purged: Vec<String>,
+    /// Referenced files which were stored before replication was enabled, and started being
+    /// replicated
+    pub backfilled_replicas: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MissingFile {
+    pub file_name: String,
+    /// The table which references the file
+    pub source: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OrphanedFile {
+    pub file_name: String,
+    pub size: u64,
+    pub last_modified: DateTime<Utc>,
+}
+
+/// How many references are copied to the database at once
+const REFERENCE_PAGE_SIZE: usize = 10000;
+
+/// The shortest grace period orphaned files may be purged with. Files are uploaded to storage
+/// before the rows referencing them are committed, so purging recent files could delete those of
+/// uploads which are still in progress.
+pub const MIN_PURGE_GRACE_PERIOD_HOURS: i64 = 6;
+
+/// A file referenced by the database
+struct Reference {
+    file_name: String,
+    /// The table which references the file
+    source: String,
+    /// Whether the file was uploaded to storage. The paths of URLs which are not on the CDN
+    /// are never purged, in case the CDN URL has changed, but are not reported as missing
+    /// either.
+    stored: bool,
+}
+
+/// Copies the files referenced by the database to the temporary `storage_references` table of
+/// the transaction a page at a time, so that they can be looked up without all of them being
+/// held in memory.
+async fn copy_references(
+    pool: &PgPool,
+    cdn_url: &str,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<(), ApiError> {
+    sqlx::query(
+        "
+        CREATE TEMPORARY TABLE storage_references (
+            file_name text NOT NULL,
+            source text NOT NULL,
+            stored boolean NOT NULL,
+            listed boolean NOT NULL DEFAULT FALSE
+        ) ON COMMIT DROP
+        ",
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    let mut rows = sqlx::query!(
+        r#"
+        SELECT url "url!", 'files' "source!" FROM files
+        UNION ALL
+        SELECT url, 'uploaded_images' FROM uploaded_images
+        UNION ALL
+        SELECT raw_url, 'uploaded_images' FROM uploaded_images
+        UNION ALL
+        SELECT icon_url, 'mods' FROM mods WHERE icon_url IS NOT NULL
+        UNION ALL
+        SELECT raw_icon_url, 'mods' FROM mods WHERE raw_icon_url IS NOT NULL
+        UNION ALL
+        SELECT image_url, 'mods_gallery' FROM mods_gallery
+        UNION ALL
+        SELECT raw_image_url, 'mods_gallery' FROM mods_gallery
+        UNION ALL
+        SELECT avatar_url, 'users' FROM users WHERE avatar_url IS NOT NULL
+        UNION ALL
+        SELECT raw_avatar_url, 'users' FROM users WHERE raw_avatar_url IS NOT NULL
+        UNION ALL
+        SELECT icon_url, 'organizations' FROM organizations WHERE icon_url IS NOT NULL
+        UNION ALL
+        SELECT raw_icon_url, 'organizations' FROM organizations WHERE raw_icon_url IS NOT NULL
+        UNION ALL
+        SELECT icon_url, 'collections' FROM collections WHERE icon_url IS NOT NULL
+        UNION ALL
+        SELECT raw_icon_url, 'collections' FROM collections WHERE raw_icon_url IS NOT NULL
+        UNION ALL
+        SELECT icon_url, 'oauth_clients' FROM oauth_clients WHERE icon_url IS NOT NULL
+        UNION ALL
+        SELECT raw_icon_url, 'oauth_clients' FROM oauth_clients WHERE raw_icon_url IS NOT NULL
+        UNION ALL
+        SELECT file_name, 'upload_session_chunks' FROM upload_session_chunks
+        "#
+    )
+    .fetch(pool);
+
+    let mut page = Vec::with_capacity(REFERENCE_PAGE_SIZE);
+    loop {
+        let row = rows.try_next().await?;
+        let finished = row.is_none();
+
+        if let Some(row) = row {
+            if row.source == "upload_session_chunks" {
+                page.push(Reference {
+                    file_name: row.url,
+                    source: row.source,
+                    stored: true,
+                });
+            } else if let Some(file_name) = get_file_name_from_url(cdn_url, &row.url) {
+                page.push(Reference {
+                    file_name,
+                    source: row.source,
+                    stored: true,
+                });
+            } else if let Ok(url) = url::Url::parse(&row.url) {
+                let path = url.path().trim_start_matches('/');
+                page.push(Reference {
+                    file_name: urlencoding::decode(path)
+                        .map(|x| x.into_owned())
+                        .unwrap_or_else(|_| path.to_string()),
+                    source: row.source,
+                    stored: false,
+                });
+            }
+        }
+
+        if page.len() >= REFERENCE_PAGE_SIZE || (finished && !page.is_empty()) {
+            sqlx::query(
+                "
+                INSERT INTO storage_references (file_name, source, stored)
+                SELECT * FROM UNNEST ($1::text[], $2::text[], $3::boolean[])
+                ",
+            )
+            .bind(page.iter().map(|x| x.file_name.clone()).collect::<Vec<_>>())
+            .bind(page.iter().map(|x| x.source.clone()).collect::<Vec<_>>())
+            .bind(page.iter().map(|x| x.stored).collect::<Vec<_>>())
+            .execute(&mut **transaction)
+            .await?;
+            page.clear();
+        }
+
+        if finished {
+            break;
+        }
+    }
+
+    sqlx::query("CREATE INDEX ON storage_references (file_name)")
+        .execute(&mut **transaction)
+        .await?;
+    sqlx::query("ANALYZE storage_references")
+        .execute(&mut **transaction)
+        .await?;
+
+    Ok(())
+}
+
+/// Checks that orphaned files aren't purged before uploads which may still be in progress finish
+pub fn check_purge_grace_period(purge_grace_period: Option<Duration>) -> Result<(), ApiError> {
+    if purge_grace_period.is_some_and(|x| x < Duration::hours(MIN_PURGE_GRACE_PERIOD_HOURS)) {
+        return Err(ApiError::InvalidInput(format!(
+            "The purge grace period must be at least {MIN_PURGE_GRACE_PERIOD_HOURS} hours"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Reconciles storage with the database, logging the outcome and recording it in the
+/// [`StorageReconciliationRun`] with the id `run_id`
+pub async fn reconcile_storage_logged(
+    pool: &PgPool,
+    file_host: &(dyn FileHost + Send + Sync),
+    purge_grace_period: Option<Duration>,
+    run_id: i64,
+) {
+    info!("Reconciling storage");
+    let outcome = match reconcile_storage(pool, file_host, purge_grace_period).await {
+        Ok(report) => {
+            info!(
+                "Done reconciling storage: {} missing, {} orphaned and {} purged files, {} files started being replicated",
+                report.missing.len(),
+                report.orphaned.len(),
+                report.purged.len(),
+                report.backfilled_replicas
+            );
+            serde_json::to_value(&report).map_err(|e| e.to_string())
+        }
+        Err(e) => {
+            warn!("Reconciling storage failed: {:?}", e);
+            Err(e.to_string())
+        }
+    };
+
+    if let Err(e) = StorageReconciliationRun::finish(run_id, outcome, pool).await {
+        warn!(
+            "Recording storage reconciliation run {} failed: {:?}",
+            run_id, e
+        );
+    }
+}
+
+/// Checks that every file referenced by the database exists in storage, and finds stored files
+/// which are not referenced by anything. If `purge_grace_period` is set, orphaned files older
+/// than it are deleted. It can't be shorter than [`MIN_PURGE_GRACE_PERIOD_HOURS`].
+pub async fn reconcile_storage(
+    pool: &PgPool,
+    file_host: &(dyn FileHost + Send + Sync),
+    purge_grace_period: Option<Duration>,
+) -> Result<StorageReport, ApiError> {
+    check_purge_grace_period(purge_grace_period)?;
+
+    let cdn_url = dotenvy::var("CDN_URL")?;
+
+    // The references only live as long as the transaction, which is never committed
+    let mut transaction = pool.begin().await?;
+    copy_references(pool, &cdn_url, &mut transaction).await?;
+
+    let mut report = StorageReport::default();
+    let purge_before = purge_grace_period.map(|x| Utc::now() - x);
+
+    for prefix in RECONCILED_PREFIXES {
+        let mut cursor = None;
+
+        loop {
+            let page = file_host.list_files(prefix, cursor).await?;
+
+            let listed: Vec<(String, bool)> = sqlx::query_as(
+                "
+                UPDATE storage_references
+                SET listed = TRUE
+                WHERE file_name = ANY($1)
+                RETURNING file_name, stored
+                ",
+            )
+            .bind(
+                page.files
+                    .iter()
+                    .map(|x| x.file_name.clone())
+                    .collect::<Vec<_>>(),
+            )
+            .fetch_all(&mut *transaction)
+            .await?;
+            let referenced = listed
+                .iter()
+                .filter(|(_, stored)| *stored)
+                .map(|(file_name, _)| file_name.as_str())
+                .collect::<HashSet<_>>();
+            let other = listed
+                .iter()
+                .filter(|(_, stored)| !*stored)
+                .map(|(file_name, _)| file_name.as_str())
+                .collect::<HashSet<_>>();
+
+            let referenced_files = page
+                .files
+                .iter()
+                .filter(|x| referenced.contains(x.file_name.as_str()))
+                .cloned()
+                .collect::<Vec<_>>();
+            report.backfilled_replicas += file_host.backfill_replicas(&referenced_files).await?;
+
+            for file in page.files {
+                report.stored_files += 1;
+
+                if referenced.contains(file.file_name.as_str()) {
+                    report.referenced_files += 1;
+                    continue;
+                }
+                if other.contains(file.file_name.as_str()) {
+                    continue;
+                }
+
+                // Files which were uploaded recently may not have been committed to the
+                // database yet, so only files older than the grace period are purged
+                if purge_before.is_some_and(|x| file.last_modified < x) {
+                    match file_host
+                        .delete_file_version(&file.file_id, &file.file_name)
+                        .await
+                    {
+                        Ok(_) => report.purged.push(file.file_name.clone()),
+                        Err(e) => warn!("Purging file {} failed: {:?}", file.file_name, e),
+                    }
+                }
+
+                report.orphaned.push(OrphanedFile {
+                    file_name: file.file_name,
+                    size: file.content_length,
+                    last_modified: file.last_modified,
+                });
+            }
+
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+    }
+
+    // Files may have been uploaded after they were listed, so make sure each file which
+    // wasn't listed is really missing
+    let mut unlisted = sqlx::query_as::<_, (String, String)>(
+        "
+        SELECT DISTINCT ON (file_name) file_name, source
+        FROM storage_references
+        WHERE stored AND NOT listed
+        ",
+    )
+    .fetch(&mut *transaction);
+
+    while let Some((file_name, source)) = unlisted.try_next().await? {
+        if file_host.stat_file(&file_name).await?.is_none() {
+            report.missing.push(MissingFile { file_name, source });
+        }
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,117 @@
+use crate::clickhouse::fetch_all_downloads;
+use crate::database::models::ProjectId;
+use crate::queue::search_index::SearchIndexQueue;
+use crate::routes::ApiError;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+/// How far back downloads and follows count towards a project's trending score
+const WINDOW_DAYS: i64 = 7;
+/// How long it takes for a download or follow to count for half as much
+const HALF_LIFE_HOURS: f64 = 48.0;
+/// How many downloads a follow is worth
+const FOLLOW_WEIGHT: f64 = 5.0;
+/// How much a score has to change, relative to the greater of the old and new score, for it to
+/// be updated. This avoids reindexing every project with downloads each time scores are computed.
+const MIN_CHANGE: f64 = 0.05;
+
+/// How much a download or follow made at a time counts for now
+fn decay(recorded: DateTime<Utc>, now: DateTime<Utc>) -> f64 {
+    let age_hours = (now - recorded).num_minutes().max(0) as f64 / 60.0;
+    0.5f64.powf(age_hours / HALF_LIFE_HOURS)
+}
+
+/// Computes the trending score of every project from its downloads and follows in the window,
+/// with recent ones counting for more, and stores the scores of projects whose score changed.
+/// Changed projects are reindexed in search. Returns the number of changed projects.
+pub async fn update_trending_scores(
+    pool: &PgPool,
+    clickhouse: &clickhouse::Client,
+    search_index_queue: &SearchIndexQueue,
+) -> Result<usize, ApiError> {
+    let now = Utc::now();
+    let start = now - Duration::days(WINDOW_DAYS);
+
+    let mut scores: HashMap<i64, f64> = HashMap::new();
+
+    for interval in fetch_all_downloads(start, now, 60, clickhouse).await? {
+        let recorded = DateTime::from_timestamp(interval.time as i64, 0).unwrap_or(now);
+        *scores.entry(interval.id as i64).or_default() +=
+            interval.total as f64 * decay(recorded, now);
+    }
+
+    let follows = sqlx::query!(
+        "
+        SELECT mod_id, created
+        FROM mod_follows
+        WHERE created > $1
+        ",
+        start,
+    )
+    .fetch_all(pool)
+    .await?;
+    for follow in follows {
+        *scores.entry(follow.mod_id).or_default() += FOLLOW_WEIGHT * decay(follow.created, now);
+    }
+
+    let (ids, scores): (Vec<i64>, Vec<f64>) = scores.into_iter().unzip();
+
+    let mut transaction = pool.begin().await?;
+
+    // Projects with nothing in the window no longer trend at all
+    let reset = sqlx::query!(
+        "
+        UPDATE mods
+        SET trending = 0
+        WHERE trending != 0 AND NOT (id = ANY($1))
+        RETURNING id
+        ",
+        &ids,
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+
+    let updated = sqlx::query!(
+        "
+        UPDATE mods m
+        SET trending = u.trending
+        FROM UNNEST($1::bigint[], $2::double precision[]) AS u(id, trending)
+        WHERE m.id = u.id AND ABS(m.trending - u.trending) > $3 * GREATEST(m.trending, u.trending)
+        RETURNING m.id
+        ",
+        &ids,
+        &scores,
+        MIN_CHANGE,
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    let changed = reset
+        .into_iter()
+        .map(|x| ProjectId(x.id))
+        .chain(updated.into_iter().map(|x| ProjectId(x.id)))
+        .collect::<Vec<_>>();
+    let count = changed.len();
+    search_index_queue.add_many(changed).await;
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recent_activity_counts_for_more() {
+        let now = Utc::now();
+
+        assert_eq!(decay(now, now), 1.0);
+        assert!((decay(now - Duration::hours(48), now) - 0.5).abs() < 1e-9);
+        assert!((decay(now - Duration::hours(96), now) - 0.25).abs() < 1e-9);
+        // Clock skew never makes activity count for more than when it happened
+        assert_eq!(decay(now + Duration::hours(1), now), 1.0);
+    }
+}
@@ -1,16 +1,23 @@
 use crate::auth::validate::get_user_record_from_bearer_token;
+use crate::database::models::file_revalidation_job_item::{
+    FileRevalidationJob, RevalidationJobStatus,
+};
+use crate::database::models::legacy_loader_fields::MinecraftGameVersion;
+use crate::database::models::storage_reconciliation_run_item::StorageReconciliationRun;
 use crate::database::redis::RedisPool;
+use crate::file_hosting::FileHost;
 use crate::models::analytics::Download;
 use crate::models::ids::ProjectId;
 use crate::models::pats::Scopes;
 use crate::queue::analytics::AnalyticsQueue;
 use crate::queue::maxmind::MaxMindIndexer;
+use crate::queue::search_index::SearchIndexQueue;
 use crate::queue::session::AuthQueue;
 use crate::routes::ApiError;
 use crate::search::SearchConfig;
 use crate::util::date::get_current_tenths_of_ms;
 use crate::util::guards::admin_key_guard;
-use actix_web::{patch, post, web, HttpRequest, HttpResponse};
+use actix_web::{get, patch, post, web, HttpRequest, HttpResponse};
 use serde::Deserialize;
 use sqlx::PgPool;
 use std::collections::HashMap;
@@ -21,7 +28,15 @@ pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("admin")
             .service(count_download)
-            .service(force_reindex),
+            .service(force_reindex)
+            .service(reconcile_storage)
+            .service(get_reconciliations)
+            .service(get_reconciliation)
+            .service(edit_game_version_pack_formats)
+            .service(start_revalidation)
+            .service(get_revalidation)
+            .service(cancel_revalidation)
+            .service(resume_revalidation),
     );
 }
 
@@ -139,9 +154,212 @@ pub async fn force_reindex(
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     config: web::Data<SearchConfig>,
+    search_index_queue: web::Data<SearchIndexQueue>,
 ) -> Result<HttpResponse, ApiError> {
-    use crate::search::indexing::index_projects;
-    let redis = redis.get_ref();
-    index_projects(pool.as_ref().clone(), redis.clone(), &config).await?;
+    search_index_queue
+        .rebuild(pool.as_ref().clone(), &redis, &config)
+        .await?;
     Ok(HttpResponse::NoContent().finish())
 }
+
+#[derive(Deserialize)]
+pub struct ReconcileStorageBody {
+    /// If set, orphaned files older than this many hours are deleted. It must be at least
+    /// `MIN_PURGE_GRACE_PERIOD_HOURS`.
+    pub purge_grace_period_hours: Option<u32>,
+}
+
+/// Starts reconciling storage in the background, as listing every stored file can take a long
+/// time. The run is returned, and its report can be fetched once it finishes.
+#[post("/_reconcile_storage", guard = "admin_key_guard")]
+pub async fn reconcile_storage(
+    pool: web::Data<PgPool>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
+    body: web::Json<ReconcileStorageBody>,
+) -> Result<HttpResponse, ApiError> {
+    let purge_grace_period = body
+        .purge_grace_period_hours
+        .map(|x| chrono::Duration::hours(x as i64));
+    crate::queue::storage::check_purge_grace_period(purge_grace_period)?;
+
+    let run_id = StorageReconciliationRun::insert(body.purge_grace_period_hours, &**pool).await?;
+    let run = StorageReconciliationRun::get(run_id, &**pool)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    let pool = pool.get_ref().clone();
+    let file_host = file_host.get_ref().clone();
+    actix_rt::spawn(async move {
+        crate::queue::storage::reconcile_storage_logged(
+            &pool,
+            &*file_host,
+            purge_grace_period,
+            run_id,
+        )
+        .await;
+    });
+
+    Ok(HttpResponse::Accepted().json(run))
+}
+
+/// The number of runs returned by [`get_reconciliations`]
+const LATEST_RECONCILIATIONS: i64 = 10;
+
+/// Gets the most recent storage reconciliation runs, including scheduled ones, newest first
+#[get("/_reconcile_storage", guard = "admin_key_guard")]
+pub async fn get_reconciliations(pool: web::Data<PgPool>) -> Result<HttpResponse, ApiError> {
+    let mut runs = Vec::new();
+    for id in StorageReconciliationRun::get_latest_ids(LATEST_RECONCILIATIONS, &**pool).await? {
+        runs.extend(StorageReconciliationRun::get(id, &**pool).await?);
+    }
+
+    Ok(HttpResponse::Ok().json(runs))
+}
+
+#[get("/_reconcile_storage/{id}", guard = "admin_key_guard")]
+pub async fn get_reconciliation(
+    pool: web::Data<PgPool>,
+    info: web::Path<(i64,)>,
+) -> Result<HttpResponse, ApiError> {
+    let run = StorageReconciliationRun::get(info.into_inner().0, &**pool)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    Ok(HttpResponse::Ok().json(run))
+}
+
+#[derive(Deserialize)]
+pub struct GameVersionPackFormats {
+    pub resource_pack_format: Option<i32>,
+    pub data_pack_format: Option<i32>,
+}
+
+/// Sets the pack formats of a Minecraft game version, which Mojang's version manifest doesn't
+/// include, so that the pack.mcmeta of packs made for it can be checked
+#[patch("/_game_versions/{version}", guard = "admin_key_guard")]
+pub async fn edit_game_version_pack_formats(
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    info: web::Path<(String,)>,
+    body: web::Json<GameVersionPackFormats>,
+) -> Result<HttpResponse, ApiError> {
+    let version = info.into_inner().0;
+    if !MinecraftGameVersion::list(None, None, &**pool, &redis)
+        .await?
+        .iter()
+        .any(|x| x.version == version)
+    {
+        return Err(ApiError::NotFound);
+    }
+
+    let mut builder = MinecraftGameVersion::builder().version(&version)?;
+    if let Some(format) = body.resource_pack_format {
+        builder = builder.resource_pack_format(format);
+    }
+    if let Some(format) = body.data_pack_format {
+        builder = builder.data_pack_format(format);
+    }
+    builder.insert(&**pool, &redis).await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+#[derive(Deserialize)]
+pub struct RevalidationBody {
+    /// Whether newly failing primary files are replaced by another passing file of their version
+    #[serde(default)]
+    pub update_primary: bool,
+    /// Whether moderation messages are posted for versions with newly failing files
+    #[serde(default)]
+    pub open_threads: bool,
+    pub files_per_minute: Option<u32>,
+}
+
+#[post("/_revalidate_files", guard = "admin_key_guard")]
+pub async fn start_revalidation(
+    pool: web::Data<PgPool>,
+    body: web::Json<RevalidationBody>,
+) -> Result<HttpResponse, ApiError> {
+    let files_per_minute = body
+        .files_per_minute
+        .unwrap_or(crate::queue::revalidation::DEFAULT_FILES_PER_MINUTE);
+    if files_per_minute == 0 {
+        return Err(ApiError::InvalidInput(
+            "files_per_minute must be greater than 0".to_string(),
+        ));
+    }
+
+    if FileRevalidationJob::get_running(&**pool).await?.is_some() {
+        return Err(ApiError::InvalidInput(
+            "A revalidation job is already running".to_string(),
+        ));
+    }
+
+    let job = FileRevalidationJob::insert(
+        body.update_primary,
+        body.open_threads,
+        files_per_minute,
+        &**pool,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(job))
+}
+
+#[get("/_revalidate_files/{id}", guard = "admin_key_guard")]
+pub async fn get_revalidation(
+    pool: web::Data<PgPool>,
+    info: web::Path<(i64,)>,
+) -> Result<HttpResponse, ApiError> {
+    let job = FileRevalidationJob::get(info.into_inner().0, &**pool)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    Ok(HttpResponse::Ok().json(job))
+}
+
+#[post("/_revalidate_files/{id}/cancel", guard = "admin_key_guard")]
+pub async fn cancel_revalidation(
+    pool: web::Data<PgPool>,
+    info: web::Path<(i64,)>,
+) -> Result<HttpResponse, ApiError> {
+    let job = FileRevalidationJob::get(info.into_inner().0, &**pool)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    if job.status != RevalidationJobStatus::Running {
+        return Err(ApiError::InvalidInput(
+            "Only running jobs can be cancelled".to_string(),
+        ));
+    }
+
+    FileRevalidationJob::set_status(job.id, RevalidationJobStatus::Cancelled, &**pool).await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+#[post("/_revalidate_files/{id}/resume", guard = "admin_key_guard")]
+pub async fn resume_revalidation(
+    pool: web::Data<PgPool>,
+    info: web::Path<(i64,)>,
+) -> Result<HttpResponse, ApiError> {
+    let job = FileRevalidationJob::get(info.into_inner().0, &**pool)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    if job.status != RevalidationJobStatus::Cancelled {
+        return Err(ApiError::InvalidInput(
+            "Only cancelled jobs can be resumed".to_string(),
+        ));
+    }
+    if FileRevalidationJob::get_running(&**pool).await?.is_some() {
+        return Err(ApiError::InvalidInput(
+            "A revalidation job is already running".to_string(),
+        ));
+    }
+
+    // The job continues after the last file it checked
+    FileRevalidationJob::set_status(job.id, RevalidationJobStatus::Running, &**pool).await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
@@ -8,6 +8,7 @@ use crate::models::projects::{
 use crate::models::v2::projects::{DonationLink, LegacyProject, LegacySideType, LegacyVersion};
 use crate::models::v2::search::LegacySearchResults;
 use crate::queue::moderation::AutomatedModerationQueue;
+use crate::queue::search_index::SearchIndexQueue;
 use crate::queue::session::AuthQueue;
 use crate::routes::v3::projects::ProjectIds;
 use crate::routes::{v2_reroute, v3, ApiError};
@@ -52,6 +53,8 @@ pub fn config(cfg: &mut web::ServiceConfig) {
 pub async fn project_search(
     web::Query(info): web::Query<SearchRequest>,
     config: web::Data<SearchConfig>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
 ) -> Result<HttpResponse, SearchError> {
     // Search now uses loader_fields instead of explicit 'client_side' and 'server_side' fields
     // While the backend for this has changed, it doesnt affect much
@@ -95,12 +98,29 @@ pub async fn project_search(
         None
     };
 
+    let facet_distribution = if let Some(facets) = info.facet_distribution {
+        let facets = serde_json::from_str::<Vec<String>>(&facets)?
+            .into_iter()
+            .map(|facet| match facet.as_str() {
+                "versions" => "game_versions".to_string(),
+                "project_type" => "project_types".to_string(),
+                "title" => "name".to_string(),
+                _ => facet,
+            })
+            .collect::<Vec<_>>();
+
+        Some(serde_json::to_string(&facets)?)
+    } else {
+        None
+    };
+
     let info = SearchRequest {
         facets: facets.and_then(|x| serde_json::to_string(&x).ok()),
+        facet_distribution,
         ..info
     };
 
-    let results = search_for_project(&info, &config).await?;
+    let results = search_for_project(&info, &config, &pool, &redis).await?;
 
     let results = LegacySearchResults::from(results);
 
@@ -253,13 +273,20 @@ pub async fn dependency_list(
     info: web::Path<(String,)>,
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
     // TODO: tests, probably
-    let response =
-        v3::projects::dependency_list(req, info, pool.clone(), redis.clone(), session_queue)
-            .await
-            .or_else(v2_reroute::flatten_404_error)?;
+    let response = v3::projects::dependency_list(
+        req,
+        info,
+        pool.clone(),
+        redis.clone(),
+        file_host,
+        session_queue,
+    )
+    .await
+    .or_else(v2_reroute::flatten_404_error)?;
 
     match v2_reroute::extract_ok_json::<crate::routes::v3::projects::DependencyInfo>(response).await
     {
@@ -391,6 +418,8 @@ pub async fn project_edit(
     redis: web::Data<RedisPool>,
     session_queue: web::Data<AuthQueue>,
     moderation_queue: web::Data<AutomatedModerationQueue>,
+    search_index_queue: web::Data<SearchIndexQueue>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
 ) -> Result<HttpResponse, ApiError> {
     let v2_new_project = new_project.into_inner();
     let client_side = v2_new_project.client_side;
@@ -498,6 +527,8 @@ pub async fn project_edit(
         redis.clone(),
         session_queue.clone(),
         moderation_queue,
+        search_index_queue.clone(),
+        file_host.clone(),
     )
     .await
     .or_else(v2_reroute::flatten_404_error)?;
@@ -528,6 +559,8 @@ pub async fn project_edit(
                     ..Default::default()
                 },
                 session_queue.clone(),
+                search_index_queue.clone(),
+                file_host.clone(),
             )
             .await?;
         }
@@ -606,6 +639,7 @@ pub async fn projects_edit(
     bulk_edit_project: web::Json<BulkEditProject>,
     redis: web::Data<RedisPool>,
     session_queue: web::Data<AuthQueue>,
+    search_index_queue: web::Data<SearchIndexQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let bulk_edit_project = bulk_edit_project.into_inner();
 
@@ -688,6 +722,7 @@ pub async fn projects_edit(
         }),
         redis,
         session_queue,
+        search_index_queue,
     )
     .await
     .or_else(v2_reroute::flatten_404_error)
@@ -709,6 +744,7 @@ pub async fn project_icon_edit(
     file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     payload: web::Payload,
     session_queue: web::Data<AuthQueue>,
+    search_index_queue: web::Data<SearchIndexQueue>,
 ) -> Result<HttpResponse, ApiError> {
     // Returns NoContent, so no need to convert
     v3::projects::project_icon_edit(
@@ -720,6 +756,7 @@ pub async fn project_icon_edit(
         file_host,
         payload,
         session_queue,
+        search_index_queue,
     )
     .await
     .or_else(v2_reroute::flatten_404_error)
@@ -733,11 +770,20 @@ pub async fn delete_project_icon(
     redis: web::Data<RedisPool>,
     file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
+    search_index_queue: web::Data<SearchIndexQueue>,
 ) -> Result<HttpResponse, ApiError> {
     // Returns NoContent, so no need to convert
-    v3::projects::delete_project_icon(req, info, pool, redis, file_host, session_queue)
-        .await
-        .or_else(v2_reroute::flatten_404_error)
+    v3::projects::delete_project_icon(
+        req,
+        info,
+        pool,
+        redis,
+        file_host,
+        session_queue,
+        search_index_queue,
+    )
+    .await
+    .or_else(v2_reroute::flatten_404_error)
 }
 
 #[derive(Serialize, Deserialize, Validate)]
@@ -762,6 +808,7 @@ pub async fn add_gallery_item(
     file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     payload: web::Payload,
     session_queue: web::Data<AuthQueue>,
+    search_index_queue: web::Data<SearchIndexQueue>,
 ) -> Result<HttpResponse, ApiError> {
     // Returns NoContent, so no need to convert
     v3::projects::add_gallery_item(
@@ -779,6 +826,7 @@ pub async fn add_gallery_item(
         file_host,
         payload,
         session_queue,
+        search_index_queue,
     )
     .await
     .or_else(v2_reroute::flatten_404_error)
@@ -814,6 +862,7 @@ pub async fn edit_gallery_item(
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     session_queue: web::Data<AuthQueue>,
+    search_index_queue: web::Data<SearchIndexQueue>,
 ) -> Result<HttpResponse, ApiError> {
     // Returns NoContent, so no need to convert
     v3::projects::edit_gallery_item(
@@ -829,6 +878,7 @@ pub async fn edit_gallery_item(
         pool,
         redis,
         session_queue,
+        search_index_queue,
     )
     .await
     .or_else(v2_reroute::flatten_404_error)
@@ -840,6 +890,7 @@ pub struct GalleryDeleteQuery {
 }
 
 #[delete("{id}/gallery")]
+#[allow(clippy::too_many_arguments)]
 pub async fn delete_gallery_item(
     req: HttpRequest,
     web::Query(item): web::Query<GalleryDeleteQuery>,
@@ -848,6 +899,7 @@ pub async fn delete_gallery_item(
     redis: web::Data<RedisPool>,
     file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
+    search_index_queue: web::Data<SearchIndexQueue>,
 ) -> Result<HttpResponse, ApiError> {
     // Returns NoContent, so no need to convert
     v3::projects::delete_gallery_item(
@@ -858,6 +910,7 @@ pub async fn delete_gallery_item(
         redis,
         file_host,
         session_queue,
+        search_index_queue,
     )
     .await
     .or_else(v2_reroute::flatten_404_error)
@@ -8,6 +8,7 @@ use crate::models::projects::{
 };
 use crate::models::v2::projects::LegacyVersion;
 use crate::queue::moderation::AutomatedModerationQueue;
+use crate::queue::search_index::SearchIndexQueue;
 use crate::queue::session::AuthQueue;
 use crate::routes::v3::project_creation::CreateError;
 use crate::routes::v3::version_creation;
@@ -80,6 +81,7 @@ struct InitialFileData {
 }
 
 // under `/api/v1/version`
+#[allow(clippy::too_many_arguments)]
 #[post("version")]
 pub async fn version_create(
     req: HttpRequest,
@@ -89,6 +91,7 @@ pub async fn version_create(
     file_host: Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: Data<AuthQueue>,
     moderation_queue: Data<AutomatedModerationQueue>,
+    search_index_queue: Data<SearchIndexQueue>,
 ) -> Result<HttpResponse, CreateError> {
     let payload = v2_reroute::alter_actix_multipart(
         payload,
@@ -233,6 +236,7 @@ pub async fn version_create(
         file_host,
         session_queue,
         moderation_queue,
+        search_index_queue,
     )
     .await?;
 
@@ -273,6 +277,7 @@ async fn get_example_version_fields(
 }
 
 // under /api/v1/version/{version_id}
+#[allow(clippy::too_many_arguments)]
 #[post("{version_id}/file")]
 pub async fn upload_file_to_version(
     req: HttpRequest,
@@ -282,6 +287,7 @@ pub async fn upload_file_to_version(
     redis: Data<RedisPool>,
     file_host: Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
+    moderation_queue: web::Data<AutomatedModerationQueue>,
 ) -> Result<HttpResponse, CreateError> {
     // Returns NoContent, so no need to convert to V2
     let response = v3::version_creation::upload_file_to_version(
@@ -292,6 +298,7 @@ pub async fn upload_file_to_version(
         redis.clone(),
         file_host,
         session_queue,
+        moderation_queue,
     )
     .await?;
     Ok(response)
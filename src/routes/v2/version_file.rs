@@ -1,5 +1,6 @@
 use super::ApiError;
 use crate::database::redis::RedisPool;
+use crate::file_hosting::FileHost;
 use crate::models::projects::{Project, Version, VersionType};
 use crate::models::v2::projects::{LegacyProject, LegacyVersion};
 use crate::queue::session::AuthQueue;
@@ -9,6 +10,7 @@ use actix_web::{delete, get, post, web, HttpRequest, HttpResponse};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -36,12 +38,20 @@ pub async fn get_version_from_hash(
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     hash_query: web::Query<HashQuery>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
-    let response =
-        v3::version_file::get_version_from_hash(req, info, pool, redis, hash_query, session_queue)
-            .await
-            .or_else(v2_reroute::flatten_404_error)?;
+    let response = v3::version_file::get_version_from_hash(
+        req,
+        info,
+        pool,
+        redis,
+        hash_query,
+        file_host,
+        session_queue,
+    )
+    .await
+    .or_else(v2_reroute::flatten_404_error)?;
 
     // Convert response to V2 format
     match v2_reroute::extract_ok_json::<Version>(response).await {
@@ -61,10 +71,11 @@ pub async fn download_version(
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     hash_query: web::Query<HashQuery>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
     // Returns TemporaryRedirect, so no need to convert to V2
-    v3::version_file::download_version(req, info, pool, redis, hash_query, session_queue)
+    v3::version_file::download_version(req, info, pool, redis, hash_query, file_host, session_queue)
         .await
         .or_else(v2_reroute::flatten_404_error)
 }
@@ -92,6 +103,7 @@ pub struct UpdateData {
     pub version_types: Option<Vec<VersionType>>,
 }
 
+#[allow(clippy::too_many_arguments)]
 #[post("{version_id}/update")]
 pub async fn get_update_from_hash(
     req: HttpRequest,
@@ -100,6 +112,7 @@ pub async fn get_update_from_hash(
     redis: web::Data<RedisPool>,
     hash_query: web::Query<HashQuery>,
     update_data: web::Json<UpdateData>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let update_data = update_data.into_inner();
@@ -124,6 +137,7 @@ pub async fn get_update_from_hash(
         redis,
         hash_query,
         web::Json(update_data),
+        file_host,
         session_queue,
     )
     .await
@@ -153,6 +167,7 @@ pub async fn get_versions_from_hashes(
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     file_data: web::Json<FileHashes>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let file_data = file_data.into_inner();
@@ -165,6 +180,7 @@ pub async fn get_versions_from_hashes(
         pool,
         redis,
         web::Json(file_data),
+        file_host,
         session_queue,
     )
     .await
@@ -251,6 +267,7 @@ pub async fn update_files(
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     update_data: web::Json<ManyUpdateData>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
 ) -> Result<HttpResponse, ApiError> {
     let update_data = update_data.into_inner();
     let update_data = v3::version_file::ManyUpdateData {
@@ -261,7 +278,7 @@ pub async fn update_files(
         hashes: update_data.hashes,
     };
 
-    let response = v3::version_file::update_files(pool, redis, web::Json(update_data))
+    let response = v3::version_file::update_files(pool, redis, web::Json(update_data), file_host)
         .await
         .or_else(v2_reroute::flatten_404_error)?;
 
@@ -301,6 +318,7 @@ pub async fn update_individual_files(
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     update_data: web::Json<ManyFileUpdateData>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let update_data = update_data.into_inner();
@@ -333,6 +351,7 @@ pub async fn update_individual_files(
         pool,
         redis,
         web::Json(update_data),
+        file_host,
         session_queue,
     )
     .await
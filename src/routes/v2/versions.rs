@@ -1,11 +1,14 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use super::ApiError;
 use crate::database::redis::RedisPool;
+use crate::file_hosting::FileHost;
 use crate::models;
 use crate::models::ids::VersionId;
 use crate::models::projects::{Dependency, FileType, Version, VersionStatus, VersionType};
 use crate::models::v2::projects::LegacyVersion;
+use crate::queue::search_index::SearchIndexQueue;
 use crate::queue::session::AuthQueue;
 use crate::routes::{v2_reroute, v3};
 use crate::search::SearchConfig;
@@ -44,6 +47,7 @@ pub async fn version_list(
     web::Query(filters): web::Query<VersionListFilters>,
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let loaders = if let Some(loaders) = filters.loaders {
@@ -94,10 +98,17 @@ pub async fn version_list(
         offset: filters.offset,
     };
 
-    let response =
-        v3::versions::version_list(req, info, web::Query(filters), pool, redis, session_queue)
-            .await
-            .or_else(v2_reroute::flatten_404_error)?;
+    let response = v3::versions::version_list(
+        req,
+        info,
+        web::Query(filters),
+        pool,
+        redis,
+        file_host,
+        session_queue,
+    )
+    .await
+    .or_else(v2_reroute::flatten_404_error)?;
 
     // Convert response to V2 format
     match v2_reroute::extract_ok_json::<Vec<Version>>(response).await {
@@ -119,12 +130,14 @@ pub async fn version_project_get(
     info: web::Path<(String, String)>,
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let id = info.into_inner();
-    let response = v3::versions::version_project_get_helper(req, id, pool, redis, session_queue)
-        .await
-        .or_else(v2_reroute::flatten_404_error)?;
+    let response =
+        v3::versions::version_project_get_helper(req, id, pool, redis, file_host, session_queue)
+            .await
+            .or_else(v2_reroute::flatten_404_error)?;
     // Convert response to V2 format
     match v2_reroute::extract_ok_json::<Version>(response).await {
         Ok(version) => {
@@ -146,12 +159,14 @@ pub async fn versions_get(
     web::Query(ids): web::Query<VersionIds>,
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let ids = v3::versions::VersionIds { ids: ids.ids };
-    let response = v3::versions::versions_get(req, web::Query(ids), pool, redis, session_queue)
-        .await
-        .or_else(v2_reroute::flatten_404_error)?;
+    let response =
+        v3::versions::versions_get(req, web::Query(ids), pool, redis, file_host, session_queue)
+            .await
+            .or_else(v2_reroute::flatten_404_error)?;
 
     // Convert response to V2 format
     match v2_reroute::extract_ok_json::<Vec<Version>>(response).await {
@@ -172,10 +187,11 @@ pub async fn version_get(
     info: web::Path<(models::ids::VersionId,)>,
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let id = info.into_inner().0;
-    let response = v3::versions::version_get_helper(req, id, pool, redis, session_queue)
+    let response = v3::versions::version_get_helper(req, id, pool, redis, file_host, session_queue)
         .await
         .or_else(v2_reroute::flatten_404_error)?;
     // Convert response to V2 format
@@ -224,13 +240,16 @@ pub struct EditVersionFileType {
 }
 
 #[patch("{id}")]
+#[allow(clippy::too_many_arguments)]
 pub async fn version_edit(
     req: HttpRequest,
     info: web::Path<(VersionId,)>,
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     new_version: web::Json<EditVersion>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
+    search_index_queue: web::Data<SearchIndexQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let new_version = new_version.into_inner();
 
@@ -248,6 +267,7 @@ pub async fn version_edit(
         (*info).0,
         pool.clone(),
         redis.clone(),
+        file_host.clone(),
         session_queue.clone(),
     )
     .await
@@ -299,6 +319,8 @@ pub async fn version_edit(
         redis,
         web::Json(serde_json::to_value(new_version)?),
         session_queue,
+        search_index_queue,
+        file_host,
     )
     .await
     .or_else(v2_reroute::flatten_404_error)?;
@@ -313,9 +335,18 @@ pub async fn version_delete(
     redis: web::Data<RedisPool>,
     session_queue: web::Data<AuthQueue>,
     search_config: web::Data<SearchConfig>,
+    search_index_queue: web::Data<SearchIndexQueue>,
 ) -> Result<HttpResponse, ApiError> {
     // Returns NoContent, so we don't need to convert the response
-    v3::versions::version_delete(req, info, pool, redis, session_queue, search_config)
-        .await
-        .or_else(v2_reroute::flatten_404_error)
+    v3::versions::version_delete(
+        req,
+        info,
+        pool,
+        redis,
+        session_queue,
+        search_config,
+        search_index_queue,
+    )
+    .await
+    .or_else(v2_reroute::flatten_404_error)
 }
@@ -10,6 +10,10 @@ use crate::models::pats::Scopes;
 use crate::queue::session::AuthQueue;
 use crate::routes::v3::project_creation::CreateError;
 use crate::routes::ApiError;
+use crate::search::indexing::COLLECTIONS_INDEX;
+use crate::search::{
+    search_for_entities, EntitySearchRequest, SearchCollection, SearchConfig, SearchError,
+};
 use crate::util::img::delete_old_images;
 use crate::util::routes::read_from_payload;
 use crate::util::validate::validation_errors_to_string;
@@ -26,6 +30,7 @@ use validator::Validate;
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.route("collections", web::get().to(collections_get));
     cfg.route("collection", web::post().to(collection_create));
+    cfg.route("search/collections", web::get().to(collections_search));
 
     cfg.service(
         web::scope("collection")
@@ -37,6 +42,16 @@ pub fn config(cfg: &mut web::ServiceConfig) {
     );
 }
 
+pub async fn collections_search(
+    web::Query(info): web::Query<EntitySearchRequest>,
+    config: web::Data<SearchConfig>,
+) -> Result<HttpResponse, SearchError> {
+    let results =
+        search_for_entities::<SearchCollection>(COLLECTIONS_INDEX, &info, &config).await?;
+
+    Ok(HttpResponse::Ok().json(results))
+}
+
 #[derive(Serialize, Deserialize, Validate, Clone)]
 pub struct CollectionCreateData {
     #[validate(
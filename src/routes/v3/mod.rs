@@ -12,10 +12,12 @@ pub mod payouts;
 pub mod project_creation;
 pub mod projects;
 pub mod reports;
+pub mod saved_searches;
 pub mod statistics;
 pub mod tags;
 pub mod teams;
 pub mod threads;
+pub mod uploads;
 pub mod users;
 pub mod version_creation;
 pub mod version_file;
@@ -39,6 +41,7 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .configure(tags::config)
             .configure(teams::config)
             .configure(threads::config)
+            .configure(uploads::config)
             .configure(users::config)
             .configure(version_file::config)
             .configure(payouts::config)
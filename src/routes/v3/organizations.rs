@@ -14,6 +14,10 @@ use crate::models::pats::Scopes;
 use crate::models::teams::{OrganizationPermissions, ProjectPermissions};
 use crate::queue::session::AuthQueue;
 use crate::routes::v3::project_creation::CreateError;
+use crate::search::indexing::ORGANIZATIONS_INDEX;
+use crate::search::{
+    search_for_entities, EntitySearchRequest, SearchConfig, SearchError, SearchOrganization,
+};
 use crate::util::img::delete_old_images;
 use crate::util::routes::read_from_payload;
 use crate::util::validate::validation_errors_to_string;
@@ -27,6 +31,7 @@ use validator::Validate;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.route("organizations", web::get().to(organizations_get));
+    cfg.route("search/organizations", web::get().to(organizations_search));
     cfg.service(
         web::scope("organization")
             .route("", web::post().to(organization_create))
@@ -48,6 +53,16 @@ pub fn config(cfg: &mut web::ServiceConfig) {
     );
 }
 
+pub async fn organizations_search(
+    web::Query(info): web::Query<EntitySearchRequest>,
+    config: web::Data<SearchConfig>,
+) -> Result<HttpResponse, SearchError> {
+    let results =
+        search_for_entities::<SearchOrganization>(ORGANIZATIONS_INDEX, &info, &config).await?;
+
+    Ok(HttpResponse::Ok().json(results))
+}
+
 pub async fn organization_projects_get(
     req: HttpRequest,
     info: web::Path<(String,)>,
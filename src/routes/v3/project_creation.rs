@@ -1,9 +1,13 @@
-use super::version_creation::{try_create_version_fields, InitialVersionData};
+use super::version_creation::{
+    check_version_metadata, try_create_version_fields, InitialVersionData,
+};
 use crate::auth::{get_user_from_headers, AuthenticationError};
 use crate::database::models::loader_fields::{Loader, LoaderField, LoaderFieldEnumValue};
+use crate::database::models::project_mod_id_item::ProjectModId;
 use crate::database::models::thread_item::ThreadBuilder;
 use crate::database::models::{self, image_item, User};
 use crate::database::redis::RedisPool;
+use crate::file_hosting::visibility::sync_file_visibility;
 use crate::file_hosting::{FileHost, FileHostingError};
 use crate::models::error::ApiError;
 use crate::models::ids::base62_impl::to_base62;
@@ -11,7 +15,8 @@ use crate::models::ids::{ImageId, OrganizationId};
 use crate::models::images::{Image, ImageContext};
 use crate::models::pats::Scopes;
 use crate::models::projects::{
-    License, Link, MonetizationStatus, ProjectId, ProjectStatus, VersionId, VersionStatus,
+    License, Link, MonetizationStatus, ProjectId, ProjectStatus, ValidationReport, VersionId,
+    VersionStatus,
 };
 use crate::models::teams::{OrganizationPermissions, ProjectPermissions};
 use crate::models::threads::ThreadType;
@@ -61,6 +66,8 @@ pub enum CreateError {
     FileHostingError(#[from] FileHostingError),
     #[error("Error while validating uploaded file: {0}")]
     FileValidationError(#[from] crate::validate::ValidationError),
+    #[error("Primary file failed validation: {}", .0.errors().map(|x| x.message.as_str()).collect::<Vec<_>>().join(" "))]
+    InvalidPrimaryFile(ValidationReport),
     #[error("{}", .0)]
     MissingValueError(String),
     #[error("Invalid format for image: {0}")]
@@ -100,6 +107,7 @@ impl actix_web::ResponseError for CreateError {
             CreateError::MissingValueError(..) => StatusCode::BAD_REQUEST,
             CreateError::InvalidIconFormat(..) => StatusCode::BAD_REQUEST,
             CreateError::InvalidInput(..) => StatusCode::BAD_REQUEST,
+            CreateError::InvalidPrimaryFile(..) => StatusCode::BAD_REQUEST,
             CreateError::InvalidGameVersion(..) => StatusCode::BAD_REQUEST,
             CreateError::InvalidLoader(..) => StatusCode::BAD_REQUEST,
             CreateError::InvalidCategory(..) => StatusCode::BAD_REQUEST,
@@ -136,6 +144,7 @@ impl actix_web::ResponseError for CreateError {
                 CreateError::SlugCollision => "invalid_input",
                 CreateError::ValidationError(..) => "invalid_input",
                 CreateError::FileValidationError(..) => "invalid_input",
+                CreateError::InvalidPrimaryFile(..) => "invalid_input",
                 CreateError::ImageError(..) => "invalid_image",
                 CreateError::RerouteError(..) => "reroute_error",
             },
@@ -350,6 +359,7 @@ async fn project_create_inner(
     let mut versions;
     let mut versions_map = std::collections::HashMap::new();
     let mut gallery_urls = Vec::new();
+    let mut mod_ids = Vec::new();
     {
         // The first multipart field must be named "data" and contain a
         // JSON `ProjectCreateData` object.
@@ -552,7 +562,7 @@ async fn project_create_inner(
                 .map(|x| x.filename.clone())
                 .collect();
             // Upload the new jar file
-            super::version_creation::upload_file(
+            let manifest = super::version_creation::upload_file(
                 &mut field,
                 file_host,
                 version_data.file_parts.len(),
@@ -573,6 +583,7 @@ async fn project_create_inner(
                 redis,
             )
             .await?;
+            mod_ids.extend(manifest.and_then(|x| x.mod_id));
 
             Ok(())
         }
@@ -715,6 +726,15 @@ async fn project_create_inner(
             })
         }
 
+        // Versions with suspicious code in their files are hidden until a moderator reviews
+        // them, which happens when the project is submitted for review
+        for version in &mut versions {
+            if version.files.iter().any(|x| !x.scan_findings.is_empty()) {
+                version.requested_status = Some(version.status);
+                version.status = VersionStatus::Held;
+            }
+        }
+
         let project_builder_actual = models::project_item::ProjectBuilder {
             project_id: project_id.into(),
             team_id,
@@ -754,6 +774,15 @@ async fn project_create_inner(
         let now = Utc::now();
 
         let id = project_builder_actual.insert(&mut *transaction).await?;
+        ProjectModId::insert_many(id, &mod_ids, transaction).await?;
+
+        // New projects are never public, so the files of their initial versions are moved to
+        // the private prefix. Nothing else references them yet, so the copies at their old path
+        // can be deleted straight away.
+        sync_file_visibility(id, file_host, transaction)
+            .await?
+            .finish(file_host, pool, redis)
+            .await?;
         User::clear_project_cache(&[current_user.id.into()], redis).await?;
 
         for image_id in project_create_data.uploaded_images {
@@ -887,6 +916,7 @@ async fn create_initial_version(
     version_data
         .validate()
         .map_err(|err| CreateError::ValidationError(validation_errors_to_string(err, None)))?;
+    check_version_metadata(version_data)?;
 
     // Randomly generate a new id to be used for the version
     let version_id: VersionId = models::generate_version_id(transaction).await?.into();
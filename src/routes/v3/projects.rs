@@ -3,12 +3,14 @@ use std::sync::Arc;
 
 use crate::auth::checks::{filter_visible_versions, is_visible_project};
 use crate::auth::{filter_visible_projects, get_user_from_headers};
+use crate::database::models::modpack_content_item::ModpackContent;
 use crate::database::models::notification_item::NotificationBuilder;
 use crate::database::models::project_item::{GalleryItem, ModCategory};
 use crate::database::models::thread_item::ThreadMessageBuilder;
 use crate::database::models::{ids as db_ids, image_item, TeamMember};
 use crate::database::redis::RedisPool;
 use crate::database::{self, models as db_models};
+use crate::file_hosting::visibility::{sync_file_visibility, MovedFiles};
 use crate::file_hosting::FileHost;
 use crate::models;
 use crate::models::ids::base62_impl::parse_base62;
@@ -21,7 +23,9 @@ use crate::models::projects::{
 use crate::models::teams::ProjectPermissions;
 use crate::models::threads::MessageBody;
 use crate::queue::moderation::AutomatedModerationQueue;
+use crate::queue::search_index::SearchIndexQueue;
 use crate::queue::session::AuthQueue;
+use crate::queue::similar_projects::SIMILAR_PROJECTS_NAMESPACE;
 use crate::routes::ApiError;
 use crate::search::indexing::remove_documents;
 use crate::search::{search_for_project, SearchConfig, SearchError};
@@ -58,6 +62,7 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .route("{id}/follow", web::post().to(project_follow))
             .route("{id}/follow", web::delete().to(project_unfollow))
             .route("{id}/organization", web::get().to(project_get_organization))
+            .route("{id}/similar", web::get().to(project_similar))
             .service(
                 web::scope("{project_id}")
                     .route(
@@ -241,6 +246,8 @@ pub async fn project_edit(
     redis: web::Data<RedisPool>,
     session_queue: web::Data<AuthQueue>,
     moderation_queue: web::Data<AutomatedModerationQueue>,
+    search_index_queue: web::Data<SearchIndexQueue>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
 ) -> Result<HttpResponse, ApiError> {
     let user = get_user_from_headers(
         &req,
@@ -277,6 +284,7 @@ pub async fn project_edit(
 
         if let Some(perms) = permissions {
             let mut transaction = pool.begin().await?;
+            let mut moved_files = MovedFiles::default();
 
             if let Some(name) = &new_project.name {
                 if !perms.contains(ProjectPermissions::EDIT_DETAILS) {
@@ -471,6 +479,9 @@ pub async fn project_edit(
                 .execute(&mut *transaction)
                 .await?;
 
+                ModpackContent::notify_newly_included(id, &mut transaction, &redis).await?;
+                moved_files = sync_file_visibility(id, &***file_host, &mut transaction).await?;
+
                 if project_item.inner.status.is_searchable() && !status.is_searchable() {
                     remove_documents(
                         &project_item
@@ -840,6 +851,7 @@ pub async fn project_edit(
             img::delete_unused_images(context, checkable_strings, &mut transaction, &redis).await?;
 
             transaction.commit().await?;
+            moved_files.finish(&***file_host, &pool, &redis).await?;
             db_models::Project::clear_cache(
                 project_item.inner.id,
                 project_item.inner.slug,
@@ -847,6 +859,7 @@ pub async fn project_edit(
                 &redis,
             )
             .await?;
+            search_index_queue.add(project_item.inner.id).await;
 
             Ok(HttpResponse::NoContent().body(""))
         } else {
@@ -903,8 +916,10 @@ pub async fn edit_project_categories(
 pub async fn project_search(
     web::Query(info): web::Query<SearchRequest>,
     config: web::Data<SearchConfig>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
 ) -> Result<HttpResponse, SearchError> {
-    let results = search_for_project(&info, &config).await?;
+    let results = search_for_project(&info, &config, &pool, &redis).await?;
 
     // TODO: add this back
     // let results = ReturnSearchResults {
@@ -951,6 +966,7 @@ pub async fn dependency_list(
     info: web::Path<(String,)>,
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let string = info.into_inner().0;
@@ -1012,6 +1028,8 @@ pub async fn dependency_list(
 
         versions.sort_by(|a, b| b.date_published.cmp(&a.date_published));
         versions.dedup_by(|a, b| a.id == b.id);
+        super::version_file::sign_hidden_file_urls(&mut versions, &***file_host, &pool, &redis)
+            .await?;
 
         Ok(HttpResponse::Ok().json(DependencyInfo { projects, versions }))
     } else {
@@ -1019,6 +1037,62 @@ pub async fn dependency_list(
     }
 }
 
+/// The projects most similar to a project, most similar first, as last ranked by the similar
+/// projects job. Empty until the job has ranked the project.
+pub async fn project_similar(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let string = info.into_inner().0;
+
+    let result = db_models::Project::get(&string, &**pool, &redis).await?;
+
+    let user_option = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_READ]),
+    )
+    .await
+    .map(|x| x.1)
+    .ok();
+
+    if let Some(project) = result {
+        if !is_visible_project(&project.inner, &user_option, &pool, false).await? {
+            return Err(ApiError::NotFound);
+        }
+
+        let mut redis_connection = redis.connect().await?;
+        let similar_ids = redis_connection
+            .get_deserialized_from_json::<Vec<db_ids::ProjectId>>(
+                SIMILAR_PROJECTS_NAMESPACE,
+                &project.inner.id.0.to_string(),
+            )
+            .await?
+            .unwrap_or_default();
+
+        let projects_result =
+            database::Project::get_many_ids(&similar_ids, &**pool, &redis).await?;
+        let mut projects =
+            filter_visible_projects(projects_result, &user_option, &pool, false).await?;
+
+        // Projects are fetched in no particular order, so they are put back in ranked order
+        projects.sort_by_key(|x| {
+            similar_ids
+                .iter()
+                .position(|id| ProjectId::from(*id) == x.id)
+        });
+
+        Ok(HttpResponse::Ok().json(projects))
+    } else {
+        Err(ApiError::NotFound)
+    }
+}
+
 #[derive(derive_new::new)]
 pub struct CategoryChanges<'a> {
     pub categories: &'a Option<Vec<String>>,
@@ -1051,6 +1125,7 @@ pub async fn projects_edit(
     bulk_edit_project: web::Json<BulkEditProject>,
     redis: web::Data<RedisPool>,
     session_queue: web::Data<AuthQueue>,
+    search_index_queue: web::Data<SearchIndexQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let user = get_user_from_headers(
         &req,
@@ -1236,6 +1311,7 @@ pub async fn projects_edit(
     }
 
     transaction.commit().await?;
+    search_index_queue.add_many(project_ids).await;
 
     Ok(HttpResponse::NoContent().body(""))
 }
@@ -1317,6 +1393,7 @@ pub async fn project_icon_edit(
     file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     mut payload: web::Payload,
     session_queue: web::Data<AuthQueue>,
+    search_index_queue: web::Data<SearchIndexQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let user = get_user_from_headers(
         &req,
@@ -1405,6 +1482,7 @@ pub async fn project_icon_edit(
     transaction.commit().await?;
     db_models::Project::clear_cache(project_item.inner.id, project_item.inner.slug, None, &redis)
         .await?;
+    search_index_queue.add(project_item.inner.id).await;
 
     Ok(HttpResponse::NoContent().body(""))
 }
@@ -1416,6 +1494,7 @@ pub async fn delete_project_icon(
     redis: web::Data<RedisPool>,
     file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
+    search_index_queue: web::Data<SearchIndexQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let user = get_user_from_headers(
         &req,
@@ -1486,6 +1565,7 @@ pub async fn delete_project_icon(
     transaction.commit().await?;
     db_models::Project::clear_cache(project_item.inner.id, project_item.inner.slug, None, &redis)
         .await?;
+    search_index_queue.add(project_item.inner.id).await;
 
     Ok(HttpResponse::NoContent().body(""))
 }
@@ -1511,6 +1591,7 @@ pub async fn add_gallery_item(
     file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     mut payload: web::Payload,
     session_queue: web::Data<AuthQueue>,
+    search_index_queue: web::Data<SearchIndexQueue>,
 ) -> Result<HttpResponse, ApiError> {
     item.validate()
         .map_err(|err| ApiError::Validation(validation_errors_to_string(err, None)))?;
@@ -1626,6 +1707,7 @@ pub async fn add_gallery_item(
     transaction.commit().await?;
     db_models::Project::clear_cache(project_item.inner.id, project_item.inner.slug, None, &redis)
         .await?;
+    search_index_queue.add(project_item.inner.id).await;
 
     Ok(HttpResponse::NoContent().body(""))
 }
@@ -1659,6 +1741,7 @@ pub async fn edit_gallery_item(
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     session_queue: web::Data<AuthQueue>,
+    search_index_queue: web::Data<SearchIndexQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let user = get_user_from_headers(
         &req,
@@ -1800,6 +1883,7 @@ pub async fn edit_gallery_item(
 
     db_models::Project::clear_cache(project_item.inner.id, project_item.inner.slug, None, &redis)
         .await?;
+    search_index_queue.add(project_item.inner.id).await;
 
     Ok(HttpResponse::NoContent().body(""))
 }
@@ -1809,6 +1893,7 @@ pub struct GalleryDeleteQuery {
     pub url: String,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn delete_gallery_item(
     req: HttpRequest,
     web::Query(item): web::Query<GalleryDeleteQuery>,
@@ -1817,6 +1902,7 @@ pub async fn delete_gallery_item(
     redis: web::Data<RedisPool>,
     file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
+    search_index_queue: web::Data<SearchIndexQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let user = get_user_from_headers(
         &req,
@@ -1905,6 +1991,7 @@ pub async fn delete_gallery_item(
 
     db_models::Project::clear_cache(project_item.inner.id, project_item.inner.slug, None, &redis)
         .await?;
+    search_index_queue.add(project_item.inner.id).await;
 
     Ok(HttpResponse::NoContent().body(""))
 }
@@ -0,0 +1,262 @@
+use super::ApiError;
+use crate::auth::get_user_from_headers;
+use crate::database::models::ids::generate_saved_search_id;
+use crate::database::models::saved_search_item::SavedSearch as DBSavedSearch;
+use crate::database::models::{self as db_models, User};
+use crate::database::redis::RedisPool;
+use crate::models::ids::SavedSearchId;
+use crate::models::pats::Scopes;
+use crate::models::projects::SearchRequest;
+use crate::models::saved_searches::SavedSearch;
+use crate::queue::session::AuthQueue;
+use crate::search::{search_for_project, SearchConfig, SearchError};
+use crate::util::validate::validation_errors_to_string;
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use serde::Deserialize;
+use sqlx::PgPool;
+use validator::Validate;
+
+/// How many searches each user may save
+const MAX_SAVED_SEARCHES: i64 = 25;
+
+/// Gets the user whose saved searches are accessed, if the current user may access them
+async fn get_searches_owner(
+    req: &HttpRequest,
+    user_id: &str,
+    scopes: &[Scopes],
+    pool: &PgPool,
+    redis: &RedisPool,
+    session_queue: &AuthQueue,
+) -> Result<db_models::UserId, ApiError> {
+    let user = get_user_from_headers(req, pool, redis, session_queue, Some(scopes))
+        .await?
+        .1;
+    let id = User::get(user_id, pool, redis)
+        .await?
+        .ok_or(ApiError::NotFound)?
+        .id;
+
+    if !user.role.is_admin() && user.id != id.into() {
+        return Err(ApiError::CustomAuthentication(
+            "You do not have permission to access the saved searches of this user!".to_string(),
+        ));
+    }
+
+    Ok(id)
+}
+
+/// Gets a saved search of a user
+async fn get_saved_search(
+    owner: db_models::UserId,
+    id: SavedSearchId,
+    pool: &PgPool,
+) -> Result<DBSavedSearch, ApiError> {
+    DBSavedSearch::get(id.into(), pool)
+        .await?
+        .filter(|x| x.user_id == owner)
+        .ok_or(ApiError::NotFound)
+}
+
+/// Checks that a search can be run, by running it
+async fn validate_search(
+    request: &SearchRequest,
+    config: &SearchConfig,
+    pool: &PgPool,
+    redis: &RedisPool,
+) -> Result<(), ApiError> {
+    search_for_project(request, config, pool, redis)
+        .await
+        .map_err(|err| match err {
+            SearchError::Database(err) => ApiError::Database(err),
+            err => ApiError::InvalidInput(format!("Invalid search: {err}")),
+        })?;
+
+    Ok(())
+}
+
+pub async fn saved_searches_list(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let owner = get_searches_owner(
+        &req,
+        &info.into_inner().0,
+        &[Scopes::USER_READ],
+        &pool,
+        &redis,
+        &session_queue,
+    )
+    .await?;
+
+    let searches = DBSavedSearch::get_many_user(owner, &**pool)
+        .await?
+        .into_iter()
+        .map(SavedSearch::from)
+        .collect::<Vec<_>>();
+
+    Ok(HttpResponse::Ok().json(searches))
+}
+
+pub async fn saved_search_get(
+    req: HttpRequest,
+    info: web::Path<(String, SavedSearchId)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let (user_id, id) = info.into_inner();
+    let owner = get_searches_owner(
+        &req,
+        &user_id,
+        &[Scopes::USER_READ],
+        &pool,
+        &redis,
+        &session_queue,
+    )
+    .await?;
+
+    let search = get_saved_search(owner, id, &pool).await?;
+
+    Ok(HttpResponse::Ok().json(SavedSearch::from(search)))
+}
+
+#[derive(Deserialize, Validate)]
+pub struct SavedSearchCreateData {
+    #[validate(length(min = 1, max = 64))]
+    pub name: String,
+    /// The search parameters, as accepted by the project search route
+    pub request: SearchRequest,
+}
+
+pub async fn saved_search_create(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    create_data: web::Json<SavedSearchCreateData>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    config: web::Data<SearchConfig>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let owner = get_searches_owner(
+        &req,
+        &info.into_inner().0,
+        &[Scopes::USER_WRITE],
+        &pool,
+        &redis,
+        &session_queue,
+    )
+    .await?;
+
+    let create_data = create_data.into_inner();
+    create_data
+        .validate()
+        .map_err(|err| ApiError::InvalidInput(validation_errors_to_string(err, None)))?;
+    validate_search(&create_data.request, &config, &pool, &redis).await?;
+
+    if DBSavedSearch::count_user(owner, &**pool).await? >= MAX_SAVED_SEARCHES {
+        return Err(ApiError::InvalidInput(format!(
+            "You may only save up to {MAX_SAVED_SEARCHES} searches!"
+        )));
+    }
+
+    let mut transaction = pool.begin().await?;
+
+    // Only projects approved from now on are matched, not those already shown by the search
+    let now = Utc::now();
+    let search = DBSavedSearch {
+        id: generate_saved_search_id(&mut transaction).await?,
+        user_id: owner,
+        name: create_data.name,
+        request: create_data.request,
+        created: now,
+        last_checked: now,
+    };
+    search.insert(&mut transaction).await?;
+
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(SavedSearch::from(search)))
+}
+
+#[derive(Deserialize, Validate)]
+pub struct EditSavedSearch {
+    #[validate(length(min = 1, max = 64))]
+    pub name: Option<String>,
+    pub request: Option<SearchRequest>,
+}
+
+pub async fn saved_search_edit(
+    req: HttpRequest,
+    info: web::Path<(String, SavedSearchId)>,
+    edit_data: web::Json<EditSavedSearch>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    config: web::Data<SearchConfig>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let (user_id, id) = info.into_inner();
+    let owner = get_searches_owner(
+        &req,
+        &user_id,
+        &[Scopes::USER_WRITE],
+        &pool,
+        &redis,
+        &session_queue,
+    )
+    .await?;
+
+    let edit_data = edit_data.into_inner();
+    edit_data
+        .validate()
+        .map_err(|err| ApiError::InvalidInput(validation_errors_to_string(err, None)))?;
+
+    let mut search = get_saved_search(owner, id, &pool).await?;
+    if let Some(name) = edit_data.name {
+        search.name = name;
+    }
+    if let Some(request) = edit_data.request {
+        validate_search(&request, &config, &pool, &redis).await?;
+        search.request = request;
+    }
+
+    let mut transaction = pool.begin().await?;
+    search.update(&mut transaction).await?;
+    transaction.commit().await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+pub async fn saved_search_delete(
+    req: HttpRequest,
+    info: web::Path<(String, SavedSearchId)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let (user_id, id) = info.into_inner();
+    let owner = get_searches_owner(
+        &req,
+        &user_id,
+        &[Scopes::USER_WRITE],
+        &pool,
+        &redis,
+        &session_queue,
+    )
+    .await?;
+
+    let search = get_saved_search(owner, id, &pool).await?;
+
+    let mut transaction = pool.begin().await?;
+    let result = DBSavedSearch::remove(search.id, &mut transaction).await?;
+    transaction.commit().await?;
+
+    if result.is_some() {
+        Ok(HttpResponse::NoContent().body(""))
+    } else {
+        Err(ApiError::NotFound)
+    }
+}
@@ -0,0 +1,374 @@
+use super::project_creation::CreateError;
+use super::version_creation::{max_version_file_size, max_version_file_size_error};
+use super::ApiError;
+use crate::auth::get_user_from_headers;
+use crate::database::models::generate_upload_session_id;
+use crate::database::models::upload_session_item::{
+    UploadSession as DBUploadSession, UploadSessionChunk,
+};
+use crate::database::redis::RedisPool;
+use crate::file_hosting::FileHost;
+use crate::models::ids::UploadSessionId;
+use crate::models::pats::Scopes;
+use crate::models::uploads::UploadSession;
+use crate::models::users::User;
+use crate::queue::session::AuthQueue;
+use crate::util::routes::{read_from_payload, spool_from_stream, SpooledFile};
+use crate::util::validate::validation_errors_to_string;
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::{Duration, Utc};
+use futures::{StreamExt, TryStreamExt};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use validator::Validate;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.route("upload", web::post().to(upload_session_create));
+
+    cfg.service(
+        web::scope("upload")
+            .route("{id}", web::get().to(upload_session_get))
+            .route("{id}", web::delete().to(upload_session_delete))
+            .route("{id}/{chunk}", web::put().to(upload_chunk)),
+    );
+}
+
+const DEFAULT_CHUNK_SIZE: u32 = 8 * (1 << 20);
+const MIN_CHUNK_SIZE: u32 = 1 << 20;
+const MAX_CHUNK_SIZE: u32 = 32 * (1 << 20);
+
+// Sessions expire this many hours after they were created or last received a chunk
+const UPLOAD_SESSION_EXPIRY_HOURS: i64 = 24;
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct UploadSessionCreate {
+    #[validate(length(min = 1, max = 1024))]
+    pub file_name: String,
+    /// The total size in bytes of the file which will be uploaded
+    pub file_size: u64,
+    /// The size in bytes of each chunk. Defaults to 8MiB
+    pub chunk_size: Option<u32>,
+}
+
+pub async fn upload_session_create(
+    req: HttpRequest,
+    create_data: web::Json<UploadSessionCreate>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::VERSION_CREATE]),
+    )
+    .await?
+    .1;
+
+    let create_data = create_data.into_inner();
+    create_data
+        .validate()
+        .map_err(|err| ApiError::Validation(validation_errors_to_string(err, None)))?;
+
+    if create_data.file_name.contains('/') {
+        return Err(ApiError::InvalidInput(
+            "File names must not contain slashes!".to_string(),
+        ));
+    }
+
+    if create_data
+        .file_name
+        .rsplit_once('.')
+        .and_then(|(_, ext)| crate::util::ext::project_file_type(ext))
+        .is_none()
+    {
+        return Err(ApiError::InvalidInput(format!(
+            "File {} has an unsupported file type",
+            create_data.file_name
+        )));
+    }
+
+    if create_data.file_size == 0 {
+        return Err(ApiError::InvalidInput(
+            "Uploaded files must not be empty".to_string(),
+        ));
+    }
+
+    let max_size = max_version_file_size();
+    if create_data.file_size > max_size {
+        return Err(ApiError::InvalidInput(max_version_file_size_error(
+            max_size,
+        )));
+    }
+
+    let chunk_size = create_data.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
+    if !(MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&chunk_size) {
+        return Err(ApiError::InvalidInput(format!(
+            "Chunk size must be between {MIN_CHUNK_SIZE} and {MAX_CHUNK_SIZE} bytes"
+        )));
+    }
+
+    let mut transaction = pool.begin().await?;
+
+    let now = Utc::now();
+    let session = DBUploadSession {
+        id: generate_upload_session_id(&mut transaction).await?,
+        user_id: user.id.into(),
+        file_name: create_data.file_name,
+        file_size: create_data.file_size as i64,
+        chunk_size: chunk_size as i64,
+        created: now,
+        expires: now + Duration::hours(UPLOAD_SESSION_EXPIRY_HOURS),
+    };
+    session.insert(&mut transaction).await?;
+
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(UploadSession::from(session, &[])))
+}
+
+async fn get_user_session(
+    id: UploadSessionId,
+    user: &User,
+    exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+) -> Result<Option<DBUploadSession>, crate::database::models::DatabaseError> {
+    Ok(DBUploadSession::get(id.into(), exec)
+        .await?
+        .filter(|x| is_active_user_session(x, user)))
+}
+
+fn is_active_user_session(session: &DBUploadSession, user: &User) -> bool {
+    session.user_id == user.id.into() && session.expires > Utc::now()
+}
+
+pub async fn upload_session_get(
+    req: HttpRequest,
+    info: web::Path<(UploadSessionId,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::VERSION_CREATE]),
+    )
+    .await?
+    .1;
+
+    let session = get_user_session(info.into_inner().0, &user, &**pool)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+    let chunks = DBUploadSession::get_chunks(session.id, &**pool).await?;
+
+    Ok(HttpResponse::Ok().json(UploadSession::from(session, &chunks)))
+}
+
+pub async fn upload_chunk(
+    req: HttpRequest,
+    info: web::Path<(UploadSessionId, u32)>,
+    mut payload: web::Payload,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::VERSION_CREATE]),
+    )
+    .await?
+    .1;
+
+    let (id, index) = info.into_inner();
+    let session = get_user_session(id, &user, &**pool)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    let expected_size = session.expected_chunk_size(index).ok_or_else(|| {
+        ApiError::InvalidInput(format!(
+            "Chunk index must be less than {}",
+            session.chunk_count()
+        ))
+    })?;
+
+    let bytes = read_from_payload(
+        &mut payload,
+        session.chunk_size as usize,
+        "Chunk exceeds the chunk size of the upload session",
+    )
+    .await?;
+
+    if bytes.len() as i64 != expected_size {
+        return Err(ApiError::InvalidInput(format!(
+            "Chunk {index} must be exactly {expected_size} bytes"
+        )));
+    }
+
+    let upload_data = file_host
+        .upload_file(
+            "application/octet-stream",
+            &session.chunk_path(index),
+            bytes.freeze(),
+        )
+        .await?;
+
+    let mut transaction = pool.begin().await?;
+    let previous = session
+        .upsert_chunk(
+            &UploadSessionChunk {
+                session_id: session.id,
+                chunk_index: index as i32,
+                file_id: upload_data.file_id.clone(),
+                file_name: upload_data.file_name.clone(),
+                size: upload_data.content_length as i64,
+            },
+            Utc::now() + Duration::hours(UPLOAD_SESSION_EXPIRY_HOURS),
+            &mut transaction,
+        )
+        .await?;
+    transaction.commit().await?;
+
+    // The chunk was uploaded again, so the object of the earlier upload is no longer needed
+    if let Some(previous) = previous {
+        delete_chunks(&***file_host, &[previous]).await;
+    }
+
+    let session = get_user_session(id, &user, &**pool)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+    let chunks = DBUploadSession::get_chunks(session.id, &**pool).await?;
+
+    Ok(HttpResponse::Ok().json(UploadSession::from(session, &chunks)))
+}
+
+pub async fn upload_session_delete(
+    req: HttpRequest,
+    info: web::Path<(UploadSessionId,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::VERSION_CREATE]),
+    )
+    .await?
+    .1;
+
+    let session = get_user_session(info.into_inner().0, &user, &**pool)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    let mut transaction = pool.begin().await?;
+    let chunks = DBUploadSession::get_chunks(session.id, &mut *transaction).await?;
+    DBUploadSession::remove(session.id, &mut transaction).await?;
+    transaction.commit().await?;
+
+    delete_chunks(&***file_host, &chunks).await;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+/// An upload session which has received all of its chunks, reassembled into a temporary file
+pub struct CompletedUpload {
+    pub file_name: String,
+    pub chunks: Vec<UploadSessionChunk>,
+    pub data: SpooledFile,
+}
+
+/// Reassembles a completed upload session owned by the user and removes it. The chunks of
+/// the session should be deleted with [`delete_chunks`] once the transaction is committed.
+pub async fn take_completed_upload(
+    id: UploadSessionId,
+    user: &User,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    file_host: &dyn FileHost,
+) -> Result<CompletedUpload, CreateError> {
+    let session = DBUploadSession::get_for_update(id.into(), transaction)
+        .await?
+        .filter(|x| is_active_user_session(x, user))
+        .ok_or_else(|| CreateError::InvalidInput(format!("Upload session {id} does not exist")))?;
+    let chunks = DBUploadSession::get_chunks(session.id, &mut **transaction).await?;
+
+    if chunks.len() != session.chunk_count() as usize {
+        return Err(CreateError::InvalidInput(format!(
+            "Upload session {id} has not received all of its chunks"
+        )));
+    }
+
+    let stream = futures::stream::iter(&chunks)
+        .then(|chunk| file_host.download_file(&chunk.file_id, &chunk.file_name))
+        .try_flatten();
+    let max_size = max_version_file_size();
+    let data = spool_from_stream(
+        Box::pin(stream),
+        max_size,
+        &max_version_file_size_error(max_size),
+    )
+    .await?;
+
+    if data.len() != session.file_size as u64 {
+        return Err(CreateError::InvalidInput(format!(
+            "Upload session {id} does not match its declared file size"
+        )));
+    }
+
+    DBUploadSession::remove(session.id, transaction).await?;
+
+    Ok(CompletedUpload {
+        file_name: session.file_name,
+        chunks,
+        data,
+    })
+}
+
+/// Deletes the stored chunks of upload sessions. Failures are only logged, as leftover
+/// chunks are not referenced by anything.
+pub async fn delete_chunks(
+    file_host: &(dyn FileHost + Send + Sync),
+    chunks: &[UploadSessionChunk],
+) {
+    for chunk in chunks {
+        if let Err(e) = file_host
+            .delete_file_version(&chunk.file_id, &chunk.file_name)
+            .await
+        {
+            warn!("Deleting upload chunk {} failed: {:?}", chunk.file_name, e);
+        }
+    }
+}
+
+/// Removes upload sessions which have expired, along with their chunks
+pub async fn remove_expired_sessions(
+    pool: &PgPool,
+    file_host: &(dyn FileHost + Send + Sync),
+) -> Result<(), ApiError> {
+    let expired = DBUploadSession::get_expired(pool).await?;
+
+    for id in &expired {
+        let mut transaction = pool.begin().await?;
+        let chunks = DBUploadSession::get_chunks(*id, &mut *transaction).await?;
+        DBUploadSession::remove(*id, &mut transaction).await?;
+        transaction.commit().await?;
+
+        delete_chunks(file_host, &chunks).await;
+    }
+
+    info!("Removed {} expired upload sessions", expired.len());
+
+    Ok(())
+}
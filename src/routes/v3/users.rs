@@ -7,7 +7,11 @@ use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use validator::Validate;
 
-use super::{oauth_clients::get_user_clients, ApiError};
+use super::{oauth_clients::get_user_clients, saved_searches, ApiError};
+use crate::search::indexing::USERS_INDEX;
+use crate::search::{
+    search_for_entities, EntitySearchRequest, SearchConfig, SearchError, SearchUser,
+};
 use crate::util::img::delete_old_images;
 use crate::{
     auth::{filter_visible_projects, get_user_from_headers},
@@ -28,6 +32,7 @@ use crate::{
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.route("user", web::get().to(user_auth_get));
     cfg.route("users", web::get().to(users_get));
+    cfg.route("search/users", web::get().to(users_search));
 
     cfg.service(
         web::scope("user")
@@ -40,10 +45,39 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .route("{id}", web::delete().to(user_delete))
             .route("{id}/follows", web::get().to(user_follows))
             .route("{id}/notifications", web::get().to(user_notifications))
-            .route("{id}/oauth_apps", web::get().to(get_user_clients)),
+            .route("{id}/oauth_apps", web::get().to(get_user_clients))
+            .route(
+                "{id}/saved_searches",
+                web::get().to(saved_searches::saved_searches_list),
+            )
+            .route(
+                "{id}/saved_searches",
+                web::post().to(saved_searches::saved_search_create),
+            )
+            .route(
+                "{id}/saved_searches/{search_id}",
+                web::get().to(saved_searches::saved_search_get),
+            )
+            .route(
+                "{id}/saved_searches/{search_id}",
+                web::patch().to(saved_searches::saved_search_edit),
+            )
+            .route(
+                "{id}/saved_searches/{search_id}",
+                web::delete().to(saved_searches::saved_search_delete),
+            ),
     );
 }
 
+pub async fn users_search(
+    web::Query(info): web::Query<EntitySearchRequest>,
+    config: web::Data<SearchConfig>,
+) -> Result<HttpResponse, SearchError> {
+    let results = search_for_entities::<SearchUser>(USERS_INDEX, &info, &config).await?;
+
+    Ok(HttpResponse::Ok().json(results))
+}
+
 pub async fn projects_list(
     req: HttpRequest,
     info: web::Path<(String,)>,
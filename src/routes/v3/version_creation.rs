@@ -1,13 +1,20 @@
 use super::project_creation::{CreateError, UploadedFile};
+use super::uploads::{delete_chunks, take_completed_upload};
 use crate::auth::get_user_from_headers;
+use crate::database::models::legacy_loader_fields::MinecraftGameVersion;
 use crate::database::models::loader_fields::{LoaderField, LoaderFieldEnumValue, VersionField};
+use crate::database::models::modpack_content_item::ModpackContent;
 use crate::database::models::notification_item::NotificationBuilder;
+use crate::database::models::project_mod_id_item::ProjectModId;
+use crate::database::models::upload_session_item::UploadSessionChunk;
 use crate::database::models::version_item::{
     DependencyBuilder, VersionBuilder, VersionFileBuilder,
 };
 use crate::database::models::{self, image_item, Organization};
 use crate::database::redis::RedisPool;
-use crate::file_hosting::FileHost;
+use crate::file_hosting::visibility::sync_file_visibility;
+use crate::file_hosting::{sign_cdn_url, FileHost, FileHostingError};
+use crate::models::ids::UploadSessionId;
 use crate::models::images::{Image, ImageContext, ImageId};
 use crate::models::notifications::NotificationBody;
 use crate::models::pack::PackFileHash;
@@ -18,11 +25,16 @@ use crate::models::projects::{
     VersionType,
 };
 use crate::models::teams::ProjectPermissions;
+use crate::models::users::User;
 use crate::queue::moderation::AutomatedModerationQueue;
+use crate::queue::search_index::SearchIndexQueue;
 use crate::queue::session::AuthQueue;
-use crate::util::routes::read_from_field;
+use crate::util::env::parse_var;
+use crate::util::routes::{spool_from_field, SpooledFile};
 use crate::util::validate::validation_errors_to_string;
-use crate::validate::{validate_file, ValidationResult};
+use crate::validate::manifest::ModManifest;
+use crate::validate::pack_contents::resolve_pack_files;
+use crate::validate::{validate_file, FileValidation, ValidationResult};
 use actix_multipart::{Field, Multipart};
 use actix_web::web::Data;
 use actix_web::{web, HttpRequest, HttpResponse};
@@ -35,6 +47,22 @@ use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use validator::Validate;
 
+const DEFAULT_MAX_VERSION_FILE_SIZE: u64 = 500 * (1 << 20);
+
+/// The largest version file which can be uploaded, in bytes. It is set by
+/// `MAX_VERSION_FILE_SIZE` and defaults to 500MiB, and can be raised past 4GiB for instances
+/// which host large server packs.
+pub fn max_version_file_size() -> u64 {
+    parse_var("MAX_VERSION_FILE_SIZE").unwrap_or(DEFAULT_MAX_VERSION_FILE_SIZE)
+}
+
+pub fn max_version_file_size_error(max_size: u64) -> String {
+    format!(
+        "Project file exceeds the maximum of {}MiB. Contact a moderator or admin to request permission to upload larger files.",
+        max_size >> 20
+    )
+}
+
 fn default_requested_status() -> VersionStatus {
     VersionStatus::Listed
 }
@@ -45,10 +73,9 @@ pub struct InitialVersionData {
     pub project_id: Option<ProjectId>,
     #[validate(length(min = 1, max = 256))]
     pub file_parts: Vec<String>,
-    #[validate(
-        length(min = 1, max = 32),
-        regex = "crate::util::validate::RE_URL_SAFE"
-    )]
+    /// Filled from the manifest of the primary file when left empty
+    #[validate(length(max = 32), regex = "crate::util::validate::RE_URL_SAFE")]
+    #[serde(default)]
     pub version_number: String,
     #[validate(
         length(min = 1, max = 64),
@@ -66,7 +93,8 @@ pub struct InitialVersionData {
     pub dependencies: Vec<Dependency>,
     #[serde(alias = "version_type")]
     pub release_channel: VersionType,
-    #[validate(length(min = 1))]
+    /// Filled from the manifest of the primary file when left empty
+    #[serde(default)]
     pub loaders: Vec<Loader>,
     pub featured: bool,
     pub primary_file: Option<String>,
@@ -84,11 +112,22 @@ pub struct InitialVersionData {
     // Flattened loader fields
     // All other fields are loader-specific VersionFields
     // These are flattened during serialization
+    // Game versions and side types are filled from the manifest of the primary file when left out
     #[serde(deserialize_with = "skip_nulls")]
     #[serde(flatten)]
     pub fields: HashMap<String, serde_json::Value>,
 }
 
+/// A newly created version, along with warnings about where its data contradicts the
+/// manifest of its primary file
+#[derive(Serialize, Deserialize)]
+pub struct CreatedVersion {
+    #[serde(flatten)]
+    pub version: Version,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 struct InitialFileData {
     #[serde(default = "HashMap::new")]
@@ -96,6 +135,7 @@ struct InitialFileData {
 }
 
 // under `/api/v1/version`
+#[allow(clippy::too_many_arguments)]
 pub async fn version_create(
     req: HttpRequest,
     mut payload: Multipart,
@@ -104,6 +144,7 @@ pub async fn version_create(
     file_host: Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: Data<AuthQueue>,
     moderation_queue: web::Data<AutomatedModerationQueue>,
+    search_index_queue: web::Data<SearchIndexQueue>,
 ) -> Result<HttpResponse, CreateError> {
     let mut transaction = client.begin().await?;
     let mut uploaded_files = Vec::new();
@@ -118,6 +159,7 @@ pub async fn version_create(
         &client,
         &session_queue,
         &moderation_queue,
+        &search_index_queue,
     )
     .await;
 
@@ -148,12 +190,14 @@ async fn version_create_inner(
     pool: &PgPool,
     session_queue: &AuthQueue,
     moderation_queue: &AutomatedModerationQueue,
+    search_index_queue: &SearchIndexQueue,
 ) -> Result<HttpResponse, CreateError> {
     let cdn_url = dotenvy::var("CDN_URL")?;
 
     let mut initial_version_data = None;
     let mut version_builder = None;
     let mut selected_loaders = None;
+    let mut manifests = HashMap::new();
 
     let user = get_user_from_headers(
         &req,
@@ -186,138 +230,12 @@ async fn version_create_inner(
                 }
 
                 let version_create_data: InitialVersionData = serde_json::from_slice(&data)?;
-                initial_version_data = Some(version_create_data);
-                let version_create_data = initial_version_data.as_ref().unwrap();
-                if version_create_data.project_id.is_none() {
-                    return Err(CreateError::MissingValueError(
-                        "Missing project id".to_string(),
-                    ));
-                }
-
-                version_create_data.validate().map_err(|err| {
-                    CreateError::ValidationError(validation_errors_to_string(err, None))
-                })?;
-
-                if !version_create_data.status.can_be_requested() {
-                    return Err(CreateError::InvalidInput(
-                        "Status specified cannot be requested".to_string(),
-                    ));
-                }
-
-                let project_id: models::ProjectId = version_create_data.project_id.unwrap().into();
-
-                // Ensure that the project this version is being added to exists
-                if models::Project::get_id(project_id, &mut **transaction, redis)
-                    .await?
-                    .is_none()
-                {
-                    return Err(CreateError::InvalidInput(
-                        "An invalid project id was supplied".to_string(),
-                    ));
-                }
-
-                // Check that the user creating this version is a team member
-                // of the project the version is being added to.
-                let team_member = models::TeamMember::get_from_user_id_project(
-                    project_id,
-                    user.id.into(),
-                    false,
-                    &mut **transaction,
-                )
-                .await?;
-
-                // Get organization attached, if exists, and the member project permissions
-                let organization = models::Organization::get_associated_organization_project_id(
-                    project_id,
-                    &mut **transaction,
-                )
-                .await?;
-
-                let organization_team_member = if let Some(organization) = &organization {
-                    models::TeamMember::get_from_user_id(
-                        organization.team_id,
-                        user.id.into(),
-                        &mut **transaction,
-                    )
-                    .await?
-                } else {
-                    None
-                };
-
-                let permissions = ProjectPermissions::get_permissions_by_role(
-                    &user.role,
-                    &team_member,
-                    &organization_team_member,
-                )
-                .unwrap_or_default();
-
-                if !permissions.contains(ProjectPermissions::UPLOAD_VERSION) {
-                    return Err(CreateError::CustomAuthenticationError(
-                        "You don't have permission to upload this version!".to_string(),
-                    ));
-                }
-
-                let version_id: VersionId = models::generate_version_id(transaction).await?.into();
+                let (builder, loaders) =
+                    prepare_version(&version_create_data, &user, transaction, redis).await?;
 
-                let all_loaders =
-                    models::loader_fields::Loader::list(&mut **transaction, redis).await?;
-                let loaders = version_create_data
-                    .loaders
-                    .iter()
-                    .map(|x| {
-                        all_loaders
-                            .iter()
-                            .find(|y| y.loader == x.0)
-                            .cloned()
-                            .ok_or_else(|| CreateError::InvalidLoader(x.0.clone()))
-                    })
-                    .collect::<Result<Vec<_>, _>>()?;
-                selected_loaders = Some(loaders.clone());
-                let loader_ids: Vec<models::LoaderId> = loaders.iter().map(|y| y.id).collect_vec();
-
-                let loader_fields =
-                    LoaderField::get_fields(&loader_ids, &mut **transaction, redis).await?;
-                let mut loader_field_enum_values = LoaderFieldEnumValue::list_many_loader_fields(
-                    &loader_fields,
-                    &mut **transaction,
-                    redis,
-                )
-                .await?;
-                let version_fields = try_create_version_fields(
-                    version_id,
-                    &version_create_data.fields,
-                    &loader_fields,
-                    &mut loader_field_enum_values,
-                )?;
-
-                let dependencies = version_create_data
-                    .dependencies
-                    .iter()
-                    .map(|d| models::version_item::DependencyBuilder {
-                        version_id: d.version_id.map(|x| x.into()),
-                        project_id: d.project_id.map(|x| x.into()),
-                        dependency_type: d.dependency_type.to_string(),
-                        file_name: None,
-                    })
-                    .collect::<Vec<_>>();
-
-                version_builder = Some(VersionBuilder {
-                    version_id: version_id.into(),
-                    project_id,
-                    author_id: user.id.into(),
-                    name: version_create_data.version_title.clone(),
-                    version_number: version_create_data.version_number.clone(),
-                    changelog: version_create_data.version_body.clone().unwrap_or_default(),
-                    files: Vec::new(),
-                    dependencies,
-                    loaders: loader_ids,
-                    version_fields,
-                    version_type: version_create_data.release_channel.to_string(),
-                    featured: version_create_data.featured,
-                    status: version_create_data.status,
-                    requested_status: None,
-                    ordering: version_create_data.ordering,
-                });
+                initial_version_data = Some(version_create_data);
+                version_builder = Some(builder);
+                selected_loaders = Some(loaders);
 
                 return Ok(());
             }
@@ -339,7 +257,7 @@ async fn version_create_inner(
 
             let existing_file_names = version.files.iter().map(|x| x.filename.clone()).collect();
 
-            upload_file(
+            let manifest = upload_file(
                 &mut field,
                 file_host,
                 version_data.file_parts.len(),
@@ -361,6 +279,11 @@ async fn version_create_inner(
             )
             .await?;
 
+            if let Some(manifest) = manifest {
+                let (file_name, _) = get_name_ext(&content_disposition)?;
+                manifests.insert(file_name.to_string(), manifest);
+            }
+
             Ok(())
         }
         .await;
@@ -379,12 +302,210 @@ async fn version_create_inner(
     let builder = version_builder
         .ok_or_else(|| CreateError::InvalidInput("`data` field is required".to_string()))?;
 
+    finish_version_create(
+        version_data,
+        builder,
+        manifests,
+        &user,
+        transaction,
+        redis,
+        pool,
+        file_host,
+        moderation_queue,
+        search_index_queue,
+    )
+    .await
+}
+
+/// Validates the initial data of a new version and checks that the user is allowed to
+/// upload it, returning the builder to which its files are added along with its loaders
+pub(crate) async fn prepare_version(
+    version_data: &InitialVersionData,
+    user: &User,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    redis: &RedisPool,
+) -> Result<(VersionBuilder, Vec<models::loader_fields::Loader>), CreateError> {
+    if version_data.project_id.is_none() {
+        return Err(CreateError::MissingValueError(
+            "Missing project id".to_string(),
+        ));
+    }
+
+    version_data
+        .validate()
+        .map_err(|err| CreateError::ValidationError(validation_errors_to_string(err, None)))?;
+
+    if !version_data.status.can_be_requested() {
+        return Err(CreateError::InvalidInput(
+            "Status specified cannot be requested".to_string(),
+        ));
+    }
+
+    let project_id: models::ProjectId = version_data.project_id.unwrap().into();
+
+    // Ensure that the project this version is being added to exists
+    if models::Project::get_id(project_id, &mut **transaction, redis)
+        .await?
+        .is_none()
+    {
+        return Err(CreateError::InvalidInput(
+            "An invalid project id was supplied".to_string(),
+        ));
+    }
+
+    // Check that the user creating this version is a team member
+    // of the project the version is being added to.
+    let team_member = models::TeamMember::get_from_user_id_project(
+        project_id,
+        user.id.into(),
+        false,
+        &mut **transaction,
+    )
+    .await?;
+
+    // Get organization attached, if exists, and the member project permissions
+    let organization = models::Organization::get_associated_organization_project_id(
+        project_id,
+        &mut **transaction,
+    )
+    .await?;
+
+    let organization_team_member = if let Some(organization) = &organization {
+        models::TeamMember::get_from_user_id(
+            organization.team_id,
+            user.id.into(),
+            &mut **transaction,
+        )
+        .await?
+    } else {
+        None
+    };
+
+    let permissions = ProjectPermissions::get_permissions_by_role(
+        &user.role,
+        &team_member,
+        &organization_team_member,
+    )
+    .unwrap_or_default();
+
+    if !permissions.contains(ProjectPermissions::UPLOAD_VERSION) {
+        return Err(CreateError::CustomAuthenticationError(
+            "You don't have permission to upload this version!".to_string(),
+        ));
+    }
+
+    let version_id: VersionId = models::generate_version_id(transaction).await?.into();
+
+    let all_loaders = models::loader_fields::Loader::list(&mut **transaction, redis).await?;
+    let loaders = version_data
+        .loaders
+        .iter()
+        .map(|x| {
+            all_loaders
+                .iter()
+                .find(|y| y.loader == x.0)
+                .cloned()
+                .ok_or_else(|| CreateError::InvalidLoader(x.0.clone()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let loader_ids: Vec<models::LoaderId> = loaders.iter().map(|y| y.id).collect_vec();
+
+    let loader_fields = LoaderField::get_fields(&loader_ids, &mut **transaction, redis).await?;
+    let mut loader_field_enum_values =
+        LoaderFieldEnumValue::list_many_loader_fields(&loader_fields, &mut **transaction, redis)
+            .await?;
+    // The fields are resolved again once the files' manifests have been read, which may
+    // fill in the loaders and fields which are missing here
+    let version_fields = create_version_fields(
+        version_id,
+        &version_data.fields,
+        &loader_fields,
+        &mut loader_field_enum_values,
+        false,
+    )?;
+
+    let dependencies = version_data
+        .dependencies
+        .iter()
+        .map(|d| models::version_item::DependencyBuilder {
+            version_id: d.version_id.map(|x| x.into()),
+            project_id: d.project_id.map(|x| x.into()),
+            dependency_type: d.dependency_type.to_string(),
+            file_name: None,
+        })
+        .collect::<Vec<_>>();
+
+    let builder = VersionBuilder {
+        version_id: version_id.into(),
+        project_id,
+        author_id: user.id.into(),
+        name: version_data.version_title.clone(),
+        version_number: version_data.version_number.clone(),
+        changelog: version_data.version_body.clone().unwrap_or_default(),
+        files: Vec::new(),
+        dependencies,
+        loaders: loader_ids,
+        version_fields,
+        version_type: version_data.release_channel.to_string(),
+        featured: version_data.featured,
+        status: version_data.status,
+        requested_status: None,
+        ordering: version_data.ordering,
+    };
+
+    Ok((builder, loaders))
+}
+
+/// Inserts a version whose files have all been uploaded, notifying the project's followers.
+/// `manifests` holds the mod manifests read from the version's files, by file name.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn finish_version_create(
+    mut version_data: InitialVersionData,
+    mut builder: VersionBuilder,
+    manifests: HashMap<String, ModManifest>,
+    user: &User,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    redis: &RedisPool,
+    pool: &PgPool,
+    file_host: &dyn FileHost,
+    moderation_queue: &AutomatedModerationQueue,
+    search_index_queue: &SearchIndexQueue,
+) -> Result<HttpResponse, CreateError> {
     if builder.files.is_empty() {
         return Err(CreateError::InvalidInput(
             "Versions must have at least one file uploaded to them".to_string(),
         ));
     }
 
+    let manifest = builder
+        .files
+        .iter()
+        .find(|x| x.primary)
+        .and_then(|x| manifests.get(&x.filename));
+    let (selected_loaders, warnings) = apply_manifest(
+        &mut version_data,
+        &mut builder,
+        manifest,
+        transaction,
+        redis,
+    )
+    .await?;
+    if let Some(manifest) = manifest {
+        detect_dependencies(&mut version_data, &mut builder, manifest, transaction).await?;
+    }
+    let mod_ids = manifests
+        .values()
+        .filter_map(|x| x.mod_id.clone())
+        .unique()
+        .collect_vec();
+
+    // Versions with suspicious code in their files are hidden until a moderator reviews them
+    let held = builder.files.iter().any(|x| !x.scan_findings.is_empty());
+    if held {
+        builder.requested_status = Some(builder.status);
+        builder.status = VersionStatus::Held;
+    }
+
     use futures::stream::TryStreamExt;
 
     let users = sqlx::query!(
@@ -411,15 +532,16 @@ async fn version_create_inner(
     .insert_many(users, &mut *transaction, redis)
     .await?;
 
-    let loader_structs = selected_loaders.unwrap_or_default();
     let (all_project_types, all_games): (Vec<String>, Vec<String>) =
-        loader_structs.iter().fold((vec![], vec![]), |mut acc, x| {
-            acc.0.extend_from_slice(&x.supported_project_types);
-            acc.1.extend(x.supported_games.clone());
-            acc
-        });
+        selected_loaders
+            .iter()
+            .fold((vec![], vec![]), |mut acc, x| {
+                acc.0.extend_from_slice(&x.supported_project_types);
+                acc.1.extend(x.supported_games.clone());
+                acc
+            });
 
-    let response = Version {
+    let mut response = Version {
         id: builder.version_id.into(),
         project_id: builder.project_id.into(),
         author_id: user.id,
@@ -458,6 +580,7 @@ async fn version_create_inner(
                 primary: file.primary,
                 size: file.size,
                 file_type: file.file_type,
+                validation: file.validation.clone(),
             })
             .collect::<Vec<_>>(),
         dependencies: version_data.dependencies,
@@ -468,6 +591,18 @@ async fn version_create_inner(
     let project_id = builder.project_id;
     builder.insert(transaction).await?;
 
+    ModpackContent::notify_newly_included(project_id, transaction, redis).await?;
+
+    // Files of a new version are only referenced by this transaction, so the copies at their
+    // old path can be deleted straight away
+    let moved = sync_file_visibility(project_id, file_host, transaction).await?;
+    for file in &mut response.files {
+        file.url = moved.url(&file.url);
+    }
+    moved.finish(file_host, pool, redis).await?;
+
+    ProjectModId::insert_many(project_id, &mod_ids, transaction).await?;
+
     for image_id in version_data.uploaded_images {
         if let Some(db_image) =
             image_item::Image::get(image_id.into(), &mut **transaction, redis).await?
@@ -512,15 +647,288 @@ async fn version_create_inner(
     .fetch_optional(pool)
     .await?;
 
-    if let Some(project_status) = project_status {
-        if project_status.status == ProjectStatus::Processing.as_str() {
-            moderation_queue.projects.insert(project_id.into());
+    if project_status
+        .as_ref()
+        .is_some_and(|x| x.status == ProjectStatus::Processing.as_str())
+    {
+        moderation_queue.projects.insert(project_id.into());
+    } else if held {
+        moderation_queue.versions.insert(version_id);
+    }
+    search_index_queue.add(project_id).await;
+
+    // Files of versions which are not public are only served through expiring URLs
+    if response.status.is_hidden()
+        || project_status.map_or(true, |x| ProjectStatus::from_string(&x.status).is_hidden())
+    {
+        let cdn_url = dotenvy::var("CDN_URL")?;
+        for file in &mut response.files {
+            file.url = sign_cdn_url(file_host, &cdn_url, &file.url).await?;
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(CreatedVersion {
+        version: response,
+        warnings,
+    }))
+}
+
+/// Fills in the loaders, version number, game versions, side types and Bedrock engine version
+/// left out of a version's data from the manifest of its primary file, and resolves the
+/// version's loaders and fields. Returns the loaders, along with warnings for any submitted
+/// data which contradicts the manifest.
+async fn apply_manifest(
+    version_data: &mut InitialVersionData,
+    builder: &mut VersionBuilder,
+    manifest: Option<&ModManifest>,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    redis: &RedisPool,
+) -> Result<(Vec<models::loader_fields::Loader>, Vec<String>), CreateError> {
+    let mut warnings = Vec::new();
+    let all_loaders = models::loader_fields::Loader::list(&mut **transaction, redis).await?;
+
+    if let Some(manifest) = manifest {
+        if version_data.loaders.is_empty() {
+            version_data.loaders = manifest
+                .loaders
+                .iter()
+                .filter(|x| all_loaders.iter().any(|y| &y.loader == *x))
+                .map(|x| Loader(x.clone()))
+                .collect();
+        } else if !manifest.loaders.is_empty()
+            && !version_data
+                .loaders
+                .iter()
+                .any(|x| manifest.supports_loader(&x.0))
+        {
+            warnings.push(format!(
+                "The primary file is a {} mod, but the version is for {}",
+                manifest.loaders.join(", "),
+                version_data.loaders.iter().map(|x| &x.0).join(", ")
+            ));
+        }
+
+        if let Some(version) = &manifest.version {
+            if version_data.version_number.is_empty() {
+                if version.len() <= 32 && crate::util::validate::RE_URL_SAFE.is_match(version) {
+                    version_data.version_number = version.clone();
+                }
+            } else if &version_data.version_number != version {
+                warnings.push(format!(
+                    "The version number {} does not match the version {} declared by the primary file",
+                    version_data.version_number, version
+                ));
+            }
+        }
+    }
+
+    check_version_metadata(version_data)?;
+
+    let loaders = version_data
+        .loaders
+        .iter()
+        .map(|x| {
+            all_loaders
+                .iter()
+                .find(|y| y.loader == x.0)
+                .cloned()
+                .ok_or_else(|| CreateError::InvalidLoader(x.0.clone()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let loader_ids: Vec<models::LoaderId> = loaders.iter().map(|y| y.id).collect_vec();
+    let loader_fields = LoaderField::get_fields(&loader_ids, &mut **transaction, redis).await?;
+
+    if let Some(manifest) = manifest {
+        let has_field = |name: &str| loader_fields.iter().any(|x| x.field == name);
+
+        if let Some(range) = manifest
+            .game_versions
+            .as_ref()
+            .filter(|_| has_field(MinecraftGameVersion::FIELD_NAME))
+        {
+            let releases =
+                MinecraftGameVersion::list(Some("release"), None, &mut **transaction, redis)
+                    .await?;
+
+            if let Some(submitted) = version_data.fields.get(MinecraftGameVersion::FIELD_NAME) {
+                let outside = submitted
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|x| x.as_str())
+                    .filter(|x| releases.iter().any(|y| &y.version == x) && !range.matches(x))
+                    .collect_vec();
+
+                if !outside.is_empty() {
+                    warnings.push(format!(
+                        "The game versions {} are outside of those supported by the primary file",
+                        outside.join(", ")
+                    ));
+                }
+            } else {
+                let matching = releases
+                    .iter()
+                    .filter(|x| range.matches(&x.version))
+                    .map(|x| x.version.clone())
+                    .collect_vec();
+
+                if !matching.is_empty() {
+                    version_data.fields.insert(
+                        MinecraftGameVersion::FIELD_NAME.to_string(),
+                        serde_json::json!(matching),
+                    );
+                }
+            }
+        }
+
+        if let Some(environment) = manifest.environment {
+            let mut mismatched = false;
+            for (field, value) in environment.side_types() {
+                if !has_field(field) {
+                    continue;
+                }
+
+                match version_data.fields.get(field) {
+                    Some(submitted) => mismatched |= submitted.as_bool() != Some(value),
+                    None => {
+                        version_data
+                            .fields
+                            .insert(field.to_string(), serde_json::json!(value));
+                    }
+                }
+            }
+
+            if mismatched {
+                warnings.push(
+                    "The side types do not match the environment declared by the primary file"
+                        .to_string(),
+                );
+            }
+        }
+
+        for (field, value) in manifest.bedrock_fields() {
+            if has_field(field) && !version_data.fields.contains_key(field) {
+                version_data.fields.insert(field.to_string(), value);
+            }
         }
     }
 
-    Ok(HttpResponse::Ok().json(response))
+    let mut loader_field_enum_values =
+        LoaderFieldEnumValue::list_many_loader_fields(&loader_fields, &mut **transaction, redis)
+            .await?;
+    builder.version_fields = try_create_version_fields(
+        builder.version_id.into(),
+        &version_data.fields,
+        &loader_fields,
+        &mut loader_field_enum_values,
+    )?;
+    builder.loaders = loader_ids;
+    builder.version_number = version_data.version_number.clone();
+
+    Ok((loaders, warnings))
+}
+
+/// Checks that the loaders and version number of a version have been specified, as they
+/// may be left out of its initial data
+pub(crate) fn check_version_metadata(version_data: &InitialVersionData) -> Result<(), CreateError> {
+    if version_data.loaders.is_empty() {
+        return Err(CreateError::InvalidInput(
+            "Loaders must be specified when they can't be read from the primary file".to_string(),
+        ));
+    }
+
+    if version_data.version_number.is_empty() {
+        return Err(CreateError::InvalidInput(
+            "A version number must be specified when it can't be read from the primary file"
+                .to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Adds the dependencies declared by the manifest of the primary file, and the mods bundled
+/// inside it, which the author hasn't declared. Mod ids are resolved to projects through the
+/// mod ids of other projects' files, and mod ids which can't be resolved are skipped, except
+/// for bundled jars, which are added by file name.
+async fn detect_dependencies(
+    version_data: &mut InitialVersionData,
+    builder: &mut VersionBuilder,
+    manifest: &ModManifest,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<(), CreateError> {
+    let detected = manifest
+        .dependencies
+        .iter()
+        .map(|x| (Some(&x.mod_id), None, x.dependency_type))
+        .chain(manifest.embedded.iter().map(|x| {
+            (
+                x.mod_id.as_ref(),
+                Some(&x.file_name),
+                DependencyType::Embedded,
+            )
+        }))
+        .filter(|(mod_id, _, _)| mod_id.is_none() || *mod_id != manifest.mod_id.as_ref())
+        .collect_vec();
+    if detected.is_empty() {
+        return Ok(());
+    }
+
+    let mod_ids = detected
+        .iter()
+        .filter_map(|(mod_id, _, _)| mod_id.cloned())
+        .unique()
+        .collect_vec();
+    let projects = ProjectModId::get_projects(&mod_ids, &mut **transaction).await?;
+
+    let mut declared = builder
+        .dependencies
+        .iter()
+        .filter_map(|x| x.project_id)
+        .chain(std::iter::once(builder.project_id))
+        .collect::<HashSet<_>>();
+
+    for (mod_id, file_name, dependency_type) in detected {
+        let project_id = mod_id.and_then(|x| projects.get(x)).copied();
+
+        let file_name = match project_id {
+            Some(project_id) => {
+                if !declared.insert(project_id) {
+                    continue;
+                }
+                None
+            }
+            None => match file_name {
+                Some(file_name)
+                    if !builder
+                        .dependencies
+                        .iter()
+                        .any(|x| x.file_name.as_ref() == Some(file_name)) =>
+                {
+                    Some(file_name.clone())
+                }
+                _ => continue,
+            },
+        };
+
+        builder.dependencies.push(DependencyBuilder {
+            project_id,
+            version_id: None,
+            file_name: file_name.clone(),
+            dependency_type: dependency_type.to_string(),
+        });
+        version_data.dependencies.push(Dependency {
+            version_id: None,
+            project_id: project_id.map(|x| x.into()),
+            file_name,
+            dependency_type,
+        });
+    }
+
+    Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn upload_file_to_version(
     req: HttpRequest,
     url_data: web::Path<(VersionId,)>,
@@ -529,6 +937,7 @@ pub async fn upload_file_to_version(
     redis: Data<RedisPool>,
     file_host: Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
+    moderation_queue: web::Data<AutomatedModerationQueue>,
 ) -> Result<HttpResponse, CreateError> {
     let mut transaction = client.begin().await?;
     let mut uploaded_files = Vec::new();
@@ -545,6 +954,7 @@ pub async fn upload_file_to_version(
         &mut uploaded_files,
         version_id,
         &session_queue,
+        &moderation_queue,
     )
     .await;
 
@@ -575,6 +985,7 @@ async fn upload_file_to_version_inner(
     uploaded_files: &mut Vec<UploadedFile>,
     version_id: models::VersionId,
     session_queue: &AuthQueue,
+    moderation_queue: &AutomatedModerationQueue,
 ) -> Result<HttpResponse, CreateError> {
     let cdn_url = dotenvy::var("CDN_URL")?;
 
@@ -591,93 +1002,23 @@ async fn upload_file_to_version_inner(
     .await?
     .1;
 
-    let result = models::Version::get(version_id, &**client, &redis).await?;
+    let (version, selected_loaders) =
+        get_version_for_upload(version_id, &user, &client, transaction, &redis).await?;
 
-    let version = match result {
-        Some(v) => v,
-        None => {
-            return Err(CreateError::InvalidInput(
-                "An invalid version id was supplied".to_string(),
-            ));
+    let project_id = ProjectId(version.inner.project_id.0 as u64);
+    let mut error = None;
+    while let Some(item) = payload.next().await {
+        let mut field: Field = item?;
+
+        if error.is_some() {
+            continue;
         }
-    };
 
-    let all_loaders = models::loader_fields::Loader::list(&mut **transaction, &redis).await?;
-    let selected_loaders = version
-        .loaders
-        .iter()
-        .map(|x| {
-            all_loaders
-                .iter()
-                .find(|y| &y.loader == x)
-                .cloned()
-                .ok_or_else(|| CreateError::InvalidLoader(x.clone()))
-        })
-        .collect::<Result<Vec<_>, _>>()?;
-
-    if models::Project::get_id(version.inner.project_id, &mut **transaction, &redis)
-        .await?
-        .is_none()
-    {
-        return Err(CreateError::InvalidInput(
-            "An invalid project id was supplied".to_string(),
-        ));
-    }
-
-    if !user.role.is_admin() {
-        let team_member = models::TeamMember::get_from_user_id_project(
-            version.inner.project_id,
-            user.id.into(),
-            false,
-            &mut **transaction,
-        )
-        .await?;
-
-        let organization = Organization::get_associated_organization_project_id(
-            version.inner.project_id,
-            &**client,
-        )
-        .await?;
-
-        let organization_team_member = if let Some(organization) = &organization {
-            models::TeamMember::get_from_user_id(
-                organization.team_id,
-                user.id.into(),
-                &mut **transaction,
-            )
-            .await?
-        } else {
-            None
-        };
-
-        let permissions = ProjectPermissions::get_permissions_by_role(
-            &user.role,
-            &team_member,
-            &organization_team_member,
-        )
-        .unwrap_or_default();
-
-        if !permissions.contains(ProjectPermissions::UPLOAD_VERSION) {
-            return Err(CreateError::CustomAuthenticationError(
-                "You don't have permission to upload files to this version!".to_string(),
-            ));
-        }
-    }
-
-    let project_id = ProjectId(version.inner.project_id.0 as u64);
-    let mut error = None;
-    while let Some(item) = payload.next().await {
-        let mut field: Field = item?;
-
-        if error.is_some() {
-            continue;
-        }
-
-        let result = async {
-            let content_disposition = field.content_disposition().clone();
-            let name = content_disposition.get_name().ok_or_else(|| {
-                CreateError::MissingValueError("Missing content name".to_string())
-            })?;
+        let result = async {
+            let content_disposition = field.content_disposition().clone();
+            let name = content_disposition.get_name().ok_or_else(|| {
+                CreateError::MissingValueError("Missing content name".to_string())
+            })?;
 
             if name == "data" {
                 let mut data = Vec::new();
@@ -745,20 +1086,448 @@ async fn upload_file_to_version_inner(
         return Err(error);
     }
 
+    insert_version_files(
+        &version,
+        file_builders,
+        transaction,
+        &redis,
+        &client,
+        file_host,
+        moderation_queue,
+    )
+    .await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct UploadedVersionData {
+    pub data: InitialVersionData,
+    /// The completed upload sessions holding each of the version's file parts
+    pub files: HashMap<String, UploadSessionId>,
+}
+
+// under `/v3/version/uploads`
+#[allow(clippy::too_many_arguments)]
+pub async fn version_create_from_uploads(
+    req: HttpRequest,
+    body: web::Json<UploadedVersionData>,
+    client: Data<PgPool>,
+    redis: Data<RedisPool>,
+    file_host: Data<Arc<dyn FileHost + Send + Sync>>,
+    session_queue: Data<AuthQueue>,
+    moderation_queue: web::Data<AutomatedModerationQueue>,
+    search_index_queue: web::Data<SearchIndexQueue>,
+) -> Result<HttpResponse, CreateError> {
+    let mut transaction = client.begin().await?;
+    let mut uploaded_files = Vec::new();
+    let mut finished_chunks = Vec::new();
+
+    let result = version_create_from_uploads_inner(
+        req,
+        body.into_inner(),
+        &mut transaction,
+        &redis,
+        &***file_host,
+        &mut uploaded_files,
+        &mut finished_chunks,
+        &client,
+        &session_queue,
+        &moderation_queue,
+        &search_index_queue,
+    )
+    .await;
+
+    if result.is_err() {
+        let undo_result =
+            super::project_creation::undo_uploads(&***file_host, &uploaded_files).await;
+        let rollback_result = transaction.rollback().await;
+
+        undo_result?;
+        if let Err(e) = rollback_result {
+            return Err(e.into());
+        }
+    } else {
+        transaction.commit().await?;
+        delete_chunks(&***file_host, &finished_chunks).await;
+    }
+
+    result
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn version_create_from_uploads_inner(
+    req: HttpRequest,
+    body: UploadedVersionData,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    redis: &RedisPool,
+    file_host: &dyn FileHost,
+    uploaded_files: &mut Vec<UploadedFile>,
+    finished_chunks: &mut Vec<UploadSessionChunk>,
+    pool: &PgPool,
+    session_queue: &AuthQueue,
+    moderation_queue: &AutomatedModerationQueue,
+    search_index_queue: &SearchIndexQueue,
+) -> Result<HttpResponse, CreateError> {
+    let cdn_url = dotenvy::var("CDN_URL")?;
+
+    let user = get_user_from_headers(
+        &req,
+        pool,
+        redis,
+        session_queue,
+        Some(&[Scopes::VERSION_CREATE]),
+    )
+    .await?
+    .1;
+
+    let version_data = body.data;
+    let (mut builder, selected_loaders) =
+        prepare_version(&version_data, &user, transaction, redis).await?;
+    let loaders = selected_loaders
+        .iter()
+        .map(|x| Loader(x.loader.clone()))
+        .collect::<Vec<_>>();
+    let mut manifests = HashMap::new();
+
+    for name in &version_data.file_parts {
+        let session_id = body.files.get(name).ok_or_else(|| {
+            CreateError::MissingValueError(format!("Missing upload session for file part {name}"))
+        })?;
+
+        let upload = take_completed_upload(*session_id, &user, transaction, file_host).await?;
+        finished_chunks.extend(upload.chunks);
+
+        let existing_file_names = builder.files.iter().map(|x| x.filename.clone()).collect();
+
+        let manifest = upload_spooled_file(
+            upload.data,
+            &upload.file_name,
+            file_host,
+            version_data.file_parts.len(),
+            uploaded_files,
+            &mut builder.files,
+            &mut builder.dependencies,
+            &cdn_url,
+            builder.project_id.into(),
+            builder.version_id.into(),
+            &builder.version_fields,
+            loaders.clone(),
+            version_data.primary_file.is_some(),
+            version_data.primary_file.as_deref() == Some(name),
+            version_data.file_types.get(name).copied().flatten(),
+            existing_file_names,
+            transaction,
+            redis,
+        )
+        .await?;
+
+        if let Some(manifest) = manifest {
+            manifests.insert(upload.file_name, manifest);
+        }
+    }
+
+    finish_version_create(
+        version_data,
+        builder,
+        manifests,
+        &user,
+        transaction,
+        redis,
+        pool,
+        file_host,
+        moderation_queue,
+        search_index_queue,
+    )
+    .await
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct UploadedFileData {
+    #[serde(default = "HashMap::new")]
+    pub file_types: HashMap<String, Option<FileType>>,
+    /// The completed upload sessions holding each of the files to add
+    pub files: HashMap<String, UploadSessionId>,
+}
+
+// under `/v3/version/{version_id}/file/uploads`
+#[allow(clippy::too_many_arguments)]
+pub async fn upload_file_to_version_from_uploads(
+    req: HttpRequest,
+    url_data: web::Path<(VersionId,)>,
+    body: web::Json<UploadedFileData>,
+    client: Data<PgPool>,
+    redis: Data<RedisPool>,
+    file_host: Data<Arc<dyn FileHost + Send + Sync>>,
+    session_queue: web::Data<AuthQueue>,
+    moderation_queue: web::Data<AutomatedModerationQueue>,
+) -> Result<HttpResponse, CreateError> {
+    let mut transaction = client.begin().await?;
+    let mut uploaded_files = Vec::new();
+    let mut finished_chunks = Vec::new();
+
+    let version_id = models::VersionId::from(url_data.into_inner().0);
+
+    let result = upload_file_to_version_from_uploads_inner(
+        req,
+        body.into_inner(),
+        &client,
+        &mut transaction,
+        &redis,
+        &***file_host,
+        &mut uploaded_files,
+        &mut finished_chunks,
+        version_id,
+        &session_queue,
+        &moderation_queue,
+    )
+    .await;
+
+    if result.is_err() {
+        let undo_result =
+            super::project_creation::undo_uploads(&***file_host, &uploaded_files).await;
+        let rollback_result = transaction.rollback().await;
+
+        undo_result?;
+        if let Err(e) = rollback_result {
+            return Err(e.into());
+        }
+    } else {
+        transaction.commit().await?;
+        delete_chunks(&***file_host, &finished_chunks).await;
+    }
+
+    result
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn upload_file_to_version_from_uploads_inner(
+    req: HttpRequest,
+    body: UploadedFileData,
+    client: &PgPool,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    redis: &RedisPool,
+    file_host: &dyn FileHost,
+    uploaded_files: &mut Vec<UploadedFile>,
+    finished_chunks: &mut Vec<UploadSessionChunk>,
+    version_id: models::VersionId,
+    session_queue: &AuthQueue,
+    moderation_queue: &AutomatedModerationQueue,
+) -> Result<HttpResponse, CreateError> {
+    let cdn_url = dotenvy::var("CDN_URL")?;
+
+    let user = get_user_from_headers(
+        &req,
+        client,
+        redis,
+        session_queue,
+        Some(&[Scopes::VERSION_WRITE]),
+    )
+    .await?
+    .1;
+
+    let (version, selected_loaders) =
+        get_version_for_upload(version_id, &user, client, transaction, redis).await?;
+    let loaders = selected_loaders
+        .iter()
+        .map(|x| Loader(x.loader.clone()))
+        .collect::<Vec<_>>();
+
+    let project_id = ProjectId(version.inner.project_id.0 as u64);
+    let mut file_builders: Vec<VersionFileBuilder> = Vec::new();
+
+    for (name, session_id) in body.files.iter().sorted_by_key(|(name, _)| *name) {
+        let upload = take_completed_upload(*session_id, &user, transaction, file_host).await?;
+        finished_chunks.extend(upload.chunks);
+
+        let mut dependencies = version
+            .dependencies
+            .iter()
+            .map(|x| DependencyBuilder {
+                project_id: x.project_id,
+                version_id: x.version_id,
+                file_name: x.file_name.clone(),
+                dependency_type: x.dependency_type.clone(),
+            })
+            .collect();
+
+        upload_spooled_file(
+            upload.data,
+            &upload.file_name,
+            file_host,
+            0,
+            uploaded_files,
+            &mut file_builders,
+            &mut dependencies,
+            &cdn_url,
+            project_id,
+            version_id.into(),
+            &version.version_fields,
+            loaders.clone(),
+            true,
+            false,
+            body.file_types.get(name).copied().flatten(),
+            version.files.iter().map(|x| x.filename.clone()).collect(),
+            transaction,
+            redis,
+        )
+        .await?;
+    }
+
+    insert_version_files(
+        &version,
+        file_builders,
+        transaction,
+        redis,
+        client,
+        file_host,
+        moderation_queue,
+    )
+    .await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+/// Fetches a version which files are being added to, along with its loaders, checking
+/// that the user is allowed to upload files to it
+pub(crate) async fn get_version_for_upload(
+    version_id: models::VersionId,
+    user: &User,
+    client: &PgPool,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    redis: &RedisPool,
+) -> Result<
+    (
+        models::version_item::QueryVersion,
+        Vec<models::loader_fields::Loader>,
+    ),
+    CreateError,
+> {
+    let result = models::Version::get(version_id, client, redis).await?;
+
+    let version = match result {
+        Some(v) => v,
+        None => {
+            return Err(CreateError::InvalidInput(
+                "An invalid version id was supplied".to_string(),
+            ));
+        }
+    };
+
+    let all_loaders = models::loader_fields::Loader::list(&mut **transaction, redis).await?;
+    let selected_loaders = version
+        .loaders
+        .iter()
+        .map(|x| {
+            all_loaders
+                .iter()
+                .find(|y| &y.loader == x)
+                .cloned()
+                .ok_or_else(|| CreateError::InvalidLoader(x.clone()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if models::Project::get_id(version.inner.project_id, &mut **transaction, redis)
+        .await?
+        .is_none()
+    {
+        return Err(CreateError::InvalidInput(
+            "An invalid project id was supplied".to_string(),
+        ));
+    }
+
+    if !user.role.is_admin() {
+        let team_member = models::TeamMember::get_from_user_id_project(
+            version.inner.project_id,
+            user.id.into(),
+            false,
+            &mut **transaction,
+        )
+        .await?;
+
+        let organization =
+            Organization::get_associated_organization_project_id(version.inner.project_id, client)
+                .await?;
+
+        let organization_team_member = if let Some(organization) = &organization {
+            models::TeamMember::get_from_user_id(
+                organization.team_id,
+                user.id.into(),
+                &mut **transaction,
+            )
+            .await?
+        } else {
+            None
+        };
+
+        let permissions = ProjectPermissions::get_permissions_by_role(
+            &user.role,
+            &team_member,
+            &organization_team_member,
+        )
+        .unwrap_or_default();
+
+        if !permissions.contains(ProjectPermissions::UPLOAD_VERSION) {
+            return Err(CreateError::CustomAuthenticationError(
+                "You don't have permission to upload files to this version!".to_string(),
+            ));
+        }
+    }
+
+    Ok((version, selected_loaders))
+}
+
+/// Inserts the files added to an existing version
+pub(crate) async fn insert_version_files(
+    version: &models::version_item::QueryVersion,
+    file_builders: Vec<VersionFileBuilder>,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    redis: &RedisPool,
+    pool: &PgPool,
+    file_host: &dyn FileHost,
+    moderation_queue: &AutomatedModerationQueue,
+) -> Result<(), CreateError> {
     if file_builders.is_empty() {
         return Err(CreateError::InvalidInput(
             "At least one file must be specified".to_string(),
         ));
-    } else {
-        for file in file_builders {
-            file.insert(version_id, &mut *transaction).await?;
-        }
     }
 
+    let held = file_builders.iter().any(|x| !x.scan_findings.is_empty());
+    for file in file_builders {
+        file.insert(version.inner.id, &mut *transaction).await?;
+    }
+
+    if held && version.inner.status != VersionStatus::Held {
+        sqlx::query!(
+            "
+            UPDATE versions
+            SET status = $1, requested_status = $2
+            WHERE id = $3
+            ",
+            VersionStatus::Held.as_str(),
+            version.inner.status.as_str(),
+            version.inner.id as models::VersionId,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        moderation_queue.versions.insert(version.inner.id.into());
+    }
+
+    ModpackContent::notify_newly_included(version.inner.project_id, transaction, redis).await?;
+
+    // The added files are only referenced by this transaction, so the copies at their old path
+    // can be deleted straight away
+    sync_file_visibility(version.inner.project_id, file_host, transaction)
+        .await?
+        .finish(file_host, pool, redis)
+        .await?;
+
     // Clear version cache
-    models::Version::clear_cache(&version, &redis).await?;
+    models::Version::clear_cache(version, redis).await?;
 
-    Ok(HttpResponse::NoContent().body(""))
+    Ok(())
 }
 
 // This function is used for adding a file to a version, uploading the initial
@@ -783,8 +1552,44 @@ pub async fn upload_file(
     other_file_names: Vec<String>,
     transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     redis: &RedisPool,
-) -> Result<(), CreateError> {
-    let (file_name, file_extension) = get_name_ext(content_disposition)?;
+) -> Result<Option<ModManifest>, CreateError> {
+    let (file_name, _) = get_name_ext(content_disposition)?;
+
+    // Check the file name before receiving the file, so invalid uploads fail early
+    check_file_name(file_name, &other_file_names)?;
+
+    let max_size = max_version_file_size();
+    let data = spool_from_field(field, max_size, &max_version_file_size_error(max_size)).await?;
+
+    upload_spooled_file(
+        data,
+        file_name,
+        file_host,
+        total_files_len,
+        uploaded_files,
+        version_files,
+        dependencies,
+        cdn_url,
+        project_id,
+        version_id,
+        version_fields,
+        loaders,
+        ignore_primary,
+        force_primary,
+        file_type,
+        other_file_names,
+        transaction,
+        redis,
+    )
+    .await
+}
+
+/// Checks that a file with this name may be added to a version, returning its content type
+fn check_file_name<'a>(
+    file_name: &'a str,
+    other_file_names: &[String],
+) -> Result<&'a str, CreateError> {
+    let file_extension = get_file_extension(file_name)?;
 
     if other_file_names.contains(&format!("{}.{}", file_name, file_extension)) {
         return Err(CreateError::InvalidInput(
@@ -798,15 +1603,38 @@ pub async fn upload_file(
         ));
     }
 
-    let content_type = crate::util::ext::project_file_type(file_extension)
-        .ok_or_else(|| CreateError::InvalidFileType(file_extension.to_string()))?;
+    crate::util::ext::project_file_type(file_extension)
+        .ok_or_else(|| CreateError::InvalidFileType(file_extension.to_string()))
+}
 
-    let data = read_from_field(
-        field, 500 * (1 << 20),
-        "Project file exceeds the maximum of 500MiB. Contact a moderator or admin to request permission to upload larger files."
-    ).await?;
+/// Validates and uploads a version file which has already been received, either from a
+/// multipart field or reassembled from a resumable upload session, returning the mod
+/// manifest read from it
+#[allow(clippy::too_many_arguments)]
+pub async fn upload_spooled_file(
+    data: SpooledFile,
+    file_name: &str,
+    file_host: &dyn FileHost,
+    total_files_len: usize,
+    uploaded_files: &mut Vec<UploadedFile>,
+    version_files: &mut Vec<VersionFileBuilder>,
+    dependencies: &mut Vec<DependencyBuilder>,
+    cdn_url: &str,
+    project_id: ProjectId,
+    version_id: VersionId,
+    version_fields: &[VersionField],
+    loaders: Vec<Loader>,
+    ignore_primary: bool,
+    force_primary: bool,
+    file_type: Option<FileType>,
+    other_file_names: Vec<String>,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    redis: &RedisPool,
+) -> Result<Option<ModManifest>, CreateError> {
+    let content_type = check_file_name(file_name, &other_file_names)?;
+    let file_extension = get_file_extension(file_name)?;
 
-    let hash = sha1::Sha1::from(&data).hexdigest();
+    let hash = &data.hashes.sha1;
     let exists = sqlx::query!(
         "
         SELECT EXISTS(SELECT 1 FROM hashes h
@@ -829,8 +1657,13 @@ pub async fn upload_file(
         ));
     }
 
-    let validation_result = validate_file(
-        data.clone().into(),
+    let FileValidation {
+        result: validation_result,
+        report: mut validation_report,
+        manifest,
+        scan_findings,
+    } = validate_file(
+        data.open().map_err(FileHostingError::from)?,
         file_extension.to_string(),
         loaders.clone(),
         file_type,
@@ -840,11 +1673,20 @@ pub async fn upload_file(
     )
     .await?;
 
+    let mut pack_contents = Vec::new();
     if let ValidationResult::PassWithPackDataAndFiles {
         ref format,
         ref files,
     } = validation_result
     {
+        pack_contents = resolve_pack_files(
+            &format.files,
+            cdn_url,
+            &mut validation_report,
+            &mut *transaction,
+        )
+        .await?;
+
         if dependencies.is_empty() {
             let hashes: Vec<Vec<u8>> = format
                 .files
@@ -904,8 +1746,7 @@ pub async fn upload_file(
         }
     }
 
-    let data = data.freeze();
-    let primary = (validation_result.is_passed()
+    let primary = (validation_report.is_passed()
         && version_files.iter().all(|x| !x.primary)
         && !ignore_primary)
         || force_primary
@@ -920,7 +1761,7 @@ pub async fn upload_file(
     let file_path = format!("data/{}/versions/{}/{}", project_id, version_id, &file_name);
 
     let upload_data = file_host
-        .upload_file(content_type, &file_path, data)
+        .upload_file_streaming(content_type, &file_path, data.len(), data.stream().await?)
         .await?;
 
     uploaded_files.push(UploadedFile {
@@ -941,10 +1782,8 @@ pub async fn upload_file(
         ));
     }
 
-    if let ValidationResult::Warning(msg) = validation_result {
-        if primary {
-            return Err(CreateError::InvalidInput(msg.to_string()));
-        }
+    if primary && !validation_report.is_passed() {
+        return Err(CreateError::InvalidPrimaryFile(validation_report));
     }
 
     version_files.push(VersionFileBuilder {
@@ -967,9 +1806,12 @@ pub async fn upload_file(
         primary,
         size: upload_data.content_length,
         file_type,
+        validation: validation_report,
+        scan_findings,
+        pack_contents,
     });
 
-    Ok(())
+    Ok(manifest)
 }
 
 pub fn get_name_ext(
@@ -978,14 +1820,18 @@ pub fn get_name_ext(
     let file_name = content_disposition
         .get_filename()
         .ok_or_else(|| CreateError::MissingValueError("Missing content file name".to_string()))?;
-    let file_extension = if let Some(last_period) = file_name.rfind('.') {
-        file_name.get((last_period + 1)..).unwrap_or("")
+    let file_extension = get_file_extension(file_name)?;
+    Ok((file_name, file_extension))
+}
+
+pub fn get_file_extension(file_name: &str) -> Result<&str, CreateError> {
+    if let Some(last_period) = file_name.rfind('.') {
+        Ok(file_name.get((last_period + 1)..).unwrap_or(""))
     } else {
-        return Err(CreateError::MissingValueError(
+        Err(CreateError::MissingValueError(
             "Missing content file extension".to_string(),
-        ));
-    };
-    Ok((file_name, file_extension))
+        ))
+    }
 }
 
 // Reused functionality between project_creation and version_creation
@@ -995,6 +1841,24 @@ pub fn try_create_version_fields(
     submitted_fields: &HashMap<String, serde_json::Value>,
     loader_fields: &[LoaderField],
     loader_field_enum_values: &mut HashMap<models::LoaderFieldId, Vec<LoaderFieldEnumValue>>,
+) -> Result<Vec<VersionField>, CreateError> {
+    create_version_fields(
+        version_id,
+        submitted_fields,
+        loader_fields,
+        loader_field_enum_values,
+        true,
+    )
+}
+
+// When `complete` is false, fields which aren't supported by the loaders and missing mandatory
+// fields are skipped, as the loaders and fields may still be filled in from a file's manifest
+fn create_version_fields(
+    version_id: VersionId,
+    submitted_fields: &HashMap<String, serde_json::Value>,
+    loader_fields: &[LoaderField],
+    loader_field_enum_values: &mut HashMap<models::LoaderFieldId, Vec<LoaderFieldEnumValue>>,
+    complete: bool,
 ) -> Result<Vec<VersionField>, CreateError> {
     let mut version_fields = vec![];
     let mut remaining_mandatory_loader_fields = loader_fields
@@ -1003,14 +1867,14 @@ pub fn try_create_version_fields(
         .map(|lf| lf.field.clone())
         .collect::<HashSet<_>>();
     for (key, value) in submitted_fields.iter() {
-        let loader_field = loader_fields
-            .iter()
-            .find(|lf| &lf.field == key)
-            .ok_or_else(|| {
-                CreateError::InvalidInput(format!(
-                    "Loader field '{key}' does not exist for any loaders supplied,"
-                ))
-            })?;
+        let Some(loader_field) = loader_fields.iter().find(|lf| &lf.field == key) else {
+            if !complete {
+                continue;
+            }
+            return Err(CreateError::InvalidInput(format!(
+                "Loader field '{key}' does not exist for any loaders supplied,"
+            )));
+        };
         remaining_mandatory_loader_fields.remove(&loader_field.field);
         let enum_variants = loader_field_enum_values
             .remove(&loader_field.id)
@@ -1026,7 +1890,7 @@ pub fn try_create_version_fields(
         version_fields.push(vf);
     }
 
-    if !remaining_mandatory_loader_fields.is_empty() {
+    if complete && !remaining_mandatory_loader_fields.is_empty() {
         return Err(CreateError::InvalidInput(format!(
             "Missing mandatory loader fields: {}",
             remaining_mandatory_loader_fields.iter().join(", ")
@@ -2,6 +2,7 @@ use super::ApiError;
 use crate::auth::checks::{filter_visible_versions, is_visible_version};
 use crate::auth::{filter_visible_projects, get_user_from_headers};
 use crate::database::redis::RedisPool;
+use crate::file_hosting::{sign_cdn_url, sign_cdn_urls, FileHost};
 use crate::models::ids::VersionId;
 use crate::models::pats::Scopes;
 use crate::models::projects::VersionType;
@@ -14,7 +15,8 @@ use futures::TryStreamExt;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -25,6 +27,7 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .route("{version_id}", web::delete().to(delete_file))
             .route("{version_id}/download", web::get().to(download_version)),
     );
+    cfg.route("file/{file_name:.*}", web::get().to(download_signed_file));
     cfg.service(
         web::scope("version_files")
             .route("update", web::post().to(update_files))
@@ -39,6 +42,7 @@ pub async fn get_version_from_hash(
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     hash_query: web::Query<HashQuery>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let user_option = get_user_from_headers(
@@ -71,7 +75,10 @@ pub async fn get_version_from_hash(
                 return Err(ApiError::NotFound);
             }
 
-            Ok(HttpResponse::Ok().json(models::projects::Version::from(version)))
+            let mut version = models::projects::Version::from(version);
+            sign_hidden_file_urls([&mut version], &***file_host, &pool, &redis).await?;
+
+            Ok(HttpResponse::Ok().json(version))
         } else {
             Err(ApiError::NotFound)
         }
@@ -114,6 +121,7 @@ pub struct UpdateData {
     pub loader_fields: Option<HashMap<String, Vec<serde_json::Value>>>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn get_update_from_hash(
     req: HttpRequest,
     info: web::Path<(String,)>,
@@ -121,6 +129,7 @@ pub async fn get_update_from_hash(
     redis: web::Data<RedisPool>,
     hash_query: web::Query<HashQuery>,
     update_data: web::Json<UpdateData>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let user_option = get_user_from_headers(
@@ -182,7 +191,10 @@ pub async fn get_update_from_hash(
                     return Err(ApiError::NotFound);
                 }
 
-                return Ok(HttpResponse::Ok().json(models::projects::Version::from(first)));
+                let mut version = models::projects::Version::from(first);
+                sign_hidden_file_urls([&mut version], &***file_host, &pool, &redis).await?;
+
+                return Ok(HttpResponse::Ok().json(version));
             }
         }
     }
@@ -201,6 +213,7 @@ pub async fn get_versions_from_hashes(
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     file_data: web::Json<FileHashes>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let user_option = get_user_from_headers(
@@ -228,13 +241,14 @@ pub async fn get_versions_from_hashes(
     .await?;
 
     let version_ids = files.iter().map(|x| x.version_id).collect::<Vec<_>>();
-    let versions_data = filter_visible_versions(
+    let mut versions_data = filter_visible_versions(
         database::models::Version::get_many(&version_ids, &**pool, &redis).await?,
         &user_option,
         &pool,
         &redis,
     )
     .await?;
+    sign_hidden_file_urls(&mut versions_data, &***file_host, &pool, &redis).await?;
 
     let mut response = HashMap::new();
 
@@ -314,6 +328,7 @@ pub async fn update_files(
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     update_data: web::Json<ManyUpdateData>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
 ) -> Result<HttpResponse, ApiError> {
     let algorithm = update_data
         .algorithm
@@ -378,6 +393,7 @@ pub async fn update_files(
             }
         }
     }
+    sign_hidden_file_urls(response.values_mut(), &***file_host, &pool, &redis).await?;
 
     Ok(HttpResponse::Ok().json(response))
 }
@@ -401,6 +417,7 @@ pub async fn update_individual_files(
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     update_data: web::Json<ManyFileUpdateData>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let user_option = get_user_from_headers(
@@ -500,6 +517,7 @@ pub async fn update_individual_files(
             }
         }
     }
+    sign_hidden_file_urls(response.values_mut(), &***file_host, &pool, &redis).await?;
 
     Ok(HttpResponse::Ok().json(response))
 }
@@ -595,6 +613,26 @@ pub async fn delete_file(
 
         let mut transaction = pool.begin().await?;
 
+        sqlx::query!(
+            "
+            DELETE FROM file_scan_findings
+            WHERE file_id = $1
+            ",
+            row.id.0
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        sqlx::query!(
+            "
+            DELETE FROM modpack_contents
+            WHERE file_id = $1
+            ",
+            row.id.0
+        )
+        .execute(&mut *transaction)
+        .await?;
+
         sqlx::query!(
             "
             DELETE FROM hashes
@@ -635,6 +673,7 @@ pub async fn download_version(
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     hash_query: web::Query<HashQuery>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let user_option = get_user_from_headers(
@@ -670,9 +709,20 @@ pub async fn download_version(
                 return Err(ApiError::NotFound);
             }
 
+            let project =
+                database::models::Project::get_id(version.inner.project_id, &**pool, &redis)
+                    .await?;
+            let url = if version.inner.status.is_hidden()
+                || project.map_or(true, |x| x.inner.status.is_hidden())
+            {
+                sign_cdn_url(&***file_host, &dotenvy::var("CDN_URL")?, &file.url).await?
+            } else {
+                file.url
+            };
+
             Ok(HttpResponse::TemporaryRedirect()
-                .append_header(("Location", &*file.url))
-                .json(DownloadRedirect { url: file.url }))
+                .append_header(("Location", &*url))
+                .json(DownloadRedirect { url }))
         } else {
             Err(ApiError::NotFound)
         }
@@ -680,3 +730,80 @@ pub async fn download_version(
         Err(ApiError::NotFound)
     }
 }
+
+/// Replaces the URLs of the files of hidden versions, or versions of hidden projects, with
+/// expiring signed URLs, so they can't be shared with people who can't see the version. Files
+/// of public versions keep their permanent URLs.
+pub async fn sign_hidden_file_urls<'a>(
+    versions: impl IntoIterator<Item = &'a mut models::projects::Version>,
+    file_host: &dyn FileHost,
+    pool: &PgPool,
+    redis: &RedisPool,
+) -> Result<(), ApiError> {
+    let cdn_url = dotenvy::var("CDN_URL")?;
+    let versions = versions.into_iter().collect::<Vec<_>>();
+
+    let project_ids = versions
+        .iter()
+        .filter(|x| !x.status.is_hidden())
+        .map(|x| database::models::ProjectId::from(x.project_id))
+        .unique()
+        .collect::<Vec<_>>();
+    let public_projects = database::models::Project::get_many_ids(&project_ids, pool, redis)
+        .await?
+        .into_iter()
+        .filter(|x| !x.inner.status.is_hidden())
+        .map(|x| models::ids::ProjectId::from(x.inner.id))
+        .collect::<HashSet<_>>();
+
+    let hidden_files = versions
+        .into_iter()
+        .filter(|x| x.status.is_hidden() || !public_projects.contains(&x.project_id))
+        .flat_map(|x| x.files.iter_mut())
+        .collect::<Vec<_>>();
+    let urls = hidden_files
+        .iter()
+        .map(|x| x.url.clone())
+        .collect::<Vec<_>>();
+    let signed_urls = sign_cdn_urls(file_host, &cdn_url, &urls).await?;
+
+    for (file, url) in hidden_files.into_iter().zip(signed_urls) {
+        file.url = url;
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct SignedFileQuery {
+    pub expires: i64,
+    pub signature: String,
+}
+
+// under /v3/file/{file_name}, for signed URLs of hosts which can't sign URLs themselves
+pub async fn download_signed_file(
+    info: web::Path<(String,)>,
+    query: web::Query<SignedFileQuery>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
+) -> Result<HttpResponse, ApiError> {
+    let file_name = info.into_inner().0;
+
+    if !crate::file_hosting::verify_local_signed_url(&file_name, query.expires, &query.signature) {
+        return Err(ApiError::NotFound);
+    }
+
+    let stream = file_host.download_file("", &file_name).await?;
+    let download_name = file_name
+        .rsplit('/')
+        .next()
+        .unwrap_or(&file_name)
+        .replace('"', "");
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/octet-stream")
+        .append_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"{download_name}\""),
+        ))
+        .streaming(stream))
+}
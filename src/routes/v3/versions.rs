@@ -1,5 +1,7 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
+use super::version_file::sign_hidden_file_urls;
 use super::ApiError;
 use crate::auth::checks::{filter_visible_versions, is_visible_project, is_visible_version};
 use crate::auth::get_user_from_headers;
@@ -7,9 +9,12 @@ use crate::database;
 use crate::database::models::loader_fields::{
     self, LoaderField, LoaderFieldEnumValue, VersionField,
 };
+use crate::database::models::modpack_content_item::ModpackContent;
 use crate::database::models::version_item::{DependencyBuilder, LoaderVersion};
 use crate::database::models::{image_item, Organization};
 use crate::database::redis::RedisPool;
+use crate::file_hosting::visibility::{sync_file_visibility, MovedFiles};
+use crate::file_hosting::FileHost;
 use crate::models;
 use crate::models::ids::base62_impl::parse_base62;
 use crate::models::ids::VersionId;
@@ -18,6 +23,7 @@ use crate::models::pats::Scopes;
 use crate::models::projects::{skip_nulls, Loader};
 use crate::models::projects::{Dependency, FileType, VersionStatus, VersionType};
 use crate::models::teams::ProjectPermissions;
+use crate::queue::search_index::SearchIndexQueue;
 use crate::queue::session::AuthQueue;
 use crate::search::indexing::remove_documents;
 use crate::search::SearchConfig;
@@ -38,12 +44,21 @@ pub fn config(cfg: &mut web::ServiceConfig) {
 
     cfg.service(
         web::scope("version")
+            .route(
+                "uploads",
+                web::post().to(super::version_creation::version_create_from_uploads),
+            )
             .route("{id}", web::get().to(version_get))
             .route("{id}", web::patch().to(version_edit))
             .route("{id}", web::delete().to(version_delete))
+            .route("{id}/contents", web::get().to(version_contents_get))
             .route(
                 "{version_id}/file",
                 web::post().to(super::version_creation::upload_file_to_version),
+            )
+            .route(
+                "{version_id}/file/uploads",
+                web::post().to(super::version_creation::upload_file_to_version_from_uploads),
             ),
     );
 }
@@ -54,16 +69,18 @@ pub async fn version_project_get(
     info: web::Path<(String, String)>,
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let info = info.into_inner();
-    version_project_get_helper(req, info, pool, redis, session_queue).await
+    version_project_get_helper(req, info, pool, redis, file_host, session_queue).await
 }
 pub async fn version_project_get_helper(
     req: HttpRequest,
     id: (String, String),
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let result = database::models::Project::get(&id.0, &**pool, &redis).await?;
@@ -94,7 +111,10 @@ pub async fn version_project_get_helper(
 
         if let Some(version) = version {
             if is_visible_version(&version.inner, &user_option, &pool, &redis).await? {
-                return Ok(HttpResponse::Ok().json(models::projects::Version::from(version)));
+                let mut version = models::projects::Version::from(version);
+                sign_hidden_file_urls([&mut version], &***file_host, &pool, &redis).await?;
+
+                return Ok(HttpResponse::Ok().json(version));
             }
         }
     }
@@ -112,6 +132,7 @@ pub async fn versions_get(
     web::Query(ids): web::Query<VersionIds>,
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let version_ids = serde_json::from_str::<Vec<models::ids::VersionId>>(&ids.ids)?
@@ -131,7 +152,8 @@ pub async fn versions_get(
     .map(|x| x.1)
     .ok();
 
-    let versions = filter_visible_versions(versions_data, &user_option, &pool, &redis).await?;
+    let mut versions = filter_visible_versions(versions_data, &user_option, &pool, &redis).await?;
+    sign_hidden_file_urls(&mut versions, &***file_host, &pool, &redis).await?;
 
     Ok(HttpResponse::Ok().json(versions))
 }
@@ -141,10 +163,11 @@ pub async fn version_get(
     info: web::Path<(models::ids::VersionId,)>,
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let id = info.into_inner().0;
-    version_get_helper(req, id, pool, redis, session_queue).await
+    version_get_helper(req, id, pool, redis, file_host, session_queue).await
 }
 
 pub async fn version_get_helper(
@@ -152,8 +175,43 @@ pub async fn version_get_helper(
     id: models::ids::VersionId,
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let version_data = database::models::Version::get(id.into(), &**pool, &redis).await?;
+
+    let user_option = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::VERSION_READ]),
+    )
+    .await
+    .map(|x| x.1)
+    .ok();
+
+    if let Some(data) = version_data {
+        if is_visible_version(&data.inner, &user_option, &pool, &redis).await? {
+            let mut version = models::projects::Version::from(data);
+            sign_hidden_file_urls([&mut version], &***file_host, &pool, &redis).await?;
+
+            return Ok(HttpResponse::Ok().json(version));
+        }
+    }
+
+    Err(ApiError::NotFound)
+}
+
+/// Gets the files of a modpack version, resolved against the files hosted on Modrinth
+pub async fn version_contents_get(
+    req: HttpRequest,
+    info: web::Path<(models::ids::VersionId,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
+    let id = info.into_inner().0;
     let version_data = database::models::Version::get(id.into(), &**pool, &redis).await?;
 
     let user_option = get_user_from_headers(
@@ -169,7 +227,16 @@ pub async fn version_get_helper(
 
     if let Some(data) = version_data {
         if is_visible_version(&data.inner, &user_option, &pool, &redis).await? {
-            return Ok(HttpResponse::Ok().json(models::projects::Version::from(data)));
+            let contents = database::models::modpack_content_item::ModpackContent::get_version(
+                data.inner.id,
+                &**pool,
+            )
+            .await?
+            .into_iter()
+            .map(models::pack::ModpackContent::from)
+            .collect::<Vec<_>>();
+
+            return Ok(HttpResponse::Ok().json(contents));
         }
     }
 
@@ -223,6 +290,7 @@ pub struct EditVersionFileType {
     pub file_type: Option<FileType>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn version_edit(
     req: HttpRequest,
     info: web::Path<(VersionId,)>,
@@ -230,6 +298,8 @@ pub async fn version_edit(
     redis: web::Data<RedisPool>,
     new_version: web::Json<serde_json::Value>,
     session_queue: web::Data<AuthQueue>,
+    search_index_queue: web::Data<SearchIndexQueue>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
 ) -> Result<HttpResponse, ApiError> {
     let new_version: EditVersion = serde_json::from_value(new_version.into_inner())?;
     version_edit_helper(
@@ -239,9 +309,12 @@ pub async fn version_edit(
         redis,
         new_version,
         session_queue,
+        search_index_queue,
+        file_host,
     )
     .await
 }
+#[allow(clippy::too_many_arguments)]
 pub async fn version_edit_helper(
     req: HttpRequest,
     info: (VersionId,),
@@ -249,6 +322,8 @@ pub async fn version_edit_helper(
     redis: web::Data<RedisPool>,
     new_version: EditVersion,
     session_queue: web::Data<AuthQueue>,
+    search_index_queue: web::Data<SearchIndexQueue>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
 ) -> Result<HttpResponse, ApiError> {
     let user = get_user_from_headers(
         &req,
@@ -309,6 +384,7 @@ pub async fn version_edit_helper(
             }
 
             let mut transaction = pool.begin().await?;
+            let mut moved_files = MovedFiles::default();
 
             if let Some(name) = &new_version.name {
                 sqlx::query!(
@@ -544,6 +620,12 @@ pub async fn version_edit_helper(
                     ));
                 }
 
+                if version_item.inner.status == VersionStatus::Held && !user.role.is_mod() {
+                    return Err(ApiError::CustomAuthentication(
+                        "This version is held for moderation, and only a moderator can change its status".to_string(),
+                    ));
+                }
+
                 sqlx::query!(
                     "
                     UPDATE versions
@@ -555,6 +637,19 @@ pub async fn version_edit_helper(
                 )
                 .execute(&mut *transaction)
                 .await?;
+
+                ModpackContent::notify_newly_included(
+                    version_item.inner.project_id,
+                    &mut transaction,
+                    &redis,
+                )
+                .await?;
+                moved_files = sync_file_visibility(
+                    version_item.inner.project_id,
+                    &***file_host,
+                    &mut transaction,
+                )
+                .await?;
             }
 
             if let Some(file_types) = &new_version.file_types {
@@ -617,6 +712,7 @@ pub async fn version_edit_helper(
             img::delete_unused_images(context, checkable_strings, &mut transaction, &redis).await?;
 
             transaction.commit().await?;
+            moved_files.finish(&***file_host, &pool, &redis).await?;
             database::models::Version::clear_cache(&version_item, &redis).await?;
             database::models::Project::clear_cache(
                 version_item.inner.project_id,
@@ -625,6 +721,7 @@ pub async fn version_edit_helper(
                 &redis,
             )
             .await?;
+            search_index_queue.add(version_item.inner.project_id).await;
             Ok(HttpResponse::NoContent().body(""))
         } else {
             Err(ApiError::CustomAuthentication(
@@ -658,6 +755,7 @@ pub async fn version_list(
     web::Query(filters): web::Query<VersionListFilters>,
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let string = info.into_inner().0;
@@ -780,7 +878,8 @@ pub async fn version_list(
         response.sort_by(|a, b| b.inner.date_published.cmp(&a.inner.date_published));
         response.dedup_by(|a, b| a.inner.id == b.inner.id);
 
-        let response = filter_visible_versions(response, &user_option, &pool, &redis).await?;
+        let mut response = filter_visible_versions(response, &user_option, &pool, &redis).await?;
+        sign_hidden_file_urls(&mut response, &***file_host, &pool, &redis).await?;
 
         Ok(HttpResponse::Ok().json(response))
     } else {
@@ -795,6 +894,7 @@ pub async fn version_delete(
     redis: web::Data<RedisPool>,
     session_queue: web::Data<AuthQueue>,
     search_config: web::Data<SearchConfig>,
+    search_index_queue: web::Data<SearchIndexQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let user = get_user_from_headers(
         &req,
@@ -867,6 +967,7 @@ pub async fn version_delete(
     remove_documents(&[version.inner.id.into()], &search_config).await?;
     database::models::Project::clear_cache(version.inner.project_id, None, Some(true), &redis)
         .await?;
+    search_index_queue.add(version.inner.project_id).await;
 
     if result.is_some() {
         Ok(HttpResponse::NoContent().body(""))
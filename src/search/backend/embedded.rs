@@ -0,0 +1,801 @@
+use crate::search::backend::{Document, SearchBackend, SearchQuery};
+use crate::search::indexing::{index_settings, IndexingError};
+use crate::search::{FacetDistribution, SearchError, SearchResults};
+use async_trait::async_trait;
+use chrono::DateTime;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use tokio::sync::RwLock;
+
+type Documents = HashMap<String, Document>;
+
+#[derive(Default)]
+struct IndexCopies {
+    current: Documents,
+    next: Documents,
+}
+
+/// An in-process search engine, keeping every index in memory. It needs no external service,
+/// which suits development and small deployments, but the indexes are lost on restart until the
+/// next full reindex.
+#[derive(Default)]
+pub struct EmbeddedBackend {
+    indexes: RwLock<HashMap<String, IndexCopies>>,
+}
+
+impl EmbeddedBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl SearchBackend for EmbeddedBackend {
+    async fn reset_next_index(
+        &self,
+        index: &str,
+        _filterable_attributes: &[String],
+    ) -> Result<(), IndexingError> {
+        let mut indexes = self.indexes.write().await;
+        indexes.entry(index.to_string()).or_default().next = HashMap::new();
+
+        Ok(())
+    }
+
+    async fn add_documents(
+        &self,
+        index: &str,
+        next: bool,
+        documents: &[Document],
+    ) -> Result<(), IndexingError> {
+        let documents = by_primary_key(index, documents);
+
+        let mut indexes = self.indexes.write().await;
+        let copies = indexes.entry(index.to_string()).or_default();
+        let copy = if next {
+            &mut copies.next
+        } else {
+            &mut copies.current
+        };
+        copy.extend(documents);
+
+        Ok(())
+    }
+
+    async fn swap_index(&self, index: &str) -> Result<(), IndexingError> {
+        let mut indexes = self.indexes.write().await;
+        let copies = indexes.entry(index.to_string()).or_default();
+        copies.current = std::mem::take(&mut copies.next);
+
+        Ok(())
+    }
+
+    async fn replace_projects(
+        &self,
+        index: &str,
+        project_ids: &[String],
+        documents: &[Document],
+    ) -> Result<(), IndexingError> {
+        let documents = by_primary_key(index, documents);
+
+        let mut indexes = self.indexes.write().await;
+        let copies = indexes.entry(index.to_string()).or_default();
+        copies.current.retain(|_, document| {
+            !document
+                .get("project_id")
+                .and_then(|x| x.as_str())
+                .is_some_and(|x| project_ids.iter().any(|id| id == x))
+        });
+        copies.current.extend(documents);
+
+        Ok(())
+    }
+
+    async fn delete_documents(&self, index: &str, ids: &[String]) -> Result<(), IndexingError> {
+        let mut indexes = self.indexes.write().await;
+        if let Some(copies) = indexes.get_mut(index) {
+            for id in ids {
+                copies.current.remove(id);
+                copies.next.remove(id);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn search(
+        &self,
+        index: &str,
+        query: &SearchQuery,
+    ) -> Result<SearchResults<Document>, SearchError> {
+        let settings = index_settings(index);
+        let sort = query
+            .sort
+            .iter()
+            .map(|x| parse_sort(x, settings.sortable_attributes))
+            .collect::<Result<Vec<_>, _>>()?;
+        let terms = tokenize(&query.query);
+        let sort_first = settings.ranking_rules.first() == Some(&"sort");
+
+        let indexes = self.indexes.read().await;
+        let documents = indexes
+            .get(index)
+            .ok_or_else(|| SearchError::InvalidIndex(index.to_string()))?;
+
+        let mut hits = documents
+            .current
+            .values()
+            .filter(|document| query.filter.as_ref().map_or(true, |f| f.matches(document)))
+            .filter_map(|document| {
+                let relevance = relevance(document, settings.searchable_attributes, &terms)?;
+                Some((relevance, document))
+            })
+            .collect::<Vec<_>>();
+
+        hits.sort_by(|(a_relevance, a), (b_relevance, b)| {
+            let by_relevance = b_relevance.cmp(a_relevance);
+            let by_sort = compare_sorted(a, b, &sort);
+
+            let ordering = if sort_first {
+                by_sort.then(by_relevance)
+            } else {
+                by_relevance.then(by_sort)
+            };
+
+            // Ties are broken by primary key, so that pages are stable
+            let primary_key = settings.primary_key;
+            ordering.then_with(|| a.get(primary_key).cmp_values(b.get(primary_key)))
+        });
+
+        // Facets count distinct documents the same way they are collapsed
+        let distinct_attribute = settings.distinct_attribute.unwrap_or(settings.primary_key);
+        let facet_distribution = (!query.facets.is_empty()).then(|| {
+            facet_distribution(
+                hits.iter().map(|(_, x)| *x),
+                distinct_attribute,
+                &query.facets,
+            )
+        });
+
+        // Only the best ranked document of each distinct value (such as the best ranked version
+        // of each project) is returned
+        let mut seen = HashSet::new();
+        let hits = hits
+            .into_iter()
+            .filter(|(_, document)| {
+                seen.insert(document.get(distinct_attribute).and_then(|x| x.as_str()))
+            })
+            .collect::<Vec<_>>();
+
+        let total_hits = hits.len();
+        let hits = hits
+            .into_iter()
+            .skip(query.page.saturating_sub(1) * query.hits_per_page)
+            .take(query.hits_per_page)
+            .map(|(_, document)| {
+                document
+                    .iter()
+                    .filter(|(key, _)| {
+                        settings.displayed_attributes == ["*"]
+                            || settings.displayed_attributes.contains(&key.as_str())
+                    })
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect::<Document>()
+            })
+            .collect::<Vec<_>>();
+
+        Ok(SearchResults {
+            hits,
+            page: query.page,
+            hits_per_page: query.hits_per_page,
+            total_hits,
+            facet_distribution,
+        })
+    }
+}
+
+/// Keys documents by their primary key in the index, as text
+fn by_primary_key(index: &str, documents: &[Document]) -> Documents {
+    let primary_key = index_settings(index).primary_key;
+
+    documents
+        .iter()
+        .map(|document| {
+            let key = match document.get(primary_key) {
+                Some(Value::String(x)) => x.clone(),
+                Some(x) => x.to_string(),
+                None => String::new(),
+            };
+            (key, document.clone())
+        })
+        .collect()
+}
+
+/// Parses a sort such as `downloads:desc`, returning the attribute and whether it is descending
+fn parse_sort<'a>(
+    sort: &'a str,
+    sortable_attributes: &[&str],
+) -> Result<(&'a str, bool), SearchError> {
+    let (field, descending) = match sort.rsplit_once(':') {
+        Some((field, "asc")) => (field, false),
+        Some((field, "desc")) => (field, true),
+        _ => (sort, false),
+    };
+
+    if !sortable_attributes.contains(&field) {
+        return Err(SearchError::InvalidIndex(sort.to_string()));
+    }
+
+    Ok((field, descending))
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_lowercase())
+        .collect()
+}
+
+/// How well a document matches the query terms, where greater is better: the number of terms
+/// found, then how many of those matched a whole word, then how early the best matching
+/// attribute comes among the searchable attributes. Returns `None` if no term is found.
+///
+/// Like Meilisearch, only the last term matches word prefixes, as it may not be fully typed.
+fn relevance(
+    document: &Document,
+    searchable_attributes: &[&str],
+    terms: &[String],
+) -> Option<(usize, usize, isize)> {
+    if terms.is_empty() {
+        return Some((0, 0, 0));
+    }
+
+    let attributes = searchable_attributes
+        .iter()
+        .map(|attribute| match document.get(*attribute) {
+            Some(Value::String(text)) => tokenize(text),
+            _ => Vec::new(),
+        })
+        .collect::<Vec<_>>();
+
+    let mut matched = 0;
+    let mut exact = 0;
+    let mut best_attribute = attributes.len();
+
+    for (i, term) in terms.iter().enumerate() {
+        let is_last = i == terms.len() - 1;
+
+        let found = attributes
+            .iter()
+            .enumerate()
+            .find_map(|(attribute, words)| {
+                if words.contains(term) {
+                    Some((attribute, true))
+                } else if is_last && words.iter().any(|word| word.starts_with(term.as_str())) {
+                    Some((attribute, false))
+                } else {
+                    None
+                }
+            });
+
+        if let Some((attribute, is_exact)) = found {
+            matched += 1;
+            if is_exact {
+                exact += 1;
+            }
+            best_attribute = best_attribute.min(attribute);
+        }
+    }
+
+    if matched == 0 {
+        return None;
+    }
+
+    Some((matched, exact, -(best_attribute as isize)))
+}
+
+/// Counts the distinct values of `distinct_attribute` (such as projects) having each value of
+/// the given facets among the documents, where values are compared as text like Meilisearch does
+fn facet_distribution<'a>(
+    documents: impl Iterator<Item = &'a Document>,
+    distinct_attribute: &str,
+    facets: &[String],
+) -> FacetDistribution {
+    let mut projects: HashMap<&str, HashMap<String, HashSet<&str>>> = HashMap::new();
+
+    for document in documents {
+        let Some(project_id) = document.get(distinct_attribute).and_then(|x| x.as_str()) else {
+            continue;
+        };
+
+        for facet in facets {
+            let values = match document.get(facet) {
+                Some(Value::Array(values)) => values.iter().collect(),
+                Some(value) => vec![value],
+                None => Vec::new(),
+            };
+
+            for value in values {
+                let value = match value {
+                    Value::String(x) => x.clone(),
+                    Value::Null | Value::Array(_) | Value::Object(_) => continue,
+                    x => x.to_string(),
+                };
+
+                projects
+                    .entry(facet)
+                    .or_default()
+                    .entry(value)
+                    .or_default()
+                    .insert(project_id);
+            }
+        }
+    }
+
+    facets
+        .iter()
+        .map(|facet| {
+            let counts = projects
+                .remove(facet.as_str())
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(value, projects)| (value, projects.len()))
+                .collect();
+
+            (facet.clone(), counts)
+        })
+        .collect()
+}
+
+fn compare_sorted(a: &Document, b: &Document, sort: &[(&str, bool)]) -> Ordering {
+    sort.iter()
+        .fold(Ordering::Equal, |ordering, (field, descending)| {
+            ordering.then_with(|| {
+                let (a, b) = (a.get(*field), b.get(*field));
+                match (a, b) {
+                    // Documents without the attribute come last either way
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    _ if *descending => b.cmp_values(a),
+                    _ => a.cmp_values(b),
+                }
+            })
+        })
+}
+
+trait CompareValues {
+    fn cmp_values(&self, other: Self) -> Ordering;
+}
+
+impl CompareValues for Option<&Value> {
+    /// Orders numbers numerically, RFC 3339 dates chronologically and anything else by its text
+    fn cmp_values(&self, other: Self) -> Ordering {
+        match (self, other) {
+            (Some(Value::Number(a)), Some(Value::Number(b))) => a
+                .as_f64()
+                .partial_cmp(&b.as_f64())
+                .unwrap_or(Ordering::Equal),
+            (Some(Value::String(a)), Some(Value::String(b))) => {
+                match (
+                    DateTime::parse_from_rfc3339(a),
+                    DateTime::parse_from_rfc3339(b),
+                ) {
+                    (Ok(a), Ok(b)) => a.cmp(&b),
+                    _ => a.cmp(b),
+                }
+            }
+            (a, b) => a.map(|x| x.to_string()).cmp(&b.map(|x| x.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::backend::to_documents;
+    use crate::search::filter::Filter;
+    use crate::search::{ResultSearchProject, SearchCollection, SearchUser, UploadSearchProject};
+    use chrono::{TimeZone, Utc};
+
+    fn project(
+        project_id: &str,
+        version_id: &str,
+        name: &str,
+        downloads: i32,
+    ) -> UploadSearchProject {
+        let date = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+
+        UploadSearchProject {
+            version_id: version_id.to_string(),
+            project_id: project_id.to_string(),
+            project_types: vec!["mod".to_string()],
+            slug: None,
+            author: "author".to_string(),
+            name: name.to_string(),
+            summary: "A project".to_string(),
+            categories: vec!["fabric".to_string()],
+            display_categories: vec!["fabric".to_string()],
+            follows: 0,
+            downloads,
+            trending: 0.0,
+            icon_url: None,
+            license: "MIT".to_string(),
+            gallery: Vec::new(),
+            featured_gallery: None,
+            date_created: date,
+            created_timestamp: date.timestamp(),
+            date_modified: date,
+            modified_timestamp: date.timestamp(),
+            open_source: true,
+            color: None,
+            version_number: version_id.to_string(),
+            version_type: "release".to_string(),
+            version_loaders: vec!["fabric".to_string()],
+            version_listed: true,
+            version_date_published: date,
+            version_published_timestamp: date.timestamp(),
+            loaders: vec!["fabric".to_string()],
+            project_loader_fields: HashMap::new(),
+            loader_fields: vec![(
+                "game_versions".to_string(),
+                vec![Value::String("1.20.1".to_string())],
+            )]
+            .into_iter()
+            .collect(),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn search_documents() {
+        let backend = EmbeddedBackend::new();
+        backend.reset_next_index("projects", &[]).await.unwrap();
+        backend
+            .add_documents(
+                "projects",
+                true,
+                &to_documents(&[
+                    project("a", "a1", "Sodium", 100),
+                    project("a", "a2", "Sodium", 100),
+                    project("b", "b1", "Sodium Extra", 500),
+                    project("c", "c1", "Lithium", 1000),
+                ])
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+        backend.swap_index("projects").await.unwrap();
+
+        let search = |query: &str, filter: Option<&str>| SearchQuery {
+            query: query.to_string(),
+            filter: filter.map(|x| {
+                let fields = ["downloads", "game_versions"]
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect();
+                Filter::parse(x, &fields).unwrap()
+            }),
+            sort: vec!["downloads:desc".to_string()],
+            facets: Vec::new(),
+            page: 1,
+            hits_per_page: 10,
+        };
+        let project_ids = |results: SearchResults<Document>| {
+            results
+                .deserialize_hits::<ResultSearchProject>()
+                .unwrap()
+                .hits
+                .into_iter()
+                .map(|x| x.project_id)
+                .collect::<Vec<_>>()
+        };
+
+        // Versions of the same project are collapsed, and relevance ranks before sorting
+        let results = backend
+            .search("projects", &search("sodium", None))
+            .await
+            .unwrap();
+        assert_eq!(results.total_hits, 2);
+        assert_eq!(project_ids(results), vec!["b", "a"]);
+
+        let results = backend
+            .search("projects", &search("sod", None))
+            .await
+            .unwrap();
+        assert_eq!(project_ids(results), vec!["b", "a"]);
+
+        let results = backend.search("projects", &search("", None)).await.unwrap();
+        assert_eq!(project_ids(results), vec!["c", "b", "a"]);
+
+        let results = backend
+            .search(
+                "projects",
+                &search("", Some("downloads >= 500 AND game_versions = 1.20.1")),
+            )
+            .await
+            .unwrap();
+        assert_eq!(project_ids(results), vec!["c", "b"]);
+
+        backend
+            .delete_documents("projects", &["b1".to_string()])
+            .await
+            .unwrap();
+        let results = backend
+            .search("projects", &search("sodium", None))
+            .await
+            .unwrap();
+        assert_eq!(project_ids(results), vec!["a"]);
+
+        // Replacing a project drops its versions which are not among the new documents
+        backend
+            .replace_projects(
+                "projects",
+                &["a".to_string()],
+                &to_documents(&[project("a", "a3", "Rubidium", 100)]).unwrap(),
+            )
+            .await
+            .unwrap();
+        let results = backend
+            .search("projects", &search("sodium", None))
+            .await
+            .unwrap();
+        assert!(results.hits.is_empty());
+        let results = backend
+            .search("projects", &search("rubidium", None))
+            .await
+            .unwrap();
+        assert_eq!(results.hits[0]["version_id"], "a3");
+    }
+
+    #[actix_rt::test]
+    async fn count_facets() {
+        let mut quilt = project("b", "b1", "Sodium Extra", 500);
+        quilt.categories = vec!["quilt".to_string()];
+        quilt.loaders = vec!["quilt".to_string()];
+
+        let backend = EmbeddedBackend::new();
+        backend.reset_next_index("projects", &[]).await.unwrap();
+        backend
+            .add_documents(
+                "projects",
+                true,
+                &to_documents(&[
+                    project("a", "a1", "Sodium", 100),
+                    project("a", "a2", "Sodium", 100),
+                    quilt,
+                    project("c", "c1", "Lithium", 1000),
+                ])
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+        backend.swap_index("projects").await.unwrap();
+
+        let results = backend
+            .search(
+                "projects",
+                &SearchQuery {
+                    query: "sodium".to_string(),
+                    facets: vec![
+                        "loaders".to_string(),
+                        "game_versions".to_string(),
+                        "open_source".to_string(),
+                    ],
+                    page: 1,
+                    hits_per_page: 1,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        let facets = results.facet_distribution.unwrap();
+
+        // Projects are counted once however many of their versions match, including those
+        // outside of the returned page
+        assert_eq!(facets["loaders"]["fabric"], 1);
+        assert_eq!(facets["loaders"]["quilt"], 1);
+        assert_eq!(facets["game_versions"]["1.20.1"], 2);
+        assert_eq!(facets["open_source"]["true"], 2);
+
+        let results = backend
+            .search(
+                "projects",
+                &SearchQuery {
+                    page: 1,
+                    hits_per_page: 10,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert!(results.facet_distribution.is_none());
+    }
+
+    #[actix_rt::test]
+    async fn match_versions_alone() {
+        let version = |version_id: &str, loader: &str, game_version: &str, day| {
+            let date = Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap();
+            let mut version = project("a", version_id, "Sodium", 100);
+            version.version_loaders = vec![loader.to_string()];
+            version.loaders = vec!["fabric".to_string(), "quilt".to_string()];
+            version.version_date_published = date;
+            version.version_published_timestamp = date.timestamp();
+            version.loader_fields = vec![(
+                "game_versions".to_string(),
+                vec![Value::String(game_version.to_string())],
+            )]
+            .into_iter()
+            .collect();
+            version
+        };
+
+        let backend = EmbeddedBackend::new();
+        backend.reset_next_index("projects", &[]).await.unwrap();
+        backend
+            .add_documents(
+                "projects",
+                true,
+                &to_documents(&[
+                    version("a1", "fabric", "1.19.4", 1),
+                    version("a2", "quilt", "1.20.4", 2),
+                    version("a3", "fabric", "1.20.4", 3),
+                    version("a4", "fabric", "1.20.4", 4),
+                ])
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+        backend.swap_index("projects").await.unwrap();
+
+        let fields = ["loaders", "version_loaders", "game_versions"]
+            .iter()
+            .map(|x| x.to_string())
+            .collect();
+        let search = |filter: &str| SearchQuery {
+            filter: Some(Filter::parse(filter, &fields).unwrap()),
+            sort: vec!["version_published_timestamp:desc".to_string()],
+            page: 1,
+            hits_per_page: 10,
+            ..Default::default()
+        };
+        let version_ids = |results: SearchResults<Document>| {
+            results
+                .hits
+                .into_iter()
+                .map(|x| x["version_id"].clone())
+                .collect::<Vec<_>>()
+        };
+
+        // The newest version with both the loader and the game version is returned
+        let results = backend
+            .search(
+                "projects",
+                &search("version_loaders = fabric AND game_versions = 1.20.4"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(version_ids(results), vec!["a4"]);
+
+        // No version is for both Quilt and 1.19.4, though the project has versions for each
+        let results = backend
+            .search(
+                "projects",
+                &search("version_loaders = quilt AND game_versions = 1.19.4"),
+            )
+            .await
+            .unwrap();
+        assert!(version_ids(results).is_empty());
+        let results = backend
+            .search(
+                "projects",
+                &search("loaders = quilt AND game_versions = 1.19.4"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(version_ids(results), vec!["a1"]);
+    }
+
+    #[actix_rt::test]
+    async fn search_entities() {
+        let date = |day| Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap();
+        let user = |id: &str, username: &str, badges: &[&str], day| SearchUser {
+            id: id.to_string(),
+            username: username.to_string(),
+            bio: Some("Makes mods".to_string()),
+            avatar_url: None,
+            role: "developer".to_string(),
+            badges: badges.iter().map(|x| x.to_string()).collect(),
+            created: date(day),
+        };
+
+        let backend = EmbeddedBackend::new();
+        backend.reset_next_index("users", &[]).await.unwrap();
+        backend
+            .add_documents(
+                "users",
+                true,
+                &to_documents(&[
+                    user("a", "jellysquid", &["midas"], 1),
+                    user("b", "jelly", &[], 2),
+                    user("c", "someone", &["midas", "translator"], 3),
+                ])
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+        backend.swap_index("users").await.unwrap();
+
+        let fields = ["badges"].iter().map(|x| x.to_string()).collect();
+        let search = |query: &str, filter: Option<&str>, sort: &str| SearchQuery {
+            query: query.to_string(),
+            filter: filter.map(|x| Filter::parse(x, &fields).unwrap()),
+            sort: vec![sort.to_string()],
+            page: 1,
+            hits_per_page: 10,
+            ..Default::default()
+        };
+        let usernames = |results: SearchResults<Document>| {
+            results
+                .deserialize_hits::<SearchUser>()
+                .unwrap()
+                .hits
+                .into_iter()
+                .map(|x| x.username)
+                .collect::<Vec<_>>()
+        };
+
+        // Users are not collapsed into one another, and are sorted after relevance
+        let results = backend
+            .search("users", &search("jelly", None, "created:desc"))
+            .await
+            .unwrap();
+        assert_eq!(usernames(results), vec!["jelly", "jellysquid"]);
+
+        let results = backend
+            .search("users", &search("", Some("badges = midas"), "created:desc"))
+            .await
+            .unwrap();
+        assert_eq!(usernames(results), vec!["someone", "jellysquid"]);
+
+        // Project attributes cannot be sorted on
+        assert!(backend
+            .search("users", &search("", None, "downloads:desc"))
+            .await
+            .is_err());
+
+        let collection = SearchCollection {
+            id: "d".to_string(),
+            user: "a".to_string(),
+            name: "Performance".to_string(),
+            description: Some("The best optimization mods".to_string()),
+            icon_url: None,
+            color: None,
+            projects: vec!["a".to_string(), "b".to_string()],
+            created: date(1),
+            updated: date(1),
+        };
+        backend.reset_next_index("collections", &[]).await.unwrap();
+        backend
+            .add_documents("collections", true, &to_documents(&[collection]).unwrap())
+            .await
+            .unwrap();
+        backend.swap_index("collections").await.unwrap();
+
+        let results = backend
+            .search(
+                "collections",
+                &SearchQuery {
+                    query: "optimization".to_string(),
+                    page: 1,
+                    hits_per_page: 10,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap()
+            .deserialize_hits::<SearchCollection>()
+            .unwrap();
+        assert_eq!(results.total_hits, 1);
+        assert_eq!(results.hits[0].projects, vec!["a", "b"]);
+    }
+}
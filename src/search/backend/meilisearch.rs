@@ -0,0 +1,274 @@
+use crate::search::backend::{Document, SearchBackend, SearchQuery};
+use crate::search::filter::Filter;
+use crate::search::indexing::{index_settings, IndexSettings, IndexingError};
+use crate::search::{SearchError, SearchResults};
+use async_trait::async_trait;
+use log::info;
+use meilisearch_sdk::client::Client;
+use meilisearch_sdk::documents::DocumentDeletionQuery;
+use meilisearch_sdk::indexes::Index;
+use meilisearch_sdk::search::Selectors;
+use meilisearch_sdk::settings::{FacetingSettings, PaginationSetting, Settings};
+use meilisearch_sdk::SwapIndexes;
+
+// The chunk size for adding projects to the indexing database. If the request size
+// is too large (>10MiB) then the request fails with an error.  This chunk size
+// assumes a max average size of 4KiB per project to avoid this cap.
+const MEILISEARCH_CHUNK_SIZE: usize = 10000000;
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+pub struct MeilisearchBackend {
+    pub address: String,
+    pub key: String,
+    pub meta_namespace: String,
+}
+
+impl MeilisearchBackend {
+    // Panics if the environment variables are not set,
+    // but these are already checked for on startup.
+    pub fn new(meta_namespace: Option<String>) -> Self {
+        let address = dotenvy::var("MEILISEARCH_ADDR").expect("MEILISEARCH_ADDR not set");
+        let key = dotenvy::var("MEILISEARCH_KEY").expect("MEILISEARCH_KEY not set");
+
+        Self {
+            address,
+            key,
+            meta_namespace: meta_namespace.unwrap_or_default(),
+        }
+    }
+
+    pub fn make_client(&self) -> Client {
+        Client::new(self.address.as_str(), Some(self.key.as_str()))
+    }
+
+    // Next: true if we want the next index (we are preparing the next swap), false if we want the current index (searching)
+    pub fn get_index_name(&self, index: &str, next: bool) -> String {
+        let alt = if next { "_alt" } else { "" };
+        format!("{}_{}_{}", self.meta_namespace, index, alt)
+    }
+
+    async fn create_or_update_index(
+        &self,
+        client: &Client,
+        index: &str,
+        next: bool,
+        filterable_attributes: &[String],
+    ) -> Result<Index, meilisearch_sdk::errors::Error> {
+        let name = self.get_index_name(index, next);
+        info!("Updating/creating index {}", name);
+
+        let index_settings = index_settings(index);
+        let settings =
+            default_settings(&index_settings).with_filterable_attributes(filterable_attributes);
+
+        match client.get_index(&name).await {
+            Ok(index) => {
+                info!("Performing index settings set.");
+                index
+                    .set_settings(&settings)
+                    .await?
+                    .wait_for_completion(client, None, Some(TIMEOUT))
+                    .await?;
+                info!("Done performing index settings set.");
+
+                Ok(index)
+            }
+            _ => {
+                info!("Creating index.");
+
+                // Only create index and set settings if the index doesn't already exist
+                let task = client
+                    .create_index(&name, Some(index_settings.primary_key))
+                    .await?;
+                let task = task
+                    .wait_for_completion(client, None, Some(TIMEOUT))
+                    .await?;
+                let index = task
+                    .try_make_index(client)
+                    .map_err(|x| x.unwrap_failure())?;
+
+                index
+                    .set_settings(&settings)
+                    .await?
+                    .wait_for_completion(client, None, Some(TIMEOUT))
+                    .await?;
+
+                Ok(index)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl SearchBackend for MeilisearchBackend {
+    async fn reset_next_index(
+        &self,
+        index: &str,
+        filterable_attributes: &[String],
+    ) -> Result<(), IndexingError> {
+        let client = self.make_client();
+
+        // First, ensure current index exists (so no error happens- current index should be worst-case empty, not missing)
+        self.create_or_update_index(&client, index, false, filterable_attributes)
+            .await?;
+
+        // Then, delete the next index if it still exists, and recreate it
+        if let Ok(next) = client.get_index(self.get_index_name(index, true)).await {
+            next.delete()
+                .await?
+                .wait_for_completion(&client, None, Some(TIMEOUT))
+                .await?;
+        }
+        self.create_or_update_index(&client, index, true, filterable_attributes)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn add_documents(
+        &self,
+        index: &str,
+        next: bool,
+        documents: &[Document],
+    ) -> Result<(), IndexingError> {
+        let client = self.make_client();
+        let primary_key = index_settings(index).primary_key;
+        let index = client.index(self.get_index_name(index, next));
+
+        // TODO: The loader fields are hardcoded into the filterable and displayed attributes as a
+        // band-aid fix. They should instead be added to the index settings as they are needed.
+        info!("Adding to index.");
+
+        for chunk in documents.chunks(MEILISEARCH_CHUNK_SIZE) {
+            info!(
+                "Adding chunk starting with {} {}",
+                primary_key, chunk[0][primary_key]
+            );
+            index
+                .add_or_replace(chunk, Some(primary_key))
+                .await?
+                .wait_for_completion(&client, None, Some(std::time::Duration::from_secs(3600)))
+                .await?;
+            info!("Added chunk of {} documents to index", chunk.len());
+        }
+
+        Ok(())
+    }
+
+    async fn swap_index(&self, index: &str) -> Result<(), IndexingError> {
+        let client = self.make_client();
+        let index_name_next = self.get_index_name(index, true);
+        let index_name = self.get_index_name(index, false);
+        let swap_indices = SwapIndexes {
+            indexes: (index_name_next.clone(), index_name),
+        };
+        client
+            .swap_indexes([&swap_indices])
+            .await?
+            .wait_for_completion(&client, None, Some(TIMEOUT))
+            .await?;
+
+        // Delete the now-old index
+        client.index(index_name_next).delete().await?;
+
+        Ok(())
+    }
+
+    async fn replace_projects(
+        &self,
+        index: &str,
+        project_ids: &[String],
+        documents: &[Document],
+    ) -> Result<(), IndexingError> {
+        let client = self.make_client();
+        let meilisearch_index = client.index(self.get_index_name(index, false));
+
+        // Tasks run in order, so the new documents are added after the old ones are removed
+        let filter = Filter::In {
+            field: "project_id".to_string(),
+            values: project_ids.to_vec(),
+        }
+        .to_meilisearch();
+        DocumentDeletionQuery::new(&meilisearch_index)
+            .with_filter(&filter)
+            .execute::<Document>()
+            .await?;
+
+        if !documents.is_empty() {
+            self.add_documents(index, false, documents).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn delete_documents(&self, index: &str, ids: &[String]) -> Result<(), IndexingError> {
+        let client = self.make_client();
+
+        for next in [false, true] {
+            client
+                .index(self.get_index_name(index, next))
+                .delete_documents(ids)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn search(
+        &self,
+        index: &str,
+        query: &SearchQuery,
+    ) -> Result<SearchResults<Document>, SearchError> {
+        let client = self.make_client();
+        let meilisearch_index = client.get_index(self.get_index_name(index, false)).await?;
+        let sort = query.sort.iter().map(|x| x.as_str()).collect::<Vec<_>>();
+        let facets = query.facets.iter().map(|x| x.as_str()).collect::<Vec<_>>();
+        let filter = query.filter.as_ref().map(|x| x.to_meilisearch());
+
+        let results = {
+            let mut search = meilisearch_index.search();
+            search
+                .with_page(query.page)
+                .with_hits_per_page(query.hits_per_page)
+                .with_query(&query.query)
+                .with_sort(&sort);
+
+            if let Some(filter) = &filter {
+                search.with_filter(filter);
+            }
+            if !facets.is_empty() {
+                search.with_facets(Selectors::Some(&facets));
+            }
+
+            search.execute::<Document>().await?
+        };
+
+        Ok(SearchResults {
+            hits: results.hits.into_iter().map(|r| r.result).collect(),
+            page: results.page.unwrap_or_default(),
+            hits_per_page: results.hits_per_page.unwrap_or_default(),
+            total_hits: results.total_hits.unwrap_or_default(),
+            facet_distribution: results.facet_distribution,
+        })
+    }
+}
+
+fn default_settings(index_settings: &IndexSettings) -> Settings {
+    let settings = Settings::new()
+        .with_displayed_attributes(index_settings.displayed_attributes)
+        .with_searchable_attributes(index_settings.searchable_attributes)
+        .with_sortable_attributes(index_settings.sortable_attributes)
+        .with_ranking_rules(index_settings.ranking_rules)
+        .with_pagination(PaginationSetting {
+            max_total_hits: 2147483647,
+        })
+        // Enough for every game version to be counted
+        .with_faceting(&FacetingSettings {
+            max_values_per_facet: 10000,
+        });
+
+    match index_settings.distinct_attribute {
+        Some(distinct_attribute) => settings.with_distinct_attribute(distinct_attribute),
+        None => settings,
+    }
+}
@@ -0,0 +1,83 @@
+use crate::search::filter::Filter;
+use crate::search::indexing::IndexingError;
+use crate::search::{SearchError, SearchResults};
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+mod embedded;
+mod meilisearch;
+
+pub use self::meilisearch::MeilisearchBackend;
+pub use embedded::EmbeddedBackend;
+
+/// A document of a search index, as a JSON object
+pub type Document = Map<String, Value>;
+
+/// Converts models such as projects or users to the documents indexing them
+pub fn to_documents<T: Serialize>(documents: &[T]) -> Result<Vec<Document>, serde_json::Error> {
+    documents
+        .iter()
+        .map(|document| match serde_json::to_value(document)? {
+            Value::Object(map) => Ok(map),
+            _ => Ok(Map::new()),
+        })
+        .collect()
+}
+
+/// A query against one of the search indexes
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    pub query: String,
+    pub filter: Option<Filter>,
+    /// The attributes to sort by, such as `downloads:desc`, most significant first
+    pub sort: Vec<String>,
+    /// The attributes to count the values of among all matching documents
+    pub facets: Vec<String>,
+    /// The page to return, starting from 1
+    pub page: usize,
+    pub hits_per_page: usize,
+}
+
+/// A search engine holding the project, user, organization and collection indexes. Each index
+/// has a current copy, which is searched, and a next copy, which a full reindex fills before
+/// swapping it in. How an index stores and ranks its documents is set by its
+/// [`IndexSettings`](crate::search::indexing::IndexSettings).
+///
+/// Documents are returned one per distinct attribute value (one per project for the project
+/// indexes), ranked by relevance and then by the query's sort, except for indexes whose
+/// ranking rules put sorting first.
+#[async_trait]
+pub trait SearchBackend {
+    /// Makes sure the current copy of an index exists, and replaces its next copy with an
+    /// empty one. Both copies are set up to filter on the given attributes.
+    async fn reset_next_index(
+        &self,
+        index: &str,
+        filterable_attributes: &[String],
+    ) -> Result<(), IndexingError>;
+    /// Adds documents to a copy of an index, replacing any with the same primary key
+    async fn add_documents(
+        &self,
+        index: &str,
+        next: bool,
+        documents: &[Document],
+    ) -> Result<(), IndexingError>;
+    /// Makes the next copy of an index current, deleting the previous copy
+    async fn swap_index(&self, index: &str) -> Result<(), IndexingError>;
+    /// Replaces every document of the given projects in the current copy of an index with the
+    /// given documents, removing any which are no longer among them
+    async fn replace_projects(
+        &self,
+        index: &str,
+        project_ids: &[String],
+        documents: &[Document],
+    ) -> Result<(), IndexingError>;
+    /// Deletes documents by their primary keys from both copies of an index
+    async fn delete_documents(&self, index: &str, ids: &[String]) -> Result<(), IndexingError>;
+    async fn search(
+        &self,
+        index: &str,
+        query: &SearchQuery,
+    ) -> Result<SearchResults<Document>, SearchError>;
+}
@@ -0,0 +1,832 @@
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+
+/// A parsed search filter. The syntax follows Meilisearch's, which is what clients send, and
+/// filters are compiled to or evaluated by whichever backend is used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+    Not(Box<Filter>),
+    Compare {
+        field: String,
+        op: CompareOp,
+        value: String,
+    },
+    In {
+        field: String,
+        values: Vec<String>,
+    },
+    Exists {
+        field: String,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    NotEq,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+
+/// A problem with a filter, and the position in the filter where it was found
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{kind} at position {position}")]
+pub struct FilterError {
+    pub position: usize,
+    pub kind: FilterErrorKind,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum FilterErrorKind {
+    #[error("Unexpected end of filter")]
+    UnexpectedEnd,
+    #[error("Unexpected {0}")]
+    Unexpected(String),
+    #[error("Expected {expected}, found {found}")]
+    Expected { expected: String, found: String },
+    #[error("Unknown operator '{0}'")]
+    UnknownOperator(String),
+    #[error("Unclosed quote")]
+    UnclosedQuote,
+    #[error("Attribute `{0}` is not filterable")]
+    UnknownField(String),
+    #[error("Expected a number, found '{0}'")]
+    ExpectedNumber(String),
+    #[error("Unknown value '{0}' to order by")]
+    UnknownValue(String),
+}
+
+impl CompareOp {
+    fn from_str(op: &str) -> Option<CompareOp> {
+        Some(match op {
+            "=" => CompareOp::Eq,
+            "!=" => CompareOp::NotEq,
+            "<" => CompareOp::Lt,
+            "<=" => CompareOp::Lte,
+            ">" => CompareOp::Gt,
+            ">=" => CompareOp::Gte,
+            _ => return None,
+        })
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            CompareOp::Eq => "=",
+            CompareOp::NotEq => "!=",
+            CompareOp::Lt => "<",
+            CompareOp::Lte => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Gte => ">=",
+        }
+    }
+
+    /// Whether the operator orders values, which must then be numbers
+    fn is_ordering(&self) -> bool {
+        !matches!(self, CompareOp::Eq | CompareOp::NotEq)
+    }
+}
+
+impl Filter {
+    /// Parses a filter over the given filterable fields
+    pub fn parse(filter: &str, fields: &HashSet<String>) -> Result<Filter, FilterError> {
+        Filter::parse_ordered(filter, fields, &HashMap::new())
+    }
+
+    /// Parses a filter over the given filterable fields, where the values of some fields are
+    /// ordered without being numbers, such as game versions from oldest to newest. Ranges and
+    /// comparisons on those fields are expanded to the values they cover, as in
+    /// `game_versions 1.20 TO 1.20.2` becoming `game_versions IN [1.20, 1.20.1, 1.20.2]`.
+    pub fn parse_ordered(
+        filter: &str,
+        fields: &HashSet<String>,
+        ordered: &HashMap<String, Vec<String>>,
+    ) -> Result<Filter, FilterError> {
+        let mut parser = Parser::new(filter, fields, ordered)?;
+        let filter = parser.parse_or()?;
+        parser.expect_end()?;
+
+        Ok(filter)
+    }
+
+    /// Parses a single condition of the legacy `facets` parameter, such as `categories:fabric`,
+    /// where `:` means `=`
+    pub fn parse_facet(facet: &str, fields: &HashSet<String>) -> Result<Filter, FilterError> {
+        let ordered = HashMap::new();
+        let mut parser = Parser::new(&facet.replacen(':', "=", 1), fields, &ordered)?;
+        let filter = parser.parse_condition()?;
+        parser.expect_end()?;
+
+        Ok(filter)
+    }
+
+    /// All of the given filters, or `None` if there are none
+    pub fn all(mut filters: Vec<Filter>) -> Option<Filter> {
+        match filters.len() {
+            0 => None,
+            1 => filters.pop(),
+            _ => Some(Filter::And(filters)),
+        }
+    }
+
+    /// Any of the given filters. No filters match nothing.
+    pub fn any(mut filters: Vec<Filter>) -> Filter {
+        if filters.len() == 1 {
+            filters.remove(0)
+        } else {
+            Filter::Or(filters)
+        }
+    }
+
+    /// The fields the filter refers to
+    pub fn fields(&self) -> Vec<&str> {
+        match self {
+            Filter::And(filters) | Filter::Or(filters) => {
+                filters.iter().flat_map(|x| x.fields()).collect()
+            }
+            Filter::Not(filter) => filter.fields(),
+            Filter::Compare { field, .. } | Filter::In { field, .. } | Filter::Exists { field } => {
+                vec![field]
+            }
+        }
+    }
+
+    /// Compiles the filter to Meilisearch's syntax, quoting every value
+    pub fn to_meilisearch(&self) -> String {
+        match self {
+            // Every document has a project id, which stands in for the literals Meilisearch lacks
+            Filter::And(filters) if filters.is_empty() => "project_id EXISTS".to_string(),
+            Filter::Or(filters) if filters.is_empty() => "project_id NOT EXISTS".to_string(),
+            Filter::And(filters) => join_meilisearch(filters, " AND "),
+            Filter::Or(filters) => join_meilisearch(filters, " OR "),
+            Filter::Not(filter) => format!("NOT ({})", filter.to_meilisearch()),
+            Filter::Compare { field, op, value } => {
+                format!("{field} {} {}", op.as_str(), quote_meilisearch(value))
+            }
+            Filter::In { field, values } => format!(
+                "{field} IN [{}]",
+                values
+                    .iter()
+                    .map(|x| quote_meilisearch(x))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Filter::Exists { field } => format!("{field} EXISTS"),
+        }
+    }
+
+    /// Whether a document matches the filter. Array fields match if any of their elements do,
+    /// and strings are compared case-insensitively.
+    pub fn matches(&self, document: &serde_json::Map<String, Value>) -> bool {
+        let values = |field: &str| match document.get(field) {
+            Some(Value::Array(values)) => values.iter().collect::<Vec<_>>(),
+            Some(Value::Null) | None => Vec::new(),
+            Some(value) => vec![value],
+        };
+
+        match self {
+            Filter::And(filters) => filters.iter().all(|x| x.matches(document)),
+            Filter::Or(filters) => filters.iter().any(|x| x.matches(document)),
+            Filter::Not(filter) => !filter.matches(document),
+            Filter::Compare {
+                field,
+                op: CompareOp::NotEq,
+                value,
+            } => !values(field).iter().any(|x| value_equals(x, value)),
+            Filter::Compare { field, op, value } => values(field).iter().any(|x| match op {
+                CompareOp::Eq => value_equals(x, value),
+                _ => match (as_number(x), value.parse::<f64>().ok()) {
+                    (Some(x), Some(value)) => match op {
+                        CompareOp::Lt => x < value,
+                        CompareOp::Lte => x <= value,
+                        CompareOp::Gt => x > value,
+                        _ => x >= value,
+                    },
+                    _ => false,
+                },
+            }),
+            Filter::In { field, values: set } => values(field)
+                .iter()
+                .any(|x| set.iter().any(|value| value_equals(x, value))),
+            Filter::Exists { field } => document.contains_key(field),
+        }
+    }
+}
+
+fn join_meilisearch(filters: &[Filter], separator: &str) -> String {
+    filters
+        .iter()
+        .map(|x| format!("({})", x.to_meilisearch()))
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn quote_meilisearch(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(x) => x.as_f64(),
+        Value::String(x) => x.parse().ok(),
+        _ => None,
+    }
+}
+
+fn value_equals(value: &Value, expected: &str) -> bool {
+    match value {
+        Value::String(x) => x.to_lowercase() == expected.to_lowercase(),
+        Value::Bool(x) => x.to_string() == expected,
+        Value::Number(_) => match (as_number(value), expected.parse::<f64>().ok()) {
+            (Some(x), Some(expected)) => x == expected,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    OpenParen,
+    CloseParen,
+    OpenBracket,
+    CloseBracket,
+    Comma,
+    Op(CompareOp),
+    /// An unquoted word, which may be a keyword
+    Word(String),
+    Quoted(String),
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::OpenParen => "'('".to_string(),
+            Token::CloseParen => "')'".to_string(),
+            Token::OpenBracket => "'['".to_string(),
+            Token::CloseBracket => "']'".to_string(),
+            Token::Comma => "','".to_string(),
+            Token::Op(_) => "operator".to_string(),
+            Token::Word(x) => format!("'{x}'"),
+            Token::Quoted(x) => format!("\"{x}\""),
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Token::Word(x) if x == keyword)
+    }
+}
+
+fn tokenize(filter: &str) -> Result<Vec<(usize, Token)>, FilterError> {
+    let mut tokens = Vec::new();
+    let mut chars = filter.char_indices().peekable();
+
+    while let Some((position, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::OpenParen,
+            ')' => Token::CloseParen,
+            '[' => Token::OpenBracket,
+            ']' => Token::CloseBracket,
+            ',' => Token::Comma,
+            '=' | '!' | '<' | '>' => {
+                let mut op = c.to_string();
+                if let Some((_, '=')) = chars.peek() {
+                    op.push('=');
+                    chars.next();
+                }
+                Token::Op(CompareOp::from_str(&op).ok_or(FilterError {
+                    position,
+                    kind: FilterErrorKind::UnknownOperator(op),
+                })?)
+            }
+            '"' | '\'' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '\\')) => {
+                            if let Some((_, escaped)) = chars.next() {
+                                value.push(escaped);
+                            }
+                        }
+                        Some((_, x)) if x == c => break,
+                        Some((_, x)) => value.push(x),
+                        None => {
+                            return Err(FilterError {
+                                position,
+                                kind: FilterErrorKind::UnclosedQuote,
+                            })
+                        }
+                    }
+                }
+                Token::Quoted(value)
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some((_, x)) = chars.peek() {
+                    if x.is_whitespace() || "()[],=!<>\"'".contains(*x) {
+                        break;
+                    }
+                    word.push(*x);
+                    chars.next();
+                }
+                Token::Word(word)
+            }
+        };
+
+        tokens.push((position, token));
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    /// Where the filter ends, which is where an unexpected end is reported
+    end: usize,
+    fields: &'a HashSet<String>,
+    /// The values of fields which are ordered without being numbers, in order
+    ordered: &'a HashMap<String, Vec<String>>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(
+        filter: &str,
+        fields: &'a HashSet<String>,
+        ordered: &'a HashMap<String, Vec<String>>,
+    ) -> Result<Self, FilterError> {
+        Ok(Parser {
+            tokens: tokenize(filter)?,
+            position: 0,
+            end: filter.len(),
+            fields,
+            ordered,
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, x)| x)
+    }
+
+    fn next(&mut self) -> Result<(usize, Token), FilterError> {
+        let token = self.tokens.get(self.position).cloned().ok_or(FilterError {
+            position: self.end,
+            kind: FilterErrorKind::UnexpectedEnd,
+        })?;
+        self.position += 1;
+
+        Ok(token)
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let matches = self.peek().is_some_and(|x| x.is_keyword(keyword));
+        if matches {
+            self.position += 1;
+        }
+        matches
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), FilterError> {
+        let (position, token) = self.next()?;
+        if token == expected {
+            Ok(())
+        } else {
+            Err(expected_error(position, expected.describe(), &token))
+        }
+    }
+
+    fn expect_end(&self) -> Result<(), FilterError> {
+        match self.tokens.get(self.position) {
+            None => Ok(()),
+            Some((position, token)) => Err(FilterError {
+                position: *position,
+                kind: FilterErrorKind::Unexpected(token.describe()),
+            }),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Filter, FilterError> {
+        let mut filters = vec![self.parse_and()?];
+        while self.eat_keyword("OR") {
+            filters.push(self.parse_and()?);
+        }
+
+        Ok(if filters.len() == 1 {
+            filters.remove(0)
+        } else {
+            Filter::Or(filters)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Filter, FilterError> {
+        let mut filters = vec![self.parse_not()?];
+        while self.eat_keyword("AND") {
+            filters.push(self.parse_not()?);
+        }
+
+        Ok(if filters.len() == 1 {
+            filters.remove(0)
+        } else {
+            Filter::And(filters)
+        })
+    }
+
+    fn parse_not(&mut self) -> Result<Filter, FilterError> {
+        if self.eat_keyword("NOT") {
+            return Ok(Filter::Not(Box::new(self.parse_not()?)));
+        }
+
+        if self.peek() == Some(&Token::OpenParen) {
+            self.position += 1;
+            let filter = self.parse_or()?;
+            self.expect(Token::CloseParen)?;
+            return Ok(filter);
+        }
+
+        self.parse_condition()
+    }
+
+    fn parse_value(&mut self) -> Result<(usize, String), FilterError> {
+        match self.next()? {
+            (position, Token::Word(x)) | (position, Token::Quoted(x)) => Ok((position, x)),
+            (position, token) => Err(expected_error(position, "a value", &token)),
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<String, FilterError> {
+        let (position, value) = self.parse_value()?;
+        check_number(position, value)
+    }
+
+    fn parse_field(&mut self) -> Result<String, FilterError> {
+        let (position, field) = self.parse_value()?;
+        if !self.fields.contains(&field) {
+            return Err(FilterError {
+                position,
+                kind: FilterErrorKind::UnknownField(field),
+            });
+        }
+
+        Ok(field)
+    }
+
+    fn parse_condition(&mut self) -> Result<Filter, FilterError> {
+        let field = self.parse_field()?;
+
+        if self.eat_keyword("EXISTS") {
+            return Ok(Filter::Exists { field });
+        }
+        if self.eat_keyword("IN") {
+            return Ok(Filter::In {
+                values: self.parse_list()?,
+                field,
+            });
+        }
+        if self.eat_keyword("NOT") {
+            let filter = if self.eat_keyword("IN") {
+                Filter::In {
+                    values: self.parse_list()?,
+                    field,
+                }
+            } else if self.eat_keyword("EXISTS") {
+                Filter::Exists { field }
+            } else {
+                let (position, token) = self.next()?;
+                return Err(expected_error(position, "IN or EXISTS", &token));
+            };
+            return Ok(Filter::Not(Box::new(filter)));
+        }
+
+        let order = self.ordered.get(&field);
+
+        match self.next()? {
+            (_, Token::Op(op)) if op.is_ordering() => {
+                if let Some(order) = order {
+                    let (position, value) = self.parse_value()?;
+                    let i = order_position(order, position, value)?;
+                    let (start, end) = match op {
+                        CompareOp::Lt => (0, i),
+                        CompareOp::Lte => (0, i + 1),
+                        CompareOp::Gt => (i + 1, order.len()),
+                        _ => (i, order.len()),
+                    };
+                    return Ok(ordered_range(field, order, start, end));
+                }
+
+                Ok(Filter::Compare {
+                    value: self.parse_number()?,
+                    field,
+                    op,
+                })
+            }
+            (_, Token::Op(op)) => Ok(Filter::Compare {
+                value: self.parse_value()?.1,
+                field,
+                op,
+            }),
+            // A range, as in `downloads 100 TO 1000`
+            (position, Token::Word(from)) | (position, Token::Quoted(from))
+                if self.eat_keyword("TO") =>
+            {
+                if let Some(order) = order {
+                    let from = order_position(order, position, from)?;
+                    let (position, to) = self.parse_value()?;
+                    let to = order_position(order, position, to)?;
+                    return Ok(ordered_range(field, order, from, to + 1));
+                }
+
+                let from = check_number(position, from)?;
+                let to = self.parse_number()?;
+                Ok(Filter::And(vec![
+                    Filter::Compare {
+                        field: field.clone(),
+                        op: CompareOp::Gte,
+                        value: from,
+                    },
+                    Filter::Compare {
+                        field,
+                        op: CompareOp::Lte,
+                        value: to,
+                    },
+                ]))
+            }
+            (position, token) => Err(expected_error(position, "an operator", &token)),
+        }
+    }
+
+    fn parse_list(&mut self) -> Result<Vec<String>, FilterError> {
+        self.expect(Token::OpenBracket)?;
+
+        let mut values = Vec::new();
+        if self.peek() == Some(&Token::CloseBracket) {
+            self.position += 1;
+            return Ok(values);
+        }
+        loop {
+            values.push(self.parse_value()?.1);
+            match self.next()? {
+                (_, Token::Comma) => {}
+                (_, Token::CloseBracket) => return Ok(values),
+                (position, token) => return Err(expected_error(position, "',' or ']'", &token)),
+            }
+        }
+    }
+}
+
+fn check_number(position: usize, value: String) -> Result<String, FilterError> {
+    if value.parse::<f64>().is_err() {
+        return Err(FilterError {
+            position,
+            kind: FilterErrorKind::ExpectedNumber(value),
+        });
+    }
+
+    Ok(value)
+}
+
+/// The position of a value among the ordered values of a field
+fn order_position(order: &[String], position: usize, value: String) -> Result<usize, FilterError> {
+    order
+        .iter()
+        .position(|x| x.eq_ignore_ascii_case(&value))
+        .ok_or(FilterError {
+            position,
+            kind: FilterErrorKind::UnknownValue(value),
+        })
+}
+
+/// The ordered values of a field from `start` up to but excluding `end`, of which there are
+/// none if `start` comes after `end`
+fn ordered_range(field: String, order: &[String], start: usize, end: usize) -> Filter {
+    Filter::In {
+        field,
+        values: order.get(start..end).unwrap_or_default().to_vec(),
+    }
+}
+
+fn expected_error(position: usize, expected: impl Into<String>, found: &Token) -> FilterError {
+    FilterError {
+        position,
+        kind: FilterErrorKind::Expected {
+            expected: expected.into(),
+            found: found.describe(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(value: Value) -> serde_json::Map<String, Value> {
+        match value {
+            Value::Object(x) => x,
+            _ => unreachable!(),
+        }
+    }
+
+    fn fields() -> HashSet<String> {
+        [
+            "categories",
+            "project_types",
+            "downloads",
+            "license",
+            "name",
+            "color",
+            "client_side",
+            "game_versions",
+        ]
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+    }
+
+    #[test]
+    fn parse_and_match() {
+        let project = document(serde_json::json!({
+            "categories": ["fabric", "Technology"],
+            "project_types": ["mod"],
+            "downloads": 1500,
+            "license": "MIT",
+            "client_side": "required",
+        }));
+
+        for (filter, expected) in [
+            ("categories = fabric", true),
+            ("categories = technology AND project_types = mod", true),
+            (
+                "(categories = forge OR categories = fabric) AND downloads > 1000",
+                true,
+            ),
+            ("categories != fabric", false),
+            ("NOT categories = forge", true),
+            ("downloads 1000 TO 2000", true),
+            ("downloads >= 2000", false),
+            ("license IN [MIT, 'Apache-2.0']", true),
+            ("license NOT IN [MIT]", false),
+            ("color EXISTS", false),
+            ("color NOT EXISTS", true),
+            ("client_side = \"required\"", true),
+            ("game_versions = 1.20.1", false),
+            ("game_versions != 1.20.1", true),
+        ] {
+            let parsed = Filter::parse(filter, &fields()).unwrap();
+            assert_eq!(parsed.matches(&project), expected, "{}", filter);
+        }
+
+        assert_eq!(
+            Filter::parse("categories = fabric OR downloads > 10", &fields())
+                .unwrap()
+                .fields(),
+            vec!["categories", "downloads"]
+        );
+
+        let facet = Filter::parse_facet("name:'Mysterious Project'", &fields()).unwrap();
+        assert!(!facet.matches(&project));
+        assert_eq!(
+            facet,
+            Filter::Compare {
+                field: "name".to_string(),
+                op: CompareOp::Eq,
+                value: "Mysterious Project".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn reject_invalid_filters() {
+        for (filter, position, kind) in [
+            ("categories =", 12, FilterErrorKind::UnexpectedEnd),
+            (
+                "categories fabric",
+                11,
+                FilterErrorKind::Expected {
+                    expected: "an operator".to_string(),
+                    found: "'fabric'".to_string(),
+                },
+            ),
+            ("(categories = fabric", 20, FilterErrorKind::UnexpectedEnd),
+            (
+                "categories = fabric)",
+                19,
+                FilterErrorKind::Unexpected("')'".to_string()),
+            ),
+            ("license IN [MIT", 15, FilterErrorKind::UnexpectedEnd),
+            ("name = 'unclosed", 7, FilterErrorKind::UnclosedQuote),
+            (
+                "downloads =< 10",
+                11,
+                FilterErrorKind::Expected {
+                    expected: "a value".to_string(),
+                    found: "operator".to_string(),
+                },
+            ),
+            (
+                "downloads ! 10",
+                10,
+                FilterErrorKind::UnknownOperator("!".to_string()),
+            ),
+            (
+                "version_id = a1",
+                0,
+                FilterErrorKind::UnknownField("version_id".to_string()),
+            ),
+            (
+                "categories = fabric OR team_id EXISTS",
+                23,
+                FilterErrorKind::UnknownField("team_id".to_string()),
+            ),
+            (
+                "downloads > many",
+                12,
+                FilterErrorKind::ExpectedNumber("many".to_string()),
+            ),
+            (
+                "downloads few TO 10",
+                10,
+                FilterErrorKind::ExpectedNumber("few".to_string()),
+            ),
+        ] {
+            assert_eq!(
+                Filter::parse(filter, &fields()),
+                Err(FilterError { position, kind }),
+                "{}",
+                filter
+            );
+        }
+
+        // A facet is a single condition
+        assert!(Filter::parse_facet("categories:fabric OR downloads > 0", &fields()).is_err());
+        assert!(Filter::parse_facet("downloads>=10", &fields()).is_ok());
+    }
+
+    #[test]
+    fn expand_ordered_ranges() {
+        let ordered = [(
+            "game_versions".to_string(),
+            ["1.19.4", "1.20", "1.20.1", "1.20.2"]
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>(),
+        )]
+        .iter()
+        .cloned()
+        .collect::<HashMap<_, _>>();
+        let parse = |filter| Filter::parse_ordered(filter, &fields(), &ordered);
+        let game_versions = |values: &[&str]| Filter::In {
+            field: "game_versions".to_string(),
+            values: values.iter().map(|x| x.to_string()).collect(),
+        };
+
+        assert_eq!(
+            parse("game_versions 1.20 TO 1.20.2"),
+            Ok(game_versions(&["1.20", "1.20.1", "1.20.2"]))
+        );
+        assert_eq!(
+            parse("game_versions >= 1.20.1"),
+            Ok(game_versions(&["1.20.1", "1.20.2"]))
+        );
+        assert_eq!(
+            parse("game_versions < 1.20"),
+            Ok(game_versions(&["1.19.4"]))
+        );
+        // A backwards range covers nothing
+        assert_eq!(
+            parse("game_versions 1.20.2 TO 1.20"),
+            Ok(game_versions(&[]))
+        );
+        assert_eq!(
+            parse("game_versions 1.20 TO 1.21"),
+            Err(FilterError {
+                position: 22,
+                kind: FilterErrorKind::UnknownValue("1.21".to_string()),
+            })
+        );
+        // Fields which are not ordered still need numbers
+        assert!(parse("downloads 10 TO 20").is_ok());
+        assert!(parse("license >= MIT").is_err());
+    }
+
+    #[test]
+    fn compile_to_meilisearch() {
+        let filter = Filter::parse(
+            r#"(categories = fabric OR name = 'Say "hi"') AND NOT license IN [MIT] AND downloads 1 TO 5"#,
+            &fields(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            filter.to_meilisearch(),
+            r#"((categories = "fabric") OR (name = "Say \"hi\"")) AND (NOT (license IN ["MIT"])) AND ((downloads >= "1") AND (downloads <= "5"))"#
+        );
+        assert_eq!(
+            Filter::Or(Vec::new()).to_meilisearch(),
+            "project_id NOT EXISTS"
+        );
+    }
+}
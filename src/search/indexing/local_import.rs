@@ -12,13 +12,21 @@ use crate::database::models::loader_fields::{
 use crate::database::models::{
     LoaderFieldEnumId, LoaderFieldEnumValueId, LoaderFieldId, ProjectId, VersionId,
 };
-use crate::models::projects::from_duplicate_version_fields;
+use crate::models::collections::CollectionStatus;
+use crate::models::ids::base62_impl::to_base62;
+use crate::models::projects::{from_duplicate_version_fields, VersionStatus};
+use crate::models::users::{Badges, DELETED_USER};
 use crate::models::v2::projects::LegacyProject;
 use crate::routes::v2_reroute;
-use crate::search::UploadSearchProject;
+use crate::search::{SearchCollection, SearchOrganization, SearchUser, UploadSearchProject};
 use sqlx::postgres::PgPool;
 
-pub async fn index_local(pool: &PgPool) -> Result<Vec<UploadSearchProject>, IndexingError> {
+/// Builds the search documents of every searchable project, or only of the given projects if
+/// there are any. Projects which are not searchable have no documents.
+pub async fn index_local(
+    pool: &PgPool,
+    project_ids: Option<&[ProjectId]>,
+) -> Result<Vec<UploadSearchProject>, IndexingError> {
     info!("Indexing local projects!");
 
     // todo: loaders, project type, game versions
@@ -28,6 +36,7 @@ pub async fn index_local(pool: &PgPool) -> Result<Vec<UploadSearchProject>, Inde
         summary: String,
         downloads: i32,
         follows: i32,
+        trending: f64,
         icon_url: Option<String>,
         updated: DateTime<Utc>,
         approved: DateTime<Utc>,
@@ -36,18 +45,20 @@ pub async fn index_local(pool: &PgPool) -> Result<Vec<UploadSearchProject>, Inde
         license: String,
     }
 
+    let filter_ids = project_ids.map(|x| x.iter().map(|x| x.0).collect::<Vec<i64>>());
     let db_projects = sqlx::query!(
         "
-        SELECT m.id id, m.name name, m.summary summary, m.downloads downloads, m.follows follows,
+        SELECT m.id id, m.name name, m.summary summary, m.downloads downloads, m.follows follows, m.trending trending,
         m.icon_url icon_url, m.updated updated, m.approved approved, m.published, m.license license, m.slug slug, m.color
         FROM mods m
-        WHERE m.status = ANY($1)
+        WHERE m.status = ANY($1) AND ($2::bigint[] IS NULL OR m.id = ANY($2))
         GROUP BY m.id;
         ",
         &*crate::models::projects::ProjectStatus::iterator()
         .filter(|x| x.is_searchable())
         .map(|x| x.to_string())
         .collect::<Vec<String>>(),
+        filter_ids.as_deref(),
     )
         .fetch(pool)
         .map_ok(|m| {
@@ -57,6 +68,7 @@ pub async fn index_local(pool: &PgPool) -> Result<Vec<UploadSearchProject>, Inde
                 summary: m.summary,
                 downloads: m.downloads,
                 follows: m.follows,
+                trending: m.trending,
                 icon_url: m.icon_url,
                 updated: m.updated,
                 approved: m.approved.unwrap_or(m.published),
@@ -314,6 +326,7 @@ pub async fn index_local(pool: &PgPool) -> Result<Vec<UploadSearchProject>, Inde
                 let project_types = version.project_types;
 
                 let mut version_loaders = version.loaders;
+                let own_loaders = version_loaders.clone();
 
                 // Uses version loaders, not project loaders.
                 let mut categories = categories.clone();
@@ -369,6 +382,7 @@ pub async fn index_local(pool: &PgPool) -> Result<Vec<UploadSearchProject>, Inde
                     display_categories: display_categories.clone(),
                     follows: project.follows,
                     downloads: project.downloads,
+                    trending: project.trending,
                     icon_url: project.icon_url.clone(),
                     author: owner.clone(),
                     date_created: project.approved,
@@ -383,6 +397,12 @@ pub async fn index_local(pool: &PgPool) -> Result<Vec<UploadSearchProject>, Inde
                     featured_gallery: featured_gallery.clone(),
                     open_source,
                     color: project.color.map(|x| x as u32),
+                    version_number: version.version_number,
+                    version_type: version.version_type,
+                    version_loaders: own_loaders,
+                    version_listed: version.listed,
+                    version_date_published: version.date_published,
+                    version_published_timestamp: version.date_published.timestamp(),
                     loader_fields,
                     project_loader_fields: project_loader_fields.clone(),
                     // 'loaders' is aggregate of all versions' loaders
@@ -399,6 +419,10 @@ pub async fn index_local(pool: &PgPool) -> Result<Vec<UploadSearchProject>, Inde
 
 struct PartialVersion {
     id: VersionId,
+    version_number: String,
+    version_type: String,
+    listed: bool,
+    date_published: DateTime<Utc>,
     loaders: Vec<String>,
     project_types: Vec<String>,
     version_fields: Vec<QueryVersionField>,
@@ -408,9 +432,17 @@ async fn index_versions(
     pool: &PgPool,
     project_ids: Vec<i64>,
 ) -> Result<HashMap<ProjectId, Vec<PartialVersion>>, IndexingError> {
-    let versions: HashMap<ProjectId, Vec<VersionId>> = sqlx::query!(
+    struct VersionData {
+        id: VersionId,
+        version_number: String,
+        version_type: String,
+        listed: bool,
+        date_published: DateTime<Utc>,
+    }
+
+    let versions: HashMap<ProjectId, Vec<VersionData>> = sqlx::query!(
         "
-        SELECT v.id, v.mod_id
+        SELECT v.id, v.mod_id, v.version_number, v.version_type, v.status, v.date_published
         FROM versions v
         WHERE mod_id = ANY($1)
         ",
@@ -419,10 +451,16 @@ async fn index_versions(
     .fetch(pool)
     .try_fold(
         HashMap::new(),
-        |mut acc: HashMap<ProjectId, Vec<VersionId>>, m| {
+        |mut acc: HashMap<ProjectId, Vec<VersionData>>, m| {
             acc.entry(ProjectId(m.mod_id))
                 .or_default()
-                .push(VersionId(m.id));
+                .push(VersionData {
+                    id: VersionId(m.id),
+                    version_number: m.version_number,
+                    version_type: m.version_type,
+                    listed: VersionStatus::from_string(&m.status).is_listed(),
+                    date_published: m.date_published,
+                });
             async move { Ok(acc) }
         },
     )
@@ -436,9 +474,9 @@ async fn index_versions(
     }
 
     let all_version_ids = versions
-        .iter()
-        .flat_map(|(_, version_ids)| version_ids.iter())
-        .map(|x| x.0)
+        .values()
+        .flatten()
+        .map(|x| x.id.0)
         .collect::<Vec<i64>>();
 
     let loaders_ptypes: DashMap<VersionId, VersionLoaderData> = sqlx::query!(
@@ -498,8 +536,9 @@ async fn index_versions(
 
     // Convert to partial versions
     let mut res_versions: HashMap<ProjectId, Vec<PartialVersion>> = HashMap::new();
-    for (project_id, version_ids) in versions.iter() {
-        for version_id in version_ids {
+    for (project_id, versions) in versions {
+        for version in versions {
+            let version_id = &version.id;
             // Extract version-specific data fetched
             // We use 'remove' as every version is only in the map once
             let version_loader_data = loaders_ptypes
@@ -513,10 +552,14 @@ async fn index_versions(
                 .unwrap_or_default();
 
             res_versions
-                .entry(*project_id)
+                .entry(project_id)
                 .or_default()
                 .push(PartialVersion {
-                    id: *version_id,
+                    id: version.id,
+                    version_number: version.version_number,
+                    version_type: version.version_type,
+                    listed: version.listed,
+                    date_published: version.date_published,
                     loaders: version_loader_data.loaders,
                     project_types: version_loader_data.project_types,
                     version_fields,
@@ -526,3 +569,98 @@ async fn index_versions(
 
     Ok(res_versions)
 }
+
+/// Builds the search documents of every user
+pub async fn index_users(pool: &PgPool) -> Result<Vec<SearchUser>, IndexingError> {
+    info!("Indexing local users!");
+
+    let users = sqlx::query!(
+        "
+        SELECT u.id, u.username, u.bio, u.avatar_url, u.role, u.badges, u.created
+        FROM users u
+        WHERE u.id != $1
+        ",
+        DELETED_USER.0 as i64,
+    )
+    .fetch(pool)
+    .map_ok(|m| SearchUser {
+        id: to_base62(m.id as u64),
+        username: m.username,
+        bio: m.bio,
+        avatar_url: m.avatar_url,
+        role: m.role,
+        badges: Badges::from_bits_truncate(m.badges as u64)
+            .iter_names()
+            .map(|(name, _)| name.to_lowercase())
+            .collect(),
+        created: m.created,
+    })
+    .try_collect::<Vec<_>>()
+    .await?;
+
+    Ok(users)
+}
+
+/// Builds the search documents of every organization
+pub async fn index_organizations(pool: &PgPool) -> Result<Vec<SearchOrganization>, IndexingError> {
+    info!("Indexing local organizations!");
+
+    let organizations = sqlx::query!(
+        "
+        SELECT o.id, o.slug, o.name, o.description, o.icon_url, o.color
+        FROM organizations o
+        "
+    )
+    .fetch(pool)
+    .map_ok(|m| SearchOrganization {
+        id: to_base62(m.id as u64),
+        slug: m.slug,
+        name: m.name,
+        description: m.description,
+        icon_url: m.icon_url,
+        color: m.color.map(|x| x as u32),
+    })
+    .try_collect::<Vec<_>>()
+    .await?;
+
+    Ok(organizations)
+}
+
+/// Builds the search documents of every listed collection. Other collections are not public,
+/// so they have no documents.
+pub async fn index_collections(pool: &PgPool) -> Result<Vec<SearchCollection>, IndexingError> {
+    info!("Indexing local collections!");
+
+    let collections = sqlx::query!(
+        "
+        SELECT c.id, c.user_id, c.name, c.description, c.icon_url, c.color, c.created, c.updated,
+        ARRAY_AGG(DISTINCT cm.mod_id) filter (where cm.mod_id is not null) mods
+        FROM collections c
+        LEFT JOIN collections_mods cm ON cm.collection_id = c.id
+        WHERE c.status = $1
+        GROUP BY c.id
+        ",
+        CollectionStatus::Listed.as_str(),
+    )
+    .fetch(pool)
+    .map_ok(|m| SearchCollection {
+        id: to_base62(m.id as u64),
+        user: to_base62(m.user_id as u64),
+        name: m.name,
+        description: m.description,
+        icon_url: m.icon_url,
+        color: m.color.map(|x| x as u32),
+        projects: m
+            .mods
+            .unwrap_or_default()
+            .into_iter()
+            .map(|x| to_base62(x as u64))
+            .collect(),
+        created: m.created,
+        updated: m.updated,
+    })
+    .try_collect::<Vec<_>>()
+    .await?;
+
+    Ok(collections)
+}
@@ -1,15 +1,15 @@
 /// This module is used for the indexing from any source.
 pub mod local_import;
 
+use crate::database::models::loader_fields::LoaderField;
+use crate::database::models::ProjectId;
 use crate::database::redis::RedisPool;
 use crate::models::ids::base62_impl::to_base62;
-use crate::search::{SearchConfig, UploadSearchProject};
-use local_import::index_local;
+use crate::search::backend::{to_documents, Document};
+use crate::search::SearchConfig;
+use itertools::Itertools;
+use local_import::{index_collections, index_local, index_organizations, index_users};
 use log::info;
-use meilisearch_sdk::client::Client;
-use meilisearch_sdk::indexes::Index;
-use meilisearch_sdk::settings::{PaginationSetting, Settings};
-use meilisearch_sdk::SwapIndexes;
 use sqlx::postgres::PgPool;
 use thiserror::Error;
 #[derive(Error, Debug)]
@@ -28,272 +28,209 @@ pub enum IndexingError {
     Task,
 }
 
-// The chunk size for adding projects to the indexing database. If the request size
-// is too large (>10MiB) then the request fails with an error.  This chunk size
-// assumes a max average size of 4KiB per project to avoid this cap.
-const MEILISEARCH_CHUNK_SIZE: usize = 10000000;
-const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
-
-pub async fn remove_documents(
-    ids: &[crate::models::ids::VersionId],
-    config: &SearchConfig,
-) -> Result<(), meilisearch_sdk::errors::Error> {
-    let mut indexes = get_indexes_for_indexing(config, false).await?;
-    let mut indexes_next = get_indexes_for_indexing(config, true).await?;
-    indexes.append(&mut indexes_next);
-
-    for index in indexes {
-        index
-            .delete_documents(&ids.iter().map(|x| to_base62(x.0)).collect::<Vec<_>>())
-            .await?;
-    }
-
-    Ok(())
+/// The project indexes. Both hold the same documents, but rank them differently.
+pub const PROJECT_INDEXES: &[&str] = &["projects", "projects_filtered"];
+
+/// The index of users, holding one document per user
+pub const USERS_INDEX: &str = "users";
+/// The index of organizations, holding one document per organization
+pub const ORGANIZATIONS_INDEX: &str = "organizations";
+/// The index of collections, holding one document per listed collection
+pub const COLLECTIONS_INDEX: &str = "collections";
+
+/// How the documents of an index are stored, searched and returned
+pub struct IndexSettings {
+    /// The attribute identifying each document
+    pub primary_key: &'static str,
+    /// The attribute by which documents are collapsed into the best ranked one, if any
+    pub distinct_attribute: Option<&'static str>,
+    /// The attributes returned in search results, where `*` stands for all of them
+    pub displayed_attributes: &'static [&'static str],
+    /// The attributes matched against the query, most relevant first
+    pub searchable_attributes: &'static [&'static str],
+    /// The attributes which can be filtered and faceted on. Projects can also be filtered on
+    /// their loader fields, see [`filterable_attributes`].
+    pub filterable_attributes: &'static [&'static str],
+    pub sortable_attributes: &'static [&'static str],
+    pub ranking_rules: &'static [&'static str],
 }
 
-pub async fn index_projects(
-    pool: PgPool,
-    redis: RedisPool,
-    config: &SearchConfig,
-) -> Result<(), IndexingError> {
-    info!("Indexing projects.");
-
-    // First, ensure current index exists (so no error happens- current index should be worst-case empty, not missing)
-    get_indexes_for_indexing(config, false).await?;
-
-    // Then, delete the next index if it still exists
-    let indices = get_indexes_for_indexing(config, true).await?;
-    for index in indices {
-        index.delete().await?;
-    }
-    // Recreate the next index for indexing
-    let indices = get_indexes_for_indexing(config, true).await?;
-
-    let all_loader_fields =
-        crate::database::models::loader_fields::LoaderField::get_fields_all(&pool, &redis)
-            .await?
-            .into_iter()
-            .map(|x| x.field)
-            .collect::<Vec<_>>();
-
-    let uploads = index_local(&pool).await?;
-    add_projects(&indices, uploads, all_loader_fields.clone(), config).await?;
-
-    // Swap the index
-    swap_index(config, "projects").await?;
-    swap_index(config, "projects_filtered").await?;
-
-    // Delete the now-old index
-    for index in indices {
-        index.delete().await?;
+/// The settings of an index. Any index which is not a user, organization or collection index
+/// is a project index.
+pub fn index_settings(index: &str) -> IndexSettings {
+    match index {
+        USERS_INDEX => IndexSettings {
+            primary_key: "id",
+            distinct_attribute: None,
+            displayed_attributes: &["*"],
+            searchable_attributes: &["username", "bio"],
+            filterable_attributes: &["badges", "role"],
+            sortable_attributes: &["created"],
+            ranking_rules: ranking_rules(index),
+        },
+        ORGANIZATIONS_INDEX => IndexSettings {
+            primary_key: "id",
+            distinct_attribute: None,
+            displayed_attributes: &["*"],
+            searchable_attributes: &["name", "slug", "description"],
+            filterable_attributes: &[],
+            sortable_attributes: &[],
+            ranking_rules: ranking_rules(index),
+        },
+        COLLECTIONS_INDEX => IndexSettings {
+            primary_key: "id",
+            distinct_attribute: None,
+            displayed_attributes: &["*"],
+            searchable_attributes: &["name", "description"],
+            filterable_attributes: &["user", "projects"],
+            sortable_attributes: &["created", "updated"],
+            ranking_rules: ranking_rules(index),
+        },
+        _ => IndexSettings {
+            primary_key: "version_id",
+            distinct_attribute: Some("project_id"),
+            displayed_attributes: DEFAULT_DISPLAYED_ATTRIBUTES,
+            searchable_attributes: DEFAULT_SEARCHABLE_ATTRIBUTES,
+            filterable_attributes: DEFAULT_ATTRIBUTES_FOR_FACETING,
+            sortable_attributes: DEFAULT_SORTABLE_ATTRIBUTES,
+            ranking_rules: ranking_rules(index),
+        },
     }
-
-    info!("Done adding projects.");
-    Ok(())
-}
-
-pub async fn swap_index(config: &SearchConfig, index_name: &str) -> Result<(), IndexingError> {
-    let client = config.make_client();
-    let index_name_next = config.get_index_name(index_name, true);
-    let index_name = config.get_index_name(index_name, false);
-    let swap_indices = SwapIndexes {
-        indexes: (index_name_next, index_name),
-    };
-    client
-        .swap_indexes([&swap_indices])
-        .await?
-        .wait_for_completion(&client, None, Some(TIMEOUT))
-        .await?;
-
-    Ok(())
 }
 
-pub async fn get_indexes_for_indexing(
-    config: &SearchConfig,
-    next: bool, // Get the 'next' one
-) -> Result<Vec<Index>, meilisearch_sdk::errors::Error> {
-    let client = config.make_client();
-    let project_name = config.get_index_name("projects", next);
-    let project_filtered_name = config.get_index_name("projects_filtered", next);
-    let projects_index = create_or_update_index(
-        &client,
-        &project_name,
-        Some(&[
+/// The ranking rules of an index, in the order they are applied. The filtered index sorts before
+/// ranking by relevance, so that sorting by downloads takes precedence over the query.
+pub fn ranking_rules(index: &str) -> &'static [&'static str] {
+    match index {
+        "projects_filtered" => &[
+            "sort",
             "words",
             "typo",
             "proximity",
             "attribute",
             "exactness",
-            "sort",
-        ]),
-    )
-    .await?;
-    let projects_filtered_index = create_or_update_index(
-        &client,
-        &project_filtered_name,
-        Some(&[
-            "sort",
+        ],
+        _ => &[
             "words",
             "typo",
             "proximity",
             "attribute",
             "exactness",
-        ]),
-    )
-    .await?;
-
-    Ok(vec![projects_index, projects_filtered_index])
+            "sort",
+        ],
+    }
 }
 
-async fn create_or_update_index(
-    client: &Client,
-    name: &str,
-    custom_rules: Option<&'static [&'static str]>,
-) -> Result<Index, meilisearch_sdk::errors::Error> {
-    info!("Updating/creating index {}", name);
-
-    match client.get_index(name).await {
-        Ok(index) => {
-            info!("Updating index settings.");
-
-            let mut settings = default_settings();
-
-            if let Some(custom_rules) = custom_rules {
-                settings = settings.with_ranking_rules(custom_rules);
-            }
-
-            info!("Performing index settings set.");
-            index
-                .set_settings(&settings)
-                .await?
-                .wait_for_completion(client, None, Some(TIMEOUT))
-                .await?;
-            info!("Done performing index settings set.");
-
-            Ok(index)
-        }
-        _ => {
-            info!("Creating index.");
-
-            // Only create index and set settings if the index doesn't already exist
-            let task = client.create_index(name, Some("version_id")).await?;
-            let task = task
-                .wait_for_completion(client, None, Some(TIMEOUT))
-                .await?;
-            let index = task
-                .try_make_index(client)
-                .map_err(|x| x.unwrap_failure())?;
-
-            let mut settings = default_settings();
-
-            if let Some(custom_rules) = custom_rules {
-                settings = settings.with_ranking_rules(custom_rules);
-            }
-
-            index
-                .set_settings(&settings)
-                .await?
-                .wait_for_completion(client, None, Some(TIMEOUT))
-                .await?;
+pub async fn remove_documents(
+    ids: &[crate::models::ids::VersionId],
+    config: &SearchConfig,
+) -> Result<(), IndexingError> {
+    let ids = ids.iter().map(|x| to_base62(x.0)).collect::<Vec<_>>();
 
-            Ok(index)
-        }
+    for index in PROJECT_INDEXES {
+        config.backend.delete_documents(index, &ids).await?;
     }
+
+    Ok(())
 }
 
-async fn add_to_index(
-    client: &Client,
-    index: &Index,
-    mods: &[UploadSearchProject],
+/// Reindexes the given projects in the current indexes, removing the documents of any which
+/// were deleted or are no longer searchable
+pub async fn update_projects(
+    project_ids: &[ProjectId],
+    pool: &PgPool,
+    config: &SearchConfig,
 ) -> Result<(), IndexingError> {
-    for chunk in mods.chunks(MEILISEARCH_CHUNK_SIZE) {
-        info!(
-            "Adding chunk starting with version id {}",
-            chunk[0].version_id
-        );
-        index
-            .add_or_replace(chunk, Some("version_id"))
-            .await?
-            .wait_for_completion(client, None, Some(std::time::Duration::from_secs(3600)))
+    let uploads = to_documents(&index_local(pool, Some(project_ids)).await?)?;
+    let project_ids = project_ids
+        .iter()
+        .map(|x| crate::models::ids::ProjectId::from(*x).to_string())
+        .collect::<Vec<_>>();
+
+    for index in PROJECT_INDEXES {
+        config
+            .backend
+            .replace_projects(index, &project_ids, &uploads)
             .await?;
-        info!("Added chunk of {} projects to index", chunk.len());
     }
 
     Ok(())
 }
 
-async fn update_and_add_to_index(
-    client: &Client,
-    index: &Index,
-    projects: &[UploadSearchProject],
-    _additional_fields: &[String],
+pub async fn index_projects(
+    pool: PgPool,
+    redis: &RedisPool,
+    config: &SearchConfig,
 ) -> Result<(), IndexingError> {
-    // TODO: Uncomment this- hardcoding loader_fields is a band-aid fix, and will be fixed soon
-    // let mut new_filterable_attributes: Vec<String> = index.get_filterable_attributes().await?;
-    // let mut new_displayed_attributes = index.get_displayed_attributes().await?;
+    info!("Indexing projects.");
 
-    // // Check if any 'additional_fields' are not already in the index
-    // // Only add if they are not already in the index
-    // let new_fields = additional_fields
-    //     .iter()
-    //     .filter(|x| !new_filterable_attributes.contains(x))
-    //     .collect::<Vec<_>>();
-    // if !new_fields.is_empty() {
-    //     info!("Adding new fields to index: {:?}", new_fields);
-    //     new_filterable_attributes.extend(new_fields.iter().map(|s: &&String| s.to_string()));
-    //     new_displayed_attributes.extend(new_fields.iter().map(|s| s.to_string()));
+    let loader_fields = LoaderField::get_fields_all(&pool, redis).await?;
+    let uploads = to_documents(&index_local(&pool, None).await?)?;
+    reindex(
+        PROJECT_INDEXES,
+        &filterable_attributes(&loader_fields),
+        &uploads,
+        config,
+    )
+    .await?;
 
-    //     // Adds new fields to the index
-    //     let filterable_task = index
-    //         .set_filterable_attributes(new_filterable_attributes)
-    //         .await?;
-    //     let displayable_task = index
-    //         .set_displayed_attributes(new_displayed_attributes)
-    //         .await?;
+    info!("Done adding projects.");
 
-    //     // Allow a long timeout for adding new attributes- it only needs to happen the once
-    //     filterable_task
-    //         .wait_for_completion(client, None, Some(TIMEOUT * 100))
-    //         .await?;
-    //     displayable_task
-    //         .wait_for_completion(client, None, Some(TIMEOUT * 100))
-    //         .await?;
-    // }
+    let users = to_documents(&index_users(&pool).await?)?;
+    reindex_entities(USERS_INDEX, &users, config).await?;
 
-    info!("Adding to index.");
+    let organizations = to_documents(&index_organizations(&pool).await?)?;
+    reindex_entities(ORGANIZATIONS_INDEX, &organizations, config).await?;
 
-    add_to_index(client, index, projects).await?;
+    let collections = to_documents(&index_collections(&pool).await?)?;
+    reindex_entities(COLLECTIONS_INDEX, &collections, config).await?;
 
+    info!("Done adding users, organizations and collections.");
     Ok(())
 }
 
-pub async fn add_projects(
-    indices: &[Index],
-    projects: Vec<UploadSearchProject>,
-    additional_fields: Vec<String>,
+/// Replaces the documents of an index which filters on its static attributes only
+async fn reindex_entities(
+    index: &str,
+    documents: &[Document],
     config: &SearchConfig,
 ) -> Result<(), IndexingError> {
-    let client = config.make_client();
-    for index in indices {
-        update_and_add_to_index(&client, index, &projects, &additional_fields).await?;
-    }
+    let filterable_attributes = index_settings(index)
+        .filterable_attributes
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>();
 
-    Ok(())
+    reindex(&[index], &filterable_attributes, documents, config).await
 }
 
-fn default_settings() -> Settings {
-    Settings::new()
-        .with_distinct_attribute("project_id")
-        .with_displayed_attributes(DEFAULT_DISPLAYED_ATTRIBUTES)
-        .with_searchable_attributes(DEFAULT_SEARCHABLE_ATTRIBUTES)
-        .with_sortable_attributes(DEFAULT_SORTABLE_ATTRIBUTES)
-        .with_filterable_attributes(DEFAULT_ATTRIBUTES_FOR_FACETING)
-        .with_pagination(PaginationSetting {
-            max_total_hits: 2147483647,
-        })
+/// Fills the next copy of each index with the documents and swaps them in once all are filled
+async fn reindex(
+    indexes: &[&str],
+    filterable_attributes: &[String],
+    documents: &[Document],
+    config: &SearchConfig,
+) -> Result<(), IndexingError> {
+    for index in indexes {
+        config
+            .backend
+            .reset_next_index(index, filterable_attributes)
+            .await?;
+    }
+
+    for index in indexes {
+        config.backend.add_documents(index, true, documents).await?;
+    }
+
+    // Swap the indexes
+    for index in indexes {
+        config.backend.swap_index(index).await?;
+    }
+
+    Ok(())
 }
 
-const DEFAULT_DISPLAYED_ATTRIBUTES: &[&str] = &[
+pub const DEFAULT_DISPLAYED_ATTRIBUTES: &[&str] = &[
     "project_id",
     "version_id",
     "project_types",
@@ -313,6 +250,10 @@ const DEFAULT_DISPLAYED_ATTRIBUTES: &[&str] = &[
     "gallery",
     "featured_gallery",
     "color",
+    "version_number",
+    "version_type",
+    "version_loaders",
+    "version_date_published",
     // Note: loader fields are not here, but are added on as they are needed (so they can be dynamically added depending on which exist).
     // TODO: remove these- as they should be automatically populated. This is a band-aid fix.
     "server_only",
@@ -321,6 +262,9 @@ const DEFAULT_DISPLAYED_ATTRIBUTES: &[&str] = &[
     "singleplayer",
     "client_and_server",
     "mrpack_loaders",
+    "min_engine_version",
+    "min_engine_version_number",
+    "bedrock_modules",
     // V2 legacy fields for logical consistency
     "client_side",
     "server_side",
@@ -342,9 +286,9 @@ const DEFAULT_DISPLAYED_ATTRIBUTES: &[&str] = &[
     "project_loader_fields",
 ];
 
-const DEFAULT_SEARCHABLE_ATTRIBUTES: &[&str] = &["name", "summary", "author", "slug"];
+pub const DEFAULT_SEARCHABLE_ATTRIBUTES: &[&str] = &["name", "summary", "author", "slug"];
 
-const DEFAULT_ATTRIBUTES_FOR_FACETING: &[&str] = &[
+pub const DEFAULT_ATTRIBUTES_FOR_FACETING: &[&str] = &[
     "categories",
     "license",
     "project_types",
@@ -359,18 +303,33 @@ const DEFAULT_ATTRIBUTES_FOR_FACETING: &[&str] = &[
     "project_id",
     "open_source",
     "color",
-    // Note: loader fields are not here, but are added on as they are needed (so they can be dynamically added depending on which exist).
-    // TODO: remove these- as they should be automatically populated. This is a band-aid fix.
-    "server_only",
-    "client_only",
-    "game_versions",
-    "singleplayer",
-    "client_and_server",
-    "mrpack_loaders",
+    "loaders",
+    "version_type",
+    "version_loaders",
+    "version_listed",
+    "version_published_timestamp",
+    // Note: loader fields are not here, see `filterable_attributes`.
     // V2 legacy fields for logical consistency
     "client_side",
     "server_side",
 ];
 
-const DEFAULT_SORTABLE_ATTRIBUTES: &[&str] =
-    &["downloads", "follows", "date_created", "date_modified"];
+/// The attributes which can be filtered and faceted on: the project attributes of
+/// [`DEFAULT_ATTRIBUTES_FOR_FACETING`] and every loader field
+pub fn filterable_attributes(loader_fields: &[LoaderField]) -> Vec<String> {
+    DEFAULT_ATTRIBUTES_FOR_FACETING
+        .iter()
+        .map(|x| x.to_string())
+        .chain(loader_fields.iter().map(|x| x.field.clone()))
+        .unique()
+        .collect()
+}
+
+pub const DEFAULT_SORTABLE_ATTRIBUTES: &[&str] = &[
+    "downloads",
+    "follows",
+    "trending",
+    "date_created",
+    "date_modified",
+    "version_published_timestamp",
+];
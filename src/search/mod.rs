@@ -1,19 +1,26 @@
+use crate::database::models::loader_fields::{LoaderField, LoaderFieldEnumValue};
+use crate::database::redis::RedisPool;
 use crate::models::error::ApiError;
 use crate::models::projects::SearchRequest;
 use actix_web::http::StatusCode;
 use actix_web::HttpResponse;
 use chrono::{DateTime, Utc};
-use itertools::Itertools;
-use meilisearch_sdk::client::Client;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::borrow::Cow;
-use std::collections::HashMap;
-use std::fmt::Write;
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use thiserror::Error;
 
+pub mod backend;
+pub mod filter;
 pub mod indexing;
 
+use backend::{Document, EmbeddedBackend, MeilisearchBackend, SearchBackend, SearchQuery};
+use filter::{CompareOp, Filter, FilterError};
+use indexing::{filterable_attributes, index_settings};
+
 #[derive(Error, Debug)]
 pub enum SearchError {
     #[error("MeiliSearch Error: {0}")]
@@ -26,19 +33,31 @@ pub enum SearchError {
     FormatError(#[from] std::fmt::Error),
     #[error("Environment Error")]
     Env(#[from] dotenvy::Error),
+    #[error("Database Error: {0}")]
+    Database(#[from] crate::database::models::DatabaseError),
     #[error("Invalid index to sort by: {0}")]
     InvalidIndex(String),
+    #[error("Invalid search filter: {0}")]
+    InvalidFilter(#[from] FilterError),
+    #[error("Invalid facet to count: {0}")]
+    InvalidFacet(String),
+    #[error("Invalid search mode: {0}")]
+    InvalidMode(String),
 }
 
 impl actix_web::ResponseError for SearchError {
     fn status_code(&self) -> StatusCode {
         match self {
             SearchError::Env(..) => StatusCode::INTERNAL_SERVER_ERROR,
+            SearchError::Database(..) => StatusCode::INTERNAL_SERVER_ERROR,
             SearchError::MeiliSearch(..) => StatusCode::BAD_REQUEST,
             SearchError::Serde(..) => StatusCode::BAD_REQUEST,
             SearchError::IntParsing(..) => StatusCode::BAD_REQUEST,
             SearchError::InvalidIndex(..) => StatusCode::BAD_REQUEST,
             SearchError::FormatError(..) => StatusCode::BAD_REQUEST,
+            SearchError::InvalidFilter(..) => StatusCode::BAD_REQUEST,
+            SearchError::InvalidFacet(..) => StatusCode::BAD_REQUEST,
+            SearchError::InvalidMode(..) => StatusCode::BAD_REQUEST,
         }
     }
 
@@ -46,46 +65,40 @@ impl actix_web::ResponseError for SearchError {
         HttpResponse::build(self.status_code()).json(ApiError {
             error: match self {
                 SearchError::Env(..) => "environment_error",
+                SearchError::Database(..) => "database_error",
                 SearchError::MeiliSearch(..) => "meilisearch_error",
                 SearchError::Serde(..) => "invalid_input",
                 SearchError::IntParsing(..) => "invalid_input",
                 SearchError::InvalidIndex(..) => "invalid_input",
                 SearchError::FormatError(..) => "invalid_input",
+                SearchError::InvalidFilter(..) => "invalid_input",
+                SearchError::InvalidFacet(..) => "invalid_input",
+                SearchError::InvalidMode(..) => "invalid_input",
             },
             description: self.to_string(),
         })
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct SearchConfig {
-    pub address: String,
-    pub key: String,
-    pub meta_namespace: String,
+    pub backend: Arc<dyn SearchBackend + Send + Sync>,
 }
 
 impl SearchConfig {
-    // Panics if the environment variables are not set,
-    // but these are already checked for on startup.
+    /// Creates the search backend selected by `SEARCH_BACKEND`, which is either `meilisearch`
+    /// (the default) or `embedded`. Meilisearch index names are prefixed with the namespace.
+    ///
+    /// Panics if the backend's environment variables are not set,
+    /// but these are already checked for on startup.
     pub fn new(meta_namespace: Option<String>) -> Self {
-        let address = dotenvy::var("MEILISEARCH_ADDR").expect("MEILISEARCH_ADDR not set");
-        let key = dotenvy::var("MEILISEARCH_KEY").expect("MEILISEARCH_KEY not set");
-
-        Self {
-            address,
-            key,
-            meta_namespace: meta_namespace.unwrap_or_default(),
-        }
-    }
-
-    pub fn make_client(&self) -> Client {
-        Client::new(self.address.as_str(), Some(self.key.as_str()))
-    }
+        let backend: Arc<dyn SearchBackend + Send + Sync> =
+            match dotenvy::var("SEARCH_BACKEND").as_deref() {
+                Ok("embedded") => Arc::new(EmbeddedBackend::new()),
+                _ => Arc::new(MeilisearchBackend::new(meta_namespace)),
+            };
 
-    // Next: true if we want the next index (we are preparing the next swap), false if we want the current index (searching)
-    pub fn get_index_name(&self, index: &str, next: bool) -> String {
-        let alt = if next { "_alt" } else { "" };
-        format!("{}_{}_{}", self.meta_namespace, index, alt)
+        Self { backend }
     }
 }
 
@@ -105,6 +118,8 @@ pub struct UploadSearchProject {
     pub display_categories: Vec<String>,
     pub follows: i32,
     pub downloads: i32,
+    /// How much the project is trending, from its recent downloads and follows
+    pub trending: f64,
     pub icon_url: Option<String>,
     pub license: String,
     pub gallery: Vec<String>,
@@ -120,6 +135,19 @@ pub struct UploadSearchProject {
     pub open_source: bool,
     pub color: Option<u32>,
 
+    // Fields of the document's version alone, unlike the loaders and loader fields aggregated
+    // from all versions of the project. The document's loader fields are also its version's.
+    pub version_number: String,
+    /// Whether the version is a release, beta or alpha
+    pub version_type: String,
+    pub version_loaders: Vec<String>,
+    /// Whether the version is listed on the project, rather than being unlisted or hidden
+    pub version_listed: bool,
+    /// RFC 3339 formatted publication date of the version
+    pub version_date_published: DateTime<Utc>,
+    /// Unix timestamp of the publication date of the version
+    pub version_published_timestamp: i64,
+
     // Hidden fields to get the Project model out of the search results.
     pub loaders: Vec<String>, // Search uses loaders as categories- this is purely for the Project model.
     pub project_loader_fields: HashMap<String, Vec<serde_json::Value>>, // Aggregation of loader_fields from all versions of the project, allowing for reconstruction of the Project model.
@@ -129,11 +157,34 @@ pub struct UploadSearchProject {
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-pub struct SearchResults {
-    pub hits: Vec<ResultSearchProject>,
+pub struct SearchResults<T = ResultSearchProject> {
+    pub hits: Vec<T>,
     pub page: usize,
     pub hits_per_page: usize,
     pub total_hits: usize,
+    /// The number of matching projects for each value of the requested facets, if any were
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub facet_distribution: Option<FacetDistribution>,
+}
+
+/// Project counts by facet and then by value
+pub type FacetDistribution = HashMap<String, HashMap<String, usize>>;
+
+impl SearchResults<Document> {
+    /// Converts the returned documents to their model
+    pub fn deserialize_hits<T: DeserializeOwned>(self) -> Result<SearchResults<T>, SearchError> {
+        Ok(SearchResults {
+            hits: self
+                .hits
+                .into_iter()
+                .map(|x| serde_json::from_value(Value::Object(x)))
+                .collect::<Result<_, _>>()?,
+            page: self.page,
+            hits_per_page: self.hits_per_page,
+            total_hits: self.total_hits,
+            facet_distribution: self.facet_distribution,
+        })
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -159,6 +210,17 @@ pub struct ResultSearchProject {
     pub featured_gallery: Option<String>,
     pub color: Option<u32>,
 
+    // The version which matched the search, only returned in the `versions` search mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_number: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_loaders: Option<Vec<String>>,
+    /// RFC 3339 formatted publication date of the version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_date_published: Option<String>,
+
     // Hidden fields to get the Project model out of the search results.
     pub loaders: Vec<String>, // Search uses loaders as categories- this is purely for the Project model.
     pub project_loader_fields: HashMap<String, Vec<serde_json::Value>>, // Aggregation of loader_fields from all versions of the project, allowing for reconstruction of the Project model.
@@ -167,18 +229,67 @@ pub struct ResultSearchProject {
     pub loader_fields: HashMap<String, Vec<serde_json::Value>>,
 }
 
-pub fn get_sort_index(
-    config: &SearchConfig,
-    index: &str,
-) -> Result<(String, [&'static str; 1]), SearchError> {
-    let projects_name = config.get_index_name("projects", false);
-    let projects_filtered_name = config.get_index_name("projects_filtered", false);
+/// A user document of the users index, which is also returned in search results
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SearchUser {
+    pub id: String,
+    pub username: String,
+    pub bio: Option<String>,
+    pub avatar_url: Option<String>,
+    pub role: String,
+    /// The names of the user's badges, such as `early_modpack_adopter`
+    pub badges: Vec<String>,
+    pub created: DateTime<Utc>,
+}
+
+/// An organization document of the organizations index, which is also returned in search results
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SearchOrganization {
+    pub id: String,
+    pub slug: String,
+    pub name: String,
+    pub description: String,
+    pub icon_url: Option<String>,
+    pub color: Option<u32>,
+}
+
+/// A collection document of the collections index, which is also returned in search results
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SearchCollection {
+    pub id: String,
+    /// The id of the user owning the collection
+    pub user: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub icon_url: Option<String>,
+    pub color: Option<u32>,
+    /// The ids of the projects in the collection
+    pub projects: Vec<String>,
+    pub created: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+}
+
+/// A search of the users, organizations or collections index
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EntitySearchRequest {
+    pub query: Option<String>,
+    /// A filter in the same syntax as the `new_filters` of project search
+    pub filters: Option<String>,
+    /// The attribute to sort by after relevance, such as `created:desc`
+    pub sort: Option<String>,
+    pub offset: Option<String>,
+    pub limit: Option<String>,
+}
+
+/// Returns the index to search and the sort to apply for a sort option
+pub fn get_sort_index(index: &str) -> Result<(&'static str, [&'static str; 1]), SearchError> {
     Ok(match index {
-        "relevance" => (projects_name, ["downloads:desc"]),
-        "downloads" => (projects_filtered_name, ["downloads:desc"]),
-        "follows" => (projects_name, ["follows:desc"]),
-        "updated" => (projects_name, ["date_modified:desc"]),
-        "newest" => (projects_name, ["date_created:desc"]),
+        "relevance" => ("projects", ["downloads:desc"]),
+        "downloads" => ("projects_filtered", ["downloads:desc"]),
+        "trending" => ("projects_filtered", ["trending:desc"]),
+        "follows" => ("projects", ["follows:desc"]),
+        "updated" => ("projects", ["date_modified:desc"]),
+        "newest" => ("projects", ["date_created:desc"]),
         i => return Err(SearchError::InvalidIndex(i.to_string())),
     })
 }
@@ -186,119 +297,220 @@ pub fn get_sort_index(
 pub async fn search_for_project(
     info: &SearchRequest,
     config: &SearchConfig,
+    pool: &PgPool,
+    redis: &RedisPool,
 ) -> Result<SearchResults, SearchError> {
-    let client = Client::new(&*config.address, Some(&*config.key));
+    search_for_project_filtered(info, None, config, pool, redis).await
+}
 
-    let offset: usize = info.offset.as_deref().unwrap_or("0").parse()?;
+/// Searches projects like `search_for_project`, only returning those which also match `restrict`
+pub async fn search_for_project_filtered(
+    info: &SearchRequest,
+    restrict: Option<Filter>,
+    config: &SearchConfig,
+    pool: &PgPool,
+    redis: &RedisPool,
+) -> Result<SearchResults, SearchError> {
     let index = info.index.as_deref().unwrap_or("relevance");
-    let limit = info
-        .limit
-        .as_deref()
-        .unwrap_or("10")
-        .parse::<usize>()?
-        .min(100);
-
-    let sort = get_sort_index(config, index)?;
-    let meilisearch_index = client.get_index(sort.0).await?;
-
-    let mut filter_string = String::new();
-
-    // Convert offset and limit to page and hits_per_page
-    let hits_per_page = limit;
-    let page = offset / limit + 1;
-
-    let results = {
-        let mut query = meilisearch_index.search();
-        query
-            .with_page(page)
-            .with_hits_per_page(hits_per_page)
-            .with_query(info.query.as_deref().unwrap_or_default())
-            .with_sort(&sort.1);
-
-        if let Some(new_filters) = info.new_filters.as_deref() {
-            query.with_filter(new_filters);
-        } else {
-            let facets = if let Some(facets) = &info.facets {
-                Some(serde_json::from_str::<Vec<Vec<Value>>>(facets)?)
-            } else {
-                None
-            };
-
-            let filters: Cow<_> = match (info.filters.as_deref(), info.version.as_deref()) {
-                (Some(f), Some(v)) => format!("({f}) AND ({v})").into(),
-                (Some(f), None) => f.into(),
-                (None, Some(v)) => v.into(),
-                (None, None) => "".into(),
-            };
+    let (index, sort) = get_sort_index(index)?;
+    let (page, hits_per_page) = get_page(info.offset.as_deref(), info.limit.as_deref())?;
+
+    // In the versions mode, filters apply to each version on its own, and the best matching
+    // version of each project is returned
+    let versions_mode = match info.mode.as_deref() {
+        None | Some("projects") => false,
+        Some("versions") => true,
+        Some(mode) => return Err(SearchError::InvalidMode(mode.to_string())),
+    };
 
-            if let Some(facets) = facets {
-                // Search can now *optionally* have a third inner array: So Vec(AND)<Vec(OR)<Vec(AND)< _ >>>
-                // For every inner facet, we will check if it can be deserialized into a Vec<&str>, and do so.
-                // If not, we will assume it is a single facet and wrap it in a Vec.
-                let facets: Vec<Vec<Vec<String>>> = facets
-                    .into_iter()
-                    .map(|facets| {
-                        facets
-                            .into_iter()
-                            .map(|facet| {
-                                if facet.is_array() {
-                                    serde_json::from_value::<Vec<String>>(facet).unwrap_or_default()
-                                } else {
-                                    vec![serde_json::from_value::<String>(facet)
-                                        .unwrap_or_default()]
-                                }
-                            })
-                            .collect_vec()
-                    })
-                    .collect_vec();
-
-                filter_string.push('(');
-                for (index, facet_outer_list) in facets.iter().enumerate() {
-                    filter_string.push('(');
-
-                    for (facet_outer_index, facet_inner_list) in facet_outer_list.iter().enumerate()
-                    {
-                        filter_string.push('(');
-                        for (facet_inner_index, facet) in facet_inner_list.iter().enumerate() {
-                            filter_string.push_str(&facet.replace(':', " = "));
-                            if facet_inner_index != (facet_inner_list.len() - 1) {
-                                filter_string.push_str(" AND ")
-                            }
-                        }
-                        filter_string.push(')');
-
-                        if facet_outer_index != (facet_outer_list.len() - 1) {
-                            filter_string.push_str(" OR ")
-                        }
-                    }
-
-                    filter_string.push(')');
-
-                    if index != (facets.len() - 1) {
-                        filter_string.push_str(" AND ")
-                    }
+    let loader_fields = LoaderField::get_fields_all(pool, redis).await?;
+    let mut fields = filterable_attributes(&loader_fields)
+        .into_iter()
+        .collect::<HashSet<_>>();
+    if versions_mode {
+        // These are aggregated from all of a project's versions, so they would match
+        // combinations which no one version has. `version_loaders` is used instead.
+        fields.remove("loaders");
+    }
+    let ordered = ordered_attributes(&loader_fields, pool, redis).await?;
+
+    let filter = if let Some(new_filters) = info.new_filters.as_deref() {
+        Some(Filter::parse_ordered(new_filters, &fields, &ordered)?)
+    } else {
+        let mut filters = Vec::new();
+
+        if let Some(facets) = &info.facets {
+            let facets = serde_json::from_str::<Vec<Vec<Value>>>(facets)?;
+
+            // Search can now *optionally* have a third inner array: So Vec(AND)<Vec(OR)<Vec(AND)< _ >>>
+            // For every inner facet, we will check if it can be deserialized into a Vec<&str>, and do so.
+            // If not, we will assume it is a single facet and wrap it in a Vec.
+            for facet_outer_list in facets {
+                let mut any = Vec::new();
+
+                for facet in facet_outer_list {
+                    let facet_inner_list = if facet.is_array() {
+                        serde_json::from_value::<Vec<String>>(facet).unwrap_or_default()
+                    } else {
+                        vec![serde_json::from_value::<String>(facet).unwrap_or_default()]
+                    };
+
+                    let all = facet_inner_list
+                        .iter()
+                        .map(|facet| Filter::parse_facet(facet, &fields))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    any.extend(Filter::all(all));
                 }
-                filter_string.push(')');
 
-                if !filters.is_empty() {
-                    write!(filter_string, " AND ({filters})")?;
-                }
-            } else {
-                filter_string.push_str(&filters);
+                filters.push(Filter::any(any));
             }
+        }
+
+        for filter in info.filters.iter().chain(&info.version) {
+            filters.push(Filter::parse_ordered(filter, &fields, &ordered)?);
+        }
+
+        Filter::all(filters)
+    };
+    let filter = if versions_mode {
+        // Hidden and unlisted versions are only indexed for the projects they belong to
+        let listed = Filter::Compare {
+            field: "version_listed".to_string(),
+            op: CompareOp::Eq,
+            value: "true".to_string(),
+        };
+        Filter::all(filter.into_iter().chain(std::iter::once(listed)).collect())
+    } else {
+        filter
+    };
+    let filter = match restrict {
+        Some(restrict) => Filter::all(
+            filter
+                .into_iter()
+                .chain(std::iter::once(restrict))
+                .collect(),
+        ),
+        None => filter,
+    };
+
+    let facets = if let Some(facets) = info.facet_distribution.as_deref() {
+        serde_json::from_str::<Vec<String>>(facets)?
+    } else {
+        Vec::new()
+    };
+    if let Some(facet) = facets.iter().find(|x| !fields.contains(*x)) {
+        return Err(SearchError::InvalidFacet(facet.clone()));
+    }
+
+    let mut sort = sort.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+    if versions_mode {
+        // Of a project's versions which match equally well, the newest is returned
+        sort.push("version_published_timestamp:desc".to_string());
+    }
 
-            if !filter_string.is_empty() {
-                query.with_filter(&filter_string);
+    let query = SearchQuery {
+        query: info.query.clone().unwrap_or_default(),
+        filter,
+        sort,
+        facets,
+        page,
+        hits_per_page,
+    };
+
+    let mut results = config
+        .backend
+        .search(index, &query)
+        .await?
+        .deserialize_hits::<ResultSearchProject>()?;
+    if !versions_mode {
+        // Any version of the project may have been returned, so it is left out
+        for hit in &mut results.hits {
+            hit.version_number = None;
+            hit.version_type = None;
+            hit.version_loaders = None;
+            hit.version_date_published = None;
+        }
+    }
+
+    Ok(results)
+}
+
+/// The values of each enum loader field in order, such as game versions from oldest to newest
+async fn ordered_attributes(
+    loader_fields: &[LoaderField],
+    pool: &PgPool,
+    redis: &RedisPool,
+) -> Result<HashMap<String, Vec<String>>, SearchError> {
+    let mut values =
+        LoaderFieldEnumValue::list_many_loader_fields(loader_fields, pool, redis).await?;
+
+    Ok(loader_fields
+        .iter()
+        .filter_map(|field| {
+            let mut values = values.remove(&field.id)?;
+            values.sort_by_key(|x| (x.ordering, x.created));
+            Some((
+                field.field.clone(),
+                values.into_iter().map(|x| x.value).collect(),
+            ))
+        })
+        .collect())
+}
+
+/// Converts an offset and limit to a page and its number of hits
+fn get_page(offset: Option<&str>, limit: Option<&str>) -> Result<(usize, usize), SearchError> {
+    let offset: usize = offset.unwrap_or("0").parse()?;
+    let limit = limit.unwrap_or("10").parse::<usize>()?.min(100);
+
+    Ok((offset / limit + 1, limit))
+}
+
+/// Searches the users, organizations or collections index, returning its documents as `T`
+pub async fn search_for_entities<T: DeserializeOwned>(
+    index: &str,
+    info: &EntitySearchRequest,
+    config: &SearchConfig,
+) -> Result<SearchResults<T>, SearchError> {
+    let settings = index_settings(index);
+    let (page, hits_per_page) = get_page(info.offset.as_deref(), info.limit.as_deref())?;
+
+    let fields = settings
+        .filterable_attributes
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<HashSet<_>>();
+    let filter = info
+        .filters
+        .as_deref()
+        .map(|x| Filter::parse(x, &fields))
+        .transpose()?;
+
+    let sort = match info.sort.as_deref() {
+        Some(sort) => {
+            let field = sort
+                .strip_suffix(":asc")
+                .or_else(|| sort.strip_suffix(":desc"))
+                .unwrap_or(sort);
+            if !settings.sortable_attributes.contains(&field) {
+                return Err(SearchError::InvalidIndex(sort.to_string()));
             }
+            vec![sort.to_string()]
         }
+        None => Vec::new(),
+    };
 
-        query.execute::<ResultSearchProject>().await?
+    let query = SearchQuery {
+        query: info.query.clone().unwrap_or_default(),
+        filter,
+        sort,
+        facets: Vec::new(),
+        page,
+        hits_per_page,
     };
 
-    Ok(SearchResults {
-        hits: results.hits.into_iter().map(|r| r.result).collect(),
-        page: results.page.unwrap_or_default(),
-        hits_per_page: results.hits_per_page.unwrap_or_default(),
-        total_hits: results.total_hits.unwrap_or_default(),
-    })
+    config
+        .backend
+        .search(index, &query)
+        .await?
+        .deserialize_hits()
 }
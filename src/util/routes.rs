@@ -1,9 +1,13 @@
+use crate::file_hosting::{FileHasher, FileHashes, FileHostingError, FileStream};
 use crate::routes::v3::project_creation::CreateError;
 use crate::routes::ApiError;
 use actix_multipart::Field;
 use actix_web::web::Payload;
-use bytes::BytesMut;
-use futures::StreamExt;
+use bytes::{Bytes, BytesMut};
+use futures::{Stream, StreamExt};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+use tokio_util::io::ReaderStream;
 
 pub async fn read_from_payload(
     payload: &mut Payload,
@@ -38,3 +42,95 @@ pub async fn read_from_field(
     }
     Ok(bytes)
 }
+
+/// An uploaded file which has been written to a temporary file instead of being held in memory.
+/// The temporary file is removed when this is dropped.
+pub struct SpooledFile {
+    path: PathBuf,
+    pub hashes: FileHashes,
+}
+
+impl SpooledFile {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn len(&self) -> u64 {
+        self.hashes.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.length == 0
+    }
+
+    pub fn open(&self) -> Result<std::fs::File, std::io::Error> {
+        std::fs::File::open(&self.path)
+    }
+
+    pub async fn stream(&self) -> Result<FileStream, FileHostingError> {
+        let file = tokio::fs::File::open(&self.path).await?;
+
+        Ok(ReaderStream::new(file)
+            .map(|chunk| chunk.map_err(FileHostingError::from))
+            .boxed())
+    }
+}
+
+impl Drop for SpooledFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Writes a multipart field to a temporary file, hashing it as it is received
+pub async fn spool_from_field(
+    field: &mut Field,
+    cap: u64,
+    err_msg: &str,
+) -> Result<SpooledFile, CreateError> {
+    spool_from_stream(field, cap, err_msg).await
+}
+
+/// Writes a stream of chunks to a temporary file, hashing it as it is received
+pub async fn spool_from_stream<S, E>(
+    mut stream: S,
+    cap: u64,
+    err_msg: &str,
+) -> Result<SpooledFile, CreateError>
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+    CreateError: From<E>,
+{
+    let path = std::env::temp_dir().join(format!("labrinth-upload-{}", uuid::Uuid::new_v4()));
+
+    let result = async {
+        let mut file = tokio::fs::File::create(&path)
+            .await
+            .map_err(FileHostingError::from)?;
+        let mut hasher = FileHasher::new();
+
+        while let Some(chunk) = stream.next().await {
+            if hasher.length() >= cap {
+                return Err(CreateError::InvalidInput(String::from(err_msg)));
+            }
+
+            let chunk = chunk?;
+            hasher.update(&chunk);
+            file.write_all(&chunk)
+                .await
+                .map_err(FileHostingError::from)?;
+        }
+        file.flush().await.map_err(FileHostingError::from)?;
+
+        Ok(hasher.finish())
+    }
+    .await;
+
+    match result {
+        Ok(hashes) => Ok(SpooledFile { path, hashes }),
+        Err(err) => {
+            let _ = tokio::fs::remove_file(&path).await;
+            Err(err)
+        }
+    }
+}
@@ -0,0 +1,341 @@
+use crate::util::env::parse_var;
+use crate::validate::ValidationError;
+use lazy_static::lazy_static;
+use std::io::{Cursor, Read, Seek};
+use zip::ZipArchive;
+
+/// Entries smaller than this are not checked for their compression ratio, as small text
+/// files can legitimately compress very well
+const MIN_RATIO_CHECKED_SIZE: u64 = 1 << 20;
+
+lazy_static! {
+    static ref ARCHIVE_LIMITS: ArchiveLimits = ArchiveLimits::from_env();
+}
+
+/// The limits enforced on every uploaded archive, including the jars and Bedrock packs bundled
+/// inside it
+#[derive(Debug, Clone)]
+pub struct ArchiveLimits {
+    /// The most entries a single archive may contain
+    pub max_entries: usize,
+    /// The most bytes all entries may add up to once decompressed, including nested archives
+    pub max_uncompressed_size: u64,
+    /// The highest ratio of uncompressed to compressed size a large entry may have
+    pub max_compression_ratio: u64,
+    /// How many levels of archives may be bundled inside each other
+    pub max_nesting_depth: usize,
+    /// The largest archive which may be bundled inside another one, as bundled archives are
+    /// read into memory to be inspected
+    pub max_nested_archive_size: u64,
+}
+
+impl Default for ArchiveLimits {
+    fn default() -> Self {
+        Self {
+            max_entries: 100_000,
+            max_uncompressed_size: 2 * (1 << 30),
+            max_compression_ratio: 100,
+            max_nesting_depth: 4,
+            max_nested_archive_size: 64 * (1 << 20),
+        }
+    }
+}
+
+impl ArchiveLimits {
+    fn from_env() -> Self {
+        let default = Self::default();
+
+        Self {
+            max_entries: parse_var("ARCHIVE_MAX_ENTRIES").unwrap_or(default.max_entries),
+            max_uncompressed_size: parse_var("ARCHIVE_MAX_UNCOMPRESSED_SIZE")
+                .unwrap_or(default.max_uncompressed_size),
+            max_compression_ratio: parse_var("ARCHIVE_MAX_COMPRESSION_RATIO")
+                .unwrap_or(default.max_compression_ratio),
+            max_nesting_depth: parse_var("ARCHIVE_MAX_NESTING_DEPTH")
+                .unwrap_or(default.max_nesting_depth),
+            max_nested_archive_size: parse_var("ARCHIVE_MAX_NESTED_SIZE")
+                .unwrap_or(default.max_nested_archive_size),
+        }
+    }
+
+    pub fn get() -> &'static ArchiveLimits {
+        &ARCHIVE_LIMITS
+    }
+}
+
+/// Opens an uploaded archive, rejecting it if it or any archive bundled inside it exceeds the
+/// configured limits or contains an entry with an unsafe path
+pub fn open_archive<R: Read + Seek>(reader: R) -> Result<ZipArchive<R>, ValidationError> {
+    open_archive_with_limits(reader, ArchiveLimits::get())
+}
+
+pub fn open_archive_with_limits<R: Read + Seek>(
+    reader: R,
+    limits: &ArchiveLimits,
+) -> Result<ZipArchive<R>, ValidationError> {
+    let mut archive = ZipArchive::new(reader)?;
+
+    let mut uncompressed_size = 0;
+    inspect_archive(&mut archive, limits, "", 0, &mut uncompressed_size)?;
+
+    Ok(archive)
+}
+
+fn inspect_archive<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    limits: &ArchiveLimits,
+    prefix: &str,
+    depth: usize,
+    uncompressed_size: &mut u64,
+) -> Result<(), ValidationError> {
+    if archive.len() > limits.max_entries {
+        return Err(ValidationError::TooManyEntries(limits.max_entries));
+    }
+
+    let mut nested_archives = Vec::new();
+    for i in 0..archive.len() {
+        // Raw access only reads the entry's header, so nothing is decompressed here
+        let file = archive.by_index_raw(i)?;
+        let name = file.name();
+
+        if !is_safe_path(name) {
+            return Err(ValidationError::UnsafePath(format!("{prefix}{name}")));
+        }
+
+        *uncompressed_size = uncompressed_size.saturating_add(file.size());
+        if *uncompressed_size > limits.max_uncompressed_size {
+            return Err(ValidationError::TooLarge(limits.max_uncompressed_size));
+        }
+
+        if file.size() >= MIN_RATIO_CHECKED_SIZE
+            && file.size() / file.compressed_size().max(1) > limits.max_compression_ratio
+        {
+            return Err(ValidationError::CompressionRatio(format!("{prefix}{name}")));
+        }
+
+        if name.ends_with(".jar") || name.ends_with(".mcpack") {
+            // Bundled archives which are too large to be read can't be inspected, so they
+            // are rejected instead of being let through unchecked
+            if file.size() > limits.max_nested_archive_size {
+                return Err(ValidationError::NestedArchiveTooLarge(
+                    format!("{prefix}{name}"),
+                    limits.max_nested_archive_size,
+                ));
+            }
+
+            nested_archives.push(name.to_string());
+        }
+    }
+
+    for name in nested_archives {
+        if depth >= limits.max_nesting_depth {
+            return Err(ValidationError::NestingTooDeep(format!("{prefix}{name}")));
+        }
+
+        // Entries which aren't actually archives are left for the validators to judge
+        if let Some(mut nested) =
+            read_nested_archive_with_limit(archive, &name, limits.max_nested_archive_size)
+        {
+            inspect_archive(
+                &mut nested,
+                limits,
+                &format!("{prefix}{name}!/"),
+                depth + 1,
+                uncompressed_size,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether an entry's path stays inside the directory the archive is extracted to. Both kinds
+/// of separators are checked, as launchers on Windows treat backslashes as separators too.
+pub fn is_safe_path(name: &str) -> bool {
+    let mut components = name.split(['/', '\\']);
+    let root = components.next().unwrap_or_default();
+
+    !name.contains('\0')
+        && !root.is_empty()
+        && !root.ends_with(':')
+        && root != ".."
+        && components.all(|x| x != "..")
+}
+
+/// Reads an entry of an archive as a string, returning `None` if it doesn't exist. At most the
+/// entry's declared size is read, so an entry can't decompress to more than was inspected.
+pub fn read_entry_to_string<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> Result<Option<String>, ValidationError> {
+    let file = match archive.by_name(name) {
+        Ok(file) => file,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let size = file.size();
+    let mut contents = String::new();
+    file.take(size).read_to_string(&mut contents)?;
+
+    Ok(Some(contents))
+}
+
+/// Reads a jar or pack bundled inside an archive into memory, unless it's too large or isn't an
+/// archive. Archives which passed [`open_archive`] don't bundle any which are too large.
+pub fn read_nested_archive<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> Option<ZipArchive<Cursor<Vec<u8>>>> {
+    read_nested_archive_with_limit(archive, name, ArchiveLimits::get().max_nested_archive_size)
+}
+
+fn read_nested_archive_with_limit<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+    max_size: u64,
+) -> Option<ZipArchive<Cursor<Vec<u8>>>> {
+    let file = archive.by_name(name).ok()?;
+    if file.size() > max_size {
+        return None;
+    }
+
+    let size = file.size();
+    let mut bytes = Vec::new();
+    file.take(size).read_to_end(&mut bytes).ok()?;
+
+    ZipArchive::new(Cursor::new(bytes)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::FileOptions;
+    use zip::CompressionMethod;
+
+    fn build_zip(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut cursor = Cursor::new(Vec::new());
+        {
+            let mut zip = zip::ZipWriter::new(&mut cursor);
+            for (name, contents) in entries {
+                zip.start_file(
+                    *name,
+                    FileOptions::default().compression_method(CompressionMethod::Deflated),
+                )
+                .unwrap();
+                zip.write_all(contents).unwrap();
+            }
+            zip.finish().unwrap();
+        }
+
+        cursor.into_inner()
+    }
+
+    fn open(bytes: Vec<u8>, limits: &ArchiveLimits) -> Result<(), ValidationError> {
+        open_archive_with_limits(Cursor::new(bytes), limits).map(|_| ())
+    }
+
+    #[test]
+    fn unsafe_paths() {
+        for path in [
+            "../escape.txt",
+            "config/../../escape.txt",
+            "/etc/passwd",
+            "\\windows\\system32",
+            "C:/escape.txt",
+            "overrides\\..\\escape.txt",
+        ] {
+            let zip = build_zip(&[("fabric.mod.json", b"{}".to_vec()), (path, vec![])]);
+            let result = open(zip, &ArchiveLimits::default());
+            assert!(
+                matches!(result, Err(ValidationError::UnsafePath(x)) if x == path),
+                "{} should be rejected",
+                path
+            );
+        }
+
+        let zip = build_zip(&[("assets/mod/..png", vec![]), ("a/b..c/d.txt", vec![])]);
+        assert!(open(zip, &ArchiveLimits::default()).is_ok());
+    }
+
+    #[test]
+    fn size_limits() {
+        let limits = ArchiveLimits {
+            max_entries: 2,
+            max_uncompressed_size: 1 << 10,
+            ..Default::default()
+        };
+
+        let zip = build_zip(&[("a", vec![]), ("b", vec![]), ("c", vec![])]);
+        assert!(matches!(
+            open(zip, &limits),
+            Err(ValidationError::TooManyEntries(2))
+        ));
+
+        let zip = build_zip(&[("a", vec![1; 600]), ("b", vec![2; 600])]);
+        assert!(matches!(
+            open(zip, &limits),
+            Err(ValidationError::TooLarge(1024))
+        ));
+
+        let zip = build_zip(&[("a", vec![1; 600])]);
+        assert!(open(zip, &limits).is_ok());
+    }
+
+    #[test]
+    fn compression_ratio() {
+        let zip = build_zip(&[("bomb.txt", vec![0; 8 << 20])]);
+        assert!(matches!(
+            open(zip, &ArchiveLimits::default()),
+            Err(ValidationError::CompressionRatio(x)) if x == "bomb.txt"
+        ));
+
+        // Small entries compress well without being suspicious
+        let zip = build_zip(&[("small.txt", vec![0; 64 << 10])]);
+        assert!(open(zip, &ArchiveLimits::default()).is_ok());
+    }
+
+    #[test]
+    fn nesting_depth() {
+        let limits = ArchiveLimits {
+            max_nesting_depth: 2,
+            ..Default::default()
+        };
+
+        let mut jar = build_zip(&[("fabric.mod.json", b"{}".to_vec())]);
+        for depth in 1..=3 {
+            jar = build_zip(&[(&format!("META-INF/jars/{depth}.jar"), jar)]);
+
+            let result = open(jar.clone(), &limits);
+            if depth <= 2 {
+                assert!(result.is_ok());
+            } else {
+                assert!(matches!(
+                    result,
+                    Err(ValidationError::NestingTooDeep(x))
+                        if x == "META-INF/jars/3.jar!/META-INF/jars/2.jar!/META-INF/jars/1.jar"
+                ));
+            }
+        }
+
+        // Bundled jars which are too large to be inspected are rejected
+        let small_limits = ArchiveLimits {
+            max_nested_archive_size: 1024,
+            ..limits.clone()
+        };
+        let jar = build_zip(&[("META-INF/jars/large.jar", vec![0; 2048])]);
+        assert!(matches!(
+            open(jar, &small_limits),
+            Err(ValidationError::NestedArchiveTooLarge(x, 1024)) if x == "META-INF/jars/large.jar"
+        ));
+
+        // Entries nested inside bundled jars are checked too
+        let nested = build_zip(&[("../escape.txt", vec![])]);
+        let jar = build_zip(&[("META-INF/jars/nested.jar", nested)]);
+        assert!(matches!(
+            open(jar, &limits),
+            Err(ValidationError::UnsafePath(x)) if x == "META-INF/jars/nested.jar!/../escape.txt"
+        ));
+    }
+}
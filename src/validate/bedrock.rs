@@ -0,0 +1,563 @@
+use crate::models::projects::{ValidationReport, ValidationSeverity};
+use crate::validate::archive::{read_entry_to_string, read_nested_archive};
+use crate::validate::manifest::ModManifest;
+use crate::validate::{SupportedGameVersions, ValidationError, ValidationResult};
+use itertools::Itertools;
+use serde::Deserialize;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{Read, Seek};
+use uuid::Uuid;
+use zip::ZipArchive;
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// The module types the game recognizes in a pack's manifest.json
+const MODULE_TYPES: &[&str] = &[
+    "resources",
+    "data",
+    "client_data",
+    "interface",
+    "world_template",
+    "skin_pack",
+    "script",
+    "javascript",
+];
+
+/// The file formats Bedrock add-ons are distributed in
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BedrockFormat {
+    /// A single behavior or resource pack (`.mcpack`)
+    Pack,
+    /// Several packs, either as folders or bundled `.mcpack` files (`.mcaddon`)
+    Addon,
+    /// A world, with the packs it applies under `world_behavior_packs` and
+    /// `world_resource_packs` (`.mcworld`)
+    World,
+}
+
+impl BedrockFormat {
+    pub fn from_extension(extension: &str) -> Option<BedrockFormat> {
+        match extension {
+            "mcpack" => Some(BedrockFormat::Pack),
+            "mcaddon" => Some(BedrockFormat::Addon),
+            "mcworld" => Some(BedrockFormat::World),
+            _ => None,
+        }
+    }
+
+    /// The folders which hold one pack per subfolder
+    fn pack_folders(&self) -> &'static [&'static str] {
+        match self {
+            BedrockFormat::Pack | BedrockFormat::Addon => &[""],
+            BedrockFormat::World => &["world_behavior_packs/", "world_resource_packs/"],
+        }
+    }
+}
+
+/// A version of the game's engine, such as `1.20.50`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct EngineVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl EngineVersion {
+    /// Parses a `[major, minor, patch]` array, or a version string as used by format version 3
+    /// manifests. Minor and patch versions must be below 1000 to fit `as_number`.
+    fn parse(value: &serde_json::Value) -> Option<EngineVersion> {
+        let parts = match value {
+            serde_json::Value::Array(parts) => parts
+                .iter()
+                .map(|x| x.as_u64().and_then(|x| u32::try_from(x).ok()))
+                .collect::<Option<Vec<_>>>()?,
+            serde_json::Value::String(version) => version
+                .split(['-', '+'])
+                .next()?
+                .split('.')
+                .map(|x| x.parse().ok())
+                .collect::<Option<Vec<_>>>()?,
+            _ => return None,
+        };
+
+        let version = match parts[..] {
+            [major, minor] => EngineVersion {
+                major,
+                minor,
+                patch: 0,
+            },
+            [major, minor, patch] => EngineVersion {
+                major,
+                minor,
+                patch,
+            },
+            _ => return None,
+        };
+
+        (version.major < 2000 && version.minor < 1000 && version.patch < 1000).then_some(version)
+    }
+
+    /// The version as a single number which orders the same way, so versions can be
+    /// filtered by engine version
+    pub fn as_number(&self) -> i32 {
+        (self.major * 1_000_000 + self.minor * 1_000 + self.patch) as i32
+    }
+}
+
+impl std::fmt::Display for EngineVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// The metadata declared in the manifest.json of a pack
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BedrockManifest {
+    pub uuid: Uuid,
+    pub version: String,
+    pub min_engine_version: Option<EngineVersion>,
+    pub module_types: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ManifestJson {
+    header: ManifestHeader,
+    #[serde(default)]
+    modules: Vec<ManifestModule>,
+}
+
+#[derive(Deserialize)]
+struct ManifestHeader {
+    uuid: String,
+    version: serde_json::Value,
+    min_engine_version: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct ManifestModule {
+    #[serde(rename = "type")]
+    module_type: String,
+}
+
+/// Parses a manifest.json, returning why it is invalid if the game would refuse to import it
+fn parse_manifest(contents: &str) -> Result<BedrockManifest, String> {
+    let manifest: ManifestJson = serde_json::from_str(contents.trim_start_matches('\u{feff}'))
+        .map_err(|e| format!("The manifest.json could not be parsed: {e}"))?;
+
+    let uuid = Uuid::parse_str(&manifest.header.uuid).map_err(|_| {
+        format!(
+            "The header UUID {} of the manifest.json is not a valid UUID",
+            manifest.header.uuid
+        )
+    })?;
+
+    let version = match &manifest.header.version {
+        serde_json::Value::String(version) if !version.is_empty() => version.clone(),
+        version => EngineVersion::parse(version)
+            .map(|x| x.to_string())
+            .ok_or_else(|| {
+                "The header version of the manifest.json must be a [major, minor, patch] array"
+                    .to_string()
+            })?,
+    };
+
+    let min_engine_version = manifest
+        .header
+        .min_engine_version
+        .as_ref()
+        .map(|x| {
+            EngineVersion::parse(x).ok_or_else(|| {
+                "The min_engine_version of the manifest.json must be a [major, minor, patch] array"
+                    .to_string()
+            })
+        })
+        .transpose()?;
+
+    if manifest.modules.is_empty() {
+        return Err("The manifest.json declares no modules".to_string());
+    }
+
+    Ok(BedrockManifest {
+        uuid,
+        version,
+        min_engine_version,
+        module_types: manifest
+            .modules
+            .into_iter()
+            .map(|x| x.module_type)
+            .collect(),
+    })
+}
+
+/// A pack found in an uploaded file
+struct FoundPack {
+    /// The path of the pack's manifest.json, through the `.mcpack` it's bundled in, if any
+    path: String,
+    manifest: Result<BedrockManifest, String>,
+}
+
+/// Finds the packs of a file and parses their manifests. A manifest at the root of the file
+/// comes first, as it describes the file itself.
+fn find_packs<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    format: BedrockFormat,
+) -> Result<Vec<FoundPack>, ValidationError> {
+    let names = archive
+        .file_names()
+        .map(|x| x.to_string())
+        .sorted()
+        .collect::<Vec<_>>();
+
+    let mut paths = names
+        .iter()
+        .filter(|x| *x == MANIFEST_FILE)
+        .cloned()
+        .collect::<Vec<_>>();
+    // A pack may be zipped either with its files at the root or inside a single folder
+    if format != BedrockFormat::Pack || paths.is_empty() {
+        paths.extend(
+            names
+                .iter()
+                .filter(|name| {
+                    format.pack_folders().iter().any(|folder| {
+                        name.strip_prefix(folder)
+                            .and_then(|x| x.strip_suffix(MANIFEST_FILE))
+                            .and_then(|x| x.strip_suffix('/'))
+                            .is_some_and(|x| !x.is_empty() && !x.contains('/'))
+                    })
+                })
+                .cloned(),
+        );
+    }
+
+    let mut packs = Vec::new();
+    for path in paths {
+        if let Some(contents) = read_entry_to_string(archive, &path)? {
+            packs.push(FoundPack {
+                manifest: parse_manifest(&contents),
+                path,
+            });
+        }
+    }
+
+    if format == BedrockFormat::Addon {
+        for name in names.iter().filter(|x| x.ends_with(".mcpack")) {
+            let Some(mut nested) = read_nested_archive(archive, name) else {
+                packs.push(FoundPack {
+                    path: name.clone(),
+                    manifest: Err(format!("{name} is not a valid .mcpack file")),
+                });
+                continue;
+            };
+
+            let nested_packs = find_packs(&mut nested, BedrockFormat::Pack)?;
+            if nested_packs.is_empty() {
+                packs.push(FoundPack {
+                    path: name.clone(),
+                    manifest: Err(format!("{name} contains no manifest.json")),
+                });
+            }
+            packs.extend(nested_packs.into_iter().map(|x| FoundPack {
+                path: format!("{name}!/{}", x.path),
+                manifest: x.manifest,
+            }));
+        }
+    }
+
+    Ok(packs)
+}
+
+/// Reports the packs of a file whose manifests the game would refuse, or which declare modules
+/// the game doesn't know
+fn validate_packs<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    report: &mut ValidationReport,
+    format: BedrockFormat,
+) -> Result<(), ValidationError> {
+    let packs = find_packs(archive, format)?;
+
+    // Worlds don't need to apply any packs
+    if packs.is_empty() && format != BedrockFormat::World {
+        report.error(
+            "missing_bedrock_manifest",
+            "No manifest.json present for Bedrock pack. Tip: Make sure manifest.json is in the root directory of your pack!",
+        );
+    }
+
+    for pack in packs {
+        match pack.manifest {
+            Ok(manifest) => {
+                for module_type in manifest
+                    .module_types
+                    .iter()
+                    .filter(|x| !MODULE_TYPES.contains(&x.as_str()))
+                {
+                    report.push(
+                        ValidationSeverity::Warning,
+                        "unknown_bedrock_module",
+                        format!("The manifest.json declares an unknown module type {module_type}"),
+                        Some(pack.path.clone()),
+                    );
+                }
+            }
+            Err(message) => report.push(
+                ValidationSeverity::Error,
+                "invalid_bedrock_manifest",
+                message,
+                Some(pack.path),
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the metadata of a Bedrock file from the manifests of its packs. The version and UUID
+/// are those of the first pack, while the engine version is the highest any pack requires.
+/// Returns `None` if the file has no valid manifest.
+pub fn read_bedrock_manifest<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    format: BedrockFormat,
+) -> Option<ModManifest> {
+    let manifests = find_packs(archive, format)
+        .ok()?
+        .into_iter()
+        .filter_map(|x| x.manifest.ok())
+        .collect::<Vec<_>>();
+    let first = manifests.first()?;
+
+    Some(ModManifest {
+        mod_id: Some(first.uuid.to_string()),
+        version: Some(first.version.clone()),
+        loaders: vec!["bedrock".to_string()],
+        min_engine_version: manifests.iter().filter_map(|x| x.min_engine_version).max(),
+        module_types: manifests
+            .iter()
+            .flat_map(|x| &x.module_types)
+            .filter(|x| MODULE_TYPES.contains(&x.as_str()))
+            .cloned()
+            .sorted()
+            .dedup()
+            .collect(),
+        ..Default::default()
+    })
+}
+
+pub struct McPackValidator;
+
+impl super::Validator for McPackValidator {
+    fn get_file_extensions(&self) -> &[&str] {
+        &["mcpack"]
+    }
+
+    fn get_supported_loaders(&self) -> &[&str] {
+        &["bedrock"]
+    }
+
+    fn get_supported_game_versions(&self) -> SupportedGameVersions {
+        SupportedGameVersions::All
+    }
+
+    fn validate(
+        &self,
+        archive: &mut ZipArchive<File>,
+        report: &mut ValidationReport,
+    ) -> Result<ValidationResult, ValidationError> {
+        validate_packs(archive, report, BedrockFormat::Pack)?;
+
+        Ok(ValidationResult::Pass)
+    }
+}
+
+pub struct McAddonValidator;
+
+impl super::Validator for McAddonValidator {
+    fn get_file_extensions(&self) -> &[&str] {
+        &["mcaddon"]
+    }
+
+    fn get_supported_loaders(&self) -> &[&str] {
+        &["bedrock"]
+    }
+
+    fn get_supported_game_versions(&self) -> SupportedGameVersions {
+        SupportedGameVersions::All
+    }
+
+    fn validate(
+        &self,
+        archive: &mut ZipArchive<File>,
+        report: &mut ValidationReport,
+    ) -> Result<ValidationResult, ValidationError> {
+        validate_packs(archive, report, BedrockFormat::Addon)?;
+
+        Ok(ValidationResult::Pass)
+    }
+}
+
+pub struct McWorldValidator;
+
+impl super::Validator for McWorldValidator {
+    fn get_file_extensions(&self) -> &[&str] {
+        &["mcworld"]
+    }
+
+    fn get_supported_loaders(&self) -> &[&str] {
+        &["bedrock"]
+    }
+
+    fn get_supported_game_versions(&self) -> SupportedGameVersions {
+        SupportedGameVersions::All
+    }
+
+    fn validate(
+        &self,
+        archive: &mut ZipArchive<File>,
+        report: &mut ValidationReport,
+    ) -> Result<ValidationResult, ValidationError> {
+        if archive.by_name("level.dat").is_err() {
+            report.error("missing_level_dat", "No level.dat present for world file. Tip: Make sure level.dat is in the root directory of your world!");
+        }
+        validate_packs(archive, report, BedrockFormat::World)?;
+
+        Ok(ValidationResult::Pass)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+
+    fn build_zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut cursor = Cursor::new(Vec::new());
+        let mut zip = zip::ZipWriter::new(&mut cursor);
+        for (name, contents) in files {
+            zip.start_file(*name, zip::write::FileOptions::default())
+                .unwrap();
+            zip.write_all(contents).unwrap();
+        }
+        zip.finish().unwrap();
+        drop(zip);
+
+        cursor.into_inner()
+    }
+
+    fn manifest_json(uuid: &str, min_engine_version: &str, module_type: &str) -> Vec<u8> {
+        format!(
+            r#"{{
+                "format_version": 2,
+                "header": {{
+                    "name": "Example",
+                    "uuid": "{uuid}",
+                    "version": [1, 2, 0],
+                    "min_engine_version": {min_engine_version}
+                }},
+                "modules": [{{ "type": "{module_type}", "uuid": "{uuid}", "version": [1, 2, 0] }}]
+            }}"#
+        )
+        .into_bytes()
+    }
+
+    const BEHAVIOR_UUID: &str = "5d4b3a1e-9f3a-4f5e-8d6b-2c1a0e9f8d7c";
+    const RESOURCE_UUID: &str = "a1b2c3d4-e5f6-4a7b-8c9d-0e1f2a3b4c5d";
+
+    #[test]
+    fn manifests() {
+        let manifest = parse_manifest(
+            &String::from_utf8(manifest_json(BEHAVIOR_UUID, "[1, 20, 50]", "data")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(manifest.version, "1.2.0");
+        assert_eq!(
+            manifest.min_engine_version.map(|x| x.as_number()),
+            Some(1_020_050)
+        );
+        assert_eq!(manifest.module_types, vec!["data".to_string()]);
+
+        let manifest = parse_manifest(
+            r#"{
+                "format_version": 3,
+                "header": { "uuid": "5d4b3a1e-9f3a-4f5e-8d6b-2c1a0e9f8d7c", "version": "2.0.0-beta", "min_engine_version": "1.21.0" },
+                "modules": [{ "type": "resources" }]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(manifest.version, "2.0.0-beta");
+        assert_eq!(
+            manifest.min_engine_version.map(|x| x.to_string()),
+            Some("1.21.0".to_string())
+        );
+
+        assert!(parse_manifest(
+            &String::from_utf8(manifest_json(BEHAVIOR_UUID, "[1, 20]", "data")).unwrap()
+        )
+        .is_ok());
+        assert!(parse_manifest(
+            &String::from_utf8(manifest_json("not-a-uuid", "[1, 20, 0]", "data")).unwrap()
+        )
+        .is_err());
+        assert!(parse_manifest(
+            &String::from_utf8(manifest_json(BEHAVIOR_UUID, "[1, 20, 1000]", "data")).unwrap()
+        )
+        .is_err());
+        assert!(parse_manifest(
+            r#"{ "header": { "uuid": "5d4b3a1e-9f3a-4f5e-8d6b-2c1a0e9f8d7c", "version": [1, 0, 0] } }"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn addons() {
+        let resource_pack = build_zip(&[(
+            "Example RP/manifest.json",
+            &manifest_json(RESOURCE_UUID, "[1, 21, 0]", "resources"),
+        )]);
+        let addon = build_zip(&[
+            (
+                "behavior/manifest.json",
+                &manifest_json(BEHAVIOR_UUID, "[1, 20, 50]", "data"),
+            ),
+            ("resources.mcpack", &resource_pack),
+            ("broken.mcpack", b"not a zip"),
+        ]);
+
+        let mut report = ValidationReport::default();
+        let mut archive = ZipArchive::new(Cursor::new(addon)).unwrap();
+        validate_packs(&mut archive, &mut report, BedrockFormat::Addon).unwrap();
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(report.findings[0].code, "invalid_bedrock_manifest");
+        assert_eq!(report.findings[0].path.as_deref(), Some("broken.mcpack"));
+
+        let manifest = read_bedrock_manifest(&mut archive, BedrockFormat::Addon).unwrap();
+        assert_eq!(manifest.mod_id.as_deref(), Some(BEHAVIOR_UUID));
+        assert_eq!(
+            manifest.min_engine_version.map(|x| x.to_string()),
+            Some("1.21.0".to_string())
+        );
+        assert_eq!(
+            manifest.module_types,
+            vec!["data".to_string(), "resources".to_string()]
+        );
+
+        let world = build_zip(&[
+            ("level.dat", b""),
+            (
+                "world_behavior_packs/example/manifest.json",
+                &manifest_json(BEHAVIOR_UUID, "[1, 20, 50]", "custom"),
+            ),
+        ]);
+        let mut report = ValidationReport::default();
+        let mut archive = ZipArchive::new(Cursor::new(world)).unwrap();
+        validate_packs(&mut archive, &mut report, BedrockFormat::World).unwrap();
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(report.findings[0].code, "unknown_bedrock_module");
+
+        let mut report = ValidationReport::default();
+        let mut archive =
+            ZipArchive::new(Cursor::new(build_zip(&[("pack_icon.png", b"")]))).unwrap();
+        validate_packs(&mut archive, &mut report, BedrockFormat::Pack).unwrap();
+        assert!(!report.is_passed());
+    }
+}
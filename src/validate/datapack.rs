@@ -1,5 +1,8 @@
+use crate::database::models::legacy_loader_fields::MinecraftGameVersion;
+use crate::models::projects::ValidationReport;
+use crate::validate::resourcepack::{check_pack_format, PackKind};
 use crate::validate::{SupportedGameVersions, ValidationError, ValidationResult};
-use std::io::Cursor;
+use std::fs::File;
 use zip::ZipArchive;
 
 pub struct DataPackValidator;
@@ -19,14 +22,25 @@ impl super::Validator for DataPackValidator {
 
     fn validate(
         &self,
-        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+        archive: &mut ZipArchive<File>,
+        report: &mut ValidationReport,
     ) -> Result<ValidationResult, ValidationError> {
         if archive.by_name("pack.mcmeta").is_err() {
-            return Ok(ValidationResult::Warning(
-                "No pack.mcmeta present for datapack file. Tip: Make sure pack.mcmeta is in the root directory of your datapack!",
-            ));
+            report.error("missing_pack_mcmeta", "No pack.mcmeta present for datapack file. Tip: Make sure pack.mcmeta is in the root directory of your datapack!");
         }
 
         Ok(ValidationResult::Pass)
     }
+
+    fn validate_with_game_versions(
+        &self,
+        archive: &mut ZipArchive<File>,
+        report: &mut ValidationReport,
+        game_versions: &[MinecraftGameVersion],
+    ) -> Result<ValidationResult, ValidationError> {
+        let result = self.validate(archive, report)?;
+        check_pack_format(archive, report, game_versions, PackKind::Data)?;
+
+        Ok(result)
+    }
 }
@@ -1,5 +1,6 @@
+use crate::models::projects::ValidationReport;
 use crate::validate::{filter_out_packs, SupportedGameVersions, ValidationError, ValidationResult};
-use std::io::Cursor;
+use std::fs::File;
 use zip::ZipArchive;
 
 pub struct FabricValidator;
@@ -19,15 +20,17 @@ impl super::Validator for FabricValidator {
 
     fn validate(
         &self,
-        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+        archive: &mut ZipArchive<File>,
+        report: &mut ValidationReport,
     ) -> Result<ValidationResult, ValidationError> {
         if archive.by_name("fabric.mod.json").is_err() {
-            return Ok(ValidationResult::Warning(
+            report.error(
+                "missing_fabric_mod_json",
                 "No fabric.mod.json present for Fabric file.",
-            ));
+            );
         }
 
-        filter_out_packs(archive)?;
+        filter_out_packs(archive, report);
 
         Ok(ValidationResult::Pass)
     }
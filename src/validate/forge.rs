@@ -1,6 +1,7 @@
+use crate::models::projects::ValidationReport;
 use crate::validate::{filter_out_packs, SupportedGameVersions, ValidationError, ValidationResult};
 use chrono::DateTime;
-use std::io::Cursor;
+use std::fs::File;
 use zip::ZipArchive;
 
 pub struct ForgeValidator;
@@ -21,18 +22,20 @@ impl super::Validator for ForgeValidator {
 
     fn validate(
         &self,
-        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+        archive: &mut ZipArchive<File>,
+        report: &mut ValidationReport,
     ) -> Result<ValidationResult, ValidationError> {
         if archive.by_name("META-INF/mods.toml").is_err()
             && archive.by_name("META-INF/MANIFEST.MF").is_err()
             && !archive.file_names().any(|x| x.ends_with(".class"))
         {
-            return Ok(ValidationResult::Warning(
+            report.error(
+                "missing_mods_toml",
                 "No mods.toml or valid class files present for Forge file.",
-            ));
+            );
         }
 
-        filter_out_packs(archive)?;
+        filter_out_packs(archive, report);
 
         Ok(ValidationResult::Pass)
     }
@@ -59,18 +62,20 @@ impl super::Validator for LegacyForgeValidator {
 
     fn validate(
         &self,
-        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+        archive: &mut ZipArchive<File>,
+        report: &mut ValidationReport,
     ) -> Result<ValidationResult, ValidationError> {
         if archive.by_name("mcmod.info").is_err()
             && archive.by_name("META-INF/MANIFEST.MF").is_err()
             && !archive.file_names().any(|x| x.ends_with(".class"))
         {
-            return Ok(ValidationResult::Warning(
+            report.error(
+                "missing_mcmod_info",
                 "Forge mod file does not contain mcmod.info or valid class files!",
-            ));
+            );
         };
 
-        filter_out_packs(archive)?;
+        filter_out_packs(archive, report);
 
         Ok(ValidationResult::Pass)
     }
@@ -0,0 +1,798 @@
+use crate::models::projects::DependencyType;
+use crate::validate::archive::{read_entry_to_string, read_nested_archive};
+use crate::validate::bedrock::EngineVersion;
+use crate::validate::ValidationError;
+use serde::Deserialize;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Seek};
+use zip::ZipArchive;
+
+/// The ids of the game and the mod loaders, which mods depend on but which aren't projects
+const PLATFORM_MOD_IDS: &[&str] = &[
+    "minecraft",
+    "java",
+    "fabricloader",
+    "quilt_loader",
+    "forge",
+    "neoforge",
+];
+
+/// The metadata declared in the manifest of a mod file (`fabric.mod.json`, `quilt.mod.json`
+/// or `mods.toml`), or in the `manifest.json` files of a Bedrock add-on
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModManifest {
+    pub mod_id: Option<String>,
+    pub version: Option<String>,
+    /// The loaders the mod is built for
+    pub loaders: Vec<String>,
+    /// The game versions the mod declares it supports
+    pub game_versions: Option<VersionRange>,
+    pub environment: Option<ModEnvironment>,
+    /// The other mods this mod declares it depends on, recommends or breaks
+    pub dependencies: Vec<ManifestDependency>,
+    /// The jars bundled inside this mod (jar-in-jar)
+    pub embedded: Vec<EmbeddedJar>,
+    /// The lowest version of the Bedrock engine the add-on's packs require
+    pub min_engine_version: Option<EngineVersion>,
+    /// The types of the modules of the add-on's packs, such as `resources` or `data`
+    pub module_types: Vec<String>,
+}
+
+impl ModManifest {
+    /// Whether the mod can be run by a loader, including loaders which can also run mods
+    /// built for another loader
+    pub fn supports_loader(&self, loader: &str) -> bool {
+        self.loaders.iter().any(|x| {
+            x == loader
+                || (x == "fabric" && loader == "quilt")
+                || (x == "forge" && loader == "neoforge")
+        })
+    }
+
+    /// The values of the Bedrock version fields matching the add-on's manifests
+    pub fn bedrock_fields(&self) -> Vec<(&'static str, serde_json::Value)> {
+        let mut fields = Vec::new();
+
+        if let Some(engine_version) = self.min_engine_version {
+            fields.push((
+                "min_engine_version",
+                serde_json::json!(engine_version.to_string()),
+            ));
+            fields.push((
+                "min_engine_version_number",
+                serde_json::json!(engine_version.as_number()),
+            ));
+        }
+        if !self.module_types.is_empty() {
+            fields.push(("bedrock_modules", serde_json::json!(self.module_types)));
+        }
+
+        fields
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestDependency {
+    pub mod_id: String,
+    pub dependency_type: DependencyType,
+}
+
+impl ManifestDependency {
+    fn new(mod_id: &str, dependency_type: DependencyType) -> Option<ManifestDependency> {
+        (!PLATFORM_MOD_IDS.contains(&mod_id)).then(|| ManifestDependency {
+            mod_id: mod_id.to_string(),
+            dependency_type,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddedJar {
+    pub file_name: String,
+    /// The mod id from the bundled jar's own manifest, if it has one
+    pub mod_id: Option<String>,
+}
+
+/// Where a mod is declared to run
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ModEnvironment {
+    Client,
+    Server,
+    Both,
+}
+
+impl ModEnvironment {
+    /// The values of the version side type fields matching this environment
+    pub fn side_types(&self) -> [(&'static str, bool); 4] {
+        let (client_only, server_only) = match self {
+            ModEnvironment::Client => (true, false),
+            ModEnvironment::Server => (false, true),
+            ModEnvironment::Both => (false, false),
+        };
+
+        [
+            ("singleplayer", true),
+            ("client_and_server", true),
+            ("client_only", client_only),
+            ("server_only", server_only),
+        ]
+    }
+
+    fn from_fabric(environment: &str) -> Option<ModEnvironment> {
+        match environment {
+            "client" => Some(ModEnvironment::Client),
+            "server" | "dedicated_server" => Some(ModEnvironment::Server),
+            "*" => Some(ModEnvironment::Both),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the manifest of a mod file, trying each of the supported formats in turn. Returns
+/// `None` if the file has no manifest, or if its manifest could not be parsed.
+pub fn read_manifest<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Option<ModManifest> {
+    let mut manifest = read_manifest_file(archive)?;
+    manifest.embedded = read_embedded_jars(archive);
+
+    Some(manifest)
+}
+
+fn read_manifest_file<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Option<ModManifest> {
+    if let Ok(Some(manifest)) = read_quilt_manifest(archive) {
+        return Some(manifest);
+    }
+    if let Ok(Some(manifest)) = read_fabric_manifest(archive) {
+        return Some(manifest);
+    }
+    if let Ok(Some(manifest)) = read_forge_manifest(archive) {
+        return Some(manifest);
+    }
+
+    None
+}
+
+/// Reads the jars bundled under `META-INF/jars` (Fabric and Quilt) or `META-INF/jarjar`
+/// (Forge and NeoForge), along with the mod ids from their own manifests
+fn read_embedded_jars<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Vec<EmbeddedJar> {
+    let names = archive
+        .file_names()
+        .filter(|x| {
+            (x.starts_with("META-INF/jars/") || x.starts_with("META-INF/jarjar/"))
+                && x.ends_with(".jar")
+        })
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>();
+
+    names
+        .into_iter()
+        .map(|name| {
+            let mod_id = read_nested_archive(archive, &name)
+                .and_then(|mut nested| read_manifest_file(&mut nested))
+                .and_then(|x| x.mod_id);
+
+            EmbeddedJar {
+                file_name: name.rsplit('/').next().unwrap_or(&name).to_string(),
+                mod_id,
+            }
+        })
+        .collect()
+}
+
+/// Strips placeholders which are only substituted by the mod's build script
+fn filter_placeholder(version: String) -> Option<String> {
+    if version.is_empty() || version.contains("${") {
+        None
+    } else {
+        Some(version)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    fn to_vec(&self) -> Vec<String> {
+        match self {
+            OneOrMany::One(x) => vec![x.clone()],
+            OneOrMany::Many(x) => x.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct FabricModJson {
+    id: String,
+    version: String,
+    environment: Option<String>,
+    #[serde(default)]
+    depends: BTreeMap<String, OneOrMany>,
+    #[serde(default)]
+    recommends: BTreeMap<String, serde_json::Value>,
+    #[serde(default)]
+    suggests: BTreeMap<String, serde_json::Value>,
+    #[serde(default)]
+    breaks: BTreeMap<String, serde_json::Value>,
+}
+
+/// Reads `fabric.mod.json`, returning `None` if the file has none
+fn read_fabric_manifest<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+) -> Result<Option<ModManifest>, ValidationError> {
+    let Some(contents) = read_entry_to_string(archive, "fabric.mod.json")? else {
+        return Ok(None);
+    };
+    let manifest: FabricModJson = serde_json::from_str(&contents)?;
+
+    let dependencies = manifest
+        .depends
+        .keys()
+        .map(|x| (x, DependencyType::Required))
+        .chain(
+            manifest
+                .recommends
+                .keys()
+                .chain(manifest.suggests.keys())
+                .map(|x| (x, DependencyType::Optional)),
+        )
+        .chain(
+            manifest
+                .breaks
+                .keys()
+                .map(|x| (x, DependencyType::Incompatible)),
+        )
+        .filter_map(|(mod_id, dependency_type)| ManifestDependency::new(mod_id, dependency_type))
+        .collect();
+
+    Ok(Some(ModManifest {
+        mod_id: Some(manifest.id),
+        version: filter_placeholder(manifest.version),
+        loaders: vec!["fabric".to_string()],
+        game_versions: manifest
+            .depends
+            .get("minecraft")
+            .and_then(|x| VersionRange::parse_predicates(&x.to_vec())),
+        environment: manifest
+            .environment
+            .as_deref()
+            .and_then(ModEnvironment::from_fabric),
+        dependencies,
+        embedded: Vec::new(),
+        min_engine_version: None,
+        module_types: Vec::new(),
+    }))
+}
+
+#[derive(Deserialize)]
+struct QuiltModJson {
+    quilt_loader: QuiltLoader,
+    minecraft: Option<QuiltMinecraft>,
+}
+
+#[derive(Deserialize)]
+struct QuiltLoader {
+    id: String,
+    version: String,
+    #[serde(default)]
+    depends: Vec<serde_json::Value>,
+    #[serde(default)]
+    breaks: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct QuiltMinecraft {
+    environment: Option<String>,
+}
+
+/// Reads `quilt.mod.json`, returning `None` if the file has none
+fn read_quilt_manifest<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+) -> Result<Option<ModManifest>, ValidationError> {
+    let Some(contents) = read_entry_to_string(archive, "quilt.mod.json")? else {
+        return Ok(None);
+    };
+    let manifest: QuiltModJson = serde_json::from_str(&contents)?;
+
+    // Dependencies are either a bare mod id, or an object with an id, version predicates and
+    // whether they're optional
+    let game_versions = manifest
+        .quilt_loader
+        .depends
+        .iter()
+        .find(|x| x.get("id").and_then(|x| x.as_str()) == Some("minecraft"))
+        .and_then(|x| x.get("versions").cloned())
+        .and_then(|x| serde_json::from_value::<OneOrMany>(x).ok())
+        .and_then(|x| VersionRange::parse_predicates(&x.to_vec()));
+
+    let quilt_dependency = |value: &serde_json::Value, dependency_type| {
+        let mod_id = value
+            .as_str()
+            .or_else(|| value.get("id").and_then(|x| x.as_str()))?;
+        let optional = value
+            .get("optional")
+            .and_then(|x| x.as_bool())
+            .unwrap_or(false);
+
+        ManifestDependency::new(
+            mod_id,
+            if optional && dependency_type == DependencyType::Required {
+                DependencyType::Optional
+            } else {
+                dependency_type
+            },
+        )
+    };
+    let dependencies = manifest
+        .quilt_loader
+        .depends
+        .iter()
+        .filter_map(|x| quilt_dependency(x, DependencyType::Required))
+        .chain(
+            manifest
+                .quilt_loader
+                .breaks
+                .iter()
+                .filter_map(|x| quilt_dependency(x, DependencyType::Incompatible)),
+        )
+        .collect();
+
+    Ok(Some(ModManifest {
+        mod_id: Some(manifest.quilt_loader.id),
+        version: filter_placeholder(manifest.quilt_loader.version),
+        loaders: vec!["quilt".to_string()],
+        game_versions,
+        environment: manifest
+            .minecraft
+            .and_then(|x| x.environment)
+            .as_deref()
+            .and_then(ModEnvironment::from_fabric),
+        dependencies,
+        embedded: Vec::new(),
+        min_engine_version: None,
+        module_types: Vec::new(),
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModsToml {
+    #[serde(default)]
+    mods: Vec<ModsTomlMod>,
+    #[serde(default)]
+    dependencies: HashMap<String, Vec<ModsTomlDependency>>,
+    #[serde(default)]
+    client_side_only: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModsTomlMod {
+    mod_id: String,
+    version: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModsTomlDependency {
+    mod_id: String,
+    version_range: Option<String>,
+    /// Used by Forge, and by NeoForge before `type` replaced it
+    mandatory: Option<bool>,
+    /// Used by NeoForge
+    #[serde(rename = "type")]
+    kind: Option<String>,
+}
+
+impl ModsTomlDependency {
+    fn dependency_type(&self) -> DependencyType {
+        match (self.kind.as_deref(), self.mandatory) {
+            (Some("optional"), _) => DependencyType::Optional,
+            (Some("incompatible" | "discouraged"), _) => DependencyType::Incompatible,
+            (Some(_), _) => DependencyType::Required,
+            (None, Some(false)) => DependencyType::Optional,
+            (None, _) => DependencyType::Required,
+        }
+    }
+}
+
+/// Reads `META-INF/neoforge.mods.toml` or `META-INF/mods.toml`, returning `None` if the file
+/// has neither
+fn read_forge_manifest<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+) -> Result<Option<ModManifest>, ValidationError> {
+    let (contents, neoforge) = match read_entry_to_string(archive, "META-INF/neoforge.mods.toml")? {
+        Some(contents) => (contents, true),
+        None => match read_entry_to_string(archive, "META-INF/mods.toml")? {
+            Some(contents) => (contents, false),
+            None => return Ok(None),
+        },
+    };
+    let manifest: ModsToml = toml::from_str(&contents)
+        .map_err(|err| ValidationError::InvalidInput(format!("Invalid mods.toml: {err}").into()))?;
+
+    let Some(first_mod) = manifest.mods.first() else {
+        return Ok(None);
+    };
+    let dependencies = manifest
+        .dependencies
+        .get(&first_mod.mod_id)
+        .map(|x| &**x)
+        .unwrap_or_default();
+
+    // NeoForge mods from before the manifest was renamed still use `mods.toml`
+    let loader = if neoforge || dependencies.iter().any(|x| x.mod_id == "neoforge") {
+        "neoforge"
+    } else {
+        "forge"
+    };
+
+    // The version is usually substituted from the jar's manifest when the mod is loaded
+    let version = match first_mod.version.clone() {
+        Some(version) if version == "${file.jarVersion}" => {
+            read_entry_to_string(archive, "META-INF/MANIFEST.MF")?.and_then(|manifest| {
+                manifest.lines().find_map(|line| {
+                    line.strip_prefix("Implementation-Version:")
+                        .map(|x| x.trim().to_string())
+                })
+            })
+        }
+        version => version,
+    };
+
+    Ok(Some(ModManifest {
+        mod_id: Some(first_mod.mod_id.clone()),
+        version: version.and_then(filter_placeholder),
+        loaders: vec![loader.to_string()],
+        game_versions: dependencies
+            .iter()
+            .find(|x| x.mod_id == "minecraft")
+            .and_then(|x| x.version_range.as_deref())
+            .and_then(VersionRange::parse_maven),
+        environment: manifest.client_side_only.then_some(ModEnvironment::Client),
+        dependencies: dependencies
+            .iter()
+            .filter_map(|x| ManifestDependency::new(&x.mod_id, x.dependency_type()))
+            .collect(),
+        embedded: Vec::new(),
+        min_engine_version: None,
+        module_types: Vec::new(),
+    }))
+}
+
+/// A set of versions, made up of alternatives which each match every one of their bounds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionRange(Vec<Vec<VersionBound>>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum VersionBound {
+    Any,
+    Equal(Vec<u64>),
+    /// Matches every version starting with these components, such as `1.20.x`
+    Prefix(Vec<u64>),
+    Greater(Vec<u64>),
+    GreaterEqual(Vec<u64>),
+    Less(Vec<u64>),
+    LessEqual(Vec<u64>),
+}
+
+impl VersionRange {
+    /// Parses Fabric and Quilt version predicates, such as `>=1.20 <1.21` or `1.20.x`. A
+    /// version matches if it matches any of the predicates.
+    pub fn parse_predicates(predicates: &[String]) -> Option<VersionRange> {
+        let alternatives = predicates
+            .iter()
+            .map(|predicate| {
+                predicate
+                    .split_whitespace()
+                    .map(Self::parse_predicate)
+                    .collect::<Option<Vec<_>>>()
+                    .map(|x| x.concat())
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        (!alternatives.is_empty()).then_some(VersionRange(alternatives))
+    }
+
+    fn parse_predicate(predicate: &str) -> Option<Vec<VersionBound>> {
+        if predicate == "*" {
+            return Some(vec![VersionBound::Any]);
+        }
+
+        for (prefix, bound) in [
+            (
+                ">=",
+                VersionBound::GreaterEqual as fn(Vec<u64>) -> VersionBound,
+            ),
+            ("<=", VersionBound::LessEqual),
+            (">", VersionBound::Greater),
+            ("<", VersionBound::Less),
+            ("=", VersionBound::Equal),
+        ] {
+            if let Some(version) = predicate.strip_prefix(prefix) {
+                return Some(vec![bound(parse_version(version)?)]);
+            }
+        }
+
+        // `~1.20.1` allows patch updates and `^1.20.1` allows minor updates
+        if let Some(version) = predicate.strip_prefix('~') {
+            let version = parse_version(version)?;
+            let upper = match version[..] {
+                [major] => vec![major + 1],
+                [major, minor, ..] => vec![major, minor + 1],
+                [] => return None,
+            };
+            return Some(vec![
+                VersionBound::GreaterEqual(version),
+                VersionBound::Less(upper),
+            ]);
+        }
+        if let Some(version) = predicate.strip_prefix('^') {
+            let version = parse_version(version)?;
+            let upper = vec![version[0] + 1];
+            return Some(vec![
+                VersionBound::GreaterEqual(version),
+                VersionBound::Less(upper),
+            ]);
+        }
+
+        for wildcard in [".x", ".X", ".*"] {
+            if let Some(version) = predicate.strip_suffix(wildcard) {
+                return Some(vec![VersionBound::Prefix(parse_version(version)?)]);
+            }
+        }
+
+        Some(vec![VersionBound::Equal(parse_version(predicate)?)])
+    }
+
+    /// Parses a Maven version range as used by Forge, such as `[1.20,1.21)` or
+    /// `[1.16.5],[1.18,)`
+    pub fn parse_maven(range: &str) -> Option<VersionRange> {
+        let range = range.trim();
+        if range == "*" {
+            return Some(VersionRange(vec![vec![VersionBound::Any]]));
+        }
+
+        let mut alternatives = Vec::new();
+        let mut rest = range;
+        while !rest.is_empty() {
+            let (set, remainder) = if rest.starts_with(['[', '(']) {
+                let end = rest.find([']', ')'])?;
+                (&rest[..=end], &rest[end + 1..])
+            } else {
+                rest.split_once(',').unwrap_or((rest, ""))
+            };
+            alternatives.push(Self::parse_maven_set(set.trim())?);
+            rest = remainder.trim_start().trim_start_matches(',').trim_start();
+        }
+
+        (!alternatives.is_empty()).then_some(VersionRange(alternatives))
+    }
+
+    fn parse_maven_set(set: &str) -> Option<Vec<VersionBound>> {
+        let Some(inner) = set
+            .strip_prefix(['[', '('])
+            .and_then(|x| x.strip_suffix([']', ')']))
+        else {
+            // Forge treats a bare version as a soft requirement, which only matches it
+            return Some(vec![VersionBound::Equal(parse_version(set)?)]);
+        };
+        let inclusive_lower = set.starts_with('[');
+        let inclusive_upper = set.ends_with(']');
+
+        let Some((lower, upper)) = inner.split_once(',') else {
+            return Some(vec![VersionBound::Equal(parse_version(inner)?)]);
+        };
+
+        let mut bounds = Vec::new();
+        if !lower.trim().is_empty() {
+            let version = parse_version(lower.trim())?;
+            bounds.push(if inclusive_lower {
+                VersionBound::GreaterEqual(version)
+            } else {
+                VersionBound::Greater(version)
+            });
+        }
+        if !upper.trim().is_empty() {
+            let version = parse_version(upper.trim())?;
+            bounds.push(if inclusive_upper {
+                VersionBound::LessEqual(version)
+            } else {
+                VersionBound::Less(version)
+            });
+        }
+        if bounds.is_empty() {
+            bounds.push(VersionBound::Any);
+        }
+
+        Some(bounds)
+    }
+
+    /// Whether a game version is in this range. Versions which aren't made up of numbers,
+    /// such as snapshots, never match.
+    pub fn matches(&self, version: &str) -> bool {
+        let Some(version) = parse_version(version) else {
+            return false;
+        };
+
+        self.0.iter().any(|bounds| {
+            bounds.iter().all(|bound| match bound {
+                VersionBound::Any => true,
+                VersionBound::Equal(x) => compare_versions(&version, x) == Ordering::Equal,
+                VersionBound::Prefix(x) => version.starts_with(x),
+                VersionBound::Greater(x) => compare_versions(&version, x) == Ordering::Greater,
+                VersionBound::GreaterEqual(x) => compare_versions(&version, x) != Ordering::Less,
+                VersionBound::Less(x) => compare_versions(&version, x) == Ordering::Less,
+                VersionBound::LessEqual(x) => compare_versions(&version, x) != Ordering::Greater,
+            })
+        })
+    }
+}
+
+/// Parses the numeric components of a version, ignoring any pre-release or build suffix
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    let version = version.split(['-', '+']).next()?;
+
+    version
+        .split('.')
+        .map(|x| x.parse().ok())
+        .collect::<Option<Vec<_>>>()
+        .filter(|x| !x.is_empty())
+}
+
+fn compare_versions(a: &[u64], b: &[u64]) -> Ordering {
+    (0..a.len().max(b.len()))
+        .map(|i| {
+            a.get(i)
+                .copied()
+                .unwrap_or(0)
+                .cmp(&b.get(i).copied().unwrap_or(0))
+        })
+        .find(|x| x.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn predicates(predicates: &[&str]) -> VersionRange {
+        VersionRange::parse_predicates(
+            &predicates.iter().map(|x| x.to_string()).collect::<Vec<_>>(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn fabric_predicates() {
+        let range = predicates(&[">=1.20- <1.21"]);
+        assert!(range.matches("1.20"));
+        assert!(range.matches("1.20.4"));
+        assert!(!range.matches("1.21"));
+        assert!(!range.matches("1.19.4"));
+        assert!(!range.matches("23w13a"));
+
+        let range = predicates(&["1.19.x", "1.20.1"]);
+        assert!(range.matches("1.19"));
+        assert!(range.matches("1.19.2"));
+        assert!(range.matches("1.20.1"));
+        assert!(!range.matches("1.20"));
+
+        let range = predicates(&["~1.20.1"]);
+        assert!(range.matches("1.20.6"));
+        assert!(!range.matches("1.20"));
+        assert!(!range.matches("1.21"));
+
+        assert!(predicates(&["*"]).matches("1.8.9"));
+        assert!(VersionRange::parse_predicates(&["1.20-pre1 >=b".to_string()]).is_none());
+    }
+
+    #[test]
+    fn maven_ranges() {
+        let range = VersionRange::parse_maven("[1.20,1.21)").unwrap();
+        assert!(range.matches("1.20"));
+        assert!(range.matches("1.20.1"));
+        assert!(!range.matches("1.21"));
+
+        let range = VersionRange::parse_maven("[1.16.5],(1.18,]").unwrap();
+        assert!(range.matches("1.16.5"));
+        assert!(!range.matches("1.17.1"));
+        assert!(!range.matches("1.18"));
+        assert!(range.matches("1.20.4"));
+
+        let range = VersionRange::parse_maven("1.12.2").unwrap();
+        assert!(range.matches("1.12.2"));
+        assert!(!range.matches("1.12.1"));
+
+        assert!(VersionRange::parse_maven("[1.20").is_none());
+    }
+
+    fn build_jar(files: &[(&str, &[u8])]) -> Vec<u8> {
+        use std::io::Write;
+
+        let mut cursor = Cursor::new(Vec::new());
+        let mut zip = zip::ZipWriter::new(&mut cursor);
+        for (name, contents) in files {
+            zip.start_file(*name, zip::write::FileOptions::default())
+                .unwrap();
+            zip.write_all(contents).unwrap();
+        }
+        zip.finish().unwrap();
+        drop(zip);
+
+        cursor.into_inner()
+    }
+
+    #[test]
+    fn dependencies() {
+        let embedded = build_jar(&[(
+            "fabric.mod.json",
+            br#"{ "id": "bundled", "version": "1.0.0" }"#,
+        )]);
+        let jar = build_jar(&[
+            (
+                "fabric.mod.json",
+                br#"{
+                    "id": "example",
+                    "version": "1.0.0",
+                    "depends": { "minecraft": "1.20.1", "fabricloader": "*", "lib": "*" },
+                    "recommends": { "extra": "*" },
+                    "breaks": { "broken": "<2" }
+                }"#,
+            ),
+            ("META-INF/jars/bundled-1.0.0.jar", &embedded),
+        ]);
+        let manifest = read_manifest(&mut ZipArchive::new(Cursor::new(jar)).unwrap()).unwrap();
+
+        assert_eq!(
+            manifest.dependencies,
+            vec![
+                ManifestDependency::new("lib", DependencyType::Required).unwrap(),
+                ManifestDependency::new("extra", DependencyType::Optional).unwrap(),
+                ManifestDependency::new("broken", DependencyType::Incompatible).unwrap(),
+            ]
+        );
+        assert_eq!(
+            manifest.embedded,
+            vec![EmbeddedJar {
+                file_name: "bundled-1.0.0.jar".to_string(),
+                mod_id: Some("bundled".to_string()),
+            }]
+        );
+
+        let jar = build_jar(&[(
+            "META-INF/neoforge.mods.toml",
+            br#"
+                [[mods]]
+                modId = "example"
+                version = "1.0.0"
+
+                [[dependencies.example]]
+                modId = "neoforge"
+                type = "required"
+
+                [[dependencies.example]]
+                modId = "lib"
+                type = "optional"
+
+                [[dependencies.example]]
+                modId = "broken"
+                type = "incompatible"
+            "#,
+        )]);
+        let manifest = read_manifest(&mut ZipArchive::new(Cursor::new(jar)).unwrap()).unwrap();
+
+        assert_eq!(manifest.loaders, vec!["neoforge".to_string()]);
+        assert_eq!(
+            manifest.dependencies,
+            vec![
+                ManifestDependency::new("lib", DependencyType::Optional).unwrap(),
+                ManifestDependency::new("broken", DependencyType::Incompatible).unwrap(),
+            ]
+        );
+    }
+}
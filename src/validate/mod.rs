@@ -1,35 +1,35 @@
 use crate::database::models::legacy_loader_fields::MinecraftGameVersion;
-use crate::database::models::loader_fields::VersionField;
+use crate::database::models::loader_fields::{Game, ValidationPolicy, VersionField};
 use crate::database::models::DatabaseError;
 use crate::database::redis::RedisPool;
 use crate::models::pack::PackFormat;
-use crate::models::projects::{FileType, Loader};
-use crate::validate::datapack::DataPackValidator;
-use crate::validate::fabric::FabricValidator;
-use crate::validate::forge::{ForgeValidator, LegacyForgeValidator};
-use crate::validate::liteloader::LiteLoaderValidator;
-use crate::validate::modpack::ModpackValidator;
-use crate::validate::neoforge::NeoForgeValidator;
-use crate::validate::plugin::*;
-use crate::validate::quilt::QuiltValidator;
-use crate::validate::resourcepack::{PackValidator, TexturePackValidator};
-use crate::validate::rift::RiftValidator;
-use crate::validate::shader::{CanvasShaderValidator, CoreShaderValidator, ShaderValidator};
+use crate::models::projects::{FileType, Loader, ValidationReport, ValidationSeverity};
+use crate::validate::bedrock::BedrockFormat;
+use crate::validate::manifest::ModManifest;
+use crate::validate::registry::ValidatorSet;
+use crate::validate::resourcepack::PackValidator;
+use crate::validate::scanner::ScanFinding;
 use chrono::{DateTime, Utc};
-use std::io::Cursor;
+use std::fs::File;
 use thiserror::Error;
 use zip::ZipArchive;
 
+pub mod archive;
+pub mod bedrock;
 mod datapack;
 mod fabric;
 mod forge;
 mod liteloader;
+pub mod manifest;
 mod modpack;
 mod neoforge;
+pub mod pack_contents;
 pub mod plugin;
 mod quilt;
+pub mod registry;
 mod resourcepack;
 mod rift;
+pub mod scanner;
 mod shader;
 
 #[derive(Error, Debug)]
@@ -46,6 +46,20 @@ pub enum ValidationError {
     Blocking(#[from] actix_web::error::BlockingError),
     #[error("Error while querying database")]
     Database(#[from] DatabaseError),
+    #[error("Validation policy {0} has an unknown validator: {1}")]
+    UnknownValidator(String, String),
+    #[error("Archive contains more than {0} entries")]
+    TooManyEntries(usize),
+    #[error("Archive exceeds the maximum uncompressed size of {0} bytes")]
+    TooLarge(u64),
+    #[error("Archive entry {0} is compressed suspiciously well")]
+    CompressionRatio(String),
+    #[error("Archive entry {0} is nested inside too many other archives")]
+    NestingTooDeep(String),
+    #[error("Bundled archive {0} exceeds the maximum size of {1} bytes")]
+    NestedArchiveTooLarge(String, u64),
+    #[error("Archive entry {0} has a path outside of the archive")]
+    UnsafePath(String),
 }
 
 #[derive(Eq, PartialEq, Debug)]
@@ -55,20 +69,20 @@ pub enum ValidationResult {
         format: PackFormat,
         files: Vec<String>,
     },
-    /// File should be marked as primary
+    /// File should be marked as primary, unless the report contains errors
     Pass,
-    /// File should not be marked primary, the reason for which is inside the String
-    Warning(&'static str),
 }
 
-impl ValidationResult {
-    pub fn is_passed(&self) -> bool {
-        match self {
-            ValidationResult::PassWithPackDataAndFiles { .. } => true,
-            ValidationResult::Pass => true,
-            ValidationResult::Warning(_) => false,
-        }
-    }
+/// The outcome of validating an uploaded file
+pub struct FileValidation {
+    pub result: ValidationResult,
+    /// Every problem found by the validators. The file should only be marked as primary if
+    /// the report has passed.
+    pub report: ValidationReport,
+    /// The metadata declared by the file's mod manifest, if it has one
+    pub manifest: Option<ModManifest>,
+    /// Suspicious code found in the file's classes
+    pub scan_findings: Vec<ScanFinding>,
 }
 
 pub enum SupportedGameVersions {
@@ -83,57 +97,55 @@ pub trait Validator: Sync {
     fn get_file_extensions(&self) -> &[&str];
     fn get_supported_loaders(&self) -> &[&str];
     fn get_supported_game_versions(&self) -> SupportedGameVersions;
+    /// Validates the archive, adding any problems found to the report
     fn validate(
         &self,
-        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+        archive: &mut ZipArchive<File>,
+        report: &mut ValidationReport,
     ) -> Result<ValidationResult, ValidationError>;
+    /// Validates the archive against the game versions its version declares. Validators which
+    /// don't check anything depending on the game versions only implement `validate`.
+    fn validate_with_game_versions(
+        &self,
+        archive: &mut ZipArchive<File>,
+        report: &mut ValidationReport,
+        _game_versions: &[MinecraftGameVersion],
+    ) -> Result<ValidationResult, ValidationError> {
+        self.validate(archive, report)
+    }
 }
 
-static ALWAYS_ALLOWED_EXT: &[&str] = &["zip", "txt"];
-
-static VALIDATORS: &[&dyn Validator] = &[
-    &ModpackValidator,
-    &FabricValidator,
-    &ForgeValidator,
-    &LegacyForgeValidator,
-    &QuiltValidator,
-    &LiteLoaderValidator,
-    &PackValidator,
-    &TexturePackValidator,
-    &PluginYmlValidator,
-    &BungeeCordValidator,
-    &VelocityValidator,
-    &SpongeValidator,
-    &CanvasShaderValidator,
-    &ShaderValidator,
-    &CoreShaderValidator,
-    &DataPackValidator,
-    &RiftValidator,
-    &NeoForgeValidator,
-];
-
-/// The return value is whether this file should be marked as primary or not, based on the analysis of the file
+/// The returned result is whether this file should be marked as primary or not, based on the analysis of the file
 #[allow(clippy::too_many_arguments)]
 pub async fn validate_file(
-    data: bytes::Bytes,
+    file: File,
     file_extension: String,
     loaders: Vec<Loader>,
     file_type: Option<FileType>,
     version_fields: Vec<VersionField>,
     transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     redis: &RedisPool,
-) -> Result<ValidationResult, ValidationError> {
-    let game_versions = version_fields
-        .into_iter()
-        .find_map(|v| MinecraftGameVersion::try_from_version_field(&v).ok())
-        .unwrap_or_default();
-    let all_game_versions =
-        MinecraftGameVersion::list(None, None, &mut *transaction, redis).await?;
+) -> Result<FileValidation, ValidationError> {
+    let validator_sets = get_validator_sets(&loaders, &mut *transaction, redis).await?;
 
-    validate_minecraft_file(
-        data,
+    let (game_versions, all_game_versions) = if validator_sets.iter().any(|x| x.uses_game_versions)
+    {
+        (
+            version_fields
+                .into_iter()
+                .find_map(|v| MinecraftGameVersion::try_from_version_field(&v).ok())
+                .unwrap_or_default(),
+            MinecraftGameVersion::list(None, None, &mut *transaction, redis).await?,
+        )
+    } else {
+        (Vec::new(), Vec::new())
+    };
+
+    validate_archive(
+        file,
         file_extension,
         loaders,
+        validator_sets,
         game_versions,
         all_game_versions,
         file_type,
@@ -141,79 +153,175 @@ pub async fn validate_file(
     .await
 }
 
-async fn validate_minecraft_file(
-    data: bytes::Bytes,
+/// Finds the validator sets of the policies of the games the loaders belong to. Games with no
+/// validation are skipped, so the result is empty if none of the games validate their files.
+async fn get_validator_sets(
+    loaders: &[Loader],
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    redis: &RedisPool,
+) -> Result<Vec<ValidatorSet>, ValidationError> {
+    let all_loaders =
+        crate::database::models::loader_fields::Loader::list(&mut **transaction, redis).await?;
+    let games = Game::list(&mut **transaction, redis).await?;
+    let policies = ValidationPolicy::list(&mut **transaction, redis).await?;
+
+    let mut validator_sets: Vec<ValidatorSet> = Vec::new();
+    for game in games.into_iter().filter(|game| {
+        all_loaders.iter().any(|x| {
+            loaders.iter().any(|y| y.0 == x.loader) && x.supported_games.contains(&game.slug)
+        })
+    }) {
+        if validator_sets
+            .iter()
+            .any(|x| x.policy == game.validation_policy)
+        {
+            continue;
+        }
+
+        // Games reference their policy by a foreign key, so it always exists
+        let Some(policy) = policies.iter().find(|x| x.name == game.validation_policy) else {
+            continue;
+        };
+
+        if let Some(set) = ValidatorSet::from_policy(policy.clone())? {
+            validator_sets.push(set);
+        }
+    }
+
+    Ok(validator_sets)
+}
+
+async fn validate_archive(
+    file: File,
     file_extension: String,
     loaders: Vec<Loader>,
+    validator_sets: Vec<ValidatorSet>,
     game_versions: Vec<MinecraftGameVersion>,
     all_game_versions: Vec<MinecraftGameVersion>,
     file_type: Option<FileType>,
-) -> Result<ValidationResult, ValidationError> {
+) -> Result<FileValidation, ValidationError> {
     actix_web::web::block(move || {
-        let reader = Cursor::new(data);
-        let mut zip = ZipArchive::new(reader)?;
+        // Files of games without validation aren't necessarily archives. Those which are still
+        // have their manifest read and their classes scanned, only the validators are skipped.
+        if validator_sets.is_empty() && ZipArchive::new(&file).is_err() {
+            return Ok(FileValidation {
+                result: ValidationResult::Pass,
+                report: ValidationReport::default(),
+                manifest: None,
+                scan_findings: Vec::new(),
+            });
+        }
 
-        if let Some(file_type) = file_type {
-            match file_type {
-                FileType::RequiredResourcePack | FileType::OptionalResourcePack => {
-                    return PackValidator.validate(&mut zip);
-                }
-                FileType::Unknown => {}
+        let mut zip = archive::open_archive(file)?;
+
+        let mut report = ValidationReport::default();
+        let mut result = ValidationResult::Pass;
+        for validator_set in validator_sets {
+            let set_result = run_validators(
+                &mut zip,
+                &mut report,
+                &validator_set,
+                &file_extension,
+                &loaders,
+                &game_versions,
+                &all_game_versions,
+                file_type,
+            )?;
+
+            if let ValidationResult::PassWithPackDataAndFiles { .. } = set_result {
+                result = set_result;
             }
         }
+        let manifest = match BedrockFormat::from_extension(&file_extension) {
+            Some(format) => bedrock::read_bedrock_manifest(&mut zip, format),
+            None => manifest::read_manifest(&mut zip),
+        };
+        let scan_findings = scanner::scan_archive(&mut zip);
 
-        let mut visited = false;
-        let mut saved_result = None;
-        for validator in VALIDATORS {
-            if loaders
-                .iter()
-                .any(|x| validator.get_supported_loaders().contains(&&*x.0))
-                && game_version_supported(
-                    &game_versions,
-                    &all_game_versions,
+        Ok(FileValidation {
+            result,
+            report,
+            manifest,
+            scan_findings,
+        })
+    })
+    .await?
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_validators(
+    zip: &mut ZipArchive<File>,
+    report: &mut ValidationReport,
+    validator_set: &ValidatorSet,
+    file_extension: &str,
+    loaders: &[Loader],
+    game_versions: &[MinecraftGameVersion],
+    all_game_versions: &[MinecraftGameVersion],
+    file_type: Option<FileType>,
+) -> Result<ValidationResult, ValidationError> {
+    if let Some(file_type) = file_type {
+        match file_type {
+            FileType::RequiredResourcePack | FileType::OptionalResourcePack => {
+                return PackValidator.validate_with_game_versions(zip, report, game_versions);
+            }
+            FileType::Unknown => {}
+        }
+    }
+
+    let mut visited = false;
+    let mut saved_result = None;
+    for validator in &validator_set.validators {
+        if loaders
+            .iter()
+            .any(|x| validator.get_supported_loaders().contains(&&*x.0))
+            && (!validator_set.uses_game_versions
+                || game_version_supported(
+                    game_versions,
+                    all_game_versions,
                     validator.get_supported_game_versions(),
-                )
-            {
-                if validator.get_file_extensions().contains(&&*file_extension) {
-                    let result = validator.validate(&mut zip)?;
-                    match result {
-                        ValidationResult::PassWithPackDataAndFiles { .. } => {
+                ))
+        {
+            if validator.get_file_extensions().contains(&file_extension) {
+                let result = validator.validate_with_game_versions(zip, report, game_versions)?;
+                match result {
+                    ValidationResult::PassWithPackDataAndFiles { .. } => {
+                        saved_result = Some(result);
+                    }
+                    ValidationResult::Pass => {
+                        if saved_result.is_none() {
                             saved_result = Some(result);
                         }
-                        ValidationResult::Pass => {
-                            if saved_result.is_none() {
-                                saved_result = Some(result);
-                            }
-                        }
-                        ValidationResult::Warning(_) => {
-                            return Ok(result);
-                        }
                     }
-                } else {
-                    visited = true;
                 }
+            } else {
+                visited = true;
             }
         }
+    }
 
-        if let Some(result) = saved_result {
-            return Ok(result);
-        }
+    if let Some(result) = saved_result {
+        return Ok(result);
+    }
 
-        if visited {
-            if ALWAYS_ALLOWED_EXT.contains(&&*file_extension) {
-                Ok(ValidationResult::Warning(
-                    "File extension is invalid for input file",
-                ))
-            } else {
-                Err(ValidationError::InvalidInput(
-                    format!("File extension {file_extension} is invalid for input file").into(),
-                ))
-            }
-        } else {
+    if visited {
+        if validator_set
+            .always_allowed_extensions
+            .iter()
+            .any(|x| x == file_extension)
+        {
+            report.error(
+                "invalid_extension",
+                "File extension is invalid for input file",
+            );
             Ok(ValidationResult::Pass)
+        } else {
+            Err(ValidationError::InvalidInput(
+                format!("File extension {file_extension} is invalid for input file").into(),
+            ))
         }
-    })
-    .await?
+    } else {
+        Ok(ValidationResult::Pass)
+    }
 }
 
 // Write tests for this
@@ -246,21 +354,23 @@ fn game_version_supported(
     }
 }
 
-pub fn filter_out_packs(
-    archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
-) -> Result<ValidationResult, ValidationError> {
-    if (archive.by_name("modlist.html").is_ok() && archive.by_name("manifest.json").is_ok())
-        || archive
-            .file_names()
-            .any(|x| x.starts_with("mods/") && x.ends_with(".jar"))
-        || archive
-            .file_names()
-            .any(|x| x.starts_with("override/mods/") && x.ends_with(".jar"))
+/// Reports mod files which are actually modpacks in another launcher's format
+pub fn filter_out_packs(archive: &mut ZipArchive<File>, report: &mut ValidationReport) {
+    let packed_mod = archive
+        .file_names()
+        .find(|x| {
+            (x.starts_with("mods/") || x.starts_with("override/mods/")) && x.ends_with(".jar")
+        })
+        .map(|x| x.to_string());
+
+    if packed_mod.is_some()
+        || (archive.by_name("modlist.html").is_ok() && archive.by_name("manifest.json").is_ok())
     {
-        return Ok(ValidationResult::Warning(
+        report.push(
+            ValidationSeverity::Error,
+            "not_a_modpack",
             "Invalid modpack file. You must upload a valid .MRPACK file.",
-        ));
+            packed_mod,
+        );
     }
-
-    Ok(ValidationResult::Pass)
 }
@@ -1,8 +1,9 @@
 use crate::models::pack::{PackFileHash, PackFormat};
+use crate::models::projects::ValidationReport;
 use crate::util::validate::validation_errors_to_string;
+use crate::validate::archive::{is_safe_path, read_entry_to_string};
 use crate::validate::{SupportedGameVersions, ValidationError, ValidationResult};
-use std::io::{Cursor, Read};
-use std::path::Component;
+use std::fs::File;
 use validator::Validate;
 use zip::ZipArchive;
 
@@ -23,20 +24,14 @@ impl super::Validator for ModpackValidator {
 
     fn validate(
         &self,
-        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+        archive: &mut ZipArchive<File>,
+        report: &mut ValidationReport,
     ) -> Result<ValidationResult, ValidationError> {
-        let pack: PackFormat = {
-            let mut file = if let Ok(file) = archive.by_name("modrinth.index.json") {
-                file
-            } else {
-                return Ok(ValidationResult::Warning("Pack manifest is missing."));
-            };
-
-            let mut contents = String::new();
-            file.read_to_string(&mut contents)?;
-
-            serde_json::from_str(&contents)?
+        let Some(contents) = read_entry_to_string(archive, "modrinth.index.json")? else {
+            report.error("missing_pack_manifest", "Pack manifest is missing.");
+            return Ok(ValidationResult::Pass);
         };
+        let pack: PackFormat = serde_json::from_str(&contents)?;
 
         pack.validate().map_err(|err| {
             ValidationError::InvalidInput(validation_errors_to_string(err, None).into())
@@ -65,19 +60,11 @@ impl super::Validator for ModpackValidator {
                 ));
             }
 
-            let path = std::path::Path::new(&file.path)
-                .components()
-                .next()
-                .ok_or_else(|| ValidationError::InvalidInput("Invalid pack file path!".into()))?;
-
-            match path {
-                Component::CurDir | Component::Normal(_) => {}
-                _ => {
-                    return Err(ValidationError::InvalidInput(
-                        "Invalid pack file path!".into(),
-                    ))
-                }
-            };
+            if !is_safe_path(&file.path) {
+                return Err(ValidationError::InvalidInput(
+                    "Invalid pack file path!".into(),
+                ));
+            }
         }
 
         Ok(ValidationResult::PassWithPackDataAndFiles {
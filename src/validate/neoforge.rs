@@ -1,5 +1,6 @@
+use crate::models::projects::ValidationReport;
 use crate::validate::{filter_out_packs, SupportedGameVersions, ValidationError, ValidationResult};
-use std::io::Cursor;
+use std::fs::File;
 use zip::ZipArchive;
 
 pub struct NeoForgeValidator;
@@ -19,19 +20,21 @@ impl super::Validator for NeoForgeValidator {
 
     fn validate(
         &self,
-        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+        archive: &mut ZipArchive<File>,
+        report: &mut ValidationReport,
     ) -> Result<ValidationResult, ValidationError> {
         if archive.by_name("META-INF/mods.toml").is_err()
             && archive.by_name("META-INF/neoforge.mods.toml").is_err()
             && archive.by_name("META-INF/MANIFEST.MF").is_err()
             && !archive.file_names().any(|x| x.ends_with(".class"))
         {
-            return Ok(ValidationResult::Warning(
+            report.error(
+                "missing_mods_toml",
                 "No neoforge.mods.toml, mods.toml, or valid class files present for NeoForge file.",
-            ));
+            );
         }
 
-        filter_out_packs(archive)?;
+        filter_out_packs(archive, report);
 
         Ok(ValidationResult::Pass)
     }
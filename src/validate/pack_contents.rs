@@ -0,0 +1,234 @@
+use crate::database::models::ids::{ProjectId, VersionId};
+use crate::database::models::modpack_content_item::ModpackContent;
+use crate::database::models::DatabaseError;
+use crate::models::ids::base62_impl::parse_base62;
+use crate::models::pack::{PackFile, PackFileHash, PackFileStatus};
+use crate::models::projects::{ProjectStatus, ValidationReport, ValidationSeverity, VersionStatus};
+use std::collections::{HashMap, HashSet};
+
+/// A hosted file which has the same hash as a file of the modpack
+struct HostedFile {
+    project_id: ProjectId,
+    version_id: VersionId,
+    size: u64,
+    hidden: bool,
+}
+
+/// Resolves the files of a modpack against the files hosted on Modrinth, reporting files
+/// which can't be downloaded from a visible version or whose declared size is wrong.
+/// `cdn_url` is the base URL files hosted on Modrinth are downloaded from.
+pub async fn resolve_pack_files(
+    files: &[PackFile],
+    cdn_url: &str,
+    report: &mut ValidationReport,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<Vec<ModpackContent>, DatabaseError> {
+    let hashes = |algorithm: &PackFileHash| {
+        files
+            .iter()
+            .filter_map(|x| x.hashes.get(algorithm))
+            .map(|x| x.as_bytes().to_vec())
+            .collect::<Vec<_>>()
+    };
+
+    let rows = sqlx::query!(
+        "
+        SELECT h.algorithm, h.hash, f.size, v.id version_id, v.status version_status,
+            m.id project_id, m.status project_status
+        FROM hashes h
+        INNER JOIN files f ON f.id = h.file_id
+        INNER JOIN versions v ON v.id = f.version_id
+        INNER JOIN mods m ON m.id = v.mod_id
+        WHERE (h.algorithm = 'sha1' AND h.hash = ANY($1))
+            OR (h.algorithm = 'sha512' AND h.hash = ANY($2))
+        ",
+        &hashes(&PackFileHash::Sha1)[..],
+        &hashes(&PackFileHash::Sha512)[..],
+    )
+    .fetch_all(&mut **transaction)
+    .await?;
+
+    let mut hosted: HashMap<(String, Vec<u8>), Vec<HostedFile>> = HashMap::new();
+    for row in rows {
+        hosted
+            .entry((row.algorithm, row.hash))
+            .or_default()
+            .push(HostedFile {
+                project_id: ProjectId(row.project_id),
+                version_id: VersionId(row.version_id),
+                size: row.size as u64,
+                hidden: VersionStatus::from_string(&row.version_status).is_hidden()
+                    || ProjectStatus::from_string(&row.project_status).is_hidden(),
+            });
+    }
+
+    let find_hosted = |file: &PackFile| {
+        [
+            ("sha512", PackFileHash::Sha512),
+            ("sha1", PackFileHash::Sha1),
+        ]
+        .iter()
+        .filter_map(|(algorithm, hash)| {
+            let hash = file.hashes.get(hash)?.as_bytes().to_vec();
+            hosted.get(&(algorithm.to_string(), hash))
+        })
+        .flatten()
+        // The same file may be uploaded to several versions, of which visible ones are preferred
+        .min_by_key(|x| x.hidden)
+    };
+
+    // Files which aren't hosted anymore may still link to the version they were hosted in
+    let linked_versions = files
+        .iter()
+        .filter(|x| find_hosted(x).is_none())
+        .filter_map(|x| {
+            x.downloads
+                .iter()
+                .find_map(|x| get_cdn_version_id(x, cdn_url))
+        })
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .map(|x| x.0)
+        .collect::<Vec<_>>();
+    let existing_versions = sqlx::query!(
+        "
+        SELECT id FROM versions
+        WHERE id = ANY($1)
+        ",
+        &linked_versions[..],
+    )
+    .fetch_all(&mut **transaction)
+    .await?
+    .into_iter()
+    .map(|x| VersionId(x.id))
+    .collect::<HashSet<_>>();
+
+    let mut contents = Vec::new();
+    for file in files {
+        let content = if let Some(hosted) = find_hosted(file) {
+            ModpackContent {
+                path: file.path.clone(),
+                status: if hosted.hidden {
+                    PackFileStatus::Hidden
+                } else {
+                    PackFileStatus::Known
+                },
+                project_id: Some(hosted.project_id),
+                version_id: Some(hosted.version_id),
+                file_size: file.file_size as u64,
+                size_mismatch: hosted.size != file.file_size as u64,
+            }
+        } else {
+            let deleted = file
+                .downloads
+                .iter()
+                .find_map(|x| get_cdn_version_id(x, cdn_url))
+                .is_some_and(|x| !existing_versions.contains(&x));
+
+            ModpackContent {
+                path: file.path.clone(),
+                status: if deleted {
+                    PackFileStatus::Deleted
+                } else {
+                    PackFileStatus::Unknown
+                },
+                project_id: None,
+                version_id: None,
+                file_size: file.file_size as u64,
+                size_mismatch: false,
+            }
+        };
+
+        match content.status {
+            PackFileStatus::Known => {}
+            PackFileStatus::Unknown => report.push(
+                ValidationSeverity::Info,
+                "unknown_pack_file",
+                "File is not hosted on Modrinth",
+                Some(file.path.clone()),
+            ),
+            PackFileStatus::Deleted => report.push(
+                ValidationSeverity::Warning,
+                "deleted_pack_file",
+                "File is downloaded from a version which has been deleted",
+                Some(file.path.clone()),
+            ),
+            PackFileStatus::Hidden => report.push(
+                ValidationSeverity::Warning,
+                "hidden_pack_file",
+                "File belongs to a version which is not publicly visible",
+                Some(file.path.clone()),
+            ),
+        }
+        if content.size_mismatch {
+            report.push(
+                ValidationSeverity::Warning,
+                "pack_file_size_mismatch",
+                format!(
+                    "File declares a size of {} bytes, which differs from the hosted file",
+                    file.file_size
+                ),
+                Some(file.path.clone()),
+            );
+        }
+
+        contents.push(content);
+    }
+
+    Ok(contents)
+}
+
+/// Gets the version a CDN download URL points to, like
+/// `{cdn_url}/data/{project_id}/versions/{version_id}/{file_name}`
+fn get_cdn_version_id(url: &str, cdn_url: &str) -> Option<VersionId> {
+    let url = url::Url::parse(url).ok()?;
+    let cdn_url = url::Url::parse(cdn_url).ok()?;
+    if url.host_str()? != cdn_url.host_str()? {
+        return None;
+    }
+
+    let mut segments = url.path_segments()?;
+    match (segments.next(), segments.next(), segments.next()) {
+        (Some("data"), Some(_), Some("versions")) => {
+            let id = parse_base62(segments.next()?).ok()?;
+            Some(VersionId(id as i64))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CDN_URL: &str = "https://cdn.modrinth.com";
+
+    #[test]
+    fn cdn_version_ids() {
+        assert_eq!(
+            get_cdn_version_id(
+                "https://cdn.modrinth.com/data/AANobbMI/versions/IZskON6d/sodium.jar",
+                CDN_URL
+            ),
+            Some(VersionId(parse_base62("IZskON6d").unwrap() as i64))
+        );
+        assert_eq!(
+            get_cdn_version_id(
+                "https://github.com/data/AANobbMI/versions/IZskON6d/sodium.jar",
+                CDN_URL
+            ),
+            None
+        );
+        assert_eq!(
+            get_cdn_version_id("https://cdn.modrinth.com/user/AANobbMI/icon.png", CDN_URL),
+            None
+        );
+        assert_eq!(
+            get_cdn_version_id(
+                "https://cdn.modrinth.com/data/AANobbMI/versions/IZskON6d/sodium.jar",
+                "https://staging-cdn.modrinth.com"
+            ),
+            None
+        );
+    }
+}
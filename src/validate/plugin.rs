@@ -1,5 +1,6 @@
+use crate::models::projects::ValidationReport;
 use crate::validate::{SupportedGameVersions, ValidationError, ValidationResult};
-use std::io::Cursor;
+use std::fs::File;
 use zip::ZipArchive;
 
 pub struct PluginYmlValidator;
@@ -19,15 +20,17 @@ impl super::Validator for PluginYmlValidator {
 
     fn validate(
         &self,
-        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+        archive: &mut ZipArchive<File>,
+        report: &mut ValidationReport,
     ) -> Result<ValidationResult, ValidationError> {
         if !archive
             .file_names()
             .any(|name| name == "plugin.yml" || name == "paper-plugin.yml")
         {
-            return Ok(ValidationResult::Warning(
+            report.error(
+                "missing_plugin_yml",
                 "No plugin.yml or paper-plugin.yml present for plugin file.",
-            ));
+            );
         };
 
         Ok(ValidationResult::Pass)
@@ -51,15 +54,17 @@ impl super::Validator for BungeeCordValidator {
 
     fn validate(
         &self,
-        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+        archive: &mut ZipArchive<File>,
+        report: &mut ValidationReport,
     ) -> Result<ValidationResult, ValidationError> {
         if !archive
             .file_names()
             .any(|name| name == "plugin.yml" || name == "bungee.yml")
         {
-            return Ok(ValidationResult::Warning(
+            report.error(
+                "missing_bungee_yml",
                 "No plugin.yml or bungee.yml present for plugin file.",
-            ));
+            );
         };
 
         Ok(ValidationResult::Pass)
@@ -83,12 +88,14 @@ impl super::Validator for VelocityValidator {
 
     fn validate(
         &self,
-        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+        archive: &mut ZipArchive<File>,
+        report: &mut ValidationReport,
     ) -> Result<ValidationResult, ValidationError> {
         if archive.by_name("velocity-plugin.json").is_err() {
-            return Ok(ValidationResult::Warning(
+            report.error(
+                "missing_velocity_plugin_json",
                 "No velocity-plugin.json present for plugin file.",
-            ));
+            );
         }
 
         Ok(ValidationResult::Pass)
@@ -112,16 +119,18 @@ impl super::Validator for SpongeValidator {
 
     fn validate(
         &self,
-        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+        archive: &mut ZipArchive<File>,
+        report: &mut ValidationReport,
     ) -> Result<ValidationResult, ValidationError> {
         if !archive.file_names().any(|name| {
             name == "sponge_plugins.json"
                 || name == "mcmod.info"
                 || name == "META-INF/sponge_plugins.json"
         }) {
-            return Ok(ValidationResult::Warning(
+            report.error(
+                "missing_sponge_plugins_json",
                 "No sponge_plugins.json or mcmod.info present for Sponge plugin.",
-            ));
+            );
         };
 
         Ok(ValidationResult::Pass)
@@ -1,6 +1,7 @@
+use crate::models::projects::ValidationReport;
 use crate::validate::{filter_out_packs, SupportedGameVersions, ValidationError, ValidationResult};
 use chrono::DateTime;
-use std::io::Cursor;
+use std::fs::File;
 use zip::ZipArchive;
 
 pub struct QuiltValidator;
@@ -20,16 +21,18 @@ impl super::Validator for QuiltValidator {
 
     fn validate(
         &self,
-        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+        archive: &mut ZipArchive<File>,
+        report: &mut ValidationReport,
     ) -> Result<ValidationResult, ValidationError> {
         if archive.by_name("quilt.mod.json").is_err() && archive.by_name("fabric.mod.json").is_err()
         {
-            return Ok(ValidationResult::Warning(
+            report.error(
+                "missing_quilt_mod_json",
                 "No quilt.mod.json present for Quilt file.",
-            ));
+            );
         }
 
-        filter_out_packs(archive)?;
+        filter_out_packs(archive, report);
 
         Ok(ValidationResult::Pass)
     }
@@ -0,0 +1,127 @@
+use crate::database::models::loader_fields::ValidationPolicy;
+use crate::validate::bedrock::{McAddonValidator, McPackValidator, McWorldValidator};
+use crate::validate::datapack::DataPackValidator;
+use crate::validate::fabric::FabricValidator;
+use crate::validate::forge::{ForgeValidator, LegacyForgeValidator};
+use crate::validate::liteloader::LiteLoaderValidator;
+use crate::validate::modpack::ModpackValidator;
+use crate::validate::neoforge::NeoForgeValidator;
+use crate::validate::plugin::*;
+use crate::validate::quilt::QuiltValidator;
+use crate::validate::resourcepack::{PackValidator, TexturePackValidator};
+use crate::validate::rift::RiftValidator;
+use crate::validate::shader::{CanvasShaderValidator, CoreShaderValidator, ShaderValidator};
+use crate::validate::{ValidationError, Validator};
+
+/// The validators which validation policies are composed of, by the names policies refer to
+/// them with
+static VALIDATORS: &[(&str, &dyn Validator)] = &[
+    ("modpack", &ModpackValidator),
+    ("fabric", &FabricValidator),
+    ("forge", &ForgeValidator),
+    ("legacy-forge", &LegacyForgeValidator),
+    ("quilt", &QuiltValidator),
+    ("liteloader", &LiteLoaderValidator),
+    ("pack", &PackValidator),
+    ("texture-pack", &TexturePackValidator),
+    ("plugin-yml", &PluginYmlValidator),
+    ("bungeecord", &BungeeCordValidator),
+    ("velocity", &VelocityValidator),
+    ("sponge", &SpongeValidator),
+    ("canvas-shader", &CanvasShaderValidator),
+    ("shader", &ShaderValidator),
+    ("core-shader", &CoreShaderValidator),
+    ("datapack", &DataPackValidator),
+    ("rift", &RiftValidator),
+    ("neoforge", &NeoForgeValidator),
+    ("mcpack", &McPackValidator),
+    ("mcaddon", &McAddonValidator),
+    ("mcworld", &McWorldValidator),
+];
+
+pub fn get_validator(name: &str) -> Option<&'static dyn Validator> {
+    VALIDATORS
+        .iter()
+        .find(|(x, _)| *x == name)
+        .map(|(_, validator)| *validator)
+}
+
+/// The validators of a validation policy, which games select through their
+/// `validation_policy`. Validators of a set are further selected by the loaders of the uploaded
+/// version.
+pub struct ValidatorSet {
+    pub policy: String,
+    pub validators: Vec<&'static dyn Validator>,
+    /// Whether the validators are restricted to some game versions, which requires the
+    /// version's `game_versions` to be resolved
+    pub uses_game_versions: bool,
+    /// The extensions which are accepted, but can't make a file primary, when no validator of
+    /// the version's loaders supports them
+    pub always_allowed_extensions: Vec<String>,
+}
+
+impl ValidatorSet {
+    /// Resolves the validators of a policy. Policies without validators store files as
+    /// uploaded, so they have no set.
+    pub fn from_policy(policy: ValidationPolicy) -> Result<Option<ValidatorSet>, ValidationError> {
+        if policy.validators.is_empty() {
+            return Ok(None);
+        }
+
+        let validators = policy
+            .validators
+            .iter()
+            .map(|name| {
+                get_validator(name).ok_or_else(|| {
+                    ValidationError::UnknownValidator(policy.name.clone(), name.clone())
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Some(ValidatorSet {
+            policy: policy.name,
+            validators,
+            uses_game_versions: policy.uses_game_versions,
+            always_allowed_extensions: policy.always_allowed_extensions,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(name: &str, validators: &[&str]) -> ValidationPolicy {
+        ValidationPolicy {
+            name: name.to_string(),
+            validators: validators.iter().map(|x| x.to_string()).collect(),
+            uses_game_versions: false,
+            always_allowed_extensions: vec!["zip".to_string()],
+        }
+    }
+
+    #[test]
+    fn policies() {
+        assert!(ValidatorSet::from_policy(policy("none", &[]))
+            .unwrap()
+            .is_none());
+
+        let set = ValidatorSet::from_policy(policy("minecraft-bedrock", &["mcpack", "mcaddon"]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(set.policy, "minecraft-bedrock");
+        assert_eq!(set.validators.len(), 2);
+        assert_eq!(set.always_allowed_extensions, ["zip"]);
+
+        assert!(matches!(
+            ValidatorSet::from_policy(policy("minecraft-legacy", &["fabric", "risugami"])),
+            Err(ValidationError::UnknownValidator(policy, validator))
+                if policy == "minecraft-legacy" && validator == "risugami"
+        ));
+
+        // Every validator needs its own name for policies to select it by
+        for (i, (name, _)) in VALIDATORS.iter().enumerate() {
+            assert!(VALIDATORS[i + 1..].iter().all(|(x, _)| x != name));
+        }
+    }
+}
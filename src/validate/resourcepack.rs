@@ -1,6 +1,10 @@
+use crate::database::models::legacy_loader_fields::MinecraftGameVersion;
+use crate::models::projects::{ValidationReport, ValidationSeverity};
+use crate::validate::archive::read_entry_to_string;
 use crate::validate::{SupportedGameVersions, ValidationError, ValidationResult};
 use chrono::DateTime;
-use std::io::Cursor;
+use itertools::Itertools;
+use std::fs::File;
 use zip::ZipArchive;
 
 pub struct PackValidator;
@@ -21,16 +25,27 @@ impl super::Validator for PackValidator {
 
     fn validate(
         &self,
-        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+        archive: &mut ZipArchive<File>,
+        report: &mut ValidationReport,
     ) -> Result<ValidationResult, ValidationError> {
         if archive.by_name("pack.mcmeta").is_err() {
-            return Ok(ValidationResult::Warning(
-                "No pack.mcmeta present for pack file. Tip: Make sure pack.mcmeta is in the root directory of your pack!",
-            ));
+            report.error("missing_pack_mcmeta", "No pack.mcmeta present for pack file. Tip: Make sure pack.mcmeta is in the root directory of your pack!");
         }
 
         Ok(ValidationResult::Pass)
     }
+
+    fn validate_with_game_versions(
+        &self,
+        archive: &mut ZipArchive<File>,
+        report: &mut ValidationReport,
+        game_versions: &[MinecraftGameVersion],
+    ) -> Result<ValidationResult, ValidationError> {
+        let result = self.validate(archive, report)?;
+        check_pack_format(archive, report, game_versions, PackKind::Resource)?;
+
+        Ok(result)
+    }
 }
 
 pub struct TexturePackValidator;
@@ -54,14 +69,257 @@ impl super::Validator for TexturePackValidator {
 
     fn validate(
         &self,
-        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+        archive: &mut ZipArchive<File>,
+        report: &mut ValidationReport,
     ) -> Result<ValidationResult, ValidationError> {
         if archive.by_name("pack.txt").is_err() {
-            return Ok(ValidationResult::Warning(
-                "No pack.txt present for pack file.",
-            ));
+            report.error("missing_pack_txt", "No pack.txt present for pack file.");
         }
 
         Ok(ValidationResult::Pass)
     }
 }
+
+#[derive(Copy, Clone)]
+pub(super) enum PackKind {
+    Resource,
+    Data,
+}
+
+/// The range of pack formats a pack.mcmeta declares support for
+#[derive(Debug, PartialEq, Eq)]
+struct PackFormats {
+    min: i64,
+    max: i64,
+}
+
+impl std::fmt::Display for PackFormats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.min == self.max {
+            write!(f, "{}", self.min)
+        } else {
+            write!(f, "{}-{}", self.min, self.max)
+        }
+    }
+}
+
+/// Reads the `pack_format` of a pack.mcmeta, widened by its `supported_formats`, which is
+/// either a single format, a `[min, max]` array or a `min_inclusive`/`max_inclusive` object
+fn read_pack_formats(mcmeta: &str) -> Option<PackFormats> {
+    let mcmeta: serde_json::Value =
+        serde_json::from_str(mcmeta.trim_start_matches('\u{feff}')).ok()?;
+    let pack = mcmeta.get("pack")?;
+    let pack_format = pack.get("pack_format")?.as_i64()?;
+
+    let supported = match pack.get("supported_formats") {
+        None => None,
+        Some(serde_json::Value::Number(x)) => x.as_i64().map(|x| (x, x)),
+        Some(serde_json::Value::Array(x)) => match &x[..] {
+            [min, max] => Some((min.as_i64()?, max.as_i64()?)),
+            _ => return None,
+        },
+        Some(x) => Some((
+            x.get("min_inclusive")?.as_i64()?,
+            x.get("max_inclusive")?.as_i64()?,
+        )),
+    };
+
+    Some(match supported {
+        Some((min, max)) => PackFormats {
+            min: min.min(pack_format),
+            max: max.max(pack_format),
+        },
+        None => PackFormats {
+            min: pack_format,
+            max: pack_format,
+        },
+    })
+}
+
+/// Warns if the pack formats a pack declares don't match the formats of its game versions, as
+/// the game would show the pack as incompatible
+pub(super) fn check_pack_format(
+    archive: &mut ZipArchive<File>,
+    report: &mut ValidationReport,
+    game_versions: &[MinecraftGameVersion],
+    kind: PackKind,
+) -> Result<(), ValidationError> {
+    // A missing pack.mcmeta is already reported by the validator
+    let Some(mcmeta) = read_entry_to_string(archive, "pack.mcmeta")? else {
+        return Ok(());
+    };
+
+    let Some(formats) = read_pack_formats(&mcmeta) else {
+        report.push(
+            ValidationSeverity::Warning,
+            "invalid_pack_mcmeta",
+            "The pack.mcmeta does not declare a valid pack_format",
+            Some("pack.mcmeta".to_string()),
+        );
+        return Ok(());
+    };
+
+    check_formats(&formats, report, game_versions, kind);
+
+    Ok(())
+}
+
+/// Checks the pack formats declared by a pack.mcmeta against those of the pack's game versions
+fn check_formats(
+    formats: &PackFormats,
+    report: &mut ValidationReport,
+    game_versions: &[MinecraftGameVersion],
+    kind: PackKind,
+) {
+    let known = game_versions
+        .iter()
+        .filter_map(|x| {
+            let format = match kind {
+                PackKind::Resource => x.resource_pack_format,
+                PackKind::Data => x.data_pack_format,
+            }?;
+            Some((x, format as i64))
+        })
+        .collect_vec();
+
+    // The pack formats of new game versions have to be set by an admin before they are known
+    if known.is_empty() && !game_versions.is_empty() {
+        report.push(
+            ValidationSeverity::Warning,
+            "unknown_pack_format",
+            format!(
+                "The pack format of the game versions {} is not known yet, so the pack.mcmeta could not be checked",
+                game_versions.iter().map(|x| &x.version).join(", ")
+            ),
+            Some("pack.mcmeta".to_string()),
+        );
+        return;
+    }
+
+    let mismatched = known
+        .into_iter()
+        .filter(|(_, format)| *format < formats.min || *format > formats.max)
+        .map(|(x, format)| format!("{} (pack format {})", x.version, format))
+        .collect_vec();
+
+    if !mismatched.is_empty() {
+        report.push(
+            ValidationSeverity::Warning,
+            "pack_format_mismatch",
+            format!(
+                "The pack.mcmeta supports pack format {}, which does not match the game versions {}",
+                formats,
+                mismatched.join(", ")
+            ),
+            Some("pack.mcmeta".to_string()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pack_formats() {
+        assert_eq!(
+            read_pack_formats(r#"{"pack": {"pack_format": 15, "description": ""}}"#),
+            Some(PackFormats { min: 15, max: 15 })
+        );
+        assert_eq!(
+            read_pack_formats(r#"{"pack": {"pack_format": 18, "supported_formats": [15, 22]}}"#),
+            Some(PackFormats { min: 15, max: 22 })
+        );
+        assert_eq!(
+            read_pack_formats(
+                r#"{"pack": {"pack_format": 34, "supported_formats": {"min_inclusive": 32, "max_inclusive": 42}}}"#
+            ),
+            Some(PackFormats { min: 32, max: 42 })
+        );
+        // The pack_format is supported even if it's outside of supported_formats
+        assert_eq!(
+            read_pack_formats(r#"{"pack": {"pack_format": 9, "supported_formats": 12}}"#),
+            Some(PackFormats { min: 9, max: 12 })
+        );
+        assert_eq!(
+            read_pack_formats("\u{feff}{\"pack\": {\"pack_format\": 1}}"),
+            Some(PackFormats { min: 1, max: 1 })
+        );
+
+        assert_eq!(read_pack_formats(r#"{"pack": {"description": ""}}"#), None);
+        assert_eq!(
+            read_pack_formats(r#"{"pack": {"pack_format": "15"}}"#),
+            None
+        );
+        assert_eq!(read_pack_formats("not json"), None);
+    }
+
+    fn game_version(version: &str, resource_pack_format: Option<i32>) -> MinecraftGameVersion {
+        MinecraftGameVersion {
+            id: crate::database::models::LoaderFieldEnumValueId(0),
+            version: version.to_string(),
+            type_: "release".to_string(),
+            created: chrono::Utc::now(),
+            major: false,
+            resource_pack_format,
+            data_pack_format: None,
+        }
+    }
+
+    fn codes(
+        formats: &PackFormats,
+        game_versions: &[MinecraftGameVersion],
+        kind: PackKind,
+    ) -> Vec<String> {
+        let mut report = ValidationReport::default();
+        check_formats(formats, &mut report, game_versions, kind);
+        report.findings.into_iter().map(|x| x.code).collect()
+    }
+
+    #[test]
+    fn game_version_formats() {
+        let formats = PackFormats { min: 15, max: 18 };
+
+        assert!(codes(
+            &formats,
+            &[game_version("1.20.1", Some(15))],
+            PackKind::Resource
+        )
+        .is_empty());
+        assert_eq!(
+            codes(
+                &formats,
+                &[game_version("1.20.4", Some(22))],
+                PackKind::Resource
+            ),
+            vec!["pack_format_mismatch"]
+        );
+        // Game versions without a known format are skipped, unless none of them has one
+        assert!(codes(
+            &formats,
+            &[
+                game_version("1.20.1", Some(15)),
+                game_version("1.21.9", None)
+            ],
+            PackKind::Resource
+        )
+        .is_empty());
+        assert_eq!(
+            codes(
+                &formats,
+                &[game_version("1.21.9", None)],
+                PackKind::Resource
+            ),
+            vec!["unknown_pack_format"]
+        );
+        assert_eq!(
+            codes(
+                &formats,
+                &[game_version("1.20.1", Some(15))],
+                PackKind::Data
+            ),
+            vec!["unknown_pack_format"]
+        );
+        assert!(codes(&formats, &[], PackKind::Resource).is_empty());
+    }
+}
@@ -0,0 +1,491 @@
+use crate::validate::archive::{read_nested_archive, ArchiveLimits};
+use lazy_static::lazy_static;
+use log::warn;
+use serde::Deserialize;
+use std::io::{Read, Seek};
+use zip::ZipArchive;
+
+/// The largest class file which is scanned
+const MAX_CLASS_SIZE: u64 = 16 * (1 << 20);
+
+lazy_static! {
+    static ref SCAN_RULES: ScanRules = ScanRules::load();
+}
+
+/// A kind of suspicious code found by the scanner
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ScanFindingKind {
+    /// Classes loaded from a URL which is hidden from static analysis, or invoked reflectively
+    ObfuscatedClassLoading,
+    /// External processes started through `Runtime.exec` or `ProcessBuilder`
+    ProcessExecution,
+    /// A file written to disk and loaded as a native library
+    NativeLibraryDrop,
+    /// A match for one of the signatures in the scan rules file
+    KnownSignature,
+}
+
+impl ScanFindingKind {
+    pub fn from_string(string: &str) -> ScanFindingKind {
+        match string {
+            "obfuscated_class_loading" => ScanFindingKind::ObfuscatedClassLoading,
+            "process_execution" => ScanFindingKind::ProcessExecution,
+            "native_library_drop" => ScanFindingKind::NativeLibraryDrop,
+            _ => ScanFindingKind::KnownSignature,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScanFindingKind::ObfuscatedClassLoading => "obfuscated_class_loading",
+            ScanFindingKind::ProcessExecution => "process_execution",
+            ScanFindingKind::NativeLibraryDrop => "native_library_drop",
+            ScanFindingKind::KnownSignature => "known_signature",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            ScanFindingKind::ObfuscatedClassLoading => "Loads classes from an obfuscated source",
+            ScanFindingKind::ProcessExecution => "Starts external processes",
+            ScanFindingKind::NativeLibraryDrop => "Writes and loads a native library",
+            ScanFindingKind::KnownSignature => "Matches a known malware signature",
+        }
+    }
+}
+
+/// Suspicious code found in a class file of an uploaded archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanFinding {
+    pub kind: ScanFindingKind,
+    /// The path of the class file, with `!/` separating the paths inside nested jars
+    pub path: String,
+    /// The name of the matched signature, for signature matches
+    pub detail: Option<String>,
+}
+
+/// The malware signatures loaded from the file at `SCAN_RULES_PATH`
+#[derive(Deserialize, Default)]
+pub struct ScanRules {
+    #[serde(default)]
+    signatures: Vec<Signature>,
+}
+
+/// A signature matches a class file if its SHA-1 hash is one of `sha1`, or if every string in
+/// `strings` is contained in one of the class file's constants
+#[derive(Deserialize)]
+struct Signature {
+    name: String,
+    #[serde(default)]
+    sha1: Vec<String>,
+    #[serde(default)]
+    strings: Vec<String>,
+}
+
+impl ScanRules {
+    /// Loads the rules file. Without one, only the built-in checks are run.
+    fn load() -> ScanRules {
+        let Ok(path) = dotenvy::var("SCAN_RULES_PATH") else {
+            return ScanRules::default();
+        };
+
+        let rules = std::fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|x| serde_json::from_str(&x).map_err(|err| err.to_string()));
+
+        match rules {
+            Ok(rules) => rules,
+            Err(err) => {
+                warn!("Failed to load scan rules from {path}: {err}");
+                ScanRules::default()
+            }
+        }
+    }
+
+    fn matches<'a>(
+        &'a self,
+        class: &'a [u8],
+        constants: &'a ClassConstants,
+    ) -> impl Iterator<Item = &'a Signature> {
+        let sha1 = sha1::Sha1::from(class).hexdigest();
+        let utf8 = constants.utf8_constants().collect::<Vec<_>>();
+
+        self.signatures.iter().filter(move |signature| {
+            signature.sha1.iter().any(|x| x.eq_ignore_ascii_case(&sha1))
+                || (!signature.strings.is_empty()
+                    && signature
+                        .strings
+                        .iter()
+                        .all(|string| utf8.iter().any(|x| x.contains(&**string))))
+        })
+    }
+}
+
+/// Scans the class files of an archive, including those in the jars bundled inside it
+pub fn scan_archive<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Vec<ScanFinding> {
+    let mut findings = Vec::new();
+    scan_entries(archive, "", 0, &SCAN_RULES, &mut findings);
+
+    findings
+}
+
+fn scan_entries<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    prefix: &str,
+    depth: usize,
+    rules: &ScanRules,
+    findings: &mut Vec<ScanFinding>,
+) {
+    for i in 0..archive.len() {
+        let Ok(mut file) = archive.by_index(i) else {
+            continue;
+        };
+        let name = file.name().to_string();
+
+        if name.ends_with(".class") && file.size() <= MAX_CLASS_SIZE {
+            let mut bytes = Vec::new();
+            if (&mut file)
+                .take(MAX_CLASS_SIZE)
+                .read_to_end(&mut bytes)
+                .is_ok()
+            {
+                scan_class(&bytes, &format!("{prefix}{name}"), rules, findings);
+            }
+        } else if name.ends_with(".jar") && depth < ArchiveLimits::get().max_nesting_depth {
+            drop(file);
+
+            if let Some(mut nested) = read_nested_archive(archive, &name) {
+                scan_entries(
+                    &mut nested,
+                    &format!("{prefix}{name}!/"),
+                    depth + 1,
+                    rules,
+                    findings,
+                );
+            }
+        }
+    }
+}
+
+fn scan_class(class: &[u8], path: &str, rules: &ScanRules, findings: &mut Vec<ScanFinding>) {
+    let Some(constants) = ClassConstants::parse(class) else {
+        return;
+    };
+    let references = constants.member_references();
+    let references_any = |owner: &str, names: &[&str]| {
+        references
+            .iter()
+            .any(|x| x.owner == owner && names.contains(&x.name))
+    };
+
+    let mut found = |kind, detail| {
+        findings.push(ScanFinding {
+            kind,
+            path: path.to_string(),
+            detail,
+        })
+    };
+
+    if references_any("java/lang/Runtime", &["exec"])
+        || references_any("java/lang/ProcessBuilder", &["start"])
+    {
+        found(ScanFindingKind::ProcessExecution, None);
+    }
+
+    // Loading classes from a URL is common, but hiding the URL by decoding it at runtime, or
+    // only calling into the loaded classes reflectively, is what droppers do
+    let loads_from_url = references_any("java/net/URLClassLoader", &["<init>", "newInstance"]);
+    let decodes_strings = references.iter().any(|x| {
+        x.owner == "java/lang/String" && x.name == "<init>" && x.descriptor.starts_with("([B")
+    }) || references_any("java/util/Base64$Decoder", &["decode"]);
+    let invokes_reflectively = references_any("java/lang/reflect/Method", &["invoke"]);
+    if loads_from_url && (decodes_strings || invokes_reflectively) {
+        found(ScanFindingKind::ObfuscatedClassLoading, None);
+    }
+
+    let loads_native = references_any("java/lang/System", &["load"])
+        || references_any("java/lang/Runtime", &["load"]);
+    let writes_files = references_any("java/io/FileOutputStream", &["<init>"])
+        || references_any("java/nio/file/Files", &["copy", "write", "newOutputStream"]);
+    if loads_native && writes_files {
+        found(ScanFindingKind::NativeLibraryDrop, None);
+    }
+
+    for signature in rules.matches(class, &constants) {
+        found(
+            ScanFindingKind::KnownSignature,
+            Some(signature.name.clone()),
+        );
+    }
+}
+
+enum Constant {
+    Utf8(String),
+    Class(u16),
+    MemberRef(u16, u16),
+    NameAndType(u16, u16),
+    Other,
+}
+
+/// A field or method referenced by a class file
+struct MemberReference<'a> {
+    owner: &'a str,
+    name: &'a str,
+    descriptor: &'a str,
+}
+
+/// The constant pool of a class file, which holds every string, class and member it refers to
+struct ClassConstants(Vec<Constant>);
+
+impl ClassConstants {
+    fn parse(class: &[u8]) -> Option<ClassConstants> {
+        let mut reader = ClassReader(class);
+        if reader.take(4)? != [0xCA, 0xFE, 0xBA, 0xBE] {
+            return None;
+        }
+        reader.take(4)?;
+
+        // The pool is indexed from 1, and longs and doubles take up two entries
+        let count = reader.u16()? as usize;
+        let mut constants = vec![Constant::Other];
+        while constants.len() < count {
+            let constant = match reader.take(1)?[0] {
+                1 => {
+                    let length = reader.u16()? as usize;
+                    Constant::Utf8(String::from_utf8_lossy(reader.take(length)?).into_owned())
+                }
+                7 => Constant::Class(reader.u16()?),
+                9..=11 => Constant::MemberRef(reader.u16()?, reader.u16()?),
+                12 => Constant::NameAndType(reader.u16()?, reader.u16()?),
+                5 | 6 => {
+                    reader.take(8)?;
+                    constants.push(Constant::Other);
+                    Constant::Other
+                }
+                3 | 4 | 17 | 18 => {
+                    reader.take(4)?;
+                    Constant::Other
+                }
+                15 => {
+                    reader.take(3)?;
+                    Constant::Other
+                }
+                8 | 16 | 19 | 20 => {
+                    reader.take(2)?;
+                    Constant::Other
+                }
+                _ => return None,
+            };
+            constants.push(constant);
+        }
+
+        Some(ClassConstants(constants))
+    }
+
+    fn utf8(&self, index: u16) -> Option<&str> {
+        match self.0.get(index as usize)? {
+            Constant::Utf8(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn utf8_constants(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().filter_map(|x| match x {
+            Constant::Utf8(value) => Some(&**value),
+            _ => None,
+        })
+    }
+
+    fn member_references(&self) -> Vec<MemberReference<'_>> {
+        self.0
+            .iter()
+            .filter_map(|x| {
+                let Constant::MemberRef(class, name_and_type) = x else {
+                    return None;
+                };
+                let Constant::Class(owner) = self.0.get(*class as usize)? else {
+                    return None;
+                };
+                let Constant::NameAndType(name, descriptor) =
+                    self.0.get(*name_and_type as usize)?
+                else {
+                    return None;
+                };
+
+                Some(MemberReference {
+                    owner: self.utf8(*owner)?,
+                    name: self.utf8(*name)?,
+                    descriptor: self.utf8(*descriptor)?,
+                })
+            })
+            .collect()
+    }
+}
+
+struct ClassReader<'a>(&'a [u8]);
+
+impl<'a> ClassReader<'a> {
+    fn take(&mut self, length: usize) -> Option<&'a [u8]> {
+        if self.0.len() < length {
+            return None;
+        }
+
+        let (taken, rest) = self.0.split_at(length);
+        self.0 = rest;
+        Some(taken)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(|x| u16::from_be_bytes([x[0], x[1]]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a class file whose constant pool references the given methods
+    fn class_file(methods: &[(&str, &str, &str)], strings: &[&str]) -> Vec<u8> {
+        let mut pool = Vec::new();
+        let mut count = 1u16;
+        let mut utf8 = |pool: &mut Vec<u8>, value: &str| {
+            pool.push(1);
+            pool.extend((value.len() as u16).to_be_bytes());
+            pool.extend(value.as_bytes());
+            count += 1;
+            count - 1
+        };
+
+        let mut entries = Vec::new();
+        for (owner, name, descriptor) in methods {
+            let owner = utf8(&mut pool, owner);
+            let name = utf8(&mut pool, name);
+            let descriptor = utf8(&mut pool, descriptor);
+            entries.push((owner, name, descriptor));
+        }
+        for string in strings {
+            utf8(&mut pool, string);
+        }
+
+        for (owner, name, descriptor) in entries {
+            pool.push(7);
+            pool.extend(owner.to_be_bytes());
+            pool.push(12);
+            pool.extend(name.to_be_bytes());
+            pool.extend(descriptor.to_be_bytes());
+            pool.push(10);
+            pool.extend(count.to_be_bytes());
+            pool.extend((count + 1).to_be_bytes());
+            count += 3;
+        }
+
+        let mut class = vec![0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 61];
+        class.extend(count.to_be_bytes());
+        class.extend(pool);
+        class
+    }
+
+    fn scan(class: &[u8], rules: &ScanRules) -> Vec<ScanFindingKind> {
+        let mut findings = Vec::new();
+        scan_class(class, "a/B.class", rules, &mut findings);
+
+        findings.into_iter().map(|x| x.kind).collect()
+    }
+
+    #[test]
+    fn suspicious_classes() {
+        let rules = ScanRules::default();
+
+        let class = class_file(
+            &[(
+                "java/lang/Runtime",
+                "exec",
+                "(Ljava/lang/String;)Ljava/lang/Process;",
+            )],
+            &[],
+        );
+        assert_eq!(scan(&class, &rules), [ScanFindingKind::ProcessExecution]);
+
+        let class = class_file(
+            &[
+                ("java/net/URLClassLoader", "<init>", "([Ljava/net/URL;)V"),
+                ("java/lang/String", "<init>", "([B)V"),
+            ],
+            &[],
+        );
+        assert_eq!(
+            scan(&class, &rules),
+            [ScanFindingKind::ObfuscatedClassLoading]
+        );
+
+        let class = class_file(
+            &[
+                ("java/io/FileOutputStream", "<init>", "(Ljava/io/File;)V"),
+                ("java/lang/System", "load", "(Ljava/lang/String;)V"),
+            ],
+            &[],
+        );
+        assert_eq!(scan(&class, &rules), [ScanFindingKind::NativeLibraryDrop]);
+
+        // Loading classes from a plain URL is not suspicious on its own
+        let class = class_file(
+            &[
+                ("java/net/URLClassLoader", "<init>", "([Ljava/net/URL;)V"),
+                ("java/lang/String", "<init>", "(Ljava/lang/String;)V"),
+            ],
+            &["https://example.com/lib.jar"],
+        );
+        assert!(scan(&class, &rules).is_empty());
+        assert!(scan(b"not a class file", &rules).is_empty());
+    }
+
+    #[test]
+    fn signatures() {
+        let class = class_file(&[], &["dev/neko/nekoinjector/Utility", "run"]);
+        let rules: ScanRules = serde_json::from_str(&format!(
+            r#"{{
+                "signatures": [
+                    {{ "name": "injector", "strings": ["nekoinjector", "run"] }},
+                    {{ "name": "hash", "sha1": ["{}"] }},
+                    {{ "name": "other", "strings": ["nekoinjector", "missing"] }}
+                ]
+            }}"#,
+            sha1::Sha1::from(&class).hexdigest().to_uppercase()
+        ))
+        .unwrap();
+
+        let mut findings = Vec::new();
+        scan_class(&class, "a/B.class", &rules, &mut findings);
+        assert_eq!(
+            findings
+                .iter()
+                .map(|x| x.detail.as_deref().unwrap())
+                .collect::<Vec<_>>(),
+            ["injector", "hash"]
+        );
+    }
+
+    #[test]
+    fn nested_jars() {
+        use std::io::{Cursor, Write};
+
+        let jar = include_bytes!("../../tests/files/suspicious-mod.jar");
+        let mut cursor = Cursor::new(Vec::new());
+        let mut zip = zip::ZipWriter::new(&mut cursor);
+        zip.start_file("META-INF/jars/suspicious-mod.jar", Default::default())
+            .unwrap();
+        zip.write_all(jar).unwrap();
+        zip.finish().unwrap();
+        drop(zip);
+
+        let findings = scan_archive(&mut ZipArchive::new(cursor).unwrap());
+        assert_eq!(
+            findings,
+            [ScanFinding {
+                kind: ScanFindingKind::ProcessExecution,
+                path: "META-INF/jars/suspicious-mod.jar!/com/example/Updater.class".to_string(),
+                detail: None,
+            }]
+        );
+    }
+}
@@ -1,5 +1,6 @@
+use crate::models::projects::ValidationReport;
 use crate::validate::{SupportedGameVersions, ValidationError, ValidationResult};
-use std::io::Cursor;
+use std::fs::File;
 use zip::ZipArchive;
 
 pub struct ShaderValidator;
@@ -19,12 +20,14 @@ impl super::Validator for ShaderValidator {
 
     fn validate(
         &self,
-        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+        archive: &mut ZipArchive<File>,
+        report: &mut ValidationReport,
     ) -> Result<ValidationResult, ValidationError> {
         if !archive.file_names().any(|x| x.starts_with("shaders/")) {
-            return Ok(ValidationResult::Warning(
+            report.error(
+                "missing_shaders_folder",
                 "No shaders folder present for OptiFine/Iris shader.",
-            ));
+            );
         }
 
         Ok(ValidationResult::Pass)
@@ -48,18 +51,18 @@ impl super::Validator for CanvasShaderValidator {
 
     fn validate(
         &self,
-        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+        archive: &mut ZipArchive<File>,
+        report: &mut ValidationReport,
     ) -> Result<ValidationResult, ValidationError> {
         if archive.by_name("pack.mcmeta").is_err() {
-            return Ok(ValidationResult::Warning(
-                "No pack.mcmeta present for pack file. Tip: Make sure pack.mcmeta is in the root directory of your pack!",
-            ));
+            report.error("missing_pack_mcmeta", "No pack.mcmeta present for pack file. Tip: Make sure pack.mcmeta is in the root directory of your pack!");
         };
 
         if !archive.file_names().any(|x| x.contains("/pipelines/")) {
-            return Ok(ValidationResult::Warning(
+            report.error(
+                "missing_shaders_folder",
                 "No pipeline shaders folder present for canvas shaders.",
-            ));
+            );
         }
 
         Ok(ValidationResult::Pass)
@@ -83,21 +86,21 @@ impl super::Validator for CoreShaderValidator {
 
     fn validate(
         &self,
-        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+        archive: &mut ZipArchive<File>,
+        report: &mut ValidationReport,
     ) -> Result<ValidationResult, ValidationError> {
         if archive.by_name("pack.mcmeta").is_err() {
-            return Ok(ValidationResult::Warning(
-                "No pack.mcmeta present for pack file. Tip: Make sure pack.mcmeta is in the root directory of your pack!",
-            ));
+            report.error("missing_pack_mcmeta", "No pack.mcmeta present for pack file. Tip: Make sure pack.mcmeta is in the root directory of your pack!");
         };
 
         if !archive
             .file_names()
             .any(|x| x.starts_with("assets/minecraft/shaders/"))
         {
-            return Ok(ValidationResult::Warning(
+            report.error(
+                "missing_shaders_folder",
                 "No shaders folder present for vanilla shaders.",
-            ));
+            );
         }
 
         Ok(ValidationResult::Pass)
@@ -35,6 +35,90 @@ impl ApiBuildable for ApiV3 {
     }
 }
 
+impl ApiV3 {
+    pub async fn reconcile_storage(
+        &self,
+        purge_grace_period_hours: Option<u32>,
+    ) -> ServiceResponse {
+        let req = actix_web::test::TestRequest::post()
+            .uri("/_internal/admin/_reconcile_storage")
+            .append_header((
+                "Modrinth-Admin",
+                dotenvy::var("LABRINTH_ADMIN_KEY").unwrap(),
+            ))
+            .set_json(serde_json::json!({
+                "purge_grace_period_hours": purge_grace_period_hours,
+            }))
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn edit_game_version_pack_formats(
+        &self,
+        version: &str,
+        formats: serde_json::Value,
+    ) -> ServiceResponse {
+        let req = actix_web::test::TestRequest::patch()
+            .uri(&format!("/_internal/admin/_game_versions/{version}"))
+            .append_header((
+                "Modrinth-Admin",
+                dotenvy::var("LABRINTH_ADMIN_KEY").unwrap(),
+            ))
+            .set_json(formats)
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn get_reconciliation(&self, id: i64) -> ServiceResponse {
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/_internal/admin/_reconcile_storage/{id}"))
+            .append_header((
+                "Modrinth-Admin",
+                dotenvy::var("LABRINTH_ADMIN_KEY").unwrap(),
+            ))
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn start_revalidation(&self, files_per_minute: Option<u32>) -> ServiceResponse {
+        let req = actix_web::test::TestRequest::post()
+            .uri("/_internal/admin/_revalidate_files")
+            .append_header((
+                "Modrinth-Admin",
+                dotenvy::var("LABRINTH_ADMIN_KEY").unwrap(),
+            ))
+            .set_json(serde_json::json!({
+                "files_per_minute": files_per_minute,
+            }))
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn get_revalidation(&self, id: i64) -> ServiceResponse {
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/_internal/admin/_revalidate_files/{id}"))
+            .append_header((
+                "Modrinth-Admin",
+                dotenvy::var("LABRINTH_ADMIN_KEY").unwrap(),
+            ))
+            .to_request();
+        self.call(req).await
+    }
+
+    /// Cancels or resumes a revalidation job
+    pub async fn set_revalidation_running(&self, id: i64, running: bool) -> ServiceResponse {
+        let action = if running { "resume" } else { "cancel" };
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("/_internal/admin/_revalidate_files/{id}/{action}"))
+            .append_header((
+                "Modrinth-Admin",
+                dotenvy::var("LABRINTH_ADMIN_KEY").unwrap(),
+            ))
+            .to_request();
+        self.call(req).await
+    }
+}
+
 #[async_trait(?Send)]
 impl Api for ApiV3 {
     async fn call(&self, req: actix_http::Request) -> ServiceResponse {
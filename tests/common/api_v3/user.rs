@@ -46,3 +46,72 @@ impl ApiUser for ApiV3 {
         self.call(req).await
     }
 }
+
+impl ApiV3 {
+    pub async fn get_saved_searches(&self, user_id: &str, pat: Option<&str>) -> ServiceResponse {
+        let req = test::TestRequest::get()
+            .uri(&format!("/v3/user/{user_id}/saved_searches"))
+            .append_pat(pat)
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn get_saved_search(
+        &self,
+        user_id: &str,
+        search_id: &str,
+        pat: Option<&str>,
+    ) -> ServiceResponse {
+        let req = test::TestRequest::get()
+            .uri(&format!("/v3/user/{user_id}/saved_searches/{search_id}"))
+            .append_pat(pat)
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn create_saved_search(
+        &self,
+        user_id: &str,
+        name: &str,
+        request: serde_json::Value,
+        pat: Option<&str>,
+    ) -> ServiceResponse {
+        let req = test::TestRequest::post()
+            .uri(&format!("/v3/user/{user_id}/saved_searches"))
+            .append_pat(pat)
+            .set_json(serde_json::json!({
+                "name": name,
+                "request": request,
+            }))
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn edit_saved_search(
+        &self,
+        user_id: &str,
+        search_id: &str,
+        patch: serde_json::Value,
+        pat: Option<&str>,
+    ) -> ServiceResponse {
+        let req = test::TestRequest::patch()
+            .uri(&format!("/v3/user/{user_id}/saved_searches/{search_id}"))
+            .append_pat(pat)
+            .set_json(patch)
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn delete_saved_search(
+        &self,
+        user_id: &str,
+        search_id: &str,
+        pat: Option<&str>,
+    ) -> ServiceResponse {
+        let req = test::TestRequest::delete()
+            .uri(&format!("/v3/user/{user_id}/saved_searches/{search_id}"))
+            .append_pat(pat)
+            .to_request();
+        self.call(req).await
+    }
+}
@@ -60,6 +60,14 @@ impl ApiV3 {
         test::read_body_json(version).await
     }
 
+    pub async fn get_version_contents(&self, id: &str, pat: Option<&str>) -> ServiceResponse {
+        let req = TestRequest::get()
+            .uri(&format!("/v3/version/{id}/contents"))
+            .append_pat(pat)
+            .to_request();
+        self.call(req).await
+    }
+
     pub async fn get_version_deserialized(&self, id: &str, pat: Option<&str>) -> Version {
         let resp = self.get_version(id, pat).await;
         assert_status!(&resp, StatusCode::OK);
@@ -103,6 +111,63 @@ impl ApiV3 {
         assert_status!(&resp, StatusCode::OK);
         test::read_body_json(resp).await
     }
+
+    pub async fn create_upload_session(
+        &self,
+        file_name: &str,
+        file_size: u64,
+        pat: Option<&str>,
+    ) -> ServiceResponse {
+        let req = test::TestRequest::post()
+            .uri("/v3/upload")
+            .append_pat(pat)
+            .set_json(json!({
+                "file_name": file_name,
+                "file_size": file_size,
+            }))
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn get_upload_session(&self, id: &str, pat: Option<&str>) -> ServiceResponse {
+        let req = test::TestRequest::get()
+            .uri(&format!("/v3/upload/{id}"))
+            .append_pat(pat)
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn upload_session_chunk(
+        &self,
+        id: &str,
+        index: u32,
+        bytes: Vec<u8>,
+        pat: Option<&str>,
+    ) -> ServiceResponse {
+        let req = test::TestRequest::put()
+            .uri(&format!("/v3/upload/{id}/{index}"))
+            .append_pat(pat)
+            .set_payload(bytes)
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn add_version_from_uploads(
+        &self,
+        data: serde_json::Value,
+        files: HashMap<String, String>,
+        pat: Option<&str>,
+    ) -> ServiceResponse {
+        let req = test::TestRequest::post()
+            .uri("/v3/version/uploads")
+            .append_pat(pat)
+            .set_json(json!({
+                "data": data,
+                "files": files,
+            }))
+            .to_request();
+        self.call(req).await
+    }
 }
 
 #[async_trait(?Send)]
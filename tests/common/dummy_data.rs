@@ -49,6 +49,8 @@ pub enum TestFile {
     BasicZip,
     BasicMod,
     BasicModDifferent,
+    // A mod with a class which starts external processes, which the scanner flags
+    SuspiciousMod,
     // Randomly generates a valid .jar with a random hash.
     // Unlike the other dummy jar files, this one is not a static file.
     // and BasicModRandom.bytes() will return a different file each time.
@@ -92,6 +94,50 @@ impl TestFile {
         )
         .to_string();
 
+        Self::build_jar(filename, fabric_mod_json, &[])
+    }
+
+    // Randomly generates a valid .jar whose fabric.mod.json doesn't declare its environment
+    // or game versions, so they can't be filled in from it
+    pub fn build_random_jar_without_metadata() -> Self {
+        let filename = format!("random-mod-{}.jar", rand::random::<u64>());
+
+        let fabric_mod_json = serde_json::json!({
+            "schemaVersion": 1,
+            "id": filename,
+            "version": "1.0.1",
+            "name": filename,
+        })
+        .to_string();
+
+        Self::build_jar(filename, fabric_mod_json, &[])
+    }
+
+    // Randomly generates a valid .jar which depends on another mod, and bundles another jar
+    pub fn build_random_jar_with_dependency(dependency: &str, embedded: &TestFile) -> Self {
+        let filename = format!("random-mod-{}.jar", rand::random::<u64>());
+
+        let fabric_mod_json = serde_json::json!({
+            "schemaVersion": 1,
+            "id": filename,
+            "version": "1.0.1",
+            "name": filename,
+            "depends": {
+              "minecraft": ">=1.20-",
+              "fabricloader": "*",
+              dependency: "*"
+            },
+            "jars": [
+              { "file": format!("META-INF/jars/{}", embedded.filename()) }
+            ]
+          }
+        )
+        .to_string();
+
+        Self::build_jar(filename, fabric_mod_json, &[embedded])
+    }
+
+    fn build_jar(filename: String, fabric_mod_json: String, embedded: &[&TestFile]) -> Self {
         // Create a simulated zip file
         let mut cursor = Cursor::new(Vec::new());
         {
@@ -110,6 +156,15 @@ impl TestFile {
             .unwrap();
             zip.write_all(fabric_mod_json.as_bytes()).unwrap();
 
+            for file in embedded {
+                zip.start_file(
+                    format!("META-INF/jars/{}", file.filename()),
+                    FileOptions::default().compression_method(CompressionMethod::Stored),
+                )
+                .unwrap();
+                zip.write_all(&file.bytes()).unwrap();
+            }
+
             zip.finish().unwrap();
         }
         let bytes = cursor.into_inner();
@@ -118,6 +173,27 @@ impl TestFile {
     }
 
     pub fn build_random_mrpack() -> Self {
+        Self::build_random_mrpack_with_files(serde_json::json!([
+            {
+                "path": "mods/animatica-0.6+1.20.jar",
+                "hashes": {
+                    "sha1": "3bcb19c759f313e69d3f7848b03c48f15167b88d",
+                    "sha512": "7d50f3f34479f8b052bfb9e2482603b4906b8984039777dc2513ecf18e9af2b599c9d094e88cec774f8525345859e721a394c8cd7c14a789c9538d2533c71d65"
+                },
+                "env": {
+                    "client": "required",
+                    "server": "required"
+                },
+                "downloads": [
+                    "https://cdn.modrinth.com/data/PRN43VSY/versions/uNgEPb10/animatica-0.6%2B1.20.jar"
+                ],
+                "fileSize": 69810
+            }
+        ]))
+    }
+
+    // Randomly generates a valid .mrpack which downloads the given files
+    pub fn build_random_mrpack_with_files(files: serde_json::Value) -> Self {
         let filename = format!("random-modpack-{}.mrpack", rand::random::<u64>());
 
         let modrinth_index_json = serde_json::json!({
@@ -125,23 +201,7 @@ impl TestFile {
             "game": "minecraft",
             "versionId": "1.20.1-9.6",
             "name": filename,
-            "files": [
-                {
-                    "path": "mods/animatica-0.6+1.20.jar",
-                    "hashes": {
-                        "sha1": "3bcb19c759f313e69d3f7848b03c48f15167b88d",
-                        "sha512": "7d50f3f34479f8b052bfb9e2482603b4906b8984039777dc2513ecf18e9af2b599c9d094e88cec774f8525345859e721a394c8cd7c14a789c9538d2533c71d65"
-                    },
-                    "env": {
-                        "client": "required",
-                        "server": "required"
-                    },
-                    "downloads": [
-                        "https://cdn.modrinth.com/data/PRN43VSY/versions/uNgEPb10/animatica-0.6%2B1.20.jar"
-                    ],
-                    "fileSize": 69810
-                }
-            ],
+            "files": files,
             "dependencies": {
                 "fabric-loader": "0.14.22",
                 "minecraft": "1.20.1"
@@ -475,6 +535,7 @@ impl TestFile {
             TestFile::BasicZip => "simple-zip.zip",
             TestFile::BasicMod => "basic-mod.jar",
             TestFile::BasicModDifferent => "basic-mod-different.jar",
+            TestFile::SuspiciousMod => "suspicious-mod.jar",
             TestFile::BasicModRandom { filename, .. } => filename,
             TestFile::BasicModpackRandom { filename, .. } => filename,
         }
@@ -494,6 +555,9 @@ impl TestFile {
             TestFile::BasicModDifferent => {
                 include_bytes!("../../tests/files/basic-mod-different.jar").to_vec()
             }
+            TestFile::SuspiciousMod => {
+                include_bytes!("../../tests/files/suspicious-mod.jar").to_vec()
+            }
             TestFile::BasicModRandom { bytes, .. } => bytes.clone(),
             TestFile::BasicModpackRandom { bytes, .. } => bytes.clone(),
         }
@@ -505,6 +569,7 @@ impl TestFile {
             TestFile::DummyProjectBeta => "mod",
             TestFile::BasicMod => "mod",
             TestFile::BasicModDifferent => "mod",
+            TestFile::SuspiciousMod => "mod",
             TestFile::BasicModRandom { .. } => "mod",
 
             TestFile::BasicZip => "resourcepack",
@@ -520,6 +585,7 @@ impl TestFile {
             TestFile::DummyProjectBeta => Some("application/java-archive"),
             TestFile::BasicMod => Some("application/java-archive"),
             TestFile::BasicModDifferent => Some("application/java-archive"),
+            TestFile::SuspiciousMod => Some("application/java-archive"),
             TestFile::BasicModRandom { .. } => Some("application/java-archive"),
 
             TestFile::BasicZip => Some("application/zip"),
@@ -108,7 +108,7 @@ async fn creating_loader_fields() {
             .add_public_version(
                 *alpha_project_id_parsed,
                 "1.0.0",
-                TestFile::build_random_jar(),
+                TestFile::build_random_jar_without_metadata(),
                 None,
                 Some(
                     serde_json::from_value(json!([{
@@ -130,7 +130,7 @@ async fn creating_loader_fields() {
             .add_public_version(
                 *alpha_project_id_parsed,
                 "1.0.0",
-                TestFile::build_random_jar(),
+                TestFile::build_random_jar_without_metadata(),
                 None,
                 Some(
                     serde_json::from_value(json!([{
@@ -633,3 +633,40 @@ async fn minecraft_game_version_update() {
     })
     .await
 }
+
+#[actix_rt::test]
+async fn game_version_pack_formats_can_be_set() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+
+        let resp = api
+            .edit_game_version_pack_formats("1.20.1", json!({ "resource_pack_format": 15 }))
+            .await;
+        assert_status!(&resp, StatusCode::NO_CONTENT);
+        let resp = api
+            .edit_game_version_pack_formats("1.20.1", json!({ "data_pack_format": 15 }))
+            .await;
+        assert_status!(&resp, StatusCode::NO_CONTENT);
+
+        // Setting one format keeps the other, and the rest of the metadata
+        let game_versions = api
+            .get_loader_field_variants_deserialized("game_versions")
+            .await;
+        let metadata = &game_versions
+            .iter()
+            .find(|x| x.value == "1.20.1")
+            .unwrap()
+            .metadata;
+        assert_eq!(metadata["resource_pack_format"], json!(15));
+        assert_eq!(metadata["data_pack_format"], json!(15));
+        assert_eq!(metadata["type"], json!("release"));
+        assert_eq!(metadata["major"], json!(false));
+
+        // Game versions which don't exist aren't created
+        let resp = api
+            .edit_game_version_pack_formats("99.0", json!({ "resource_pack_format": 99 }))
+            .await;
+        assert_status!(&resp, StatusCode::NOT_FOUND);
+    })
+    .await
+}
@@ -0,0 +1,61 @@
+use actix_http::StatusCode;
+use actix_web::test;
+use common::api_v3::ApiV3;
+use common::environment::{with_test_environment, TestEnvironment};
+use labrinth::file_hosting::MockHost;
+use labrinth::queue::revalidation::revalidate_files_batch;
+
+mod common;
+
+#[actix_rt::test]
+async fn revalidation_job_can_be_cancelled_and_resumed() {
+    with_test_environment(None, |env: TestEnvironment<ApiV3>| async move {
+        // A rate of zero files would never finish
+        let resp = env.api.start_revalidation(Some(0)).await;
+        assert_status!(&resp, StatusCode::BAD_REQUEST);
+
+        let resp = env.api.start_revalidation(Some(6000)).await;
+        assert_status!(&resp, StatusCode::OK);
+        let job: serde_json::Value = test::read_body_json(resp).await;
+        let id = job["id"].as_i64().unwrap();
+        assert_eq!(job["status"], "running");
+        assert_eq!(job["checked_files"], 0);
+        assert!(job["total_files"].as_i64().unwrap() > 0);
+
+        // Only one job may run at a time
+        let resp = env.api.start_revalidation(None).await;
+        assert_status!(&resp, StatusCode::BAD_REQUEST);
+
+        let resp = env.api.set_revalidation_running(id, false).await;
+        assert_status!(&resp, StatusCode::NO_CONTENT);
+        let resp = env.api.set_revalidation_running(id, false).await;
+        assert_status!(&resp, StatusCode::BAD_REQUEST);
+
+        // Cancelled jobs are not processed
+        let batch = revalidate_files_batch(&env.db.pool, &env.db.redis_pool, &MockHost::new())
+            .await
+            .unwrap();
+        assert!(batch.is_none());
+
+        let resp = env.api.set_revalidation_running(id, true).await;
+        assert_status!(&resp, StatusCode::NO_CONTENT);
+
+        // The dummy files fit in a single batch, which finishes the job
+        let job = revalidate_files_batch(&env.db.pool, &env.db.redis_pool, &MockHost::new())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(job.id, id);
+        assert_eq!(job.checked_files + job.errored_files, job.total_files);
+
+        let resp = env.api.get_revalidation(id).await;
+        assert_status!(&resp, StatusCode::OK);
+        let job: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(job["status"], "finished");
+        assert!(job["finished"].is_string());
+
+        let resp = env.api.get_revalidation(id + 1).await;
+        assert_status!(&resp, StatusCode::NOT_FOUND);
+    })
+    .await;
+}
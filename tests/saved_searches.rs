@@ -0,0 +1,78 @@
+use actix_http::StatusCode;
+use actix_web::test;
+use common::api_v3::ApiV3;
+use common::database::{FRIEND_USER_PAT, USER_USER_ID, USER_USER_PAT};
+use common::environment::{with_test_environment, TestEnvironment};
+use serde_json::json;
+
+mod common;
+
+#[actix_rt::test]
+async fn saved_searches_can_be_created_edited_and_deleted() {
+    with_test_environment(None, |env: TestEnvironment<ApiV3>| async move {
+        let api = &env.api;
+
+        // Searches which can't be run aren't saved
+        let resp = api
+            .create_saved_search(
+                USER_USER_ID,
+                "Broken",
+                json!({ "new_filters": "categories = " }),
+                USER_USER_PAT,
+            )
+            .await;
+        assert_status!(&resp, StatusCode::BAD_REQUEST);
+
+        let resp = api
+            .create_saved_search(
+                USER_USER_ID,
+                "Fabric mods",
+                json!({ "new_filters": "categories = fabric" }),
+                USER_USER_PAT,
+            )
+            .await;
+        assert_status!(&resp, StatusCode::OK);
+        let search: serde_json::Value = test::read_body_json(resp).await;
+        let id = search["id"].as_str().unwrap().to_string();
+        assert_eq!(search["name"], "Fabric mods");
+        assert_eq!(search["request"]["new_filters"], "categories = fabric");
+
+        let resp = api.get_saved_searches(USER_USER_ID, USER_USER_PAT).await;
+        assert_status!(&resp, StatusCode::OK);
+        let searches: Vec<serde_json::Value> = test::read_body_json(resp).await;
+        assert_eq!(searches.len(), 1);
+        assert_eq!(searches[0]["id"], id);
+
+        // Other users can't see or change them
+        let resp = api.get_saved_searches(USER_USER_ID, FRIEND_USER_PAT).await;
+        assert_status!(&resp, StatusCode::UNAUTHORIZED);
+        let resp = api
+            .delete_saved_search(USER_USER_ID, &id, FRIEND_USER_PAT)
+            .await;
+        assert_status!(&resp, StatusCode::UNAUTHORIZED);
+
+        let resp = api
+            .edit_saved_search(
+                USER_USER_ID,
+                &id,
+                json!({ "name": "Quilt mods", "request": { "new_filters": "categories = quilt" } }),
+                USER_USER_PAT,
+            )
+            .await;
+        assert_status!(&resp, StatusCode::NO_CONTENT);
+
+        let resp = api.get_saved_search(USER_USER_ID, &id, USER_USER_PAT).await;
+        assert_status!(&resp, StatusCode::OK);
+        let search: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(search["name"], "Quilt mods");
+        assert_eq!(search["request"]["new_filters"], "categories = quilt");
+
+        let resp = api
+            .delete_saved_search(USER_USER_ID, &id, USER_USER_PAT)
+            .await;
+        assert_status!(&resp, StatusCode::NO_CONTENT);
+        let resp = api.get_saved_search(USER_USER_ID, &id, USER_USER_PAT).await;
+        assert_status!(&resp, StatusCode::NOT_FOUND);
+    })
+    .await;
+}
@@ -0,0 +1,274 @@
+use actix_http::StatusCode;
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::Duration;
+use common::api_v3::ApiV3;
+use common::environment::{with_test_environment, TestEnvironment};
+use futures::TryStreamExt;
+use labrinth::file_hosting::{
+    DeleteFileData, FileHost, FileHostingError, FileListPage, FileMetadata, FileStream,
+    FilesystemHost, MockHost, ReplicaBackend, ReplicatedHost, UploadFileData,
+};
+use labrinth::queue::storage::reconcile_storage;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+mod common;
+
+#[actix_rt::test]
+async fn reconcile_storage_reports_orphaned_files() {
+    with_test_environment(None, |env: TestEnvironment<ApiV3>| async move {
+        let alpha_project_id = &env.dummy.project_alpha.project_id;
+
+        // A file left behind in storage which nothing references
+        let orphan = format!("data/orphaned-{}.jar", uuid::Uuid::new_v4());
+        let path = std::path::Path::new(&dotenvy::var("MOCK_FILE_PATH").unwrap()).join(&orphan);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, b"orphaned").unwrap();
+
+        // Uploads which are still in progress could be purged with a short grace period
+        let resp = env.api.reconcile_storage(Some(1)).await;
+        assert_status!(&resp, StatusCode::BAD_REQUEST);
+
+        let report = reconcile_storage(&env.db.pool, &MockHost::new(), Some(Duration::hours(6)))
+            .await
+            .unwrap();
+
+        assert!(report.orphaned.iter().any(|x| x.file_name == orphan));
+
+        // The version files of the dummy projects are referenced by the database
+        let alpha_versions = format!("data/{alpha_project_id}/versions/");
+        assert!(!report
+            .orphaned
+            .iter()
+            .any(|x| x.file_name.starts_with(&alpha_versions)));
+        assert!(report.referenced_files > 0);
+
+        // Files newer than the grace period are not purged
+        assert!(!report.purged.contains(&orphan));
+        assert!(path.exists());
+
+        std::fs::remove_file(path).unwrap();
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn reconcile_storage_records_runs() {
+    with_test_environment(None, |env: TestEnvironment<ApiV3>| async move {
+        let orphan = format!("data/orphaned-{}.jar", uuid::Uuid::new_v4());
+        let path = std::path::Path::new(&dotenvy::var("MOCK_FILE_PATH").unwrap()).join(&orphan);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, b"orphaned").unwrap();
+
+        let resp = env.api.reconcile_storage(None).await;
+        assert_status!(&resp, StatusCode::ACCEPTED);
+        let run: serde_json::Value = actix_web::test::read_body_json(resp).await;
+        let run_id = run["id"].as_i64().unwrap();
+
+        // The run finishes in the background, after which its report can be fetched
+        let mut run = run;
+        for _ in 0..100 {
+            if run["status"] != "running" {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+            let resp = env.api.get_reconciliation(run_id).await;
+            assert_status!(&resp, StatusCode::OK);
+            run = actix_web::test::read_body_json(resp).await;
+        }
+
+        assert_eq!(run["status"], "finished");
+        assert!(run["report"]["orphaned"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|x| x["file_name"] == orphan.as_str()));
+
+        std::fs::remove_file(path).unwrap();
+    })
+    .await;
+}
+
+// A file host which fails every request while it is marked as down
+struct FlakyHost {
+    host: FilesystemHost,
+    down: AtomicBool,
+}
+
+impl FlakyHost {
+    fn check(&self) -> Result<(), FileHostingError> {
+        if self.down.load(Ordering::SeqCst) {
+            Err(FileHostingError::S3Error("Backend is down".to_string()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[async_trait]
+impl FileHost for FlakyHost {
+    async fn upload_file(
+        &self,
+        content_type: &str,
+        file_name: &str,
+        file_bytes: Bytes,
+    ) -> Result<UploadFileData, FileHostingError> {
+        self.check()?;
+        self.host
+            .upload_file(content_type, file_name, file_bytes)
+            .await
+    }
+
+    async fn upload_file_streaming(
+        &self,
+        content_type: &str,
+        file_name: &str,
+        content_length: u64,
+        stream: FileStream,
+    ) -> Result<UploadFileData, FileHostingError> {
+        self.check()?;
+        self.host
+            .upload_file_streaming(content_type, file_name, content_length, stream)
+            .await
+    }
+
+    async fn download_file(
+        &self,
+        file_id: &str,
+        file_name: &str,
+    ) -> Result<FileStream, FileHostingError> {
+        self.check()?;
+        self.host.download_file(file_id, file_name).await
+    }
+
+    async fn delete_file_version(
+        &self,
+        file_id: &str,
+        file_name: &str,
+    ) -> Result<DeleteFileData, FileHostingError> {
+        self.check()?;
+        self.host.delete_file_version(file_id, file_name).await
+    }
+
+    async fn list_files(
+        &self,
+        prefix: &str,
+        cursor: Option<String>,
+    ) -> Result<FileListPage, FileHostingError> {
+        self.check()?;
+        self.host.list_files(prefix, cursor).await
+    }
+
+    async fn stat_file(&self, file_name: &str) -> Result<Option<FileMetadata>, FileHostingError> {
+        self.check()?;
+        self.host.stat_file(file_name).await
+    }
+
+    async fn get_signed_url(
+        &self,
+        file_name: &str,
+        expires_in: std::time::Duration,
+    ) -> Result<String, FileHostingError> {
+        self.check()?;
+        self.host.get_signed_url(file_name, expires_in).await
+    }
+}
+
+#[actix_rt::test]
+async fn replicated_host_fails_over_and_repairs() {
+    with_test_environment(None, |env: TestEnvironment<ApiV3>| async move {
+        let root = std::env::temp_dir().join(format!("labrinth-test-{}", uuid::Uuid::new_v4()));
+        let primary = Arc::new(FlakyHost {
+            host: FilesystemHost::new(root.join("primary").to_str().unwrap()).unwrap(),
+            down: AtomicBool::new(true),
+        });
+        let secondary =
+            Arc::new(FilesystemHost::new(root.join("secondary").to_str().unwrap()).unwrap());
+
+        let host = ReplicatedHost::new(
+            env.db.pool.clone(),
+            ReplicaBackend {
+                id: "primary".to_string(),
+                host: primary.clone(),
+            },
+            vec![ReplicaBackend {
+                id: "secondary".to_string(),
+                host: secondary.clone(),
+            }],
+        );
+
+        // Uploads succeed while the primary is down, and reads fail over to the secondary
+        let file_name = "data/replicated/file.jar";
+        let upload = host
+            .upload_file(
+                "application/java-archive",
+                file_name,
+                Bytes::from("contents"),
+            )
+            .await
+            .unwrap();
+        let data = host
+            .download_file(&upload.file_id, file_name)
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap()
+            .concat();
+        assert_eq!(data, b"contents");
+
+        // Once the primary is back, the repair task copies the file to it
+        primary.down.store(false, Ordering::SeqCst);
+        assert!(primary.stat_file(file_name).await.unwrap().is_none());
+        let report = host.repair_replicas().await.unwrap();
+        assert_eq!(report.repaired, 1);
+        assert!(primary.stat_file(file_name).await.unwrap().is_some());
+
+        // Deleting the file removes it from every backend
+        host.delete_file_version(&upload.file_id, file_name)
+            .await
+            .unwrap();
+        assert!(primary.stat_file(file_name).await.unwrap().is_none());
+        assert!(secondary.stat_file(file_name).await.unwrap().is_none());
+        let report = host.repair_replicas().await.unwrap();
+        assert_eq!(report.repaired + report.deleted + report.failed, 0);
+
+        // Files stored before replication was enabled are copied once they are backfilled
+        let file_name = "data/replicated/old.jar";
+        primary
+            .upload_file("application/java-archive", file_name, Bytes::from("old"))
+            .await
+            .unwrap();
+        let metadata = primary.stat_file(file_name).await.unwrap().unwrap();
+        assert_eq!(
+            host.backfill_replicas(std::slice::from_ref(&metadata))
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(host.backfill_replicas(&[metadata]).await.unwrap(), 0);
+        let report = host.repair_replicas().await.unwrap();
+        assert_eq!(report.repaired, 1);
+        assert!(secondary.stat_file(file_name).await.unwrap().is_some());
+
+        // Deleting a version which was replaced since keeps the current one on every backend
+        let file_name = "data/replicated/replaced.jar";
+        let first = host
+            .upload_file("application/java-archive", file_name, Bytes::from("first"))
+            .await
+            .unwrap();
+        host.upload_file("application/java-archive", file_name, Bytes::from("second"))
+            .await
+            .unwrap();
+        host.delete_file_version(&first.file_id, file_name)
+            .await
+            .unwrap();
+        assert!(primary.stat_file(file_name).await.unwrap().is_some());
+        assert!(secondary.stat_file(file_name).await.unwrap().is_some());
+
+        std::fs::remove_dir_all(root).unwrap();
+    })
+    .await;
+}
@@ -1,8 +1,10 @@
-use crate::common::api_common::{ApiProject, ApiTeams};
+use crate::common::api_common::{ApiProject, ApiTeams, ApiUser};
+use actix_http::StatusCode;
+use common::api_v3::ApiV3;
 use common::dummy_data::TestFile;
 use common::{
-    database::{FRIEND_USER_ID, FRIEND_USER_PAT, USER_USER_ID, USER_USER_PAT},
-    environment::with_test_environment_all,
+    database::{ENEMY_USER_PAT, FRIEND_USER_ID, FRIEND_USER_PAT, USER_USER_ID, USER_USER_PAT},
+    environment::{with_test_environment, with_test_environment_all, TestEnvironment},
 };
 
 mod common;
@@ -15,6 +17,7 @@ mod common;
 // patch user
 // patch user icon
 // user follows
+// delete user
 
 #[actix_rt::test]
 pub async fn get_user_projects_after_creating_project_returns_new_project() {
@@ -103,3 +106,19 @@ pub async fn get_user_projects_after_leaving_team_shows_no_team_projects() {
     })
     .await;
 }
+
+#[actix_rt::test]
+pub async fn delete_user_with_upload_sessions() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = test_env.api;
+        let resp = api
+            .create_upload_session("basic-mod.jar", 1024, ENEMY_USER_PAT)
+            .await;
+        assert_status!(&resp, StatusCode::OK);
+
+        // The user's upload sessions are deleted with them
+        let resp = api.delete_user("enemy", ENEMY_USER_PAT).await;
+        assert_status!(&resp, StatusCode::NO_CONTENT);
+    })
+    .await;
+}
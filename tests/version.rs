@@ -1,16 +1,18 @@
 use std::collections::HashMap;
 
-use crate::common::api_common::ApiVersion;
+use crate::common::api_common::{Api, ApiProject, ApiTeams, ApiVersion};
 use crate::common::database::*;
 use crate::common::dummy_data::{DummyProjectAlpha, DummyProjectBeta, TestFile};
 use crate::common::get_json_val_str;
 use actix_http::StatusCode;
 use actix_web::test;
+use common::api_v3::request_data::get_public_version_creation_data_json;
 use common::api_v3::ApiV3;
 use common::asserts::assert_common_version_ids;
 use common::database::USER_USER_PAT;
 use common::environment::{with_test_environment, with_test_environment_all};
 use futures::StreamExt;
+use itertools::Itertools;
 use labrinth::database::models::version_item::VERSIONS_NAMESPACE;
 use labrinth::models::ids::base62_impl::parse_base62;
 use labrinth::models::projects::{
@@ -687,3 +689,551 @@ async fn version_ordering_when_specified_orders_specified_before_unspecified() {
     })
     .await;
 }
+
+#[actix_rt::test]
+async fn can_create_version_from_upload_session() {
+    with_test_environment(
+        None,
+        |env: common::environment::TestEnvironment<ApiV3>| async move {
+            let alpha_project_id_parsed = env.dummy.project_alpha.project_id_parsed;
+            let file = TestFile::BasicMod;
+            let bytes = file.bytes();
+
+            let resp = env
+                .api
+                .create_upload_session(&file.filename(), bytes.len() as u64, USER_USER_PAT)
+                .await;
+            assert_status!(&resp, StatusCode::OK);
+            let session: serde_json::Value = test::read_body_json(resp).await;
+            let session_id = session["id"].as_str().unwrap().to_string();
+            assert_eq!(session["complete"], json!(false));
+            assert_eq!(session["missing_chunks"], json!([0]));
+
+            // Chunks must be exactly the expected size
+            let resp = env
+                .api
+                .upload_session_chunk(
+                    &session_id,
+                    0,
+                    bytes[..bytes.len() - 1].to_vec(),
+                    USER_USER_PAT,
+                )
+                .await;
+            assert_status!(&resp, StatusCode::BAD_REQUEST);
+
+            // Sessions are only accessible to the user who created them
+            let resp = env
+                .api
+                .upload_session_chunk(&session_id, 0, bytes.clone(), FRIEND_USER_PAT)
+                .await;
+            assert_status!(&resp, StatusCode::NOT_FOUND);
+
+            let resp = env
+                .api
+                .upload_session_chunk(&session_id, 0, bytes.clone(), USER_USER_PAT)
+                .await;
+            assert_status!(&resp, StatusCode::OK);
+            let session: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(session["complete"], json!(true));
+            assert_eq!(session["missing_chunks"], json!([]));
+
+            let mut data = get_public_version_creation_data_json("1.2.3.4", None, &file);
+            data["project_id"] = json!(alpha_project_id_parsed);
+            let resp = env
+                .api
+                .add_version_from_uploads(
+                    data,
+                    HashMap::from([(file.filename(), session_id.clone())]),
+                    USER_USER_PAT,
+                )
+                .await;
+            assert_status!(&resp, StatusCode::OK);
+            let version: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(version["files"][0]["filename"], json!(file.filename()));
+            assert_eq!(version["files"][0]["size"], json!(bytes.len()));
+
+            // The session is consumed by the created version
+            let resp = env.api.get_upload_session(&session_id, USER_USER_PAT).await;
+            assert_status!(&resp, StatusCode::NOT_FOUND);
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+async fn retried_upload_chunks_replace_earlier_uploads() {
+    with_test_environment(
+        None,
+        |env: common::environment::TestEnvironment<ApiV3>| async move {
+            let alpha_project_id_parsed = env.dummy.project_alpha.project_id_parsed;
+            let file = TestFile::BasicMod;
+            let bytes = file.bytes();
+
+            let resp = env
+                .api
+                .create_upload_session(&file.filename(), bytes.len() as u64, USER_USER_PAT)
+                .await;
+            assert_status!(&resp, StatusCode::OK);
+            let session: serde_json::Value = test::read_body_json(resp).await;
+            let session_id = session["id"].as_str().unwrap().to_string();
+
+            // A chunk uploaded again with different bytes replaces the earlier upload
+            let mut corrupted = bytes.clone();
+            corrupted[0] ^= 0xff;
+            let resp = env
+                .api
+                .upload_session_chunk(&session_id, 0, corrupted, USER_USER_PAT)
+                .await;
+            assert_status!(&resp, StatusCode::OK);
+            let resp = env
+                .api
+                .upload_session_chunk(&session_id, 0, bytes.clone(), USER_USER_PAT)
+                .await;
+            assert_status!(&resp, StatusCode::OK);
+
+            let mut data = get_public_version_creation_data_json("1.2.3.4", None, &file);
+            data["project_id"] = json!(alpha_project_id_parsed);
+            let resp = env
+                .api
+                .add_version_from_uploads(
+                    data,
+                    HashMap::from([(file.filename(), session_id)]),
+                    USER_USER_PAT,
+                )
+                .await;
+            assert_status!(&resp, StatusCode::OK);
+            let version: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(
+                version["files"][0]["hashes"]["sha1"],
+                json!(sha1::Sha1::from(&bytes).hexdigest())
+            );
+            assert_eq!(version["files"][0]["size"], json!(bytes.len()));
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+async fn hidden_version_files_use_signed_urls() {
+    with_test_environment(
+        None,
+        |env: common::environment::TestEnvironment<ApiV3>| async move {
+            let cdn_url = dotenvy::var("CDN_URL").unwrap();
+            let self_addr = dotenvy::var("SELF_ADDR").unwrap();
+            let alpha_version_id = &env.dummy.project_alpha.version_id;
+            let beta_version_id = &env.dummy.project_beta.version_id;
+
+            // Files of public versions keep their permanent URLs
+            let version = env
+                .api
+                .get_version_deserialized(alpha_version_id, USER_USER_PAT)
+                .await;
+            assert!(version.files[0].url.starts_with(&cdn_url));
+            assert!(!version.files[0].url.contains("/private/"));
+
+            // Files of versions of private projects are only served through signed URLs, and are
+            // stored under a prefix the CDN doesn't serve
+            let version = env
+                .api
+                .get_version_deserialized(beta_version_id, USER_USER_PAT)
+                .await;
+            let url = version.files[0].url.clone();
+            let path = url.strip_prefix(&self_addr).unwrap().to_string();
+            assert!(path.starts_with("/v3/file/private/data/"));
+
+            let req = test::TestRequest::get().uri(&path).to_request();
+            let resp = env.api.call(req).await;
+            assert_status!(&resp, StatusCode::OK);
+            let body = test::read_body(resp).await;
+            assert_eq!(body, TestFile::DummyProjectBeta.bytes());
+
+            // Tampered signatures are rejected
+            let (path, _) = path.split_once("&signature=").unwrap();
+            let req = test::TestRequest::get()
+                .uri(&format!("{path}&signature=00"))
+                .to_request();
+            let resp = env.api.call(req).await;
+            assert_status!(&resp, StatusCode::NOT_FOUND);
+
+            // Hiding a version moves its files to the private prefix
+            let resp = env
+                .api
+                .edit_version(
+                    alpha_version_id,
+                    json!({ "status": "draft" }),
+                    USER_USER_PAT,
+                )
+                .await;
+            assert_status!(&resp, StatusCode::NO_CONTENT);
+
+            let version = env
+                .api
+                .get_version_deserialized(alpha_version_id, USER_USER_PAT)
+                .await;
+            let url = version.files[0].url.clone();
+            let path = url.strip_prefix(&self_addr).unwrap().to_string();
+            assert!(path.starts_with("/v3/file/private/data/"));
+
+            let req = test::TestRequest::get().uri(&path).to_request();
+            let resp = env.api.call(req).await;
+            assert_status!(&resp, StatusCode::OK);
+            let body = test::read_body(resp).await;
+            assert_eq!(body, TestFile::DummyProjectAlpha.bytes());
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+async fn version_metadata_is_filled_from_mod_manifest() {
+    with_test_environment(
+        None,
+        |env: common::environment::TestEnvironment<ApiV3>| async move {
+            let alpha_project_id_parsed = env.dummy.project_alpha.project_id_parsed;
+
+            // The loaders, version number, game versions and side types are all left out, and
+            // read from the jar's fabric.mod.json instead
+            let resp = env
+                .api
+                .add_public_version(
+                    alpha_project_id_parsed,
+                    "",
+                    TestFile::build_random_jar(),
+                    None,
+                    Some(
+                        serde_json::from_value(json!([
+                            { "op": "remove", "path": "/loaders" },
+                            { "op": "remove", "path": "/version_number" },
+                            { "op": "remove", "path": "/game_versions" },
+                            { "op": "remove", "path": "/singleplayer" },
+                            { "op": "remove", "path": "/client_and_server" },
+                            { "op": "remove", "path": "/client_only" },
+                            { "op": "remove", "path": "/server_only" },
+                        ]))
+                        .unwrap(),
+                    ),
+                    USER_USER_PAT,
+                )
+                .await;
+            assert_status!(&resp, StatusCode::OK);
+            let version: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(version["version_number"], json!("1.0.1"));
+            assert_eq!(version["loaders"], json!(["fabric"]));
+            // The jar supports >=1.20, which matches every release game version from 1.20.1
+            assert_eq!(
+                version["game_versions"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .filter_map(|x| x.as_str())
+                    .sorted()
+                    .collect::<Vec<_>>(),
+                vec!["1.20.1", "1.20.2", "1.20.3", "1.20.5"]
+            );
+            assert_eq!(version["client_only"], json!(true));
+            assert_eq!(version["server_only"], json!(false));
+            assert!(version.get("warnings").is_none());
+
+            // Submitted data which contradicts the manifest is kept, but warned about
+            let resp = env
+                .api
+                .add_public_version(
+                    alpha_project_id_parsed,
+                    "2.0.0",
+                    TestFile::build_random_jar(),
+                    None,
+                    Some(
+                        serde_json::from_value(json!([
+                            { "op": "replace", "path": "/client_only", "value": false },
+                            { "op": "replace", "path": "/server_only", "value": true },
+                        ]))
+                        .unwrap(),
+                    ),
+                    USER_USER_PAT,
+                )
+                .await;
+            assert_status!(&resp, StatusCode::OK);
+            let version: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(version["version_number"], json!("2.0.0"));
+            assert_eq!(version["server_only"], json!(true));
+            assert_eq!(version["warnings"].as_array().unwrap().len(), 2);
+
+            // Loaders are still required when the file has no manifest to read them from
+            let resp = env
+                .api
+                .add_public_version(
+                    alpha_project_id_parsed,
+                    "1.0.0",
+                    TestFile::BasicZip,
+                    None,
+                    Some(
+                        serde_json::from_value(json!([{ "op": "remove", "path": "/loaders" }]))
+                            .unwrap(),
+                    ),
+                    USER_USER_PAT,
+                )
+                .await;
+            assert_status!(&resp, StatusCode::BAD_REQUEST);
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+async fn version_dependencies_are_detected_from_mod_manifest() {
+    with_test_environment(
+        None,
+        |env: common::environment::TestEnvironment<ApiV3>| async move {
+            let alpha_project_id_parsed = env.dummy.project_alpha.project_id_parsed;
+            let beta_project_id_parsed = env.dummy.project_beta.project_id_parsed;
+
+            // Uploading a jar to alpha associates its mod id with alpha
+            let alpha_jar = TestFile::build_random_jar();
+            let alpha_mod_id = alpha_jar.filename();
+            let resp = env
+                .api
+                .add_public_version(
+                    alpha_project_id_parsed,
+                    "1.0.0",
+                    alpha_jar,
+                    None,
+                    None,
+                    USER_USER_PAT,
+                )
+                .await;
+            assert_status!(&resp, StatusCode::OK);
+
+            // A jar depending on alpha's mod id, and bundling a jar with an unknown mod id
+            let embedded = TestFile::build_random_jar();
+            let resp = env
+                .api
+                .add_public_version(
+                    beta_project_id_parsed,
+                    "1.0.0",
+                    TestFile::build_random_jar_with_dependency(&alpha_mod_id, &embedded),
+                    None,
+                    None,
+                    USER_USER_PAT,
+                )
+                .await;
+            assert_status!(&resp, StatusCode::OK);
+            let version: serde_json::Value = test::read_body_json(resp).await;
+            let dependencies: Vec<Dependency> =
+                serde_json::from_value(version["dependencies"].clone()).unwrap();
+
+            assert_eq!(dependencies.len(), 2);
+            assert!(dependencies.iter().any(|x| {
+                x.project_id == Some(alpha_project_id_parsed)
+                    && x.dependency_type == DependencyType::Required
+            }));
+            assert!(dependencies.iter().any(|x| {
+                x.file_name == Some(embedded.filename())
+                    && x.dependency_type == DependencyType::Embedded
+            }));
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+async fn versions_with_suspicious_code_are_held() {
+    with_test_environment(
+        None,
+        |env: common::environment::TestEnvironment<ApiV3>| async move {
+            let alpha_project_id_parsed = env.dummy.project_alpha.project_id_parsed;
+
+            let resp = env
+                .api
+                .add_public_version(
+                    alpha_project_id_parsed,
+                    "1.0.0",
+                    TestFile::SuspiciousMod,
+                    None,
+                    None,
+                    USER_USER_PAT,
+                )
+                .await;
+            assert_status!(&resp, StatusCode::OK);
+            let version: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(version["status"], json!("held"));
+            assert_eq!(version["requested_status"], json!("listed"));
+            let version_id = version["id"].as_str().unwrap();
+
+            // Held versions are hidden from everyone but the project's members and moderators
+            let resp = env.api.get_version(version_id, ENEMY_USER_PAT).await;
+            assert_status!(&resp, StatusCode::NOT_FOUND);
+
+            // Only moderators can release them
+            let resp = env
+                .api
+                .edit_version(version_id, json!({ "status": "listed" }), USER_USER_PAT)
+                .await;
+            assert_status!(&resp, StatusCode::UNAUTHORIZED);
+
+            let resp = env
+                .api
+                .edit_version(version_id, json!({ "status": "listed" }), MOD_USER_PAT)
+                .await;
+            assert_status!(&resp, StatusCode::NO_CONTENT);
+
+            let resp = env.api.get_version(version_id, ENEMY_USER_PAT).await;
+            assert_status!(&resp, StatusCode::OK);
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+async fn version_files_include_validation_report() {
+    with_test_environment(
+        None,
+        |env: common::environment::TestEnvironment<ApiV3>| async move {
+            let alpha_version_id = &env.dummy.project_alpha.version_id;
+
+            // A zip is not a valid Fabric mod, so it is stored as a non-primary file with an error
+            let resp = env
+                .api
+                .upload_file_to_version(alpha_version_id, &TestFile::BasicZip, USER_USER_PAT)
+                .await;
+            assert_status!(&resp, StatusCode::NO_CONTENT);
+
+            let resp = env.api.get_version(alpha_version_id, USER_USER_PAT).await;
+            assert_status!(&resp, StatusCode::OK);
+            let version: serde_json::Value = test::read_body_json(resp).await;
+            let files = version["files"].as_array().unwrap();
+
+            let primary = files.iter().find(|x| x["primary"] == json!(true)).unwrap();
+            assert!(primary.get("validation").is_none());
+
+            let zip = files
+                .iter()
+                .find(|x| x["filename"] == json!(TestFile::BasicZip.filename()))
+                .unwrap();
+            assert_eq!(zip["primary"], json!(false));
+            let findings = zip["validation"]["findings"].as_array().unwrap();
+            assert_eq!(findings.len(), 1);
+            assert_eq!(findings[0]["severity"], json!("error"));
+            assert_eq!(findings[0]["code"], json!("invalid_extension"));
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+async fn modpack_contents_are_resolved_against_hosted_files() {
+    with_test_environment(
+        None,
+        |env: common::environment::TestEnvironment<ApiV3>| async move {
+            let alpha = &env.dummy.project_alpha;
+            let beta = &env.dummy.project_beta;
+            let alpha_version = env
+                .api
+                .get_version_deserialized(&alpha.version_id, USER_USER_PAT)
+                .await;
+            let beta_version = env
+                .api
+                .get_version_deserialized(&beta.version_id, USER_USER_PAT)
+                .await;
+
+            let pack_file = |path: &str, file: &labrinth::models::projects::VersionFile| {
+                json!({
+                    "path": path,
+                    "hashes": {
+                        "sha1": file.hashes["sha1"],
+                        "sha512": file.hashes["sha512"],
+                    },
+                    "downloads": [file.url],
+                    "fileSize": file.size,
+                })
+            };
+            let mut alpha_file = pack_file("mods/alpha.jar", &alpha_version.files[0]);
+            alpha_file["fileSize"] = json!(alpha_version.files[0].size + 1);
+
+            let mrpack = TestFile::build_random_mrpack_with_files(json!([
+                alpha_file,
+                pack_file("mods/beta.jar", &beta_version.files[0]),
+                {
+                    "path": "mods/unknown.jar",
+                    "hashes": {
+                        "sha1": "0000000000000000000000000000000000000000",
+                        "sha512": "0".repeat(128),
+                    },
+                    "downloads": ["https://github.com/modrinth/unknown/releases/unknown.jar"],
+                    "fileSize": 1,
+                },
+                {
+                    "path": "mods/deleted.jar",
+                    "hashes": {
+                        "sha1": "1111111111111111111111111111111111111111",
+                        "sha512": "1".repeat(128),
+                    },
+                    "downloads": [
+                        "https://cdn.modrinth.com/data/PRN43VSY/versions/uNgEPb10/deleted.jar"
+                    ],
+                    "fileSize": 1,
+                },
+            ]));
+
+            let (pack, _) = env
+                .api
+                .add_public_project(
+                    "resolved-modpack",
+                    Some(TestFile::build_random_mrpack()),
+                    None,
+                    FRIEND_USER_PAT,
+                )
+                .await;
+            let version = env
+                .api
+                .add_public_version_deserialized(
+                    pack.id,
+                    "2.0.0",
+                    mrpack,
+                    None,
+                    None,
+                    FRIEND_USER_PAT,
+                )
+                .await;
+
+            let resp = env
+                .api
+                .get_version_contents(&version.id.to_string(), FRIEND_USER_PAT)
+                .await;
+            assert_status!(&resp, StatusCode::OK);
+            let contents: Vec<serde_json::Value> = test::read_body_json(resp).await;
+            let content = |path: &str| contents.iter().find(|x| x["path"] == json!(path)).unwrap();
+
+            assert_eq!(content("mods/alpha.jar")["status"], json!("known"));
+            assert_eq!(
+                content("mods/alpha.jar")["project_id"],
+                json!(alpha.project_id)
+            );
+            assert_eq!(content("mods/alpha.jar")["size_mismatch"], json!(true));
+            // Versions which aren't visible are not revealed
+            assert_eq!(content("mods/beta.jar")["status"], json!("hidden"));
+            assert!(content("mods/beta.jar")["project_id"].is_null());
+            assert_eq!(content("mods/unknown.jar")["status"], json!("unknown"));
+            assert_eq!(content("mods/deleted.jar")["status"], json!("deleted"));
+
+            // The resolution is reported as findings of the modpack file
+            let codes = version.files[0]
+                .validation
+                .findings
+                .iter()
+                .map(|x| x.code.as_str())
+                .collect_vec();
+            assert!(codes.contains(&"pack_file_size_mismatch"));
+            assert!(codes.contains(&"hidden_pack_file"));
+            assert!(codes.contains(&"deleted_pack_file"));
+
+            // The members of included projects are notified, unless the project is hidden
+            let link = format!("/project/{}/version/{}", pack.id, version.id);
+            let notifications = env
+                .api
+                .get_user_notifications_deserialized_common(USER_USER_ID, USER_USER_PAT)
+                .await;
+            assert_eq!(notifications.iter().filter(|x| x.link == link).count(), 1);
+        },
+    )
+    .await;
+}